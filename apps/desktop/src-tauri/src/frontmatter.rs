@@ -0,0 +1,160 @@
+// Minimal YAML frontmatter handling for backend features that need to read
+// note metadata. Values are kept as raw YAML text per top-level key.
+
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Frontmatter {
+    entries: Vec<(String, String)>,
+}
+
+/// Splits a note into its frontmatter block (without the `---` fences) and body.
+pub fn split(text: &str) -> (Option<&str>, &str) {
+    let rest = match text.strip_prefix("---\n").or_else(|| text.strip_prefix("---\r\n")) {
+        Some(rest) => rest,
+        None => return (None, text),
+    };
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == "---" {
+            let yaml = &rest[..offset];
+            let body = &rest[offset + line.len()..];
+            return (Some(yaml), body);
+        }
+        offset += line.len();
+    }
+    (None, text)
+}
+
+fn unquote(value: &str) -> String {
+    let value = value.trim();
+    if value.len() >= 2
+        && ((value.starts_with('"') && value.ends_with('"')) || (value.starts_with('\'') && value.ends_with('\'')))
+    {
+        value[1..value.len() - 1].to_string()
+    } else {
+        value.to_string()
+    }
+}
+
+impl Frontmatter {
+    pub fn parse(yaml: &str) -> Self {
+        let mut entries: Vec<(String, String)> = Vec::new();
+        for line in yaml.lines() {
+            let is_top_level = !line.starts_with(' ') && !line.starts_with('\t') && !line.starts_with('-');
+            match line.split_once(':') {
+                Some((key, value)) if is_top_level && !key.trim().is_empty() && !key.starts_with('#') => {
+                    entries.push((key.trim().to_string(), value.trim().to_string()));
+                }
+                _ => {
+                    // Continuation of the previous key (block lists, folded text).
+                    if let Some((_, value)) = entries.last_mut() {
+                        value.push('\n');
+                        value.push_str(line);
+                    }
+                }
+            }
+        }
+        Self { entries }
+    }
+
+    pub fn from_entries(entries: Vec<(String, String)>) -> Self {
+        Self { entries }
+    }
+
+    /// Keys in order, each with its raw value (see `raw`).
+    pub fn entries(&self) -> &[(String, String)] {
+        &self.entries
+    }
+
+    /// YAML for the entries, one key per line, as `parse` reads it back.
+    pub fn render(&self) -> String {
+        let mut yaml = String::new();
+        for (key, value) in &self.entries {
+            yaml.push_str(key);
+            yaml.push(':');
+            if !value.is_empty() && !value.starts_with('\n') {
+                yaml.push(' ');
+            }
+            yaml.push_str(value);
+            yaml.push('\n');
+        }
+        yaml
+    }
+
+    pub fn from_note(text: &str) -> Self {
+        match split(text) {
+            (Some(yaml), _) => Self::parse(yaml),
+            (None, _) => Self::default(),
+        }
+    }
+
+    /// The value exactly as written after `key:`, including continuation lines.
+    pub fn raw(&self, key: &str) -> Option<&str> {
+        self.entries
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// A scalar value with surrounding quotes removed.
+    pub fn get_str(&self, key: &str) -> Option<String> {
+        let raw = self.raw(key)?;
+        if raw.contains('\n') || raw.starts_with('[') {
+            return None;
+        }
+        let value = unquote(raw);
+        if value.is_empty() { None } else { Some(value) }
+    }
+
+    /// A list value, written either inline (`[a, b]`) or as a block (`- a`).
+    /// A plain scalar is treated as a single-item list.
+    pub fn get_list(&self, key: &str) -> Vec<String> {
+        let raw = match self.raw(key) {
+            Some(raw) => raw,
+            None => return Vec::new(),
+        };
+        let first_line = raw.lines().next().unwrap_or_default().trim();
+        if let Some(inner) = first_line.strip_prefix('[').and_then(|s| s.strip_suffix(']')) {
+            return inner
+                .split(',')
+                .map(unquote)
+                .filter(|item| !item.is_empty())
+                .collect();
+        }
+        if !first_line.is_empty() {
+            return vec![unquote(first_line)];
+        }
+        raw.lines()
+            .skip(1)
+            .filter_map(|line| line.trim().strip_prefix('-'))
+            .map(unquote)
+            .filter(|item| !item.is_empty())
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scalars_and_lists() {
+        let note = "---\ntitle: \"Field notes\"\nlang: fr\ntags: [alpha, 'beta']\naliases:\n  - One\n  - Two\n---\nBody\n";
+        let (yaml, body) = split(note);
+        assert_eq!(body, "Body\n");
+
+        let fm = Frontmatter::parse(yaml.unwrap());
+        assert_eq!(fm.get_str("title").as_deref(), Some("Field notes"));
+        assert_eq!(fm.get_str("lang").as_deref(), Some("fr"));
+        assert_eq!(fm.get_list("tags"), vec!["alpha", "beta"]);
+        assert_eq!(fm.get_list("aliases"), vec!["One", "Two"]);
+        assert_eq!(fm.get_str("aliases"), None);
+        assert_eq!(fm.render(), yaml.unwrap());
+    }
+
+    #[test]
+    fn test_no_frontmatter() {
+        assert_eq!(split("# Title\n---\n"), (None, "# Title\n---\n"));
+        assert_eq!(Frontmatter::from_note("plain"), Frontmatter::default());
+    }
+}
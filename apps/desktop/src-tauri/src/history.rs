@@ -0,0 +1,416 @@
+// Local version history: a snapshot of a note each time it is saved, kept
+// under `.liminal/history/` and thinned out as it ages. History stays on this
+// device; sync skips it like other hidden folders.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use tauri::AppHandle;
+
+use crate::sync::{self, content_hash};
+use crate::vault::{resolve_safe_path, vault_root};
+
+pub const HISTORY_DIR: &str = ".liminal/history";
+const INDEX_FILE: &str = "index.json";
+const OBJECTS_DIR: &str = "objects";
+const INDEX_VERSION: u32 = 1;
+const HOUR_MS: u64 = 60 * 60 * 1000;
+const DAY_MS: u64 = 24 * HOUR_MS;
+/// Every save is kept for a day, then the last save of each hour for a week,
+/// then the last save of each day.
+const KEEP_ALL_MS: u64 = DAY_MS;
+const KEEP_HOURLY_MS: u64 = 7 * DAY_MS;
+
+/// Serializes index updates between commands.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// One saved version of a note. `saved_at` (ms) identifies it within the
+/// note's history.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Version {
+    pub saved_at: u64,
+    pub hash: String,
+    pub size: u64,
+}
+
+/// `index.json`: each path's versions, oldest first.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Index {
+    version: u32,
+    notes: BTreeMap<String, Vec<Version>>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiffKind {
+    Equal,
+    Insert,
+    Delete,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DiffLine {
+    pub kind: DiffKind,
+    pub text: String,
+}
+
+pub struct History {
+    dir: PathBuf,
+}
+
+impl History {
+    pub fn open(root: &Path) -> Self {
+        Self { dir: root.join(HISTORY_DIR) }
+    }
+
+    fn index(&self) -> Result<Index, String> {
+        match fs::read_to_string(self.dir.join(INDEX_FILE)) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| format!("Invalid history index: {}", e)),
+            Err(_) => Ok(Index { version: INDEX_VERSION, ..Default::default() }),
+        }
+    }
+
+    fn save_index(&self, index: &Index) -> Result<(), String> {
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string_pretty(index).map_err(|e| e.to_string())?;
+        let path = self.dir.join(INDEX_FILE);
+        let tmp = path.with_extension("tmp");
+        fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &path).map_err(|e| e.to_string())
+    }
+
+    fn object_path(&self, hash: &str) -> PathBuf {
+        self.dir.join(OBJECTS_DIR).join(&hash[..2.min(hash.len())]).join(hash)
+    }
+
+    /// Records a save of `path`. `previous` is what the save replaced: it
+    /// becomes the first version of a note that has no history yet.
+    pub fn save(&self, path: &str, previous: Option<&[u8]>, current: &[u8]) -> Result<(), String> {
+        self.save_at(path, previous, current, sync::now_ms())
+    }
+
+    fn save_at(&self, path: &str, previous: Option<&[u8]>, current: &[u8], now: u64) -> Result<(), String> {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.index()?;
+        let versions = index.notes.entry(path.to_string()).or_default();
+        if versions.is_empty() {
+            if let Some(previous) = previous.filter(|previous| *previous != current) {
+                versions.push(self.store(previous, now.saturating_sub(1))?);
+            }
+        }
+        let hash = content_hash(current);
+        if versions.last().is_some_and(|last| last.hash == hash) {
+            return Ok(());
+        }
+        let saved_at = versions.last().map_or(now, |last| now.max(last.saved_at + 1));
+        versions.push(self.store(current, saved_at)?);
+
+        let dropped = thin(versions, now);
+        self.save_index(&index)?;
+        self.remove_unreferenced(&index, &dropped);
+        Ok(())
+    }
+
+    /// Applies the retention policy to every note, so history of notes that
+    /// are no longer saved ages out too. Returns how many versions went.
+    pub fn thin_all(&self) -> Result<usize, String> {
+        self.thin_all_at(sync::now_ms())
+    }
+
+    fn thin_all_at(&self, now: u64) -> Result<usize, String> {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.index()?;
+        let dropped: Vec<Version> = index.notes.values_mut().flat_map(|versions| thin(versions, now)).collect();
+        if dropped.is_empty() {
+            return Ok(0);
+        }
+        self.save_index(&index)?;
+        self.remove_unreferenced(&index, &dropped);
+        Ok(dropped.len())
+    }
+
+    /// Deletes the content of dropped versions no other version shares.
+    fn remove_unreferenced(&self, index: &Index, dropped: &[Version]) {
+        for version in dropped {
+            let referenced = index.notes.values().flatten().any(|v| v.hash == version.hash);
+            if !referenced {
+                let _ = fs::remove_file(self.object_path(&version.hash));
+            }
+        }
+    }
+
+    fn store(&self, bytes: &[u8], saved_at: u64) -> Result<Version, String> {
+        let hash = content_hash(bytes);
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            fs::create_dir_all(path.parent().unwrap_or(&self.dir)).map_err(|e| e.to_string())?;
+            fs::write(&path, bytes).map_err(|e| e.to_string())?;
+        }
+        Ok(Version { saved_at, hash, size: bytes.len() as u64 })
+    }
+
+    /// Versions of `path`, newest first.
+    pub fn versions(&self, path: &str) -> Result<Vec<Version>, String> {
+        let mut versions = self.index()?.notes.remove(path).unwrap_or_default();
+        versions.reverse();
+        Ok(versions)
+    }
+
+    pub fn read(&self, path: &str, saved_at: u64) -> Result<Vec<u8>, String> {
+        let version = self
+            .versions(path)?
+            .into_iter()
+            .find(|v| v.saved_at == saved_at)
+            .ok_or_else(|| format!("No version {} of {}", saved_at, path))?;
+        let bytes = fs::read(self.object_path(&version.hash)).map_err(|e| format!("Version {} is missing: {}", saved_at, e))?;
+        if content_hash(&bytes) != version.hash {
+            return Err(format!("Version {} is corrupt", saved_at));
+        }
+        Ok(bytes)
+    }
+
+    /// Moves the history of `old` (a note, or every note under a folder) to
+    /// `new`.
+    pub fn rename(&self, old: &str, new: &str) -> Result<(), String> {
+        let _guard = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut index = self.index()?;
+        let prefix = format!("{}/", old);
+        let moved: Vec<String> = index
+            .notes
+            .keys()
+            .filter(|path| *path == old || path.starts_with(&prefix))
+            .cloned()
+            .collect();
+        if moved.is_empty() {
+            return Ok(());
+        }
+        for path in moved {
+            let versions = index.notes.remove(&path).unwrap_or_default();
+            let target = format!("{}{}", new, &path[old.len()..]);
+            index.notes.entry(target).or_default().extend(versions);
+        }
+        for versions in index.notes.values_mut() {
+            versions.sort_by_key(|v| v.saved_at);
+        }
+        self.save_index(&index)
+    }
+}
+
+/// Applies the retention policy to `versions` (oldest first) and returns the
+/// versions it dropped. The newest version is always kept.
+fn thin(versions: &mut Vec<Version>, now: u64) -> Vec<Version> {
+    let mut kept = Vec::with_capacity(versions.len());
+    let mut dropped = Vec::new();
+    let mut last_bucket = None;
+    for version in versions.drain(..).rev() {
+        let age = now.saturating_sub(version.saved_at);
+        let bucket = if age < KEEP_ALL_MS {
+            None
+        } else if age < KEEP_HOURLY_MS {
+            Some((HOUR_MS, version.saved_at / HOUR_MS))
+        } else {
+            Some((DAY_MS, version.saved_at / DAY_MS))
+        };
+        if bucket.is_some() && bucket == last_bucket {
+            dropped.push(version);
+            continue;
+        }
+        last_bucket = bucket;
+        kept.push(version);
+    }
+    kept.reverse();
+    *versions = kept;
+    dropped
+}
+
+/// Line diff from `old` to `new`; within a change, removed lines come first.
+pub fn diff(old: &str, new: &str) -> Vec<DiffLine> {
+    let old: Vec<String> = old.lines().map(String::from).collect();
+    let new: Vec<String> = new.lines().map(String::from).collect();
+    let line = |kind, text: &String| DiffLine { kind, text: text.clone() };
+    let Some(matches) = sync::merge::lcs_matches(&old, &new) else {
+        // Too large to align: everything changed.
+        let deleted = old.iter().map(|text| line(DiffKind::Delete, text));
+        return deleted.chain(new.iter().map(|text| line(DiffKind::Insert, text))).collect();
+    };
+
+    let mut lines = Vec::new();
+    let mut next = 0;
+    for (text, partner) in old.iter().zip(matches) {
+        match partner {
+            Some(j) => {
+                lines.extend(new[next..j].iter().map(|text| line(DiffKind::Insert, text)));
+                lines.push(line(DiffKind::Equal, text));
+                next = j + 1;
+            }
+            None => lines.push(line(DiffKind::Delete, text)),
+        }
+    }
+    lines.extend(new[next..].iter().map(|text| line(DiffKind::Insert, text)));
+    lines
+}
+
+/// Snapshots a save made by a vault command. History is best effort: a
+/// failure is logged and does not fail the save.
+pub fn record_write(root: &Path, path: &str, previous: Option<&[u8]>, current: &[u8]) {
+    if let Err(e) = History::open(root).save(&sync::normalize(path), previous, current) {
+        eprintln!("Failed to record note history: {}", e);
+    }
+}
+
+pub fn record_move(root: &Path, old: &str, new: &str) {
+    if let Err(e) = History::open(root).rename(&sync::normalize(old), &sync::normalize(new)) {
+        eprintln!("Failed to move note history: {}", e);
+    }
+}
+
+fn text(bytes: Vec<u8>) -> Result<String, String> {
+    String::from_utf8(bytes).map_err(|_| "Version is not text".to_string())
+}
+
+/// Thins the whole history first, as retention otherwise only runs for the
+/// note being saved.
+#[tauri::command]
+pub fn history_list(app: AppHandle, relative_path: String) -> Result<Vec<Version>, String> {
+    let root = vault_root(app)?;
+    let history = History::open(&root);
+    if let Err(e) = history.thin_all() {
+        eprintln!("Failed to thin note history: {}", e);
+    }
+    history.versions(&sync::normalize(&relative_path))
+}
+
+#[tauri::command]
+pub fn history_read(app: AppHandle, relative_path: String, saved_at: u64) -> Result<String, String> {
+    let root = vault_root(app)?;
+    text(History::open(&root).read(&sync::normalize(&relative_path), saved_at)?)
+}
+
+/// Diffs two versions; without `to`, against the note as it is now.
+#[tauri::command]
+pub fn history_diff(app: AppHandle, relative_path: String, from: u64, to: Option<u64>) -> Result<Vec<DiffLine>, String> {
+    let root = vault_root(app)?;
+    let history = History::open(&root);
+    let path = sync::normalize(&relative_path);
+    let old = text(history.read(&path, from)?)?;
+    let new = match to {
+        Some(to) => text(history.read(&path, to)?)?,
+        None => fs::read_to_string(resolve_safe_path(&root, &path)?).unwrap_or_default(),
+    };
+    Ok(diff(&old, &new))
+}
+
+/// Writes a version back to the note, which becomes the newest version. A
+/// deleted note is recreated.
+#[tauri::command]
+pub fn history_restore(app: AppHandle, relative_path: String, saved_at: u64) -> Result<(), String> {
+    let root = vault_root(app)?;
+    let path = sync::normalize(&relative_path);
+    let bytes = History::open(&root).read(&path, saved_at)?;
+    let full_path = resolve_safe_path(&root, &path)?;
+    let previous = fs::read(&full_path).ok();
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    fs::write(&full_path, &bytes).map_err(|e| e.to_string())?;
+    sync::observe(&root, |core| core.record_write(&path));
+    record_write(&root, &path, previous.as_deref(), &bytes);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_versions_dedup_restore_and_rename() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(dir.path());
+        history.save_at("a.md", Some(b"before\n"), b"one\n", 1_000).unwrap();
+        history.save_at("a.md", Some(b"one\n"), b"one\n", 2_000).unwrap();
+        history.save_at("a.md", Some(b"one\n"), b"one\ntwo\n", 3_000).unwrap();
+        history.save_at("a.md", Some(b"one\ntwo\n"), b"before\n", 4_000).unwrap();
+
+        let versions = history.versions("a.md").unwrap();
+        let times: Vec<u64> = versions.iter().map(|v| v.saved_at).collect();
+        assert_eq!(times, vec![4_000, 3_000, 1_000, 999]);
+        // The same content is stored once.
+        assert_eq!(versions[0].hash, versions[3].hash);
+        let objects = walkdir::WalkDir::new(dir.path().join(HISTORY_DIR).join(OBJECTS_DIR))
+            .into_iter()
+            .filter(|e| e.as_ref().unwrap().file_type().is_file())
+            .count();
+        assert_eq!(objects, 3);
+        assert_eq!(history.read("a.md", 3_000).unwrap(), b"one\ntwo\n");
+
+        let lines = diff("one\ntwo\nthree\n", "one\n2\nthree\nfour\n");
+        let kinds: Vec<(DiffKind, &str)> = lines.iter().map(|l| (l.kind, l.text.as_str())).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (DiffKind::Equal, "one"),
+                (DiffKind::Delete, "two"),
+                (DiffKind::Insert, "2"),
+                (DiffKind::Equal, "three"),
+                (DiffKind::Insert, "four"),
+            ]
+        );
+
+        history.rename("a.md", "notes/a.md").unwrap();
+        assert!(history.versions("a.md").unwrap().is_empty());
+        assert_eq!(history.versions("notes/a.md").unwrap().len(), 4);
+        history.save_at("notes/b.md", None, b"b\n", 5_000).unwrap();
+        history.rename("notes", "archive").unwrap();
+        assert_eq!(history.versions("archive/a.md").unwrap().len(), 4);
+        assert_eq!(history.versions("archive/b.md").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_retention_thins_old_versions() {
+        let dir = tempfile::tempdir().unwrap();
+        let history = History::open(dir.path());
+        let now = 100 * DAY_MS;
+        // Every ten minutes for ten days.
+        let mut saved = 0;
+        let mut at = now - 10 * DAY_MS;
+        while at < now {
+            history.save_at("a.md", None, format!("{}\n", saved).as_bytes(), at).unwrap();
+            saved += 1;
+            at += 10 * 60 * 1000;
+        }
+        history.save_at("a.md", None, b"last\n", now).unwrap();
+
+        let versions = history.versions("a.md").unwrap();
+        let age = |v: &Version| now - v.saved_at;
+        let recent = versions.iter().filter(|v| age(v) < KEEP_ALL_MS).count();
+        let hourly = versions.iter().filter(|v| age(v) >= KEEP_ALL_MS && age(v) < KEEP_HOURLY_MS).count();
+        let daily = versions.iter().filter(|v| age(v) >= KEEP_HOURLY_MS).count();
+        // The last day's 143 saves and the final one; one per hour for the
+        // six days before; one per day for the three before that.
+        assert_eq!((recent, hourly, daily), (144, 145, 3));
+        assert_eq!(history.read("a.md", now).unwrap(), b"last\n");
+        let objects = || {
+            walkdir::WalkDir::new(dir.path().join(HISTORY_DIR).join(OBJECTS_DIR))
+                .into_iter()
+                .filter(|e| e.as_ref().unwrap().file_type().is_file())
+                .count()
+        };
+        // Dropped versions' content is gone.
+        assert_eq!(objects(), versions.len());
+
+        // A note saved six times in an hour and never again keeps them all
+        // until the whole index is thinned.
+        let then = now - 10 * DAY_MS;
+        for i in 0..6 {
+            history.save_at("b.md", None, format!("b{}\n", i).as_bytes(), then + i * 10 * 60 * 1000).unwrap();
+        }
+        assert_eq!(history.versions("b.md").unwrap().len(), 6);
+        assert_eq!(history.thin_all_at(now).unwrap(), 5);
+        assert_eq!(history.read("b.md", then + 50 * 60 * 1000).unwrap(), b"b5\n");
+        assert_eq!(history.versions("a.md").unwrap(), versions);
+        assert_eq!(objects(), versions.len() + 1);
+    }
+}
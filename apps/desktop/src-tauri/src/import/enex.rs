@@ -0,0 +1,588 @@
+// Evernote `.enex` import. Note content is ENML, an XHTML subset, converted to
+// Markdown; resources become attachments, matched to `<en-media>` by the MD5
+// of their data, and decoded from the file again when they are written.
+
+use base64::Engine;
+use md5::{Digest, Md5};
+use roxmltree::{Document, Node, ParsingOptions};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use super::{destination, file_name, note_with_properties, relative_to, wikilink, ImportReport, Plan};
+
+/// HTML entities ENML allows but XML doesn't define.
+const ENTITIES: &[(&str, &str)] = &[
+    ("&nbsp;", "&#160;"),
+    ("&ndash;", "&#8211;"),
+    ("&mdash;", "&#8212;"),
+    ("&hellip;", "&#8230;"),
+    ("&lsquo;", "&#8216;"),
+    ("&rsquo;", "&#8217;"),
+    ("&ldquo;", "&#8220;"),
+    ("&rdquo;", "&#8221;"),
+    ("&bull;", "&#8226;"),
+    ("&middot;", "&#183;"),
+    ("&copy;", "&#169;"),
+    ("&reg;", "&#174;"),
+    ("&trade;", "&#8482;"),
+    ("&euro;", "&#8364;"),
+    ("&pound;", "&#163;"),
+    ("&laquo;", "&#171;"),
+    ("&raquo;", "&#187;"),
+];
+const EXTENSIONS: &[(&str, &str)] = &[
+    ("image/png", "png"),
+    ("image/jpeg", "jpg"),
+    ("image/gif", "gif"),
+    ("image/svg+xml", "svg"),
+    ("image/webp", "webp"),
+    ("application/pdf", "pdf"),
+    ("audio/mpeg", "mp3"),
+    ("audio/wav", "wav"),
+    ("text/plain", "txt"),
+];
+
+fn parse(xml: &str) -> Result<Document<'_>, roxmltree::Error> {
+    Document::parse_with_options(xml, ParsingOptions { allow_dtd: true, ..Default::default() })
+}
+
+fn child<'a, 'input>(node: Node<'a, 'input>, name: &str) -> Option<Node<'a, 'input>> {
+    node.children().find(|child| child.has_tag_name(name))
+}
+
+fn child_text<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    child(node, name).and_then(|child| child.text()).map(str::trim).filter(|text| !text.is_empty())
+}
+
+/// `20240115T093000Z` as `2024-01-15T09:30:00Z`.
+fn iso_date(date: &str) -> String {
+    if date.len() == 16 && date.is_char_boundary(8) && &date[8..9] == "T" {
+        format!("{}-{}-{}T{}:{}:{}Z", &date[..4], &date[4..6], &date[6..8], &date[9..11], &date[11..13], &date[13..15])
+    } else {
+        date.to_string()
+    }
+}
+
+fn read_enex(file: &Path, label: &str, report: &mut ImportReport) -> Option<String> {
+    fs::read_to_string(file).map_err(|e| report.error(label, e.to_string())).ok()
+}
+
+fn notes<'a, 'input>(doc: &'a Document<'input>) -> impl Iterator<Item = Node<'a, 'input>> {
+    doc.root_element().children().filter(|node| node.has_tag_name("note"))
+}
+
+/// An exported notebook, and the paths reserved for notes of every notebook.
+struct Notebook<'a> {
+    file: &'a Path,
+    dir: &'a str,
+    titles: &'a HashMap<String, String>,
+}
+
+pub fn plan(root: &Path, source: &Path, folder: &str, report: &mut ImportReport) -> Result<Plan, String> {
+    let files: Vec<PathBuf> = if source.is_dir() {
+        let mut files: Vec<PathBuf> = fs::read_dir(source)
+            .map_err(|e| e.to_string())?
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("enex")))
+            .collect();
+        files.sort();
+        files
+    } else {
+        vec![source.to_path_buf()]
+    };
+    if files.is_empty() {
+        return Err("No .enex files found".to_string());
+    }
+
+    // Every note's path is reserved before any is converted, so links
+    // between notes resolve in either direction; each file is read again to
+    // convert its notes.
+    let mut plan = Plan::new(root);
+    let mut titles: HashMap<String, String> = HashMap::new();
+    let mut notebooks = Vec::new();
+    for file in files {
+        let label = file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+        let Some(text) = read_enex(&file, &label, report) else {
+            continue;
+        };
+        let doc = match parse(&text) {
+            Ok(doc) => doc,
+            Err(e) => {
+                report.error(label, format!("Invalid ENEX: {}", e));
+                continue;
+            }
+        };
+        // Each file is one exported notebook.
+        let notebook = file.file_stem().unwrap_or_default().to_string_lossy();
+        let dir = format!("{}/{}", folder, file_name(&notebook));
+        let mut paths = Vec::new();
+        for note in notes(&doc) {
+            let title = child_text(note, "title").unwrap_or("Untitled");
+            let path = plan.reserve(&format!("{}/{}.md", dir, file_name(title)));
+            titles.entry(title.to_string()).or_insert_with(|| path.clone());
+            paths.push(path);
+        }
+        notebooks.push((file, label, dir, paths));
+    }
+
+    for (file, label, dir, paths) in notebooks {
+        let Some(text) = read_enex(&file, &label, report) else {
+            continue;
+        };
+        let Ok(doc) = parse(&text) else {
+            continue;
+        };
+        let notebook = Notebook { file: &file, dir: &dir, titles: &titles };
+        for (note, path) in notes(&doc).zip(paths) {
+            let item = format!("{}: {}", label, child_text(note, "title").unwrap_or("Untitled"));
+            if let Err(e) = import_note(note, &notebook, path, &item, &mut plan, report) {
+                report.error(item, e);
+            }
+        }
+    }
+    Ok(plan)
+}
+
+/// Decodes base64 from `input`, skipping whitespace, handing `sink` a block
+/// at a time.
+fn decode_base64(mut input: impl Read, mut sink: impl FnMut(&[u8]) -> Result<(), String>) -> Result<(), String> {
+    let mut block = [0u8; 8192];
+    let mut pending = Vec::new();
+    let mut decoded = Vec::new();
+    loop {
+        let read = input.read(&mut block).map_err(|e| e.to_string())?;
+        pending.extend(block[..read].iter().filter(|b| !b.is_ascii_whitespace()));
+        // Whole quads decode on their own; the last one may be padded.
+        let whole = if read == 0 { pending.len() } else { pending.len() / 4 * 4 };
+        if whole > 0 {
+            decoded.clear();
+            base64::engine::general_purpose::STANDARD
+                .decode_vec(&pending[..whole], &mut decoded)
+                .map_err(|e| e.to_string())?;
+            sink(&decoded)?;
+            pending.drain(..whole);
+        }
+        if read == 0 {
+            return Ok(());
+        }
+    }
+}
+
+struct Media {
+    path: String,
+    name: String,
+    image: bool,
+}
+
+fn import_note(note: Node, notebook: &Notebook, path: String, item: &str, plan: &mut Plan, report: &mut ImportReport) -> Result<(), String> {
+    let content = child_text(note, "content").ok_or("Note has no content")?;
+
+    let mut media = HashMap::new();
+    let mut attachments = Vec::new();
+    for (i, resource) in note.children().filter(|node| node.has_tag_name("resource")).enumerate() {
+        let label = format!("{}: resource {}", item, i + 1);
+        // The data is hashed now and read from the file's own bytes when
+        // written, which only works while they are the plain text.
+        let Some(data) = child(resource, "data").and_then(|data| data.first_child()).filter(|data| data.is_text()) else {
+            report.error(label, "Resource has no data");
+            continue;
+        };
+        let range = data.range();
+        if data.text() != note.document().input_text().get(range.clone()) {
+            report.error(label, "Resource data is not plain base64");
+            continue;
+        }
+        let mut hasher = Md5::new();
+        let raw = data.text().unwrap_or_default().as_bytes();
+        if let Err(e) = decode_base64(raw, |chunk| {
+            hasher.update(chunk);
+            Ok(())
+        }) {
+            report.error(label, e);
+            continue;
+        }
+        let hash = hex::encode(hasher.finalize());
+        let mime = child_text(resource, "mime").unwrap_or_default();
+        let name = child(resource, "resource-attributes")
+            .and_then(|attributes| child_text(attributes, "file-name"))
+            .map(file_name)
+            .unwrap_or_else(|| {
+                let extension = EXTENSIONS.iter().find(|(m, _)| *m == mime).map_or("bin", |(_, ext)| ext);
+                format!("{}.{}", hash, extension)
+            });
+        let attachment = plan.reserve(&format!("{}/attachments/{}", notebook.dir, name));
+        attachments.push((format!("{}: {}", item, name), attachment.clone(), range));
+        media.insert(hash, Media { path: attachment, name, image: mime.starts_with("image/") });
+    }
+
+    let mut markdown = Enml {
+        note: &path,
+        media: &media,
+        titles: notebook.titles,
+        missing: Vec::new(),
+        unresolved: Vec::new(),
+        encrypted: 0,
+    };
+    let body = markdown.convert(content)?;
+    for hash in markdown.missing {
+        report.error(item, format!("Missing resource: {}", hash));
+    }
+    for text in markdown.unresolved {
+        report.error(item, format!("Linked note not in export: {}", text));
+    }
+    if markdown.encrypted > 0 {
+        report.skipped(item, format!("{} encrypted sections are not imported", markdown.encrypted));
+    }
+
+    let attributes = child(note, "note-attributes");
+    let tags: Vec<Value> = note.children().filter(|node| node.has_tag_name("tag")).filter_map(|tag| tag.text()).map(Value::from).collect();
+    let properties = vec![
+        ("created".to_string(), child_text(note, "created").map(iso_date).into()),
+        ("updated".to_string(), child_text(note, "updated").map(iso_date).into()),
+        ("tags".to_string(), if tags.is_empty() { Value::Null } else { Value::Array(tags) }),
+        ("source".to_string(), attributes.and_then(|a| child_text(a, "source-url")).into()),
+        ("author".to_string(), attributes.and_then(|a| child_text(a, "author")).into()),
+    ];
+    plan.add(item, path, note_with_properties(properties, &body));
+    for (item, path, range) in attachments {
+        let source = notebook.file.to_path_buf();
+        plan.defer(item, path, move |out, _| {
+            let mut input = fs::File::open(&source).map_err(|e| e.to_string())?;
+            input.seek(SeekFrom::Start(range.start as u64)).map_err(|e| e.to_string())?;
+            decode_base64(input.take(range.len() as u64), |chunk| out.write_all(chunk).map_err(|e| e.to_string()))
+        });
+    }
+    Ok(())
+}
+
+/// Converts ENML to Markdown. `<div>`s are lines, as Evernote uses them;
+/// `<p>`s and other blocks are paragraphs.
+struct Enml<'a> {
+    note: &'a str,
+    media: &'a HashMap<String, Media>,
+    /// Reserved note paths by title, for `evernote:///view/` links.
+    titles: &'a HashMap<String, String>,
+    missing: Vec<String>,
+    /// Text of note links whose target isn't in the import.
+    unresolved: Vec<String>,
+    encrypted: usize,
+}
+
+/// Ends the current line.
+fn line(out: &mut String) {
+    if !out.is_empty() && !out.ends_with('\n') {
+        out.push('\n');
+    }
+}
+
+/// Ends the current paragraph.
+fn paragraph(out: &mut String) {
+    line(out);
+    if !out.is_empty() && !out.ends_with("\n\n") {
+        out.push('\n');
+    }
+}
+
+/// Whitespace runs as single spaces, as HTML renders them.
+fn collapse(text: &str) -> String {
+    let mut out = String::new();
+    let mut space = false;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            space = true;
+        } else {
+            if space {
+                out.push(' ');
+            }
+            space = false;
+            out.push(c);
+        }
+    }
+    if space {
+        out.push(' ');
+    }
+    out
+}
+
+/// Trailing spaces and extra blank lines removed.
+fn tidy(text: &str) -> String {
+    let mut out = String::new();
+    let mut blank = 0;
+    for line in text.trim().lines() {
+        let line = line.trim_end();
+        blank = if line.is_empty() { blank + 1 } else { 0 };
+        if blank < 2 {
+            out.push_str(line);
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Text of a code block, with `<div>`s and `<br>`s as line breaks.
+fn code_text(node: Node, out: &mut String) {
+    for child in node.children() {
+        if child.is_text() {
+            out.push_str(child.text().unwrap_or_default());
+        } else if child.has_tag_name("br") {
+            out.push('\n');
+        } else {
+            code_text(child, out);
+            if child.has_tag_name("div") || child.has_tag_name("p") {
+                line(out);
+            }
+        }
+    }
+}
+
+impl Enml<'_> {
+    fn convert(&mut self, content: &str) -> Result<String, String> {
+        let mut xml = content.to_string();
+        for (entity, code) in ENTITIES {
+            xml = xml.replace(entity, code);
+        }
+        let doc = parse(&xml).map_err(|e| format!("Invalid note content: {}", e))?;
+        let mut out = String::new();
+        self.render(doc.root_element(), &mut out);
+        Ok(tidy(&out))
+    }
+
+    fn rendered(&mut self, node: Node) -> String {
+        let mut out = String::new();
+        self.render(node, &mut out);
+        out
+    }
+
+    fn render(&mut self, node: Node, out: &mut String) {
+        for child in node.children() {
+            if child.is_text() {
+                let text = collapse(child.text().unwrap_or_default());
+                let at_line_start = out.is_empty() || out.ends_with('\n');
+                out.push_str(if at_line_start { text.trim_start() } else { &text });
+                continue;
+            }
+            if !child.is_element() {
+                continue;
+            }
+            let style = child.attribute("style").unwrap_or_default();
+            match child.tag_name().name() {
+                "br" => out.push('\n'),
+                "div" if style.contains("-en-codeblock") => self.code_block(child, out),
+                "div" => {
+                    line(out);
+                    self.render(child, out);
+                    line(out);
+                }
+                "pre" => self.code_block(child, out),
+                tag @ ("h1" | "h2" | "h3" | "h4" | "h5" | "h6") => {
+                    paragraph(out);
+                    let level = tag[1..].parse().unwrap_or(1);
+                    out.push_str(&format!("{} {}", "#".repeat(level), self.rendered(child).trim()));
+                    paragraph(out);
+                }
+                "b" | "strong" => self.wrap(child, "**", out),
+                "i" | "em" => self.wrap(child, "*", out),
+                "s" | "strike" | "del" => self.wrap(child, "~~", out),
+                "code" => self.wrap(child, "`", out),
+                "a" => {
+                    let text = self.rendered(child);
+                    let href = child.attribute("href").filter(|href| !href.is_empty());
+                    if href.is_some_and(|href| href.starts_with("evernote:///view/")) {
+                        // Note links carry ids the export doesn't include, so
+                        // the target is found by the link's text or title.
+                        let target = [Some(text.trim()), child.attribute("title")]
+                            .into_iter()
+                            .flatten()
+                            .find_map(|name| self.titles.get(name.trim()));
+                        match target {
+                            Some(path) => {
+                                out.push_str(&wikilink(path, text.trim()));
+                                continue;
+                            }
+                            None => self.unresolved.push(text.trim().to_string()),
+                        }
+                    }
+                    match href {
+                        Some(href) if text.trim().is_empty() => out.push_str(&format!("<{}>", href)),
+                        Some(href) => out.push_str(&format!("[{}]({})", text.trim(), href)),
+                        None => out.push_str(&text),
+                    }
+                }
+                "img" => {
+                    if let Some(src) = child.attribute("src") {
+                        out.push_str(&format!("![{}]({})", child.attribute("alt").unwrap_or_default(), src));
+                    }
+                }
+                "en-media" => self.media(child, out),
+                "en-todo" => {
+                    let in_list = child.ancestors().any(|ancestor| ancestor.has_tag_name("li"));
+                    out.push_str(if in_list { "" } else { "- " });
+                    out.push_str(if child.attribute("checked") == Some("true") { "[x] " } else { "[ ] " });
+                }
+                "en-crypt" => {
+                    self.encrypted += 1;
+                    out.push_str("*[Encrypted]*");
+                }
+                "ul" | "ol" => self.list(child, out),
+                "blockquote" => {
+                    paragraph(out);
+                    for text in tidy(&self.rendered(child)).lines() {
+                        out.push_str(if text.is_empty() { ">" } else { "> " });
+                        out.push_str(text);
+                        out.push('\n');
+                    }
+                    paragraph(out);
+                }
+                "hr" => {
+                    paragraph(out);
+                    out.push_str("---");
+                    paragraph(out);
+                }
+                "table" => self.table(child, out),
+                "p" => {
+                    paragraph(out);
+                    self.render(child, out);
+                    paragraph(out);
+                }
+                _ => self.render(child, out),
+            }
+        }
+    }
+
+    fn wrap(&mut self, node: Node, marker: &str, out: &mut String) {
+        let text = self.rendered(node);
+        let trimmed = text.trim();
+        if trimmed.is_empty() {
+            out.push_str(&text);
+            return;
+        }
+        let start = text.len() - text.trim_start().len();
+        let end = text.trim_end().len();
+        out.push_str(&format!("{}{}{}{}{}", &text[..start], marker, trimmed, marker, &text[end..]));
+    }
+
+    fn code_block(&mut self, node: Node, out: &mut String) {
+        let mut code = String::new();
+        code_text(node, &mut code);
+        paragraph(out);
+        out.push_str(&format!("```\n{}\n```", code.trim_end_matches('\n')));
+        paragraph(out);
+    }
+
+    fn media(&mut self, node: Node, out: &mut String) {
+        let hash = node.attribute("hash").unwrap_or_default();
+        match self.media.get(hash) {
+            Some(media) => {
+                let link = destination(&relative_to(self.note, &media.path));
+                if media.image {
+                    out.push_str(&format!("![]({})", link));
+                } else {
+                    out.push_str(&format!("[{}]({})", media.name, link));
+                }
+            }
+            None => self.missing.push(hash.to_string()),
+        }
+    }
+
+    /// Items are indented under their marker; Evernote's newer checklists
+    /// are lists styled `--en-todo`.
+    fn list(&mut self, node: Node, out: &mut String) {
+        paragraph(out);
+        let ordered = node.has_tag_name("ol");
+        let todo = node.attribute("style").is_some_and(|style| style.contains("--en-todo:true"));
+        for (i, item) in node.children().filter(|child| child.has_tag_name("li")).enumerate() {
+            let marker = if ordered { format!("{}. ", i + 1) } else { "- ".to_string() };
+            let checked = item.attribute("style").is_some_and(|style| style.contains("--en-checked:true"));
+            let text = self.rendered(item);
+            let mut lines = text.lines().map(str::trim_end).filter(|line| !line.trim().is_empty());
+            out.push_str(&marker);
+            if todo {
+                out.push_str(if checked { "[x] " } else { "[ ] " });
+            }
+            out.push_str(lines.next().unwrap_or_default());
+            out.push('\n');
+            for line in lines {
+                out.push_str(&" ".repeat(marker.len()));
+                out.push_str(line);
+                out.push('\n');
+            }
+        }
+        paragraph(out);
+    }
+
+    /// The first row is the header.
+    fn table(&mut self, node: Node, out: &mut String) {
+        paragraph(out);
+        let rows: Vec<Node> = node.descendants().filter(|child| child.has_tag_name("tr")).collect();
+        for (i, row) in rows.into_iter().enumerate() {
+            let cells: Vec<String> = row
+                .children()
+                .filter(|cell| cell.has_tag_name("td") || cell.has_tag_name("th"))
+                .map(|cell| tidy(&self.rendered(cell)).trim().replace('\n', " ").replace('|', "\\|"))
+                .collect();
+            out.push_str(&format!("| {} |\n", cells.join(" | ")));
+            if i == 0 {
+                out.push_str(&format!("|{}\n", " --- |".repeat(cells.len())));
+            }
+        }
+        paragraph(out);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_notes_resources_and_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("vault");
+        fs::create_dir_all(&root).unwrap();
+        // Larger than one decoded block, and wrapped the way Evernote does.
+        let png: Vec<u8> = (0..20_000u32).map(|i| (i * 7 % 251) as u8).collect();
+        let hash = hex::encode(Md5::digest(&png));
+        let encoded = base64::engine::general_purpose::STANDARD.encode(&png);
+        let data = encoded.as_bytes().chunks(76).map(|line| std::str::from_utf8(line).unwrap()).collect::<Vec<_>>().join("\n");
+        let content = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?><!DOCTYPE en-note SYSTEM "http://xml.evernote.com/pub/enml2.dtd">
+<en-note><div>Hello <b>bold</b>&nbsp;and <a href="https://example.com">a link</a></div><div><br/></div>
+<ul><li>One</li><li>Two<ul><li>Nested</li></ul></li></ul>
+<div><en-todo checked="true"/>Done</div><en-media type="image/png" hash="{}"/><en-media type="application/pdf" hash="ffff"/>
+<table><tr><td>A</td><td>B</td></tr><tr><td>1</td><td>2</td></tr></table>
+<div>See <a href="evernote:///view/1/s1/aa/aa/">Packing list</a> and <a href="evernote:///view/1/s1/bb/bb/">Gone</a></div></en-note>"#,
+            hash
+        );
+        let enex = format!(
+            r#"<?xml version="1.0" encoding="UTF-8"?>
+<en-export><note><title>Trip: plans</title><content><![CDATA[{}]]></content><created>20240115T093000Z</created>
+<tag>travel</tag><note-attributes><source-url>https://example.com/trip</source-url></note-attributes>
+<resource><data encoding="base64">{}</data><mime>image/png</mime><resource-attributes><file-name>map.png</file-name></resource-attributes></resource></note>
+<note><title>Broken</title><content><![CDATA[<en-note><div>unclosed</en-note>]]></content></note>
+<note><title>Packing list</title><content><![CDATA[<en-note><div>Socks</div></en-note>]]></content></note></en-export>"#,
+            content, data
+        );
+        let source = dir.path().join("Travel.enex");
+        fs::write(&source, enex).unwrap();
+
+        let mut report = ImportReport::new(false);
+        plan(&root, &source, "Evernote", &mut report).unwrap().finish(&mut report);
+        assert_eq!(
+            fs::read_to_string(root.join("Evernote/Travel/Trip- plans.md")).unwrap(),
+            "---\ncreated: \"2024-01-15T09:30:00Z\"\ntags: [\"travel\"]\nsource: \"https://example.com/trip\"\n---\n\
+             Hello **bold** and [a link](https://example.com)\n\n\
+             - One\n- Two\n  - Nested\n\n\
+             - [x] Done\n![](attachments/map.png)\n\n\
+             | A | B |\n| --- | --- |\n| 1 | 2 |\n\n\
+             See [[Evernote/Travel/Packing list]] and [Gone](evernote:///view/1/s1/bb/bb/)\n"
+        );
+        assert_eq!(fs::read(root.join("Evernote/Travel/attachments/map.png")).unwrap(), png);
+        let errors: Vec<(&str, &str)> = report.errors.iter().map(|e| (e.item.as_str(), e.detail.as_str())).collect();
+        assert_eq!(errors[0], ("Travel.enex: Trip: plans", "Missing resource: ffff"));
+        assert_eq!(errors[1], ("Travel.enex: Trip: plans", "Linked note not in export: Gone"));
+        assert_eq!(errors[2].0, "Travel.enex: Broken");
+        assert_eq!(report.carried.len(), 3);
+    }
+}
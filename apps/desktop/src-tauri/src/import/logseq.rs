@@ -0,0 +1,298 @@
+// Logseq graph import. Pages and journals stay Markdown outlines; page
+// properties become frontmatter, namespaces become folders, and block
+// references become links showing the referenced block's text.
+
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+use std::sync::OnceLock;
+use walkdir::WalkDir;
+
+use super::{
+    destination, file_name, is_external, link_regex, note_with_properties, relative_to, resolve_link, wikilink,
+    ImportReport, Plan,
+};
+
+const MONTHS: [&str; 12] = ["Jan", "Feb", "Mar", "Apr", "May", "Jun", "Jul", "Aug", "Sep", "Oct", "Nov", "Dec"];
+/// Page properties that hold lists of page names.
+const LIST_PROPERTIES: &[&str] = &["tags", "alias"];
+
+fn property_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"^([A-Za-z0-9_-]+):: ?(.*)$").expect("valid property pattern"))
+}
+
+fn embed_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"\{\{embed\s+(\[\[[^\]]+\]\]|\(\([0-9a-f-]+\)\))\s*\}\}").expect("valid embed pattern"))
+}
+
+fn page_link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"(#?)\[\[([^\[\]]+)\]\]").expect("valid page link pattern"))
+}
+
+fn block_ref_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"\(\(([0-9a-f]{8}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{4}-[0-9a-f]{12})\)\)").expect("valid block ref pattern")
+    })
+}
+
+struct Page {
+    source: String,
+    path: String,
+    properties: Vec<(String, String)>,
+    body: String,
+}
+
+/// Leading `key:: value` lines, and the rest of the page.
+fn split_properties(text: &str) -> (Vec<(String, String)>, String) {
+    let mut properties = Vec::new();
+    let mut rest = text;
+    for line in text.split_inclusive('\n') {
+        match property_regex().captures(line.trim_end()) {
+            Some(caps) => properties.push((caps[1].to_lowercase(), caps[2].trim().to_string())),
+            None if line.trim().is_empty() && !properties.is_empty() => {}
+            None => break,
+        }
+        rest = &rest[line.len()..];
+    }
+    (properties, rest.to_string())
+}
+
+/// `a, [[b c]], #d` as page names.
+fn names(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|name| name.trim().trim_start_matches('#').trim_start_matches("[[").trim_end_matches("]]").trim().to_string())
+        .filter(|name| !name.is_empty())
+        .collect()
+}
+
+/// A page name from its file name: `a___b` and `a%2Fb` are the namespace
+/// page `a/b`.
+fn page_name(stem: &str) -> String {
+    percent_decode_str(stem).decode_utf8_lossy().replace("___", "/")
+}
+
+/// A journal's page name in Logseq's default date format, `Jan 15th, 2024`.
+fn journal_name(date: &str) -> Option<String> {
+    let mut parts = date.split('-').map(|part| part.parse::<usize>().ok());
+    let (year, month, day) = (parts.next()??, parts.next()??, parts.next()??);
+    let suffix = match day {
+        1 | 21 | 31 => "st",
+        2 | 22 => "nd",
+        3 | 23 => "rd",
+        _ => "th",
+    };
+    Some(format!("{} {}{}, {}", MONTHS.get(month.checked_sub(1)?)?, day, suffix, year))
+}
+
+pub fn plan(root: &Path, source: &Path, folder: &str, report: &mut ImportReport) -> Result<Plan, String> {
+    if !source.join("pages").is_dir() && !source.join("journals").is_dir() {
+        return Err("Not a Logseq graph: it has no pages or journals folder".to_string());
+    }
+    if source.join("logseq/config.edn").exists() {
+        report.skipped("logseq/config.edn", "Logseq settings are not imported");
+    }
+    for dir in ["whiteboards", "draws"] {
+        if source.join(dir).is_dir() {
+            report.skipped(dir, "Whiteboards and drawings are not supported");
+        }
+    }
+
+    let mut plan = Plan::new(root);
+    let mut assets = HashMap::new();
+    let files = WalkDir::new(source.join("assets")).sort_by_file_name().into_iter().filter_map(|e| e.ok());
+    for entry in files.filter(|e| e.file_type().is_file()) {
+        let Ok(relative) = entry.path().strip_prefix(source) else {
+            continue;
+        };
+        let item = crate::sync::normalize(&relative.to_string_lossy());
+        let path = plan.reserve(&format!("{}/{}", folder, item));
+        assets.insert(item.clone(), path.clone());
+        let source = entry.path().to_path_buf();
+        plan.defer(item, path, move |out, _| {
+            let mut file = fs::File::open(&source).map_err(|e| e.to_string())?;
+            io::copy(&mut file, out).map(|_| ()).map_err(|e| e.to_string())
+        });
+    }
+
+    let mut pages = Vec::new();
+    let mut lookup: HashMap<String, String> = HashMap::new();
+    for dir in ["pages", "journals"] {
+        let Ok(entries) = fs::read_dir(source.join(dir)) else {
+            continue;
+        };
+        let mut files: Vec<_> = entries.filter_map(|e| e.ok().map(|e| e.path())).filter(|p| p.is_file()).collect();
+        files.sort();
+        for file in files {
+            let name = file.file_name().unwrap_or_default().to_string_lossy().into_owned();
+            let item = format!("{}/{}", dir, name);
+            let Some(stem) = name.strip_suffix(".md") else {
+                if name.ends_with(".org") {
+                    report.skipped(item, "Org-mode pages are not supported");
+                }
+                continue;
+            };
+            let text = match fs::read_to_string(&file) {
+                Ok(text) => text,
+                Err(e) => {
+                    report.error(item, e.to_string());
+                    continue;
+                }
+            };
+            let (properties, body) = split_properties(&text);
+            let title = properties.iter().find(|(key, _)| key == "title").map(|(_, value)| value.clone());
+
+            let (page_names, wanted) = if dir == "journals" {
+                let date = stem.replace('_', "-");
+                let page_names = [Some(date.clone()), Some(stem.to_string()), journal_name(&date)].into_iter().flatten().collect();
+                (page_names, format!("{}/journals/{}.md", folder, date))
+            } else {
+                let name = title.unwrap_or_else(|| page_name(stem));
+                let parts: Vec<String> = name.split('/').map(file_name).collect();
+                (vec![name], format!("{}/{}.md", folder, parts.join("/")))
+            };
+            let path = plan.reserve(&wanted);
+            let aliases = properties.iter().filter(|(key, _)| key == "alias").flat_map(|(_, value)| names(value));
+            for name in page_names.into_iter().chain(aliases) {
+                lookup.entry(name.to_lowercase()).or_insert_with(|| path.clone());
+            }
+            pages.push(Page { source: item, path, properties, body });
+        }
+    }
+
+    // Blocks with an `id::` can be referenced from anywhere in the graph.
+    let mut blocks: HashMap<String, (String, String)> = HashMap::new();
+    for page in &pages {
+        let mut block = "";
+        for line in page.body.lines().map(str::trim) {
+            if let Some(text) = line.strip_prefix("- ") {
+                block = text;
+            } else if let Some(id) = line.strip_prefix("id:: ") {
+                blocks.insert(id.trim().to_string(), (page.path.clone(), block.to_string()));
+            }
+        }
+    }
+
+    for page in pages {
+        let body = convert(&page, &lookup, &blocks, &assets, report);
+        let properties = page
+            .properties
+            .iter()
+            .map(|(key, value)| match key.as_str() {
+                list if LIST_PROPERTIES.contains(&list) => {
+                    let key = if list == "alias" { "aliases" } else { list };
+                    (key.to_string(), Value::from(names(value)))
+                }
+                _ => (key.clone(), Value::from(value.as_str())),
+            })
+            .collect();
+        plan.add(page.source, page.path, note_with_properties(properties, body.trim_start()));
+    }
+    Ok(plan)
+}
+
+/// Rewrites embeds, page links, block references and asset links. Block ids
+/// and `collapsed::` state are dropped.
+fn convert(
+    page: &Page,
+    lookup: &HashMap<String, String>,
+    blocks: &HashMap<String, (String, String)>,
+    assets: &HashMap<String, String>,
+    report: &mut ImportReport,
+) -> String {
+    let body: String = page
+        .body
+        .split_inclusive('\n')
+        .filter(|line| !line.trim().starts_with("id:: ") && line.trim() != "collapsed:: true")
+        .collect();
+    let body = embed_regex().replace_all(&body, "$1");
+    let body = page_link_regex().replace_all(&body, |caps: &Captures| {
+        match lookup.get(&caps[2].to_lowercase()) {
+            Some(path) if caps[1].is_empty() => wikilink(path, &caps[2]),
+            _ => caps[0].to_string(),
+        }
+    });
+    let body = block_ref_regex().replace_all(&body, |caps: &Captures| match blocks.get(&caps[1]) {
+        Some((path, text)) => wikilink(path, text),
+        None => {
+            report.error(&page.source, format!("Block reference not found: {}", &caps[1]));
+            caps[0].to_string()
+        }
+    });
+    link_regex()
+        .replace_all(&body, |caps: &Captures| {
+            let link = &caps[3];
+            if is_external(link) {
+                return caps[0].to_string();
+            }
+            let target = resolve_link(&page.source, &percent_decode_str(link).decode_utf8_lossy());
+            match assets.get(&target) {
+                Some(path) => format!("{}[{}]({})", &caps[1], &caps[2], destination(&relative_to(&page.path, path))),
+                None => {
+                    if target.starts_with("assets/") {
+                        report.error(&page.source, format!("Asset not found: {}", link));
+                    }
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_graph_pages_journals_and_references() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("vault");
+        let graph = dir.path().join("graph");
+        for sub in ["pages", "journals", "assets", "logseq"] {
+            fs::create_dir_all(graph.join(sub)).unwrap();
+        }
+        fs::create_dir_all(&root).unwrap();
+        fs::write(graph.join("logseq/config.edn"), "{}").unwrap();
+        fs::write(graph.join("assets/pic 1.png"), "png").unwrap();
+        fs::write(graph.join("pages/old.org"), "* Org").unwrap();
+        fs::write(
+            graph.join("pages/Project___Alpha.md"),
+            "tags:: work, [[big idea]]\nalias:: PA\n\n- Task one\n  id:: 6500a1b2-0000-4000-8000-000000000001\n  collapsed:: true\n- See ![pic](../assets/pic%201.png)\n",
+        )
+        .unwrap();
+        fs::write(
+            graph.join("journals/2024_01_15.md"),
+            "- Worked on [[project/alpha]] and ((6500a1b2-0000-4000-8000-000000000001))\n- {{embed [[PA]]}} and [[Nowhere]] #[[big idea]]\n- Broken ((6500a1b2-0000-4000-8000-0000000000ff))\n",
+        )
+        .unwrap();
+        fs::write(graph.join("pages/Links.md"), "- [[Jan 15th, 2024]]\n").unwrap();
+
+        let mut report = ImportReport::new(false);
+        plan(&root, &graph, "Logseq", &mut report).unwrap().finish(&mut report);
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(
+            read("Logseq/Project/Alpha.md"),
+            "---\ntags: [\"work\",\"big idea\"]\naliases: [\"PA\"]\n---\n- Task one\n- See ![pic](<../assets/pic 1.png>)\n"
+        );
+        assert_eq!(
+            read("Logseq/journals/2024-01-15.md"),
+            "- Worked on [[Logseq/Project/Alpha|project/alpha]] and [[Logseq/Project/Alpha|Task one]]\n\
+             - [[Logseq/Project/Alpha|PA]] and [[Nowhere]] #[[big idea]]\n\
+             - Broken ((6500a1b2-0000-4000-8000-0000000000ff))\n"
+        );
+        assert_eq!(read("Logseq/Links.md"), "- [[Logseq/journals/2024-01-15|Jan 15th, 2024]]\n");
+        assert_eq!(read("Logseq/assets/pic 1.png"), "png");
+        let skipped: Vec<&str> = report.skipped.iter().map(|s| s.item.as_str()).collect();
+        assert_eq!(skipped, vec!["logseq/config.edn", "pages/old.org"]);
+        assert_eq!(report.errors.len(), 1);
+        assert_eq!(report.errors[0].item, "journals/2024_01_15.md");
+    }
+}
@@ -0,0 +1,341 @@
+// Importers that bring notes and configuration from other apps into the open
+// vault. Each returns an ImportReport of what was and wasn't carried over.
+
+use regex::Regex;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+use tauri::AppHandle;
+
+use crate::frontmatter::Frontmatter;
+use crate::sync;
+
+mod enex;
+mod logseq;
+mod notion;
+mod obsidian;
+
+pub const IMAGE_EXTENSIONS: &[&str] = &["png", "jpg", "jpeg", "gif", "svg", "webp", "bmp", "avif"];
+
+/// Builds the files an export becomes, reporting problems per item.
+type Planner = fn(&Path, &Path, &str, &mut ImportReport) -> Result<Plan, String>;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct ReportItem {
+    pub item: String,
+    pub detail: String,
+}
+
+#[derive(Serialize, Debug, Default)]
+pub struct ImportReport {
+    pub dry_run: bool,
+    pub carried: Vec<ReportItem>,
+    pub skipped: Vec<ReportItem>,
+    pub errors: Vec<ReportItem>,
+}
+
+impl ImportReport {
+    fn new(dry_run: bool) -> Self {
+        Self { dry_run, ..Default::default() }
+    }
+
+    fn carried(&mut self, item: impl Into<String>, detail: impl Into<String>) {
+        self.carried.push(ReportItem { item: item.into(), detail: detail.into() });
+    }
+
+    fn skipped(&mut self, item: impl Into<String>, detail: impl Into<String>) {
+        self.skipped.push(ReportItem { item: item.into(), detail: detail.into() });
+    }
+
+    fn error(&mut self, item: impl Into<String>, detail: impl Into<String>) {
+        self.errors.push(ReportItem { item: item.into(), detail: detail.into() });
+    }
+}
+
+/// Sets app settings, keeping the ones the import doesn't touch.
+fn merge_settings(settings_path: &Path, values: HashMap<String, serde_json::Value>) -> Result<(), String> {
+    if values.is_empty() {
+        return Ok(());
+    }
+    let mut settings = crate::settings::read_settings_file(settings_path)?;
+    settings.extend(values);
+    crate::settings::write_settings_file(settings_path, &settings)
+}
+
+/// Writes a file the way vault commands do, so sync sees it.
+fn write_file(root: &Path, path: &str, contents: &[u8]) -> Result<Option<Vec<u8>>, String> {
+    let full_path = crate::vault::resolve_safe_path(root, path)?;
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let previous = fs::read(&full_path).ok();
+    fs::write(&full_path, contents).map_err(|e| e.to_string())?;
+    sync::observe(root, |core| core.record_write(path));
+    Ok(previous)
+}
+
+/// Writes a file from a stream, so it is never held in memory. A file that
+/// fails part way is removed.
+fn write_streamed(root: &Path, path: &str, write: impl FnOnce(&mut dyn Write) -> Result<(), String>) -> Result<(), String> {
+    let full_path = crate::vault::resolve_safe_path(root, path)?;
+    if let Some(parent) = full_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let mut file = File::create(&full_path).map_err(|e| e.to_string())?;
+    if let Err(e) = write(&mut file).and_then(|()| file.flush().map_err(|e| e.to_string())) {
+        drop(file);
+        let _ = fs::remove_file(&full_path);
+        return Err(e);
+    }
+    sync::observe(root, |core| core.record_write(path));
+    Ok(())
+}
+
+/// Writes a note, also recording it in local history.
+fn write_note(root: &Path, path: &str, contents: &str) -> Result<(), String> {
+    let previous = write_file(root, path, contents.as_bytes())?;
+    crate::history::record_write(root, path, previous.as_deref(), contents.as_bytes());
+    Ok(())
+}
+
+/// Writes a planned file's contents, as a note when it is one.
+fn write_contents(root: &Path, path: &str, contents: Vec<u8>) -> Result<(), String> {
+    if path.ends_with(".md") {
+        String::from_utf8(contents)
+            .map_err(|e| e.to_string())
+            .and_then(|text| write_note(root, path, &text))
+    } else {
+        write_file(root, path, &contents).map(|_| ())
+    }
+}
+
+/// Produces a file's contents into the writer when the plan is carried out.
+type Deferred = Box<dyn FnOnce(&mut dyn Write, &mut ImportReport) -> Result<(), String>>;
+
+enum Contents {
+    Bytes(Vec<u8>),
+    /// Read when written, so an import holds at most one file in memory.
+    /// Notes are buffered for history; other files stream to disk.
+    Deferred(Deferred),
+}
+
+struct PlannedFile {
+    item: String,
+    path: String,
+    contents: Contents,
+}
+
+/// Files an import adds to the vault. Paths are reserved before any content
+/// is converted, so links can point at them; existing files are never
+/// overwritten.
+struct Plan {
+    root: PathBuf,
+    taken: HashSet<String>,
+    files: Vec<PlannedFile>,
+    /// Temporary files deferred contents read from; removed once the plan
+    /// is carried out.
+    scratch: Vec<tempfile::TempDir>,
+}
+
+impl Plan {
+    fn new(root: &Path) -> Self {
+        Self { root: root.to_path_buf(), taken: HashSet::new(), files: Vec::new(), scratch: Vec::new() }
+    }
+
+    /// A free vault path for `wanted`, numbered (`Note 2.md`) when taken.
+    fn reserve(&mut self, wanted: &str) -> String {
+        let wanted = sync::normalize(wanted);
+        let (stem, extension) = match wanted.rfind('.') {
+            Some(dot) if dot > wanted.rfind('/').map_or(0, |slash| slash + 1) => wanted.split_at(dot),
+            _ => (wanted.as_str(), ""),
+        };
+        let mut n = 1;
+        loop {
+            let candidate = if n == 1 { wanted.clone() } else { format!("{} {}{}", stem, n, extension) };
+            if !self.taken.contains(&candidate.to_lowercase()) && !self.root.join(&candidate).exists() {
+                self.taken.insert(candidate.to_lowercase());
+                return candidate;
+            }
+            n += 1;
+        }
+    }
+
+    fn add(&mut self, item: impl Into<String>, path: String, contents: impl Into<Vec<u8>>) {
+        self.files.push(PlannedFile { item: item.into(), path, contents: Contents::Bytes(contents.into()) });
+    }
+
+    fn defer(
+        &mut self,
+        item: impl Into<String>,
+        path: String,
+        write: impl FnOnce(&mut dyn Write, &mut ImportReport) -> Result<(), String> + 'static,
+    ) {
+        self.files.push(PlannedFile { item: item.into(), path, contents: Contents::Deferred(Box::new(write)) });
+    }
+
+    /// Keeps `dir` until the plan is carried out.
+    fn hold(&mut self, dir: tempfile::TempDir) {
+        self.scratch.push(dir);
+    }
+
+    /// Writes the files, or in a dry run only reports where they would go.
+    /// Deferred contents are still read in a dry run, for the problems they
+    /// report.
+    fn finish(self, report: &mut ImportReport) {
+        for file in self.files {
+            let result = match file.contents {
+                Contents::Bytes(_) if report.dry_run => Ok(()),
+                Contents::Deferred(write) if report.dry_run => write(&mut io::sink(), report),
+                Contents::Bytes(bytes) => write_contents(&self.root, &file.path, bytes),
+                Contents::Deferred(write) if file.path.ends_with(".md") => {
+                    let mut bytes = Vec::new();
+                    write(&mut bytes, report).and_then(|()| write_contents(&self.root, &file.path, bytes))
+                }
+                Contents::Deferred(write) => write_streamed(&self.root, &file.path, |out| write(out, report)),
+            };
+            match result {
+                Ok(()) => report.carried(file.item, file.path),
+                Err(e) => report.error(file.item, e),
+            }
+        }
+    }
+}
+
+/// A note with `properties` as frontmatter. Values are written as JSON,
+/// which YAML reads as is.
+fn note_with_properties(properties: Vec<(String, Value)>, body: &str) -> String {
+    let entries: Vec<(String, String)> = properties
+        .into_iter()
+        .filter(|(_, value)| !value.is_null() && *value != Value::String(String::new()))
+        .map(|(key, value)| (key, value.to_string()))
+        .collect();
+    if entries.is_empty() {
+        return body.to_string();
+    }
+    format!("---\n{}---\n{}", Frontmatter::from_entries(entries).render(), body)
+}
+
+/// A title made safe to use as a file name and wikilink target.
+fn file_name(title: &str) -> String {
+    let name: String =
+        title.chars().map(|c| if c.is_control() || "/\\:*?\"<>|#^[]".contains(c) { '-' } else { c }).collect();
+    let name = name.trim().trim_matches('.').trim();
+    if name.is_empty() {
+        "Untitled".to_string()
+    } else {
+        name.to_string()
+    }
+}
+
+/// A wikilink to the note at `path`, showing `text` when it differs from the
+/// note's name.
+fn wikilink(path: &str, text: &str) -> String {
+    let target = path.strip_suffix(".md").unwrap_or(path);
+    let name = target.rsplit('/').next().unwrap_or(target);
+    if text.is_empty() || text == name {
+        format!("[[{}]]", target)
+    } else {
+        format!("[[{}|{}]]", target, text.replace(['[', ']', '|'], ""))
+    }
+}
+
+/// The path of `target` from the folder that holds `note`.
+fn relative_to(note: &str, target: &str) -> String {
+    let from: Vec<&str> = note.split('/').collect();
+    let from = &from[..from.len() - 1];
+    let to: Vec<&str> = target.split('/').collect();
+    let common = from.iter().zip(&to[..to.len() - 1]).take_while(|(a, b)| a == b).count();
+    let mut parts = vec![".."; from.len() - common];
+    parts.extend(&to[common..]);
+    parts.join("/")
+}
+
+/// The vault path a relative link in `note` points at.
+fn resolve_link(note: &str, link: &str) -> String {
+    let mut parts: Vec<&str> = note.split('/').collect();
+    parts.pop();
+    for part in link.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            _ => parts.push(part),
+        }
+    }
+    parts.join("/")
+}
+
+/// A Markdown link destination, in angle brackets when it needs them.
+fn destination(path: &str) -> String {
+    if path.contains([' ', '(', ')']) {
+        format!("<{}>", path)
+    } else {
+        path.to_string()
+    }
+}
+
+/// `[text](destination)` and `![alt](destination)` links.
+fn link_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r#"(!?)\[([^\]]*)\]\(([^)\s]+)(?:\s+"[^"]*")?\)"#).expect("valid link pattern"))
+}
+
+fn is_external(link: &str) -> bool {
+    link.contains("://") || link.starts_with("mailto:") || link.starts_with('#')
+}
+
+/// Imports the `.obsidian/` configuration of the open vault. Notes are
+/// rewritten only with `convert`; `dry_run` reports without writing anything.
+#[tauri::command]
+pub async fn import_obsidian(app: AppHandle, convert: Option<bool>, dry_run: Option<bool>) -> Result<ImportReport, String> {
+    let root = crate::vault::vault_root(app.clone())?;
+    let settings_path = crate::settings::get_settings_path(&app)?;
+    let options = obsidian::Options { convert: convert.unwrap_or(false), dry_run: dry_run.unwrap_or(false) };
+    tokio::task::spawn_blocking(move || obsidian::import(&root, &settings_path, options))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Converts an export into notes under `folder` in the open vault.
+async fn import_export(
+    app: AppHandle,
+    source: String,
+    folder: String,
+    dry_run: Option<bool>,
+    planner: Planner,
+) -> Result<ImportReport, String> {
+    let root = crate::vault::vault_root(app)?;
+    let folder = sync::normalize(&folder);
+    crate::vault::resolve_safe_path(&root, &folder)?;
+    tokio::task::spawn_blocking(move || {
+        let mut report = ImportReport::new(dry_run.unwrap_or(false));
+        let plan = planner(&root, Path::new(&source), &folder, &mut report)?;
+        plan.finish(&mut report);
+        Ok(report)
+    })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Imports a Notion "Markdown & CSV" export zip.
+#[tauri::command]
+pub async fn import_notion(app: AppHandle, source: String, folder: Option<String>, dry_run: Option<bool>) -> Result<ImportReport, String> {
+    import_export(app, source, folder.unwrap_or_else(|| "Notion".to_string()), dry_run, notion::plan).await
+}
+
+/// Imports an Evernote `.enex` file, or a folder of them.
+#[tauri::command]
+pub async fn import_enex(app: AppHandle, source: String, folder: Option<String>, dry_run: Option<bool>) -> Result<ImportReport, String> {
+    import_export(app, source, folder.unwrap_or_else(|| "Evernote".to_string()), dry_run, enex::plan).await
+}
+
+/// Imports a Logseq graph folder.
+#[tauri::command]
+pub async fn import_logseq(app: AppHandle, source: String, folder: Option<String>, dry_run: Option<bool>) -> Result<ImportReport, String> {
+    import_export(app, source, folder.unwrap_or_else(|| "Logseq".to_string()), dry_run, logseq::plan).await
+}
@@ -0,0 +1,348 @@
+// Notion "Markdown & CSV" export import. Notion appends a 32-character id to
+// every page and folder name; ids are dropped, database rows become notes with
+// the columns as properties, and links between pages become wikilinks.
+
+use percent_encoding::percent_decode_str;
+use regex::{Captures, Regex};
+use serde_json::Value;
+use std::cell::RefCell;
+use std::collections::{BTreeMap, HashMap};
+use std::fs::File;
+use std::io::{self, Write};
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::OnceLock;
+use zip::ZipArchive;
+
+use super::{
+    destination, file_name, is_external, link_regex, note_with_properties, relative_to, resolve_link, wikilink,
+    ImportReport, Plan,
+};
+
+/// A row page's properties, and the columns of its database.
+type RowProperties = (Vec<(String, Value)>, Vec<String>);
+
+fn id_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r"^(.*?)\s*[0-9a-f]{32}(?:_all)?((?:\.[A-Za-z0-9]+)?)$").expect("valid id pattern"))
+}
+
+/// A file or folder name without its Notion id.
+fn strip_id(name: &str) -> String {
+    match id_regex().captures(name) {
+        Some(caps) if !caps[1].is_empty() => format!("{}{}", &caps[1], &caps[2]),
+        _ => name.to_string(),
+    }
+}
+
+/// A file in the export: its name in an archive, which is opened once and
+/// shared by every file read from it.
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    archive: Rc<RefCell<ZipArchive<File>>>,
+}
+
+impl Entry {
+    fn copy_to(&self, out: &mut dyn Write) -> Result<(), String> {
+        let mut archive = self.archive.borrow_mut();
+        let mut entry = archive.by_name(&self.name).map_err(|e| e.to_string())?;
+        io::copy(&mut entry, out).map(|_| ()).map_err(|e| e.to_string())
+    }
+
+    fn read(&self) -> Result<Vec<u8>, String> {
+        let mut bytes = Vec::new();
+        self.copy_to(&mut bytes)?;
+        Ok(bytes)
+    }
+}
+
+/// Lists the files in the export by name, without reading them. The
+/// `Part-N.zip` archives large exports are split into are extracted to
+/// `scratch` and listed in turn.
+fn list_zip(path: &Path, scratch: &Path, files: &mut BTreeMap<String, Entry>, report: &mut ImportReport) -> Result<(), String> {
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", path.display(), e))?;
+    let mut archive = ZipArchive::new(file).map_err(|e| format!("Not a zip file: {}", e))?;
+    let mut names = Vec::new();
+    for i in 0..archive.len() {
+        let mut entry = match archive.by_index(i) {
+            Ok(entry) => entry,
+            Err(e) => {
+                report.error(format!("Entry {}", i + 1), e.to_string());
+                continue;
+            }
+        };
+        if !entry.is_file() {
+            continue;
+        }
+        let name = crate::sync::normalize(entry.name());
+        if !name.ends_with(".zip") {
+            names.push((name, entry.name().to_string()));
+            continue;
+        }
+        let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+        let nested = scratch.join(format!("{}-{}.zip", stem, i));
+        let extracted = File::create(&nested)
+            .and_then(|mut out| io::copy(&mut entry, &mut out))
+            .map_err(|e| e.to_string());
+        drop(entry);
+        if let Err(e) = extracted.and_then(|_| list_zip(&nested, scratch, files, report)) {
+            report.error(name, e);
+        }
+    }
+
+    let archive = Rc::new(RefCell::new(archive));
+    for (name, raw) in names {
+        files.insert(name, Entry { name: raw, archive: archive.clone() });
+    }
+    Ok(())
+}
+
+/// Rows of a CSV file, with quoted fields as Notion writes them. Blank lines
+/// are dropped.
+fn parse_csv(text: &str) -> Vec<Vec<String>> {
+    let mut rows = Vec::new();
+    let mut row = Vec::new();
+    let mut field = String::new();
+    let mut quoted = false;
+    let mut chars = text.trim_start_matches('\u{feff}').chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if quoted && chars.peek() == Some(&'"') => {
+                chars.next();
+                field.push('"');
+            }
+            '"' if quoted => quoted = false,
+            '"' if field.is_empty() => quoted = true,
+            ',' if !quoted => row.push(std::mem::take(&mut field)),
+            '\r' if !quoted => {}
+            '\n' if !quoted => {
+                row.push(std::mem::take(&mut field));
+                rows.push(std::mem::take(&mut row));
+            }
+            _ => field.push(c),
+        }
+    }
+    if !field.is_empty() || !row.is_empty() {
+        row.push(field);
+        rows.push(row);
+    }
+    rows.retain(|row| row.len() > 1 || row.first().is_some_and(|field| !field.is_empty()));
+    rows
+}
+
+/// A database column as a note property. A `Tags` column becomes the
+/// `tags` list.
+fn property(column: &str, value: &str) -> (String, Value) {
+    if column.eq_ignore_ascii_case("tags") {
+        let tags = value.split(',').map(str::trim).filter(|tag| !tag.is_empty()).map(Value::from).collect();
+        return ("tags".to_string(), Value::Array(tags));
+    }
+    (column.to_string(), Value::String(value.to_string()))
+}
+
+/// Drops the `Column: value` lines Notion writes under a row page's title;
+/// they become frontmatter instead.
+fn strip_property_lines(text: &str, columns: &[String]) -> String {
+    let mut out = String::new();
+    let mut header = true;
+    let mut dropped = false;
+    for line in text.split_inclusive('\n') {
+        if header {
+            let is_property = line.split_once(": ").is_some_and(|(key, _)| columns.iter().any(|column| column == key));
+            if is_property {
+                dropped = true;
+                continue;
+            }
+            if dropped && line.trim().is_empty() {
+                dropped = false;
+                continue;
+            }
+            header = line.starts_with("# ") || line.trim().is_empty();
+        }
+        out.push_str(line);
+    }
+    out
+}
+
+/// Plans the import from the export's file names; only databases are read
+/// here. Pages and attachments are read one at a time as they are written.
+pub fn plan(root: &Path, source: &Path, folder: &str, report: &mut ImportReport) -> Result<Plan, String> {
+    let scratch = tempfile::tempdir().map_err(|e| e.to_string())?;
+    let mut files = BTreeMap::new();
+    list_zip(source, scratch.path(), &mut files, report)?;
+
+    let mut plan = Plan::new(root);
+    plan.hold(scratch);
+    let mut paths: HashMap<String, String> = HashMap::new();
+    let mut databases = Vec::new();
+    for name in files.keys() {
+        if let Some(stem) = name.strip_suffix(".csv") {
+            // `_all` has every row; the plain CSV only those in the exported view.
+            let has_all = files.contains_key(&format!("{}_all.csv", stem));
+            if !has_all {
+                databases.push(name.clone());
+            }
+            continue;
+        }
+        let stripped: Vec<String> = name.split('/').map(strip_id).collect();
+        paths.insert(name.clone(), plan.reserve(&format!("{}/{}", folder, stripped.join("/"))));
+    }
+
+    let mut properties: HashMap<String, RowProperties> = HashMap::new();
+    for database in &databases {
+        let bytes = match files[database].read() {
+            Ok(bytes) => bytes,
+            Err(e) => {
+                report.error(database, e);
+                continue;
+            }
+        };
+        let rows = parse_csv(&String::from_utf8_lossy(&bytes));
+        let Some((columns, rows)) = rows.split_first() else {
+            report.error(database, "Empty database");
+            continue;
+        };
+        let dir = database.trim_end_matches(".csv").trim_end_matches("_all");
+        let stripped: Vec<String> = dir.split('/').map(strip_id).collect();
+        let db_folder = format!("{}/{}", folder, stripped.join("/"));
+        let mut row_pages: Vec<&String> = paths
+            .keys()
+            .filter(|page| page.ends_with(".md") && page.strip_prefix(&format!("{}/", dir)).is_some_and(|rest| !rest.contains('/')))
+            .collect();
+        row_pages.sort();
+
+        for (i, row) in rows.iter().enumerate() {
+            let item = format!("{} row {}", database, i + 1);
+            if row.len() != columns.len() {
+                report.error(item, format!("Expected {} columns, found {}", columns.len(), row.len()));
+                continue;
+            }
+            let title = row[0].trim();
+            let props: Vec<(String, Value)> =
+                columns.iter().zip(row).skip(1).filter(|(_, value)| !value.is_empty()).map(|(c, v)| property(c, v)).collect();
+            // Notion exports a page for each row, named after its title.
+            let page_name = format!("{}.md", file_name(title));
+            let page = row_pages
+                .iter()
+                .position(|page| strip_id(page.rsplit('/').next().unwrap_or_default()) == page_name)
+                .map(|index| row_pages.remove(index));
+            match page {
+                Some(page) => {
+                    properties.insert(page.clone(), (props, columns.clone()));
+                }
+                None => {
+                    let path = plan.reserve(&format!("{}/{}", db_folder, page_name));
+                    plan.add(item, path, note_with_properties(props, &format!("# {}\n", title)));
+                }
+            }
+        }
+    }
+
+    let paths = Rc::new(paths);
+    for (name, entry) in files {
+        let Some(path) = paths.get(&name).cloned() else {
+            continue;
+        };
+        if !name.ends_with(".md") {
+            plan.defer(name, path, move |out, _| entry.copy_to(out));
+            continue;
+        }
+        let row = properties.remove(&name);
+        let (paths, note) = (paths.clone(), path.clone());
+        plan.defer(name.clone(), path, move |out, report| {
+            let text = String::from_utf8(entry.read()?).map_err(|_| "Not UTF-8 text".to_string())?;
+            let mut body = convert_links(&name, &note, &text, &paths, report);
+            let props = match row {
+                Some((props, columns)) => {
+                    body = strip_property_lines(&body, &columns);
+                    props
+                }
+                None => Vec::new(),
+            };
+            out.write_all(note_with_properties(props, &body).as_bytes()).map_err(|e| e.to_string())
+        });
+    }
+    Ok(plan)
+}
+
+/// Rewrites links to exported pages as wikilinks, and links to attachments
+/// to their new paths. Links to databases are left as they are.
+fn convert_links(name: &str, path: &str, text: &str, paths: &HashMap<String, String>, report: &mut ImportReport) -> String {
+    link_regex()
+        .replace_all(text, |caps: &Captures| {
+            let (bang, label, link) = (&caps[1], &caps[2], &caps[3]);
+            if is_external(link) {
+                return caps[0].to_string();
+            }
+            let decoded = percent_decode_str(link).decode_utf8_lossy();
+            let file = decoded.split('#').next().unwrap_or_default();
+            let target = resolve_link(name, file);
+            match paths.get(&target) {
+                Some(found) if found.ends_with(".md") && bang.is_empty() => wikilink(found, label),
+                Some(found) => format!("{}[{}]({})", bang, label, destination(&relative_to(path, found))),
+                None if target.ends_with(".csv") => caps[0].to_string(),
+                None => {
+                    report.error(name, format!("Link target not in export: {}", decoded));
+                    caps[0].to_string()
+                }
+            }
+        })
+        .into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+    use std::io::Write;
+    use zip::write::SimpleFileOptions;
+
+    const HOME: &str = "Home 0123456789abcdef0123456789abcdef";
+    const TASKS: &str = "Tasks fedcba9876543210fedcba9876543210";
+
+    #[test]
+    fn test_pages_databases_and_links() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("vault");
+        fs::create_dir_all(&root).unwrap();
+        let export = dir.path().join("export.zip");
+        let mut zip = zip::ZipWriter::new(File::create(&export).unwrap());
+        let files = [
+            (format!("{}.md", HOME), "# Home\n\nSee [Write docs](Home%200123456789abcdef0123456789abcdef/Tasks%20fedcba9876543210fedcba9876543210/Write%20docs%2011111111111111111111111111111111.md) and ![a chart](Home%200123456789abcdef0123456789abcdef/chart%201.png) and [gone](Gone%2022222222222222222222222222222222.md).\n".to_string()),
+            (format!("{}/{}.csv", HOME, TASKS), "Name,Status\nWrite docs,Done\n".to_string()),
+            (format!("{}/{}_all.csv", HOME, TASKS), "\u{feff}Name,Status,Tags\nWrite docs,Done,\"a, b\"\n\"Ship, soon\",Todo,\nBroken,1\n".to_string()),
+            (format!("{}/{}/Write docs 11111111111111111111111111111111.md", HOME, TASKS), "# Write docs\n\nStatus: Done\nTags: a, b\n\nBody text\n".to_string()),
+        ];
+        for (name, content) in &files {
+            zip.start_file(name.as_str(), SimpleFileOptions::default()).unwrap();
+            zip.write_all(content.as_bytes()).unwrap();
+        }
+        // Large exports come as nested part archives.
+        let mut part = zip::ZipWriter::new(std::io::Cursor::new(Vec::new()));
+        part.start_file(format!("{}/chart 1.png", HOME), SimpleFileOptions::default()).unwrap();
+        part.write_all(b"png").unwrap();
+        zip.start_file("Export-Part-2.zip", SimpleFileOptions::default()).unwrap();
+        zip.write_all(&part.finish().unwrap().into_inner()).unwrap();
+        zip.finish().unwrap();
+
+        let mut dry = ImportReport::new(true);
+        plan(&root, &export, "Notion", &mut dry).unwrap().finish(&mut dry);
+        assert_eq!(dry.carried.len(), 4);
+        assert_eq!(dry.errors.len(), 2);
+        assert!(!root.join("Notion").exists());
+
+        let mut report = ImportReport::new(false);
+        plan(&root, &export, "Notion", &mut report).unwrap().finish(&mut report);
+        let read = |path: &str| fs::read_to_string(root.join(path)).unwrap();
+        assert_eq!(
+            read("Notion/Home.md"),
+            "# Home\n\nSee [[Notion/Home/Tasks/Write docs]] and ![a chart](<Home/chart 1.png>) and [gone](Gone%2022222222222222222222222222222222.md).\n"
+        );
+        assert_eq!(read("Notion/Home/chart 1.png"), "png");
+        assert_eq!(read("Notion/Home/Tasks/Write docs.md"), "---\nStatus: \"Done\"\ntags: [\"a\",\"b\"]\n---\n# Write docs\n\nBody text\n");
+        assert_eq!(read("Notion/Home/Tasks/Ship, soon.md"), "---\nStatus: \"Todo\"\n---\n# Ship, soon\n");
+        let errors: Vec<&str> = report.errors.iter().map(|e| e.detail.as_str()).collect();
+        assert_eq!(errors, vec!["Expected 3 columns, found 2", "Link target not in export: Gone 22222222222222222222222222222222.md"]);
+    }
+}
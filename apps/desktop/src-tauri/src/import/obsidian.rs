@@ -0,0 +1,607 @@
+// Obsidian vault import: maps `.obsidian/` configuration onto app settings and
+// `.liminal/` files, and converts syntax Liminal doesn't render.
+
+use serde_json::{json, Map, Value};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use walkdir::WalkDir;
+
+use super::{destination, merge_settings, relative_to, write_note, ImportReport, IMAGE_EXTENSIONS};
+use crate::sync;
+
+const CONFIG_DIR: &str = ".obsidian";
+pub const PREFERENCES_FILE: &str = ".liminal/preferences.json";
+pub const BOOKMARKS_FILE: &str = ".liminal/bookmarks.json";
+
+/// `app.json` keys with an app setting of the same meaning.
+const EDITOR_SETTINGS: &[(&str, &str)] = &[
+    ("showLineNumber", "editor.showLineNumbers"),
+    ("readableLineLength", "editor.readableLineLength"),
+    ("spellcheck", "editor.spellcheck.enabled"),
+];
+/// `app.json` keys mapped onto `.liminal/preferences.json`.
+const PREFERENCE_KEYS: &[&str] =
+    &["attachmentFolderPath", "newFileLocation", "newFileFolderPath", "useMarkdownLinks", "newLinkFormat"];
+/// Core plugins with a built-in counterpart.
+const BUILT_IN: &[(&str, &str)] = &[
+    ("file-explorer", "File tree"),
+    ("global-search", "Search"),
+    ("switcher", "Search"),
+    ("graph", "Graph view"),
+    ("backlink", "Backlinks panel"),
+    ("outgoing-link", "Backlinks panel"),
+    ("tag-pane", "Tag browser"),
+    ("file-recovery", "Local note history"),
+];
+/// Core plugins whose own config file is imported and reported.
+const CONFIGURED: &[&str] = &["templates", "daily-notes", "bookmarks", "starred"];
+
+#[derive(Clone, Copy)]
+pub struct Options {
+    /// Rewrite notes that use Obsidian-only syntax.
+    pub convert: bool,
+    pub dry_run: bool,
+}
+
+/// What the import will write.
+#[derive(Default)]
+struct Mapped {
+    settings: HashMap<String, Value>,
+    preferences: Map<String, Value>,
+    bookmarks: Vec<Value>,
+}
+
+pub fn import(root: &Path, settings_path: &Path, options: Options) -> Result<ImportReport, String> {
+    let config = root.join(CONFIG_DIR);
+    if !config.is_dir() {
+        return Err("This vault has no .obsidian folder".to_string());
+    }
+    let mut report = ImportReport::new(options.dry_run);
+    let mut mapped = Mapped::default();
+
+    if let Some(app) = read_config(&config, "app.json", &mut report) {
+        map_app(&app, &mut mapped, &mut report);
+    }
+    if let Some(appearance) = read_config(&config, "appearance.json", &mut report) {
+        map_appearance(&appearance, &mut mapped, &mut report);
+    }
+    if let Some(templates) = read_config(&config, "templates.json", &mut report) {
+        map_preference("templates.json", &templates, "templates", &["folder", "dateFormat", "timeFormat"], &mut mapped, &mut report);
+    }
+    if let Some(daily) = read_config(&config, "daily-notes.json", &mut report) {
+        map_preference("daily-notes.json", &daily, "dailyNotes", &["folder", "format", "template", "autorun"], &mut mapped, &mut report);
+    }
+    for file in ["bookmarks.json", "starred.json"] {
+        if let Some(bookmarks) = read_config(&config, file, &mut report) {
+            let before = mapped.bookmarks.len();
+            for item in bookmarks.get("items").and_then(Value::as_array).into_iter().flatten() {
+                if let Some(bookmark) = bookmark(file, item, &mut report) {
+                    mapped.bookmarks.push(bookmark);
+                }
+            }
+            if mapped.bookmarks.len() > before {
+                stored(&mut report, file, BOOKMARKS_FILE);
+            }
+        }
+    }
+    if let Some(plugins) = read_json(&config.join("core-plugins.json"), "core-plugins.json", &mut report) {
+        map_core_plugins(&plugins, &mut report);
+    }
+    if let Some(Value::Array(plugins)) = read_json(&config.join("community-plugins.json"), "community-plugins.json", &mut report) {
+        for id in plugins.iter().filter_map(Value::as_str) {
+            report.skipped(format!("community-plugins.json: {}", id), "Community plugins are not imported");
+        }
+    }
+    if let Some(hotkeys) = read_config(&config, "hotkeys.json", &mut report).filter(|hotkeys| !hotkeys.is_empty()) {
+        report.skipped("hotkeys.json", format!("{} custom hotkeys are not imported", hotkeys.len()));
+    }
+
+    if !options.dry_run {
+        merge_settings(settings_path, std::mem::take(&mut mapped.settings))?;
+        write_preferences(root, mapped.preferences)?;
+        write_bookmarks(root, mapped.bookmarks)?;
+    }
+    convert_notes(root, options, &mut report);
+    Ok(report)
+}
+
+/// A config file as a JSON value; missing files are None and unreadable ones
+/// are reported.
+fn read_json(path: &Path, name: &str, report: &mut ImportReport) -> Option<Value> {
+    let content = fs::read_to_string(path).ok()?;
+    match serde_json::from_str(&content) {
+        Ok(value) => Some(value),
+        Err(e) => {
+            report.error(name, e.to_string());
+            None
+        }
+    }
+}
+
+fn read_config(config: &Path, name: &str, report: &mut ImportReport) -> Option<Map<String, Value>> {
+    match read_json(&config.join(name), name, report)? {
+        Value::Object(map) => Some(map),
+        _ => {
+            report.error(name, "Expected a JSON object");
+            None
+        }
+    }
+}
+
+fn map_app(app: &Map<String, Value>, mapped: &mut Mapped, report: &mut ImportReport) {
+    if let Some(folder) = app.get("attachmentFolderPath").and_then(Value::as_str) {
+        let attachments = match folder {
+            "" | "/" => json!({ "location": "root" }),
+            "." | "./" => json!({ "location": "note" }),
+            _ if folder.starts_with("./") => json!({ "location": "subfolder", "folder": sync::normalize(folder) }),
+            _ => json!({ "location": "folder", "folder": sync::normalize(folder) }),
+        };
+        mapped.preferences.insert("attachments".to_string(), attachments);
+        stored(report, "app.json: attachmentFolderPath", "preferences.attachments");
+    }
+    if let Some(location) = app.get("newFileLocation").and_then(Value::as_str) {
+        let folder = app.get("newFileFolderPath").and_then(Value::as_str).map(sync::normalize);
+        let location = match location {
+            "current" => "current",
+            "folder" => "folder",
+            _ => "root",
+        };
+        mapped.preferences.insert("newNotes".to_string(), json!({ "location": location, "folder": folder }));
+        stored(report, "app.json: newFileLocation", "preferences.newNotes");
+    }
+    if app.contains_key("useMarkdownLinks") || app.contains_key("newLinkFormat") {
+        let markdown = app.get("useMarkdownLinks").and_then(Value::as_bool).unwrap_or(false);
+        let path = app.get("newLinkFormat").and_then(Value::as_str).unwrap_or("shortest");
+        let style = if markdown { "markdown" } else { "wikilink" };
+        mapped.preferences.insert("links".to_string(), json!({ "style": style, "path": path }));
+        stored(report, "app.json: link format", "preferences.links");
+    }
+
+    for (key, value) in app {
+        if PREFERENCE_KEYS.contains(&key.as_str()) {
+            continue;
+        }
+        match EDITOR_SETTINGS.iter().find(|(obsidian, _)| obsidian == key) {
+            Some((_, setting)) if value.is_boolean() => {
+                mapped.settings.insert(setting.to_string(), value.clone());
+                report.carried(format!("app.json: {}", key), *setting);
+            }
+            _ => report.skipped(format!("app.json: {}", key), "No Liminal equivalent"),
+        }
+    }
+}
+
+fn map_appearance(appearance: &Map<String, Value>, mapped: &mut Mapped, report: &mut ImportReport) {
+    for (key, value) in appearance {
+        let item = format!("appearance.json: {}", key);
+        match key.as_str() {
+            "baseFontSize" => match value.as_f64() {
+                Some(size) => {
+                    let size = size.round().clamp(10.0, 30.0) as u64;
+                    mapped.settings.insert("appearance.fontSize".to_string(), json!(size));
+                    report.carried(item, "appearance.fontSize");
+                }
+                None => report.error(item, "Expected a number"),
+            },
+            "theme" => {
+                let theme = match value.as_str() {
+                    Some("obsidian") => Some("dark"),
+                    Some("moonstone") => Some("light"),
+                    Some("system") => Some("system"),
+                    _ => None,
+                };
+                match theme {
+                    Some(theme) => {
+                        mapped.settings.insert("appearance.theme".to_string(), json!(theme));
+                        report.carried(item, "appearance.theme");
+                    }
+                    None => report.skipped(item, "Unknown theme"),
+                }
+            }
+            "cssTheme" if value.as_str().is_none_or(str::is_empty) => {}
+            "cssTheme" => report.skipped(item, "Community themes are not supported"),
+            "enabledCssSnippets" if value.as_array().is_none_or(Vec::is_empty) => {}
+            "enabledCssSnippets" => report.skipped(item, "CSS snippets are not supported"),
+            _ => report.skipped(item, "No Liminal equivalent"),
+        }
+    }
+}
+
+/// Copies a core plugin's settings into `.liminal/preferences.json`.
+fn map_preference(
+    file: &str,
+    config: &Map<String, Value>,
+    preference: &str,
+    keys: &[&str],
+    mapped: &mut Mapped,
+    report: &mut ImportReport,
+) {
+    let values: Map<String, Value> =
+        config.iter().filter(|(key, _)| keys.contains(&key.as_str())).map(|(k, v)| (k.clone(), v.clone())).collect();
+    mapped.preferences.insert(preference.to_string(), Value::Object(values));
+    stored(report, file, &format!("preferences.{}", preference));
+}
+
+/// Preferences and bookmarks are kept for when the app can use them. Nothing
+/// reads them yet, so they are reported as skipped rather than carried.
+fn stored(report: &mut ImportReport, item: &str, target: &str) {
+    report.skipped(item, format!("{}: stored, not yet applied", target));
+}
+
+/// Converts a bookmark or starred item; headings and blocks keep their
+/// subpath on a file bookmark.
+fn bookmark(file: &str, item: &Value, report: &mut ImportReport) -> Option<Value> {
+    let kind = item["type"].as_str().unwrap_or_default();
+    let title = item["title"].as_str();
+    let label = title.or(item["path"].as_str()).or(item["query"].as_str()).unwrap_or(kind);
+    let mut bookmark = match kind {
+        "file" | "folder" => json!({ "type": kind, "path": item["path"].as_str()? }),
+        "heading" | "block" => json!({ "type": "file", "path": item["path"].as_str()?, "subpath": item["subpath"] }),
+        "search" => json!({ "type": "search", "query": item["query"].as_str()? }),
+        "url" => json!({ "type": "url", "url": item["url"].as_str()? }),
+        "group" => {
+            let items: Vec<Value> = item["items"]
+                .as_array()
+                .into_iter()
+                .flatten()
+                .filter_map(|child| bookmark(file, child, report))
+                .collect();
+            json!({ "type": "group", "items": items })
+        }
+        _ => {
+            report.skipped(format!("{}: {}", file, label), format!("Liminal can't bookmark a {}", kind));
+            return None;
+        }
+    };
+    if let Some(title) = title {
+        bookmark["title"] = json!(title);
+    }
+    Some(bookmark)
+}
+
+fn map_core_plugins(plugins: &Value, report: &mut ImportReport) {
+    let enabled: Vec<&str> = match plugins {
+        Value::Array(ids) => ids.iter().filter_map(Value::as_str).collect(),
+        Value::Object(map) => map.iter().filter(|(_, on)| on.as_bool() == Some(true)).map(|(id, _)| id.as_str()).collect(),
+        _ => {
+            report.error("core-plugins.json", "Expected a list or an object");
+            return;
+        }
+    };
+    for id in enabled.into_iter().filter(|id| !CONFIGURED.contains(id)) {
+        let item = format!("core-plugins.json: {}", id);
+        match BUILT_IN.iter().find(|(plugin, _)| *plugin == id) {
+            Some((_, feature)) => report.carried(item, format!("Built in: {}", feature)),
+            None if id == "sync" => report.skipped(item, "Set up a Git, folder or WebDAV sync provider instead"),
+            None => report.skipped(item, "No Liminal equivalent"),
+        }
+    }
+}
+
+fn write_preferences(root: &Path, preferences: Map<String, Value>) -> Result<(), String> {
+    if preferences.is_empty() {
+        return Ok(());
+    }
+    let path = root.join(PREFERENCES_FILE);
+    let mut existing = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{}: {}", PREFERENCES_FILE, e))?,
+        Err(_) => Map::new(),
+    };
+    existing.extend(preferences);
+    write_liminal_file(&path, &Value::Object(existing))
+}
+
+/// Adds bookmarks that aren't already in `.liminal/bookmarks.json`.
+fn write_bookmarks(root: &Path, bookmarks: Vec<Value>) -> Result<(), String> {
+    if bookmarks.is_empty() {
+        return Ok(());
+    }
+    let path = root.join(BOOKMARKS_FILE);
+    let mut existing: Value = match fs::read_to_string(&path) {
+        Ok(content) => serde_json::from_str(&content).map_err(|e| format!("{}: {}", BOOKMARKS_FILE, e))?,
+        Err(_) => json!({ "items": [] }),
+    };
+    let items = existing["items"].as_array_mut().ok_or(format!("{}: expected an items list", BOOKMARKS_FILE))?;
+    for bookmark in bookmarks {
+        if !items.contains(&bookmark) {
+            items.push(bookmark);
+        }
+    }
+    write_liminal_file(&path, &existing)
+}
+
+fn write_liminal_file(path: &Path, value: &Value) -> Result<(), String> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let content = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+/// Reports, and with `convert` rewrites, notes that use comments or embeds.
+fn convert_notes(root: &Path, options: Options, report: &mut ImportReport) {
+    let mut notes = Vec::new();
+    let mut attachments: HashMap<String, Vec<String>> = HashMap::new();
+    let entries = WalkDir::new(root)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_entry(|e| e.depth() == 0 || !e.file_name().to_string_lossy().starts_with('.'))
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in entries {
+        let Ok(relative) = entry.path().strip_prefix(root) else {
+            continue;
+        };
+        let path = sync::normalize(&relative.to_string_lossy());
+        if path.ends_with(".md") {
+            notes.push(path);
+        } else {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            attachments.entry(name).or_default().push(path);
+        }
+    }
+
+    for path in notes {
+        let text = match fs::read_to_string(root.join(&path)) {
+            Ok(text) => text,
+            Err(e) => {
+                report.error(&path, e.to_string());
+                continue;
+            }
+        };
+        let converted = convert(&path, &text, &attachments);
+        for name in &converted.missing {
+            report.error(&path, format!("Attachment not found: {}", name));
+        }
+        if converted.text == text {
+            continue;
+        }
+        let summary = converted.summary();
+        if !options.convert {
+            report.skipped(&path, format!("{}; import with convert to rewrite", summary));
+        } else if options.dry_run {
+            report.carried(&path, summary);
+        } else {
+            match write_note(root, &path, &converted.text) {
+                Ok(()) => report.carried(&path, summary),
+                Err(e) => report.error(&path, e),
+            }
+        }
+    }
+}
+
+#[derive(Default)]
+struct Converted {
+    text: String,
+    comments: usize,
+    attachments: usize,
+    note_embeds: usize,
+    missing: Vec<String>,
+}
+
+impl Converted {
+    fn summary(&self) -> String {
+        [
+            (self.comments, "comment"),
+            (self.attachments, "attachment embed"),
+            (self.note_embeds, "note embed"),
+        ]
+        .into_iter()
+        .filter(|(count, _)| *count > 0)
+        .map(|(count, what)| format!("{} {}{}", count, what, if count == 1 { "" } else { "s" }))
+        .collect::<Vec<_>>()
+        .join(", ")
+    }
+}
+
+/// Rewrites `%%comments%%` as HTML comments, attachment embeds as Markdown
+/// images or links relative to the note, and note embeds as wikilinks. Code
+/// is left alone; missing attachments keep their embed.
+fn convert(note: &str, text: &str, attachments: &HashMap<String, Vec<String>>) -> Converted {
+    let mut out = Converted::default();
+    let mut fence: Option<&str> = None;
+    let mut in_comment = false;
+
+    for line in text.split_inclusive('\n') {
+        let trimmed = line.trim_start();
+        if !in_comment {
+            if let Some(marker) = fence {
+                if trimmed.starts_with(marker) {
+                    fence = None;
+                }
+                out.text.push_str(line);
+                continue;
+            }
+            if let Some(marker) = ["```", "~~~"].into_iter().find(|marker| trimmed.starts_with(marker)) {
+                fence = Some(marker);
+                out.text.push_str(line);
+                continue;
+            }
+        }
+
+        let mut rest = line;
+        while let Some(c) = rest.chars().next() {
+            if in_comment {
+                match rest.find("%%") {
+                    Some(end) => {
+                        out.text.push_str(&rest[..end]);
+                        out.text.push_str("-->");
+                        rest = &rest[end + 2..];
+                        in_comment = false;
+                    }
+                    None => {
+                        out.text.push_str(rest);
+                        rest = "";
+                    }
+                }
+            } else if c == '`' {
+                let run = rest.len() - rest.trim_start_matches('`').len();
+                let span = rest[run..].find(&rest[..run]).map_or(run, |end| run + end + run);
+                out.text.push_str(&rest[..span]);
+                rest = &rest[span..];
+            } else if let Some(after) = rest.strip_prefix("%%") {
+                out.text.push_str("<!--");
+                out.comments += 1;
+                in_comment = true;
+                rest = after;
+            } else if let Some((inner, after)) = rest.strip_prefix("![[").and_then(|after| after.split_once("]]")) {
+                match embed(inner, note, attachments) {
+                    Embed::Note(link) => {
+                        out.text.push_str(&link);
+                        out.note_embeds += 1;
+                    }
+                    Embed::Attachment(link) => {
+                        out.text.push_str(&link);
+                        out.attachments += 1;
+                    }
+                    Embed::Missing(name) => {
+                        out.text.push_str(&rest[..rest.len() - after.len()]);
+                        out.missing.push(name);
+                    }
+                }
+                rest = after;
+            } else {
+                out.text.push(c);
+                rest = &rest[c.len_utf8()..];
+            }
+        }
+    }
+    if in_comment {
+        out.text.push_str("-->");
+    }
+    out
+}
+
+enum Embed {
+    Note(String),
+    Attachment(String),
+    Missing(String),
+}
+
+fn embed(inner: &str, note: &str, attachments: &HashMap<String, Vec<String>>) -> Embed {
+    let (target, label) = match inner.split_once('|') {
+        Some((target, label)) => (target.trim(), Some(label.trim())),
+        None => (inner.trim(), None),
+    };
+    let name = target.split('#').next().unwrap_or_default();
+    let extension = Path::new(name)
+        .extension()
+        .and_then(|e| e.to_str())
+        .filter(|e| e.chars().all(|c| c.is_ascii_alphanumeric()))
+        .map(str::to_lowercase);
+    let extension = match extension.as_deref() {
+        None | Some("md") => return Embed::Note(format!("[[{}]]", inner)),
+        Some(extension) => extension.to_string(),
+    };
+
+    let wanted = sync::normalize(name);
+    let basename = wanted.rsplit('/').next().unwrap_or_default();
+    let found = attachments
+        .get(basename)
+        .and_then(|paths| paths.iter().find(|path| **path == wanted || path.ends_with(&format!("/{}", wanted))));
+    let Some(path) = found else {
+        return Embed::Missing(name.to_string());
+    };
+
+    let destination = destination(&relative_to(note, path));
+    // `|300` and `|300x200` are image sizes rather than text.
+    let text = label.filter(|label| !label.is_empty() && !label.chars().all(|c| c.is_ascii_digit() || c == 'x'));
+    if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+        Embed::Attachment(format!("![{}]({})", text.unwrap_or_default(), destination))
+    } else {
+        Embed::Attachment(format!("[{}]({})", text.unwrap_or(basename), destination))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_convert_comments_and_embeds_outside_code() {
+        let attachments = HashMap::from([
+            ("diagram one.png".to_string(), vec!["assets/diagram one.png".to_string()]),
+            ("spec.pdf".to_string(), vec!["projects/spec.pdf".to_string()]),
+        ]);
+        let text = "Intro %%hidden%% text\n\
+                    %%\nmulti\nline\n%%\n\
+                    ![[diagram one.png|300]] ![[spec.pdf]] ![[Other note#Part]] ![[gone.png]]\n\
+                    `%%code%%` and ``![[x.png]]``\n\
+                    ```\n%% fenced %%\n```\n";
+        let converted = convert("projects/plan.md", text, &attachments);
+        assert_eq!(
+            converted.text,
+            "Intro <!--hidden--> text\n\
+             <!--\nmulti\nline\n-->\n\
+             ![](<../assets/diagram one.png>) [spec.pdf](spec.pdf) [[Other note#Part]] ![[gone.png]]\n\
+             `%%code%%` and ``![[x.png]]``\n\
+             ```\n%% fenced %%\n```\n"
+        );
+        assert_eq!((converted.comments, converted.attachments, converted.note_embeds), (2, 2, 1));
+        assert_eq!(converted.missing, vec!["gone.png"]);
+        assert_eq!(converted.summary(), "2 comments, 2 attachment embeds, 1 note embed");
+    }
+
+    #[test]
+    fn test_import_maps_config_and_converts_only_when_asked() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path().join("vault");
+        let config = root.join(CONFIG_DIR);
+        fs::create_dir_all(&config).unwrap();
+        let settings_path = dir.path().join("settings.json");
+        fs::write(&settings_path, r#"{"editor.wordWrap": true}"#).unwrap();
+        fs::write(
+            config.join("app.json"),
+            r#"{"attachmentFolderPath": "./assets", "useMarkdownLinks": true, "showLineNumber": true, "vimMode": true}"#,
+        )
+        .unwrap();
+        fs::write(config.join("appearance.json"), r#"{"baseFontSize": 40, "theme": "moonstone", "cssTheme": "Minimal"}"#).unwrap();
+        fs::write(config.join("templates.json"), r#"{"folder": "Templates"}"#).unwrap();
+        fs::write(
+            config.join("bookmarks.json"),
+            r#"{"items": [{"type": "file", "path": "a.md"}, {"type": "group", "title": "G", "items": [{"type": "graph"}, {"type": "search", "query": "tag:#x"}]}]}"#,
+        )
+        .unwrap();
+        fs::write(config.join("core-plugins.json"), r#"{"graph": true, "sync": true, "canvas": true, "templates": true, "audio-recorder": false}"#).unwrap();
+        fs::write(root.join("a.md"), "Text %%note%%\n").unwrap();
+
+        let dry = import(&root, &settings_path, Options { convert: true, dry_run: true }).unwrap();
+        assert!(dry.dry_run && dry.carried.iter().any(|c| c.item == "a.md"));
+        assert!(!root.join(PREFERENCES_FILE).exists());
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "Text %%note%%\n");
+
+        let report = import(&root, &settings_path, Options { convert: false, dry_run: false }).unwrap();
+        let settings = crate::settings::read_settings_file(&settings_path).unwrap();
+        assert_eq!(settings["editor.wordWrap"], json!(true));
+        assert_eq!(settings["editor.showLineNumbers"], json!(true));
+        assert_eq!(settings["appearance.fontSize"], json!(30));
+        assert_eq!(settings["appearance.theme"], json!("light"));
+        let preferences: Value = serde_json::from_str(&fs::read_to_string(root.join(PREFERENCES_FILE)).unwrap()).unwrap();
+        assert_eq!(preferences["attachments"], json!({ "location": "subfolder", "folder": "assets" }));
+        assert_eq!(preferences["links"]["style"], json!("markdown"));
+        assert_eq!(preferences["templates"], json!({ "folder": "Templates" }));
+        let bookmarks: Value = serde_json::from_str(&fs::read_to_string(root.join(BOOKMARKS_FILE)).unwrap()).unwrap();
+        assert_eq!(
+            bookmarks["items"],
+            json!([
+                { "type": "file", "path": "a.md" },
+                { "type": "group", "title": "G", "items": [{ "type": "search", "query": "tag:#x" }] }
+            ])
+        );
+        let skipped: Vec<&str> = report.skipped.iter().map(|s| s.item.as_str()).collect();
+        for item in ["app.json: vimMode", "appearance.json: cssTheme", "bookmarks.json: graph", "core-plugins.json: sync", "core-plugins.json: canvas", "a.md"] {
+            assert!(skipped.contains(&item), "{} not skipped", item);
+        }
+        let not_applied = |item: &str| report.skipped.iter().any(|s| s.item == item && s.detail.ends_with("not yet applied"));
+        assert!(not_applied("templates.json") && not_applied("bookmarks.json"));
+        assert!(!report.carried.iter().any(|c| c.item.starts_with("app.json: attachment")));
+        assert!(report.carried.iter().any(|c| c.item == "core-plugins.json: graph"));
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "Text %%note%%\n");
+
+        import(&root, &settings_path, Options { convert: true, dry_run: false }).unwrap();
+        assert_eq!(fs::read_to_string(root.join("a.md")).unwrap(), "Text <!--note-->\n");
+        let bookmarks: Value = serde_json::from_str(&fs::read_to_string(root.join(BOOKMARKS_FILE)).unwrap()).unwrap();
+        assert_eq!(bookmarks["items"].as_array().unwrap().len(), 2);
+    }
+}
@@ -3,8 +3,12 @@ use tauri::Manager;
 mod vault;
 mod settings;
 mod plugins;
+mod frontmatter;
+mod sync;
+mod history;
+mod import;
 
-use plugins::{PluginRegistry, tts::TtsPlugin};
+use plugins::{PluginRegistry, backup::BackupPlugin, folder_sync::FolderSyncPlugin, git_sync::GitSyncPlugin, tts::{self, TtsPlugin}, webdav_sync::WebDavSyncPlugin};
 
 #[tauri::command]
 fn get_linux_accent_colour() -> String {
@@ -76,12 +80,23 @@ pub fn run() {
         .setup(|app| {
             let mut registry = PluginRegistry::<tauri::Wry>::new();
             registry.register(Box::new(TtsPlugin));
+            registry.register(Box::new(GitSyncPlugin));
+            registry.register(Box::new(FolderSyncPlugin));
+            registry.register(Box::new(WebDavSyncPlugin));
+            registry.register(Box::new(BackupPlugin));
 
             // Auto-activate for now, eventually this will be driven by settings
             registry.activate(app.handle().clone(), "core.tts").unwrap();
+            registry.activate(app.handle().clone(), "core.sync.git").unwrap();
+            registry.activate(app.handle().clone(), "core.sync.folder").unwrap();
+            registry.activate(app.handle().clone(), "core.sync.webdav").unwrap();
+            registry.activate(app.handle().clone(), "core.backup").unwrap();
 
             app.manage(registry);
 
+            let config_dir = app.path().app_config_dir()?;
+            sync::spawn_watcher(vault::vault_config_path_in(&config_dir));
+
             if std::env::var("TAURI_FORCE_DEVTOOLS").is_ok() {
                 if let Some(main) = app.get_webview_window("main") {
                     main.open_devtools();
@@ -100,6 +115,7 @@ pub fn run() {
         .plugin(tauri_plugin_clipboard_manager::init())
         .plugin(tauri_plugin_window_state::Builder::default().build())
         .plugin(tauri_plugin_notification::init())
+        .register_asynchronous_uri_scheme_protocol(tts::AUDIO_SCHEME, tts::audio_protocol)
         .invoke_handler(tauri::generate_handler![
             vault::get_vault_config,
             vault::set_vault_config,
@@ -111,6 +127,19 @@ pub fn run() {
             vault::delete_item,
             settings::get_settings,
             settings::set_setting,
+            sync::sync_pending_ops,
+            sync::sync_compact,
+            sync::sync_conflicts,
+            sync::sync_resolve_conflict,
+            sync::sync_scan,
+            history::history_list,
+            history::history_read,
+            history::history_diff,
+            history::history_restore,
+            import::import_obsidian,
+            import::import_notion,
+            import::import_enex,
+            import::import_logseq,
             get_linux_accent_colour,
             plugins::native_plugin_invoke
         ])
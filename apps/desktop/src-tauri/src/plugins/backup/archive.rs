@@ -0,0 +1,140 @@
+// Backup archives: zip, or tar compressed with zstd. Both are written and
+// read as a flat list of named entries.
+
+use std::fs::{self, File};
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use zip::write::SimpleFileOptions;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Format {
+    Zip,
+    TarZst,
+}
+
+impl Format {
+    /// `plugins.core.backup.format`: `zip` (default) or `tar.zst`.
+    pub fn from_setting(value: Option<&str>) -> Self {
+        match value {
+            Some("tar.zst") => Format::TarZst,
+            _ => Format::Zip,
+        }
+    }
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::TarZst => "tar.zst",
+        }
+    }
+
+    /// The format of an archive, by file name.
+    pub fn of(name: &str) -> Option<Self> {
+        [Format::Zip, Format::TarZst]
+            .into_iter()
+            .find(|format| name.ends_with(&format!(".{}", format.extension())))
+    }
+}
+
+enum Writer {
+    Zip(Box<zip::ZipWriter<File>>),
+    TarZst(tar::Builder<zstd::Encoder<'static, File>>),
+}
+
+/// Writes to `<name>.partial` and renames on `finish`, so an interrupted
+/// backup never looks like a complete archive.
+pub struct ArchiveWriter {
+    writer: Writer,
+    partial: PathBuf,
+    path: PathBuf,
+}
+
+impl ArchiveWriter {
+    pub fn create(path: &Path) -> Result<Self, String> {
+        let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+        let format = Format::of(name).ok_or_else(|| format!("Unknown archive format: {}", name))?;
+        let partial = path.with_file_name(format!("{}.partial", name));
+        let file = File::create(&partial).map_err(|e| e.to_string())?;
+        let writer = match format {
+            Format::Zip => Writer::Zip(Box::new(zip::ZipWriter::new(file))),
+            Format::TarZst => Writer::TarZst(tar::Builder::new(zstd::Encoder::new(file, 0).map_err(|e| e.to_string())?)),
+        };
+        Ok(Self { writer, partial, path: path.to_path_buf() })
+    }
+
+    pub fn add(&mut self, name: &str, bytes: &[u8]) -> Result<(), String> {
+        match &mut self.writer {
+            Writer::Zip(zip) => {
+                let options = SimpleFileOptions::default().compression_method(zip::CompressionMethod::Deflated);
+                zip.start_file(name, options).map_err(|e| e.to_string())?;
+                zip.write_all(bytes).map_err(|e| e.to_string())
+            }
+            Writer::TarZst(tar) => {
+                let mut header = tar::Header::new_gnu();
+                header.set_size(bytes.len() as u64);
+                header.set_mode(0o644);
+                header.set_mtime(crate::sync::now_ms() / 1000);
+                tar.append_data(&mut header, name, bytes).map_err(|e| e.to_string())
+            }
+        }
+    }
+
+    pub fn finish(self) -> Result<(), String> {
+        match self.writer {
+            Writer::Zip(zip) => {
+                zip.finish().map_err(|e| e.to_string())?;
+            }
+            Writer::TarZst(tar) => {
+                let encoder = tar.into_inner().map_err(|e| e.to_string())?;
+                encoder.finish().map_err(|e| e.to_string())?;
+            }
+        }
+        fs::rename(&self.partial, &self.path).map_err(|e| e.to_string())
+    }
+}
+
+/// Calls `visit` with the name and content of each file entry, in archive
+/// order.
+pub fn read_entries(path: &Path, mut visit: impl FnMut(&str, &mut dyn Read) -> Result<(), String>) -> Result<(), String> {
+    let name = path.file_name().and_then(|n| n.to_str()).unwrap_or_default();
+    let file = File::open(path).map_err(|e| format!("Cannot open {}: {}", name, e))?;
+    match Format::of(name) {
+        Some(Format::Zip) => {
+            let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+            for i in 0..archive.len() {
+                let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+                if entry.is_file() {
+                    let entry_name = entry.name().to_string();
+                    visit(&entry_name, &mut entry)?;
+                }
+            }
+        }
+        Some(Format::TarZst) => {
+            let decoder = zstd::Decoder::new(file).map_err(|e| e.to_string())?;
+            let mut archive = tar::Archive::new(decoder);
+            for entry in archive.entries().map_err(|e| e.to_string())? {
+                let mut entry = entry.map_err(|e| e.to_string())?;
+                if entry.header().entry_type().is_file() {
+                    let entry_name = entry.path().map_err(|e| e.to_string())?.to_string_lossy().into_owned();
+                    visit(&entry_name, &mut entry)?;
+                }
+            }
+        }
+        None => return Err(format!("Unknown archive format: {}", name)),
+    }
+    Ok(())
+}
+
+/// One entry's content, if the archive has it.
+pub fn read_entry(path: &Path, wanted: &str) -> Result<Option<Vec<u8>>, String> {
+    let mut found = None;
+    read_entries(path, |name, reader| {
+        if found.is_none() && name == wanted {
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+            found = Some(bytes);
+        }
+        Ok(())
+    })?;
+    Ok(found)
+}
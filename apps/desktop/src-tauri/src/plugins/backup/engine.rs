@@ -0,0 +1,425 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::archive::{self, ArchiveWriter, Format};
+use crate::sync::{self, content_hash};
+use crate::vault::resolve_safe_path;
+
+pub const MANIFEST: &str = "manifest.json";
+const FILES_PREFIX: &str = "files/";
+const SETTINGS_ENTRY: &str = "app/settings.json";
+const MANIFEST_VERSION: u32 = 1;
+/// After this many incremental backups the next one is full, so a restore
+/// never needs a long chain of archives.
+const MAX_CHAIN: u32 = 7;
+/// Never backed up: sync metadata belongs to this device, and Git data is a
+/// history of its own.
+const EXCLUDED: &[&str] = &[".sync", ".git"];
+const LIMINAL_DIR: &str = ".liminal";
+
+/// `plugins.core.backup.*` settings.
+#[derive(Clone, Debug)]
+pub struct BackupConfig {
+    pub enabled: bool,
+    pub dir: Option<String>,
+    pub format: Format,
+    pub interval_hours: u64,
+    pub include_liminal: bool,
+    pub include_settings: bool,
+}
+
+impl BackupConfig {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Self {
+        let get = |key: &str| settings.get(&format!("plugins.core.backup.{}", key));
+        let flag = |key: &str| get(key).and_then(|v| v.as_bool()).unwrap_or(false);
+        Self {
+            enabled: flag("enabled"),
+            dir: get("dir")
+                .and_then(|v| v.as_str())
+                .map(|s| s.trim().to_string())
+                .filter(|s| !s.is_empty()),
+            format: Format::from_setting(get("format").and_then(|v| v.as_str())),
+            interval_hours: get("intervalHours").and_then(|v| v.as_u64()).unwrap_or(24).max(1),
+            include_liminal: flag("includeLiminal"),
+            include_settings: flag("includeSettings"),
+        }
+    }
+}
+
+/// Where a file's content is: its hash, and the archive (a file name in the
+/// same folder) that stores it.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub hash: String,
+    pub size: u64,
+    pub archive: String,
+}
+
+/// `manifest.json`, the last entry of every archive: the whole vault as it
+/// was at `created_at`. An incremental archive stores only what changed since
+/// `base` and points at older archives for the rest.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub created_at: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<String>,
+    /// Incremental archives since the last full one.
+    pub depth: u32,
+    pub files: BTreeMap<String, ManifestEntry>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<ManifestEntry>,
+}
+
+impl Manifest {
+    /// Every entry to read, grouped by the archive that stores it.
+    fn by_archive(&self) -> BTreeMap<&str, BTreeMap<String, &ManifestEntry>> {
+        let mut groups: BTreeMap<&str, BTreeMap<String, &ManifestEntry>> = BTreeMap::new();
+        for (path, entry) in &self.files {
+            groups.entry(&entry.archive).or_default().insert(format!("{}{}", FILES_PREFIX, path), entry);
+        }
+        if let Some(entry) = &self.settings {
+            groups.entry(&entry.archive).or_default().insert(SETTINGS_ENTRY.to_string(), entry);
+        }
+        groups
+    }
+}
+
+/// Sent as `backup://progress` while a backup, verify or restore runs.
+#[derive(Clone, Debug, Serialize)]
+pub struct Progress {
+    pub phase: &'static str,
+    pub done: usize,
+    pub total: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BackupReport {
+    pub archive: String,
+    pub full: bool,
+    pub files: usize,
+    /// Files whose content this archive stores; the rest are in older ones.
+    pub stored: usize,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ArchiveInfo {
+    pub name: String,
+    pub path: String,
+    pub size: u64,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RestoreReport {
+    pub files: usize,
+    pub settings: bool,
+}
+
+/// Archive names start with the vault folder's name.
+fn slug(root: &Path) -> String {
+    let name = root.file_name().map(|n| n.to_string_lossy().into_owned()).unwrap_or_default();
+    let slug: String = name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    if slug.is_empty() { "vault".to_string() } else { slug }
+}
+
+/// `20250101T120000Z` in `<slug>-20250101T120000Z.zip`.
+fn stamp(ms: u64) -> String {
+    sync::format_iso(ms).replace(['-', ':'], "")
+}
+
+fn is_archive_of(name: &str, slug: &str) -> bool {
+    let Some(format) = Format::of(name) else {
+        return false;
+    };
+    let stamp = name
+        .strip_prefix(slug)
+        .and_then(|rest| rest.strip_prefix('-'))
+        .and_then(|rest| rest.strip_suffix(&format!(".{}", format.extension())));
+    stamp.is_some_and(|s| s.len() == 16 && s.as_bytes()[8] == b'T' && s.ends_with('Z'))
+}
+
+/// The vault's archives in `dir`, oldest first.
+pub fn list(root: &Path, dir: &Path) -> Result<Vec<ArchiveInfo>, String> {
+    let slug = slug(root);
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Ok(Vec::new());
+    };
+    let mut archives: Vec<ArchiveInfo> = entries
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let size = entry.metadata().ok()?.len();
+            is_archive_of(&name, &slug).then(|| ArchiveInfo { path: entry.path().to_string_lossy().into_owned(), name, size })
+        })
+        .collect();
+    archives.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(archives)
+}
+
+pub fn read_manifest(path: &Path) -> Result<Manifest, String> {
+    let bytes = archive::read_entry(path, MANIFEST)?.ok_or_else(|| format!("{} has no {}", path.display(), MANIFEST))?;
+    let manifest: Manifest = serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", MANIFEST, e))?;
+    if manifest.version > MANIFEST_VERSION {
+        return Err(format!("The backup uses a newer format (version {})", manifest.version));
+    }
+    Ok(manifest)
+}
+
+/// The latest archive to build on, unless a full backup is due.
+fn base(root: &Path, dir: &Path) -> Option<(String, Manifest)> {
+    let latest = list(root, dir).ok()?.pop()?;
+    let manifest = read_manifest(Path::new(&latest.path)).ok()?;
+    let complete = manifest.by_archive().keys().all(|name| dir.join(name).is_file());
+    (complete && manifest.depth < MAX_CHAIN).then_some((latest.name, manifest))
+}
+
+/// Vault files to back up, as vault-relative paths.
+fn vault_files(root: &Path, dir: &Path, include_liminal: bool) -> Result<Vec<String>, String> {
+    let backup_dir = dir.canonicalize().ok();
+    let walker = WalkDir::new(root).into_iter().filter_entry(|entry| {
+        let top = entry.depth() == 1 && entry.file_name().to_str().is_some_and(|name| {
+            EXCLUDED.contains(&name) || (name == LIMINAL_DIR && !include_liminal)
+        });
+        let is_backup_dir = entry.file_type().is_dir() && entry.path().canonicalize().ok() == backup_dir;
+        !top && !is_backup_dir
+    });
+    let mut files = Vec::new();
+    for entry in walker {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if entry.file_type().is_file() {
+            let relative = entry.path().strip_prefix(root).map_err(|e| e.to_string())?;
+            files.push(relative.to_string_lossy().replace('\\', "/"));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+/// Writes a timestamped archive of the vault at `root` into `config.dir`.
+/// Unless `full`, it stores only files changed since the previous archive.
+pub fn backup(
+    root: &Path,
+    settings_path: &Path,
+    config: &BackupConfig,
+    full: bool,
+    progress: &dyn Fn(Progress),
+) -> Result<BackupReport, String> {
+    let dir = PathBuf::from(config.dir.as_deref().ok_or("No backup folder is set")?);
+    fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+    let base = if full { None } else { base(root, &dir) };
+
+    let mut now = sync::now_ms();
+    let mut name = format!("{}-{}.{}", slug(root), stamp(now), config.format.extension());
+    while dir.join(&name).exists() {
+        now += 1000;
+        name = format!("{}-{}.{}", slug(root), stamp(now), config.format.extension());
+    }
+
+    let files = vault_files(root, &dir, config.include_liminal)?;
+    let mut writer = ArchiveWriter::create(&dir.join(&name))?;
+    let mut manifest = Manifest {
+        version: MANIFEST_VERSION,
+        created_at: now,
+        base: base.as_ref().map(|(name, _)| name.clone()),
+        depth: base.as_ref().map_or(0, |(_, manifest)| manifest.depth + 1),
+        files: BTreeMap::new(),
+        settings: None,
+    };
+    let mut stored = 0;
+    for (done, path) in files.iter().enumerate() {
+        let bytes = fs::read(root.join(path)).map_err(|e| format!("Cannot read {}: {}", path, e))?;
+        let hash = content_hash(&bytes);
+        let unchanged = base.as_ref().and_then(|(_, base)| base.files.get(path)).filter(|entry| entry.hash == hash);
+        let entry = match unchanged {
+            Some(entry) => entry.clone(),
+            None => {
+                writer.add(&format!("{}{}", FILES_PREFIX, path), &bytes)?;
+                stored += 1;
+                ManifestEntry { hash, size: bytes.len() as u64, archive: name.clone() }
+            }
+        };
+        manifest.files.insert(path.clone(), entry);
+        progress(Progress { phase: "backup", done: done + 1, total: files.len() });
+    }
+    if config.include_settings {
+        if let Ok(bytes) = fs::read(settings_path) {
+            writer.add(SETTINGS_ENTRY, &bytes)?;
+            manifest.settings = Some(ManifestEntry { hash: content_hash(&bytes), size: bytes.len() as u64, archive: name.clone() });
+        }
+    }
+    let content = serde_json::to_vec_pretty(&manifest).map_err(|e| e.to_string())?;
+    writer.add(MANIFEST, &content)?;
+    writer.finish()?;
+
+    Ok(BackupReport {
+        archive: dir.join(&name).to_string_lossy().into_owned(),
+        full: manifest.base.is_none(),
+        files: manifest.files.len(),
+        stored,
+    })
+}
+
+/// Reads every entry the backup needs, across the archives it builds on, and
+/// hands each one to `found` once its hash checks out.
+fn read_backup(
+    archive_path: &Path,
+    phase: &'static str,
+    progress: &dyn Fn(Progress),
+    mut found: impl FnMut(&str, Vec<u8>) -> Result<(), String>,
+) -> Result<Manifest, String> {
+    let manifest = read_manifest(archive_path)?;
+    let dir = archive_path.parent().unwrap_or(Path::new("."));
+    let groups = manifest.by_archive();
+    let total = groups.values().map(|entries| entries.len()).sum();
+    let mut done = 0;
+    for (name, entries) in groups {
+        let path = dir.join(name);
+        if !path.is_file() {
+            return Err(format!("This backup needs {}, which is missing", name));
+        }
+        let mut remaining = entries;
+        archive::read_entries(&path, |entry_name, reader| {
+            let Some(entry) = remaining.remove(entry_name) else {
+                return Ok(());
+            };
+            let mut bytes = Vec::new();
+            reader.read_to_end(&mut bytes).map_err(|e| e.to_string())?;
+            if content_hash(&bytes) != entry.hash {
+                return Err(format!("{} is corrupt in {}", entry_name, name));
+            }
+            found(entry_name, bytes)?;
+            done += 1;
+            progress(Progress { phase, done, total });
+            Ok(())
+        })?;
+        if let Some(missing) = remaining.keys().next() {
+            return Err(format!("{} is missing from {}", missing, name));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Checks that every file of the backup is present and intact.
+pub fn verify(archive_path: &Path, progress: &dyn Fn(Progress)) -> Result<Manifest, String> {
+    read_backup(archive_path, "verify", progress, |_, _| Ok(()))
+}
+
+/// Verifies the backup, then writes its files into `target`, replacing files
+/// with the same paths. With `settings_target`, saved app settings are
+/// restored there too.
+pub fn restore(
+    archive_path: &Path,
+    target: &Path,
+    settings_target: Option<&Path>,
+    progress: &dyn Fn(Progress),
+) -> Result<RestoreReport, String> {
+    verify(archive_path, progress)?;
+    let mut report = RestoreReport { files: 0, settings: false };
+    read_backup(archive_path, "restore", progress, |entry_name, bytes| {
+        let destination = match entry_name.strip_prefix(FILES_PREFIX) {
+            Some(path) => resolve_safe_path(target, path)?,
+            None => match settings_target {
+                Some(path) => path.to_path_buf(),
+                None => return Ok(()),
+            },
+        };
+        if let Some(parent) = destination.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let tmp = destination.with_file_name(format!(
+            ".{}.restore",
+            destination.file_name().map(|n| n.to_string_lossy()).unwrap_or_default()
+        ));
+        fs::write(&tmp, &bytes).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, &destination).map_err(|e| e.to_string())?;
+        if entry_name.starts_with(FILES_PREFIX) {
+            report.files += 1;
+        } else {
+            report.settings = true;
+        }
+        Ok(())
+    })?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(dir: &Path, format: Format) -> BackupConfig {
+        BackupConfig {
+            enabled: true,
+            dir: Some(dir.to_string_lossy().into_owned()),
+            format,
+            interval_hours: 24,
+            include_liminal: false,
+            include_settings: true,
+        }
+    }
+
+    #[test]
+    fn test_incremental_backup_and_restore() {
+        for format in [Format::Zip, Format::TarZst] {
+            let (vault, backups, target) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+            let root = vault.path().join("My Vault");
+            fs::create_dir_all(root.join("notes")).unwrap();
+            fs::create_dir_all(root.join(".sync")).unwrap();
+            fs::create_dir_all(root.join(".liminal/history")).unwrap();
+            fs::write(root.join("notes/a.md"), "a\n").unwrap();
+            fs::write(root.join("b.md"), "b\n").unwrap();
+            fs::write(root.join(".sync/files.json"), "{}").unwrap();
+            fs::write(root.join(".liminal/history/index.json"), "{}").unwrap();
+            let settings = vault.path().join("settings.json");
+            fs::write(&settings, "{\"theme\":\"dark\"}").unwrap();
+            let config = config(backups.path(), format);
+
+            let first = backup(&root, &settings, &config, false, &|_| {}).unwrap();
+            assert!(first.full);
+            assert_eq!((first.files, first.stored), (2, 2));
+            assert!(first.archive.ends_with(format.extension()));
+
+            fs::write(root.join("b.md"), "b changed\n").unwrap();
+            fs::write(root.join("c.md"), "c\n").unwrap();
+            fs::remove_file(root.join("notes/a.md")).unwrap();
+            // A second backup within the same second gets the next name.
+            let second = backup(&root, &settings, &config, false, &|_| {}).unwrap();
+            assert!(!second.full);
+            assert_eq!((second.files, second.stored), (2, 2));
+            fs::write(root.join("notes/a.md"), "a\n").unwrap();
+            let third = backup(&root, &settings, &config, false, &|_| {}).unwrap();
+            assert_eq!((third.files, third.stored), (3, 1));
+            assert_eq!(list(&root, backups.path()).unwrap().len(), 3);
+
+            let steps = std::cell::Cell::new(0);
+            let restored_settings = target.path().join("settings.json");
+            let report = restore(Path::new(&third.archive), &target.path().join("out"), Some(&restored_settings), &|p| {
+                steps.set(steps.get() + 1);
+                assert!(p.done <= p.total);
+            })
+            .unwrap();
+            assert_eq!((report.files, report.settings), (3, true));
+            assert_eq!(steps.get(), 8);
+            let out = target.path().join("out");
+            assert_eq!(fs::read_to_string(out.join("notes/a.md")).unwrap(), "a\n");
+            assert_eq!(fs::read_to_string(out.join("b.md")).unwrap(), "b changed\n");
+            assert_eq!(fs::read_to_string(out.join("c.md")).unwrap(), "c\n");
+            assert!(!out.join(".sync").exists() && !out.join(".liminal").exists());
+            assert_eq!(fs::read_to_string(&restored_settings).unwrap(), "{\"theme\":\"dark\"}");
+
+            // The third archive still needs the second, for b.md and c.md.
+            fs::remove_file(&second.archive).unwrap();
+            let error = verify(Path::new(&third.archive), &|_| {}).unwrap_err();
+            assert!(error.contains("missing"), "{}", error);
+            assert!(backup(&root, &settings, &config, false, &|_| {}).unwrap().full);
+        }
+    }
+}
@@ -0,0 +1,272 @@
+use super::traits::{NativeBackendPlugin, NativePluginContext, ActivePlugin, Invocable};
+use crate::sync::SyncCore;
+use engine::{BackupConfig, Progress};
+use serde_json::Value;
+use std::fs;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::time::Duration;
+use tauri::{Emitter, Runtime};
+use tokio::sync::Mutex;
+
+mod archive;
+mod engine;
+
+/// Carries `engine::Progress` while a backup, verify or restore runs.
+pub const PROGRESS_EVENT: &str = "backup://progress";
+/// How often the scheduler checks whether a backup is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+type EventSink = Arc<dyn Fn(&str, Value) + Send + Sync>;
+
+pub struct BackupPlugin;
+
+impl<R: Runtime> NativeBackendPlugin<R> for BackupPlugin {
+    fn id(&self) -> &'static str {
+        "core.backup"
+    }
+
+    fn activate(&self, ctx: NativePluginContext<R>) -> Result<ActivePlugin, String> {
+        let app_handle = ctx.app_handle.clone();
+        let events: EventSink = Arc::new(move |event: &str, payload: Value| {
+            let _ = app_handle.emit(event, payload);
+        });
+        let instance = Arc::new(BackupInstance::new(ctx.settings_path()?, ctx.vault_config_path()?, events));
+        spawn_scheduler(&instance);
+        Ok(ActivePlugin { instance })
+    }
+
+    fn deactivate(&self, _handle: ActivePlugin) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// Outcome of the last backup, for `status`.
+#[derive(Default)]
+struct BackupState {
+    last_backup_ms: Option<u64>,
+    last_error: Option<String>,
+}
+
+pub struct BackupInstance {
+    settings_path: PathBuf,
+    vault_config_path: PathBuf,
+    events: EventSink,
+    state: Arc<Mutex<BackupState>>,
+    /// Held while an archive is written or restored, so jobs never overlap.
+    busy: Arc<Mutex<()>>,
+}
+
+impl BackupInstance {
+    pub fn new(settings_path: PathBuf, vault_config_path: PathBuf, events: EventSink) -> Self {
+        Self {
+            settings_path,
+            vault_config_path,
+            events,
+            state: Arc::new(Mutex::new(BackupState::default())),
+            busy: Arc::new(Mutex::new(())),
+        }
+    }
+
+    fn config(settings_path: &Path) -> Result<BackupConfig, String> {
+        let settings = crate::settings::read_settings_file(settings_path)?;
+        Ok(BackupConfig::from_settings(&settings))
+    }
+
+    fn backup_dir(config: &BackupConfig) -> Result<PathBuf, String> {
+        config.dir.as_ref().map(PathBuf::from).ok_or_else(|| "No backup folder is set".to_string())
+    }
+
+    /// Runs `job` off the async runtime, one job at a time, reporting
+    /// progress as events.
+    async fn run<T, F>(busy: Arc<Mutex<()>>, events: EventSink, job: F) -> Result<T, String>
+    where
+        T: Send + 'static,
+        F: FnOnce(&dyn Fn(Progress)) -> Result<T, String> + Send + 'static,
+    {
+        let _busy = busy.lock().await;
+        tokio::task::spawn_blocking(move || {
+            let progress = |progress: Progress| {
+                if let Ok(payload) = serde_json::to_value(progress) {
+                    events(PROGRESS_EVENT, payload);
+                }
+            };
+            job(&progress)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    async fn backup_logic(
+        root: PathBuf,
+        settings_path: PathBuf,
+        config: BackupConfig,
+        full: bool,
+        events: EventSink,
+        state: Arc<Mutex<BackupState>>,
+        busy: Arc<Mutex<()>>,
+    ) -> Result<engine::BackupReport, String> {
+        let result =
+            Self::run(busy, events, move |progress| engine::backup(&root, &settings_path, &config, full, progress)).await;
+
+        let mut state = state.lock().await;
+        match &result {
+            Ok(_) => {
+                state.last_backup_ms = Some(crate::sync::now_ms());
+                state.last_error = None;
+            }
+            Err(e) => state.last_error = Some(e.clone()),
+        }
+        result
+    }
+
+    /// Restores into `target`, which must be new or empty, or over the vault
+    /// once the user has confirmed.
+    async fn restore_logic(
+        root: PathBuf,
+        settings_path: PathBuf,
+        payload: Value,
+        events: EventSink,
+        busy: Arc<Mutex<()>>,
+    ) -> Result<engine::RestoreReport, String> {
+        let archive = PathBuf::from(payload["archive"].as_str().ok_or("Missing archive")?);
+        let restore_settings = payload["restore_settings"].as_bool().unwrap_or(false);
+        let target = match payload["target"].as_str() {
+            Some(target) => {
+                let target = PathBuf::from(target);
+                let empty = fs::read_dir(&target).map(|mut entries| entries.next().is_none()).unwrap_or(true);
+                if !empty {
+                    return Err("Restore into a new or empty folder, or over the vault".to_string());
+                }
+                Some(target)
+            }
+            None if payload["confirm"].as_bool() == Some(true) => None,
+            None => return Err("Restoring over the vault replaces its files and needs confirmation".to_string()),
+        };
+
+        Self::run(busy, events, move |progress| {
+            let settings_target = restore_settings.then_some(settings_path.as_path());
+            let report = engine::restore(&archive, target.as_deref().unwrap_or(&root), settings_target, progress)?;
+            if target.is_none() {
+                SyncCore::open(&root).scan()?;
+            }
+            Ok(report)
+        })
+        .await
+    }
+
+    async fn status_logic(root: PathBuf, config: BackupConfig, state: Arc<Mutex<BackupState>>) -> Result<Value, String> {
+        let latest = match &config.dir {
+            Some(dir) => engine::list(&root, Path::new(dir))?.pop(),
+            None => None,
+        };
+        let state = state.lock().await;
+        Ok(serde_json::json!({
+            "enabled": config.enabled,
+            "dir": config.dir,
+            "format": config.format.extension(),
+            "interval_hours": config.interval_hours,
+            "include_liminal": config.include_liminal,
+            "include_settings": config.include_settings,
+            "latest": latest,
+            "last_backup_ms": state.last_backup_ms,
+            "last_error": state.last_error,
+        }))
+    }
+}
+
+/// Backs up once the newest archive is `intervalHours` old. Settings and the
+/// vault are re-read on every check; archive times survive restarts.
+fn spawn_scheduler(instance: &Arc<BackupInstance>) {
+    let instance: Weak<BackupInstance> = Arc::downgrade(instance);
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(CHECK_INTERVAL).await;
+            let Some(instance) = instance.upgrade() else {
+                break;
+            };
+            let Ok(config) = BackupInstance::config(&instance.settings_path) else {
+                continue;
+            };
+            let Ok(root) = crate::vault::read_vault_root(&instance.vault_config_path) else {
+                continue;
+            };
+            let Ok(dir) = BackupInstance::backup_dir(&config) else {
+                continue;
+            };
+            if !config.enabled {
+                continue;
+            }
+
+            let interval = Duration::from_secs(config.interval_hours * 60 * 60);
+            let due = engine::list(&root, &dir)
+                .ok()
+                .and_then(|mut archives| archives.pop())
+                .and_then(|latest| fs::metadata(latest.path).and_then(|m| m.modified()).ok())
+                .is_none_or(|modified| modified.elapsed().unwrap_or_default() >= interval);
+            if !due {
+                continue;
+            }
+
+            let result = BackupInstance::backup_logic(
+                root,
+                instance.settings_path.clone(),
+                config,
+                false,
+                instance.events.clone(),
+                instance.state.clone(),
+                instance.busy.clone(),
+            )
+            .await;
+            if let Err(e) = result {
+                eprintln!("Scheduled backup failed: {}", e);
+            }
+        }
+    });
+}
+
+impl Invocable for BackupInstance {
+    fn invoke(&self, method: &str, payload: Value) -> Pin<Box<dyn Future<Output = Result<Value, String>> + Send>> {
+        let method = method.to_string();
+        let settings_path = self.settings_path.clone();
+        let vault_config_path = self.vault_config_path.clone();
+        let events = self.events.clone();
+        let state = self.state.clone();
+        let busy = self.busy.clone();
+
+        Box::pin(async move {
+            let config = BackupInstance::config(&settings_path)?;
+            let root = crate::vault::read_vault_root(&vault_config_path)?;
+            match method.as_str() {
+                "status" => BackupInstance::status_logic(root, config, state).await,
+                "backup" => {
+                    let full = payload["full"].as_bool().unwrap_or(false);
+                    let report =
+                        BackupInstance::backup_logic(root, settings_path, config, full, events, state, busy).await?;
+                    serde_json::to_value(report).map_err(|e| e.to_string())
+                }
+                "list" => {
+                    let archives = engine::list(&root, &BackupInstance::backup_dir(&config)?)?;
+                    Ok(serde_json::json!({ "archives": archives }))
+                }
+                "verify" => {
+                    let archive = PathBuf::from(payload["archive"].as_str().ok_or("Missing archive")?);
+                    let manifest = BackupInstance::run(busy, events, move |progress| engine::verify(&archive, progress)).await?;
+                    Ok(serde_json::json!({
+                        "ok": true,
+                        "created_at": manifest.created_at,
+                        "files": manifest.files.len(),
+                        "settings": manifest.settings.is_some(),
+                    }))
+                }
+                "restore" => {
+                    let report = BackupInstance::restore_logic(root, settings_path, payload, events, busy).await?;
+                    serde_json::to_value(report).map_err(|e| e.to_string())
+                }
+                _ => Err(format!("Method {} not found", method)),
+            }
+        })
+    }
+}
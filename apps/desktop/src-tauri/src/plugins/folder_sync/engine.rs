@@ -0,0 +1,309 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::path::Path;
+
+use super::store::{Change, Manifest, RemoteEntry, RemoteFolder};
+use crate::sync::provider::{ProviderConfig, ProviderSettings};
+use crate::sync::{self, BaseRef, ConflictRecord, ProviderState, Reconciled, SyncCore};
+
+/// Name used for the provider's state file, settings and conflict records.
+pub const PROVIDER: &str = "folder";
+
+/// `plugins.core.sync.folder.*` settings.
+#[derive(Clone, Debug)]
+pub struct FolderConfig {
+    pub enabled: bool,
+    pub path: Option<String>,
+    /// Set when sync encryption is on.
+    pub passphrase: Option<String>,
+    pub debounce_secs: u64,
+    pub interval_secs: u64,
+}
+
+impl FolderConfig {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Self {
+        let read = ProviderSettings::new(settings, PROVIDER);
+        Self {
+            enabled: read.enabled(),
+            path: read.text("path"),
+            passphrase: sync::crypto::passphrase_from_settings(settings),
+            debounce_secs: read.secs("debounceSecs", 30),
+            interval_secs: read.secs("intervalSecs", 300),
+        }
+    }
+}
+
+impl ProviderConfig for FolderConfig {
+    fn ready(&self) -> bool {
+        self.enabled && self.path.is_some()
+    }
+
+    fn debounce_secs(&self) -> u64 {
+        self.debounce_secs
+    }
+
+    fn interval_secs(&self) -> Option<u64> {
+        Some(self.interval_secs)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub pulled: Vec<String>,
+    pub pushed: Vec<String>,
+    /// Files both sides changed that merged cleanly.
+    pub merged: Vec<String>,
+    pub conflicts: Vec<ConflictRecord>,
+}
+
+/// One sync pass: the manifest, the vault and the provider state, updated
+/// together.
+struct Pass<'a> {
+    core: &'a SyncCore,
+    remote: &'a RemoteFolder,
+    manifest: Manifest,
+    state: ProviderState,
+    device: String,
+    changes: Vec<Change>,
+}
+
+impl Pass<'_> {
+    /// Publishes this device's version of `path`, or its deletion.
+    fn push(&mut self, path: &str, bytes: Option<&[u8]>) -> Result<(), String> {
+        let hash = bytes.map(|bytes| self.remote.write_object(bytes)).transpose()?;
+        let file_id = match self.core.index()?.files.get(path) {
+            Some(record) => record.file_id.clone(),
+            None => match self.manifest.files.get(path) {
+                Some(entry) => entry.file_id.clone(),
+                None => sync::new_file_id(path),
+            },
+        };
+        self.manifest.seq += 1;
+        let entry = RemoteEntry {
+            file_id: file_id.clone(),
+            hash: hash.clone(),
+            size: bytes.map_or(0, |bytes| bytes.len() as u64),
+            seq: self.manifest.seq,
+            device: self.device.clone(),
+            modified_at: sync::now_ms(),
+        };
+        self.changes.push(Change {
+            seq: entry.seq,
+            path: path.to_string(),
+            file_id,
+            hash: hash.clone(),
+            device: self.device.clone(),
+            at: entry.modified_at,
+        });
+        self.manifest.files.insert(path.to_string(), entry);
+        self.mark_synced(path, hash);
+        Ok(())
+    }
+
+    /// Takes the remote version of `path` into the vault.
+    fn pull(&mut self, path: &str) -> Result<(), String> {
+        let hash = self.manifest.hash(path).map(String::from);
+        match &hash {
+            Some(hash) => self.core.apply_write(path, &self.remote.read_object(hash)?)?,
+            None => self.core.apply_delete(path)?,
+        }
+        self.mark_synced(path, hash);
+        Ok(())
+    }
+
+    fn mark_synced(&mut self, path: &str, hash: Option<String>) {
+        match hash {
+            Some(hash) => {
+                let seq = self.manifest.files.get(path).map(|entry| entry.seq.to_string());
+                let base = BaseRef { provider_rev: seq, content_hash: Some(hash) };
+                self.state.synced.insert(path.to_string(), base);
+            }
+            None => {
+                self.state.synced.remove(path);
+            }
+        }
+    }
+}
+
+/// Syncs the vault at `root` with the folder at `remote_path`, both ways.
+/// With a passphrase the folder holds only ciphertext.
+pub fn sync(root: &Path, remote_path: &Path, passphrase: Option<&str>) -> Result<SyncReport, String> {
+    let remote = RemoteFolder::open(remote_path, passphrase)?;
+    let (report, state) = exchange(root, &remote, PROVIDER)?;
+    let core = SyncCore::open(root);
+    core.save_provider_state(PROVIDER, &state)?;
+    core.compact(state.acked)?;
+    Ok(report)
+}
+
+/// One pass of `provider` against `remote`. Only paths changed locally since
+/// the last pass's op (`acked`) or remotely since its manifest `seq`
+/// (`cursor`) are compared. A path changed on both sides is merged, or kept
+/// as a conflict duplicate; when one side deleted it, the edit wins.
+/// Returns the provider state to save once the remote's changes are
+/// durable; nothing is saved here.
+pub fn exchange(root: &Path, remote: &RemoteFolder, provider: &str) -> Result<(SyncReport, ProviderState), String> {
+    let core = SyncCore::open(root);
+    let device = core.device()?.device_name;
+    core.scan()?;
+    let _lock = remote.lock(&device)?;
+    let manifest = remote.manifest()?;
+
+    let mut state = core.provider_state(provider)?;
+    let fresh = state.remote_id.as_deref() != Some(manifest.remote_id.as_str());
+    if fresh {
+        state = ProviderState { remote_id: Some(manifest.remote_id.clone()), ..Default::default() };
+    }
+    let index = core.index()?;
+    let (local_paths, acked) = core.changed_since(state.acked)?;
+    let mut paths: BTreeSet<String> = manifest.changed_since(state.cursor).cloned().collect();
+    if fresh {
+        paths.extend(index.files.keys().cloned());
+    } else {
+        paths.extend(local_paths);
+    }
+
+    let mut pass = Pass { core: &core, remote, manifest, state, device, changes: Vec::new() };
+    let mut report = SyncReport::default();
+    for path in paths.into_iter().filter(|path| sync::is_tracked(path)) {
+        let local = index.files.get(&path).map(|record| record.hash.clone());
+        let remote_hash = pass.manifest.hash(&path).map(String::from);
+        let base = pass.state.base_hash(&path).map(String::from);
+        if local == remote_hash {
+            pass.mark_synced(&path, local);
+            continue;
+        }
+        let read_local = || fs::read(root.join(&path)).map_err(|e| e.to_string());
+
+        if local == base {
+            pass.pull(&path)?;
+            report.pulled.push(path);
+        } else if remote_hash == base || remote_hash.is_none() {
+            let bytes = local.as_ref().map(|_| read_local()).transpose()?;
+            pass.push(&path, bytes.as_deref())?;
+            report.pushed.push(path);
+        } else if local.is_none() {
+            pass.pull(&path)?;
+            report.pulled.push(path);
+        } else {
+            let remote_bytes = remote.read_object(remote_hash.as_deref().unwrap_or_default())?;
+            // The base revision may predate this remote; merge without it.
+            let base_bytes = base.and_then(|hash| remote.read_object(&hash).ok());
+            match core.reconcile(&path, base_bytes.as_deref(), &read_local()?, &remote_bytes, provider)? {
+                Reconciled::Merged => {
+                    pass.push(&path, Some(&read_local()?))?;
+                    report.merged.push(path);
+                }
+                Reconciled::Conflict(record) => {
+                    pass.mark_synced(&path, remote_hash);
+                    let copy = fs::read(root.join(&record.conflict_path)).map_err(|e| e.to_string())?;
+                    pass.push(&record.conflict_path, Some(&copy))?;
+                    report.conflicts.push(record);
+                }
+            }
+        }
+    }
+
+    let Pass { manifest, mut state, changes, .. } = pass;
+    if !changes.is_empty() {
+        remote.save_manifest(&manifest)?;
+        remote.append_log(&changes)?;
+    }
+    state.cursor = manifest.seq;
+    state.acked = acked;
+    Ok((report, state))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(dir: &Path, path: &str) -> String {
+        fs::read_to_string(dir.join(path)).unwrap()
+    }
+
+    #[test]
+    fn test_sync_two_vaults_through_folder() {
+        let (remote, a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::create_dir_all(a.path().join("notes")).unwrap();
+        fs::write(a.path().join("notes/one.md"), "# One\n\nfirst\n\nsecond\n").unwrap();
+        fs::write(a.path().join("gone.md"), "bye\n").unwrap();
+        let report = sync(a.path(), remote.path(), None).unwrap();
+        assert_eq!(report.pushed, vec!["gone.md", "notes/one.md"]);
+
+        let report = sync(b.path(), remote.path(), None).unwrap();
+        assert_eq!(report.pulled, vec!["gone.md", "notes/one.md"]);
+        assert_eq!(read(b.path(), "notes/one.md"), "# One\n\nfirst\n\nsecond\n");
+        // Nothing changed since: nothing to compare.
+        let report = sync(b.path(), remote.path(), None).unwrap();
+        assert!(report.pulled.is_empty() && report.pushed.is_empty());
+
+        // Different paragraphs edited on each side merge.
+        fs::write(a.path().join("notes/one.md"), "# One\n\nFIRST\n\nsecond\n").unwrap();
+        fs::write(b.path().join("notes/one.md"), "# One\n\nfirst\n\nSECOND\n").unwrap();
+        sync(a.path(), remote.path(), None).unwrap();
+        let report = sync(b.path(), remote.path(), None).unwrap();
+        assert_eq!(report.merged, vec!["notes/one.md"]);
+        sync(a.path(), remote.path(), None).unwrap();
+        assert_eq!(read(a.path(), "notes/one.md"), "# One\n\nFIRST\n\nSECOND\n");
+
+        // A delete and a folder rename travel.
+        fs::remove_file(a.path().join("gone.md")).unwrap();
+        fs::rename(b.path().join("notes"), b.path().join("archive")).unwrap();
+        sync(a.path(), remote.path(), None).unwrap();
+        let report = sync(b.path(), remote.path(), None).unwrap();
+        assert_eq!(report.pulled, vec!["gone.md"]);
+        assert!(!b.path().join("gone.md").exists());
+        sync(a.path(), remote.path(), None).unwrap();
+        assert_eq!(read(a.path(), "archive/one.md"), "# One\n\nFIRST\n\nSECOND\n");
+        assert!(!a.path().join("notes/one.md").exists());
+
+        // The same line on both sides: a conflict duplicate on both devices.
+        fs::write(a.path().join("archive/one.md"), "from a\n").unwrap();
+        fs::write(b.path().join("archive/one.md"), "from b\n").unwrap();
+        sync(a.path(), remote.path(), None).unwrap();
+        let report = sync(b.path(), remote.path(), None).unwrap();
+        let conflict = &report.conflicts[0];
+        assert_eq!(read(b.path(), "archive/one.md"), "from a\n");
+        assert_eq!(read(b.path(), &conflict.conflict_path), "from b\n");
+        assert_eq!(SyncCore::open(b.path()).conflicts().unwrap(), report.conflicts);
+        sync(a.path(), remote.path(), None).unwrap();
+        assert_eq!(read(a.path(), &conflict.conflict_path), "from b\n");
+
+        let history = RemoteFolder::open(remote.path(), None).unwrap().history("archive/one.md").unwrap();
+        assert_eq!(history.len(), 2);
+        assert!(RemoteFolder::open(&remote.path().join("missing"), None).is_err());
+    }
+
+    #[test]
+    fn test_encrypted_folder_holds_only_ciphertext() {
+        let (remote, a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::create_dir_all(a.path().join("Secret Plans")).unwrap();
+        fs::write(a.path().join("Secret Plans/launch.md"), "# Launch codes\n").unwrap();
+        sync(a.path(), remote.path(), Some("hunter2")).unwrap();
+
+        for entry in walkdir::WalkDir::new(remote.path()).into_iter().filter_map(|e| e.ok()) {
+            let name = entry.path().to_string_lossy().to_string();
+            assert!(!name.contains("Secret") && !name.contains("launch"), "{}", name);
+            if entry.file_type().is_file() {
+                let bytes = fs::read(entry.path()).unwrap();
+                assert!(!bytes.windows(6).any(|w| w == b"Launch" || w == b"Secret"), "{}", name);
+            }
+        }
+
+        let wrong = sync(b.path(), remote.path(), Some("hunter3")).unwrap_err();
+        assert!(wrong.contains("Wrong passphrase"));
+        assert!(sync(b.path(), remote.path(), None).unwrap_err().contains("encrypted"));
+        sync(b.path(), remote.path(), Some("hunter2")).unwrap();
+        assert_eq!(read(b.path(), "Secret Plans/launch.md"), "# Launch codes\n");
+        let history = RemoteFolder::open(remote.path(), Some("hunter2")).unwrap().history("Secret Plans/launch.md").unwrap();
+        assert_eq!(history.len(), 1);
+
+        // A folder with plaintext data is never mixed with ciphertext.
+        let plain = tempfile::tempdir().unwrap();
+        sync(a.path(), plain.path(), None).unwrap();
+        assert!(sync(a.path(), plain.path(), Some("hunter2")).unwrap_err().contains("unencrypted"));
+    }
+}
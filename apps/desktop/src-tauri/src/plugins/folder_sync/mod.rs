@@ -0,0 +1,114 @@
+use super::traits::{NativeBackendPlugin, NativePluginContext, ActivePlugin, Invocable};
+use crate::sync::provider::ProviderRunner;
+use crate::sync::{ConflictStatus, SyncCore};
+use engine::{FolderConfig, PROVIDER};
+use serde_json::Value;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::pin::Pin;
+use std::sync::Arc;
+use tauri::Runtime;
+
+pub(crate) mod engine;
+pub(crate) mod store;
+
+pub struct FolderSyncPlugin;
+
+impl<R: Runtime> NativeBackendPlugin<R> for FolderSyncPlugin {
+    fn id(&self) -> &'static str {
+        "core.sync.folder"
+    }
+
+    fn activate(&self, ctx: NativePluginContext<R>) -> Result<ActivePlugin, String> {
+        let runner = ProviderRunner::new(
+            PROVIDER,
+            ctx.settings_path()?,
+            ctx.vault_config_path()?,
+            FolderConfig::from_settings,
+            Box::new(|root, config| Box::pin(FolderSyncInstance::sync_logic(root, config))),
+        );
+        runner.spawn_debouncer();
+        Ok(ActivePlugin { instance: Arc::new(FolderSyncInstance { runner }) })
+    }
+
+    fn deactivate(&self, _handle: ActivePlugin) -> Result<(), String> {
+        crate::sync::set_provider_enabled(PROVIDER, false);
+        Ok(())
+    }
+}
+
+pub struct FolderSyncInstance {
+    runner: Arc<ProviderRunner<FolderConfig>>,
+}
+
+impl FolderSyncInstance {
+    fn remote_path(config: &FolderConfig) -> Result<PathBuf, String> {
+        config.path.as_ref().map(PathBuf::from).ok_or_else(|| "No sync folder is set".to_string())
+    }
+
+    async fn sync_logic(root: PathBuf, config: FolderConfig) -> Result<Value, String> {
+        let remote = Self::remote_path(&config)?;
+        let passphrase = config.passphrase;
+        let report = tokio::task::spawn_blocking(move || engine::sync(&root, &remote, passphrase.as_deref()))
+            .await
+            .map_err(|e| e.to_string())??;
+        serde_json::to_value(report).map_err(|e| e.to_string())
+    }
+
+    async fn status_logic(root: PathBuf, config: FolderConfig, runner: &ProviderRunner<FolderConfig>) -> Result<Value, String> {
+        let (cursor, pending, conflicts) = tokio::task::spawn_blocking(move || {
+            let core = SyncCore::open(&root);
+            let provider_state = core.provider_state(PROVIDER)?;
+            let pending = core.pending(provider_state.acked, usize::MAX)?.ops.len();
+            let conflicts: Vec<_> = core
+                .conflicts()?
+                .into_iter()
+                .filter(|c| c.status == ConflictStatus::Unresolved)
+                .collect();
+            Ok::<_, String>((provider_state.cursor, pending, conflicts))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+        let last = runner.last_sync().await;
+        Ok(serde_json::json!({
+            "enabled": config.enabled,
+            "path": config.path,
+            "available": config.path.as_ref().is_some_and(|path| Path::new(path).is_dir()),
+            "encrypted": config.passphrase.is_some(),
+            "cursor": cursor,
+            "pending": pending,
+            "last_sync_ms": last.last_sync_ms,
+            "last_error": last.last_error,
+            "conflicts": conflicts,
+        }))
+    }
+}
+
+impl Invocable for FolderSyncInstance {
+    fn invoke(&self, method: &str, payload: Value) -> Pin<Box<dyn Future<Output = Result<Value, String>> + Send>> {
+        let method = method.to_string();
+        let runner = self.runner.clone();
+
+        Box::pin(async move {
+            let config = runner.config()?;
+            let root = runner.root()?;
+            match method.as_str() {
+                "status" => FolderSyncInstance::status_logic(root, config, &runner).await,
+                "sync" => runner.sync(root, config).await,
+                "history" => {
+                    let path = payload["path"].as_str().ok_or("Missing path")?.to_string();
+                    let remote = FolderSyncInstance::remote_path(&config)?;
+                    let history = tokio::task::spawn_blocking(move || {
+                        let remote = store::RemoteFolder::open(&remote, config.passphrase.as_deref())?;
+                        remote.history(&crate::sync::normalize(&path))
+                    })
+                    .await
+                    .map_err(|e| e.to_string())??;
+                    Ok(serde_json::json!({ "history": history }))
+                }
+                _ => Err(format!("Method {} not found", method)),
+            }
+        })
+    }
+}
@@ -0,0 +1,258 @@
+// The remote side of folder sync: a directory holding the current manifest,
+// every revision's content and a log of changes, all sealed when the remote
+// is encrypted. See docs/SYNC.md.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{ErrorKind, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use crate::sync;
+use crate::sync::crypto::{KeyParams, VaultKey, KEYS_FILE};
+
+const MANIFEST_FILE: &str = "manifest.json";
+const SEALED_MANIFEST_FILE: &str = "manifest.sealed";
+const LOG_FILE: &str = "changes.log";
+const OBJECTS_DIR: &str = "objects";
+const LOCK_FILE: &str = "sync.lock";
+const MANIFEST_VERSION: u32 = 1;
+/// A lock older than this was left by a device that stopped mid-sync.
+const STALE_LOCK: Duration = Duration::from_secs(10 * 60);
+
+/// A path's latest revision. `hash` is `None` once the file is deleted; the
+/// entry stays so other devices learn of the delete.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RemoteEntry {
+    pub file_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub size: u64,
+    /// Manifest `seq` of the change; devices pull entries past their cursor.
+    pub seq: u64,
+    pub device: String,
+    pub modified_at: u64,
+}
+
+/// `manifest.json`. `remote_id` tells devices when the folder was replaced
+/// and they must sync from scratch.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Manifest {
+    pub version: u32,
+    pub remote_id: String,
+    pub seq: u64,
+    pub files: BTreeMap<String, RemoteEntry>,
+}
+
+impl Manifest {
+    pub fn hash(&self, path: &str) -> Option<&str> {
+        self.files.get(path).and_then(|entry| entry.hash.as_deref())
+    }
+
+    /// Paths changed after `cursor`.
+    pub fn changed_since(&self, cursor: u64) -> impl Iterator<Item = &String> {
+        self.files.iter().filter(move |(_, entry)| entry.seq > cursor).map(|(path, _)| path)
+    }
+}
+
+/// One line of `changes.log`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Change {
+    pub seq: u64,
+    pub path: String,
+    pub file_id: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hash: Option<String>,
+    pub device: String,
+    pub at: u64,
+}
+
+/// Held while a device syncs; removed on drop.
+pub struct RemoteLock {
+    path: PathBuf,
+}
+
+impl Drop for RemoteLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+pub struct RemoteFolder {
+    root: PathBuf,
+    key: Option<VaultKey>,
+}
+
+impl RemoteFolder {
+    /// Opens the folder, which must already exist: a missing folder is more
+    /// likely an unmounted share than a new remote. With a passphrase, an
+    /// empty folder becomes an encrypted remote; an encrypted folder needs
+    /// the passphrase it was created with.
+    pub fn open(root: &Path, passphrase: Option<&str>) -> Result<Self, String> {
+        if !root.is_dir() {
+            return Err(format!("Sync folder {} is not available", root.display()));
+        }
+        let keys_path = root.join(KEYS_FILE);
+        let key = match (fs::read_to_string(&keys_path), passphrase) {
+            (Ok(content), Some(passphrase)) => {
+                let params: KeyParams = serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", KEYS_FILE, e))?;
+                Some(VaultKey::unlock(passphrase, &params)?)
+            }
+            (Ok(_), None) => return Err("The sync folder is encrypted; set the sync passphrase".to_string()),
+            (Err(_), Some(passphrase)) => {
+                if root.join(MANIFEST_FILE).exists() {
+                    return Err("The sync folder holds unencrypted data; use an empty folder for encrypted sync".to_string());
+                }
+                let (key, params) = VaultKey::create(passphrase)?;
+                let content = serde_json::to_string_pretty(&params).map_err(|e| e.to_string())?;
+                match OpenOptions::new().write(true).create_new(true).open(&keys_path) {
+                    Ok(mut file) => file.write_all(content.as_bytes()).map_err(|e| e.to_string())?,
+                    // Another device created the key first.
+                    Err(e) if e.kind() == ErrorKind::AlreadyExists => return Self::open(root, Some(passphrase)),
+                    Err(e) => return Err(e.to_string()),
+                }
+                Some(key)
+            }
+            (Err(_), None) => None,
+        };
+        Ok(Self { root: root.to_path_buf(), key })
+    }
+
+    pub fn lock(&self, device: &str) -> Result<RemoteLock, String> {
+        let path = self.root.join(LOCK_FILE);
+        let stale = fs::metadata(&path)
+            .and_then(|m| m.modified())
+            .is_ok_and(|modified| modified.elapsed().unwrap_or_default() > STALE_LOCK);
+        if stale {
+            let _ = fs::remove_file(&path);
+        }
+        match OpenOptions::new().write(true).create_new(true).open(&path) {
+            Ok(mut file) => {
+                let _ = write!(file, "{} {}", device, sync::now_ms());
+                Ok(RemoteLock { path })
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                let holder = fs::read_to_string(&path).unwrap_or_default();
+                let holder = holder.split_whitespace().next().unwrap_or("another device");
+                Err(format!("The sync folder is in use by {}", holder))
+            }
+            Err(e) => Err(e.to_string()),
+        }
+    }
+
+    fn manifest_path(&self) -> PathBuf {
+        self.root.join(if self.key.is_some() { SEALED_MANIFEST_FILE } else { MANIFEST_FILE })
+    }
+
+    /// The manifest, or an empty one for a new remote.
+    pub fn manifest(&self) -> Result<Manifest, String> {
+        let path = self.manifest_path();
+        if !path.exists() {
+            let remote_id = sync::new_file_id(&self.root.to_string_lossy()).replacen("f_", "r_", 1);
+            return Ok(Manifest { version: MANIFEST_VERSION, remote_id, seq: 0, files: BTreeMap::new() });
+        }
+        let mut content = fs::read(&path).map_err(|e| e.to_string())?;
+        if let Some(key) = &self.key {
+            content = key.open(&content, b"manifest")?;
+        }
+        let manifest: Manifest = serde_json::from_slice(&content).map_err(|e| format!("Invalid {}: {}", MANIFEST_FILE, e))?;
+        if manifest.version > MANIFEST_VERSION {
+            return Err(format!("The sync folder uses a newer format (version {})", manifest.version));
+        }
+        Ok(manifest)
+    }
+
+    pub fn save_manifest(&self, manifest: &Manifest) -> Result<(), String> {
+        let mut content = serde_json::to_vec_pretty(manifest).map_err(|e| e.to_string())?;
+        if let Some(key) = &self.key {
+            content = key.seal(&content, b"manifest")?;
+        }
+        write_atomic(&self.manifest_path(), &content)
+    }
+
+    /// Where content with `hash` is stored; encrypted remotes hide the hash.
+    fn object_path(&self, hash: &str) -> PathBuf {
+        let name = match &self.key {
+            Some(key) => key.object_name(hash),
+            None => hash.to_string(),
+        };
+        self.root.join(OBJECTS_DIR).join(&name[..2.min(name.len())]).join(name)
+    }
+
+    /// Stores a revision under its content hash and returns the hash.
+    pub fn write_object(&self, bytes: &[u8]) -> Result<String, String> {
+        let hash = sync::content_hash(bytes);
+        let path = self.object_path(&hash);
+        if !path.exists() {
+            fs::create_dir_all(path.parent().unwrap_or(&self.root)).map_err(|e| e.to_string())?;
+            match &self.key {
+                Some(key) => write_atomic(&path, &key.seal(bytes, hash.as_bytes())?)?,
+                None => write_atomic(&path, bytes)?,
+            }
+        }
+        Ok(hash)
+    }
+
+    /// Reads a revision, checking it against its hash.
+    pub fn read_object(&self, hash: &str) -> Result<Vec<u8>, String> {
+        if hash.len() < 2 || !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid revision {}", hash));
+        }
+        let mut bytes = fs::read(self.object_path(hash)).map_err(|e| format!("Revision {} is missing: {}", hash, e))?;
+        if let Some(key) = &self.key {
+            bytes = key.open(&bytes, hash.as_bytes())?;
+        }
+        if sync::content_hash(&bytes) != hash {
+            return Err(format!("Revision {} is corrupt", hash));
+        }
+        Ok(bytes)
+    }
+
+    pub fn append_log(&self, changes: &[Change]) -> Result<(), String> {
+        let mut lines = String::new();
+        for change in changes {
+            let line = serde_json::to_string(change).map_err(|e| e.to_string())?;
+            match &self.key {
+                Some(key) => lines.push_str(&STANDARD.encode(key.seal(line.as_bytes(), b"change")?)),
+                None => lines.push_str(&line),
+            }
+            lines.push('\n');
+        }
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.root.join(LOG_FILE))
+            .map_err(|e| e.to_string())?;
+        file.write_all(lines.as_bytes()).map_err(|e| e.to_string())
+    }
+
+    /// Every logged revision of `path`, oldest first.
+    pub fn history(&self, path: &str) -> Result<Vec<Change>, String> {
+        let content = match fs::read_to_string(self.root.join(LOG_FILE)) {
+            Ok(content) => content,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let open = |line: &str| match &self.key {
+            Some(key) => key.open(&STANDARD.decode(line).ok()?, b"change").ok(),
+            None => Some(line.as_bytes().to_vec()),
+        };
+        Ok(content
+            .lines()
+            .filter_map(|line| serde_json::from_slice::<Change>(&open(line)?).ok())
+            .filter(|change| change.path == path)
+            .collect())
+    }
+}
+
+fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), String> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, bytes).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, path).map_err(|e| e.to_string())
+}
@@ -0,0 +1,180 @@
+use super::traits::{NativeBackendPlugin, NativePluginContext, ActivePlugin, Invocable};
+use crate::sync::provider::ProviderRunner;
+use crate::sync::{ConflictStatus, SyncCore};
+use repo::{GitConfig, PROVIDER};
+use serde_json::Value;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tauri::Runtime;
+
+mod repo;
+mod sealed;
+
+pub struct GitSyncPlugin;
+
+impl<R: Runtime> NativeBackendPlugin<R> for GitSyncPlugin {
+    fn id(&self) -> &'static str {
+        "core.sync.git"
+    }
+
+    fn activate(&self, ctx: NativePluginContext<R>) -> Result<ActivePlugin, String> {
+        let runner = ProviderRunner::new(
+            PROVIDER,
+            ctx.settings_path()?,
+            ctx.vault_config_path()?,
+            GitConfig::from_settings,
+            Box::new(|root, config| Box::pin(GitSyncInstance::sync_logic(root, config))),
+        );
+        runner.spawn_debouncer();
+        Ok(ActivePlugin { instance: Arc::new(GitSyncInstance { runner }) })
+    }
+
+    fn deactivate(&self, _handle: ActivePlugin) -> Result<(), String> {
+        crate::sync::set_provider_enabled(PROVIDER, false);
+        Ok(())
+    }
+}
+
+pub struct GitSyncInstance {
+    runner: Arc<ProviderRunner<GitConfig>>,
+}
+
+impl GitSyncInstance {
+    /// Git commits the working tree rather than replaying ops, so once a
+    /// commit or sync has succeeded it has taken every op up to `last_seq`.
+    fn ack(core: &SyncCore, last_seq: u64) -> Result<(), String> {
+        let mut state = core.provider_state(PROVIDER)?;
+        state.acked = last_seq;
+        core.save_provider_state(PROVIDER, &state)?;
+        core.compact(last_seq).map(|_| ())
+    }
+
+    /// Commits, and with a remote fetches, merges and pushes, then marks the
+    /// ops logged so far as pushed. Files the merge changed are scanned
+    /// first, so they are not pushed back. With encryption the sealed mirror
+    /// is synced instead, and it keeps its own acknowledgements.
+    async fn sync_logic(root: PathBuf, config: GitConfig) -> Result<Value, String> {
+        if config.sealed() {
+            let report = tokio::task::spawn_blocking(move || {
+                let device = SyncCore::open(&root).device()?.device_name;
+                sealed::sync(&root, &config, &device)
+            })
+            .await
+            .map_err(|e| e.to_string())??;
+            return serde_json::to_value(report).map_err(|e| e.to_string());
+        }
+        let report = tokio::task::spawn_blocking(move || {
+            let core = SyncCore::open(&root);
+            let device = core.device()?.device_name;
+            let mut last_seq = core.index()?.last_seq;
+            let report = repo::sync(&root, &config, &device)?;
+            for conflict in &report.conflicts {
+                core.record_conflict(&conflict.path, &conflict.conflict_path, PROVIDER)?;
+            }
+            if config.remote.is_some() {
+                core.scan()?;
+                last_seq = core.index()?.last_seq;
+            }
+            Self::ack(&core, last_seq)?;
+            Ok::<_, String>(report)
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+        serde_json::to_value(report).map_err(|e| e.to_string())
+    }
+
+    async fn commit_logic(root: PathBuf, config: GitConfig, runner: &ProviderRunner<GitConfig>) -> Result<Option<String>, String> {
+        let _busy = runner.lock().await;
+        tokio::task::spawn_blocking(move || {
+            let core = SyncCore::open(&root);
+            let device = core.device()?.device_name;
+            let last_seq = core.index()?.last_seq;
+            let committed = repo::commit(&root, &config, &device)?;
+            // A local commit sends nothing through the sealed mirror.
+            if !config.sealed() {
+                Self::ack(&core, last_seq)?;
+            }
+            Ok(committed)
+        })
+        .await
+        .map_err(|e| e.to_string())?
+    }
+
+    async fn status_logic(root: PathBuf, config: GitConfig, runner: &ProviderRunner<GitConfig>) -> Result<Value, String> {
+        let branch = config.branch.clone();
+        let encrypted = config.sealed();
+        let (repo_status, conflicts) = tokio::task::spawn_blocking(move || {
+            let core = SyncCore::open(&root);
+            let conflicts: Vec<_> = core
+                .conflicts()?
+                .into_iter()
+                .filter(|c| c.status == ConflictStatus::Unresolved)
+                .collect();
+            let repo_path = if encrypted { sealed::mirror_path(&root) } else { root.clone() };
+            let Ok(repo) = git2::Repository::open(&repo_path) else {
+                return Ok::<_, String>((None, conflicts));
+            };
+            let head = repo.head().ok().and_then(|head| head.target()).map(|oid| oid.to_string());
+            let (ahead, behind) = repo::ahead_behind(&repo, &branch)?;
+            // The mirror only changes during a sync; count the vault changes
+            // it has not taken yet.
+            let uncommitted = if encrypted {
+                core.pending(core.provider_state(PROVIDER)?.acked, usize::MAX)?.ops.len()
+            } else {
+                repo::uncommitted(&repo)?
+            };
+            Ok((Some((head, ahead, behind, uncommitted)), conflicts))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+        let last = runner.last_sync().await;
+        let (initialized, head, ahead, behind, uncommitted) = match repo_status {
+            Some((head, ahead, behind, uncommitted)) => (true, head, ahead, behind, uncommitted),
+            None => (false, None, 0, 0, 0),
+        };
+        Ok(serde_json::json!({
+            "enabled": config.enabled,
+            "initialized": initialized,
+            "remote": config.remote,
+            "branch": config.branch,
+            "encrypted": encrypted,
+            "head": head,
+            "ahead": ahead,
+            "behind": behind,
+            "uncommitted": uncommitted,
+            "last_sync_ms": last.last_sync_ms,
+            "last_error": last.last_error,
+            "conflicts": conflicts,
+        }))
+    }
+}
+
+impl Invocable for GitSyncInstance {
+    fn invoke(&self, method: &str, payload: Value) -> Pin<Box<dyn Future<Output = Result<Value, String>> + Send>> {
+        let method = method.to_string();
+        let runner = self.runner.clone();
+
+        Box::pin(async move {
+            let config = runner.config()?;
+            let root = runner.root()?;
+            match method.as_str() {
+                "status" => GitSyncInstance::status_logic(root, config, &runner).await,
+                "sync" => runner.sync(root, config).await,
+                "commit" => {
+                    let committed = GitSyncInstance::commit_logic(root, config, &runner).await?;
+                    Ok(serde_json::json!({ "committed": committed }))
+                }
+                "resolve_conflict" => {
+                    let id = payload["id"].as_str().ok_or("Missing id")?;
+                    let delete_copy = payload.get("delete_copy").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let record = SyncCore::open(&root).resolve_conflict(id, delete_copy)?;
+                    serde_json::to_value(record).map_err(|e| e.to_string())
+                }
+                _ => Err(format!("Method {} not found", method)),
+            }
+        })
+    }
+}
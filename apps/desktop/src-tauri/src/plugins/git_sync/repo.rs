@@ -0,0 +1,457 @@
+use git2::build::CheckoutBuilder;
+use git2::{
+    AnnotatedCommit, Cred, CredentialType, FetchOptions, IndexAddOption, IndexEntry, Oid, PushOptions, RemoteCallbacks,
+    Repository, RepositoryInitOptions, Signature, StatusOptions,
+};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+
+use crate::sync::{self, provider::{ProviderConfig, ProviderSettings}};
+
+/// Name used for the provider's state file, settings and conflict records.
+pub const PROVIDER: &str = "git";
+const REMOTE_NAME: &str = "origin";
+/// Credential callbacks are retried by libgit2 until they fail; give up
+/// after this many offers.
+const MAX_AUTH_ATTEMPTS: usize = 3;
+
+/// `plugins.core.sync.git.*` settings.
+#[derive(Clone, Debug)]
+pub struct GitConfig {
+    pub enabled: bool,
+    pub remote: Option<String>,
+    pub branch: String,
+    pub username: Option<String>,
+    pub token: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Set when sync encryption is on.
+    pub passphrase: Option<String>,
+    pub debounce_secs: u64,
+    pub interval_secs: u64,
+}
+
+impl GitConfig {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Self {
+        let read = ProviderSettings::new(settings, PROVIDER);
+        Self {
+            enabled: read.enabled(),
+            remote: read.text("remote"),
+            branch: read.text("branch").unwrap_or_else(|| "main".to_string()),
+            username: read.text("username"),
+            token: read.text("token"),
+            author_name: read.text("authorName"),
+            author_email: read.text("authorEmail"),
+            passphrase: sync::crypto::passphrase_from_settings(settings),
+            debounce_secs: read.secs("debounceSecs", 30),
+            interval_secs: read.secs("intervalSecs", 300),
+        }
+    }
+
+    /// Encryption is on and there is a remote: the vault is synced through
+    /// the sealed mirror (see `sealed`) and never pushed itself.
+    pub fn sealed(&self) -> bool {
+        self.passphrase.is_some() && self.remote.is_some()
+    }
+}
+
+impl ProviderConfig for GitConfig {
+    fn ready(&self) -> bool {
+        self.enabled
+    }
+
+    fn debounce_secs(&self) -> u64 {
+        self.debounce_secs
+    }
+
+    /// Without a remote there is nothing to pull, and changes are only
+    /// committed.
+    fn interval_secs(&self) -> Option<u64> {
+        self.remote.as_ref().map(|_| self.interval_secs)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Conflict {
+    pub path: String,
+    /// This device's version, kept next to the merged file.
+    pub conflict_path: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub committed: Option<String>,
+    /// `up-to-date`, `fast-forward`, `merge`, or `none` without a remote branch.
+    pub merge: &'static str,
+    pub pushed: bool,
+    /// Files both sides changed that merged cleanly.
+    pub merged: Vec<String>,
+    pub conflicts: Vec<Conflict>,
+    pub ahead: usize,
+    pub behind: usize,
+}
+
+pub fn git_err(e: git2::Error) -> String {
+    e.message().to_string()
+}
+
+pub fn open_or_init(root: &Path, branch: &str) -> Result<Repository, String> {
+    if let Ok(repo) = Repository::open(root) {
+        return Ok(repo);
+    }
+    let mut options = RepositoryInitOptions::new();
+    options.initial_head(branch);
+    Repository::init_opts(root, &options).map_err(git_err)
+}
+
+pub fn signature<'a>(repo: &Repository, config: &GitConfig, device: &str) -> Result<Signature<'a>, String> {
+    match (&config.author_name, &config.author_email) {
+        (Some(name), Some(email)) => Signature::now(name, email),
+        _ => repo
+            .signature()
+            .or_else(|_| Signature::now(config.author_name.as_deref().unwrap_or(device), "liminal-notes@localhost")),
+    }
+    .map_err(git_err)
+}
+
+/// Stages every synced file (see `sync::is_tracked`) and commits if the
+/// tree changed.
+pub fn commit_changes(repo: &Repository, message: &str, signature: &Signature) -> Result<Option<Oid>, String> {
+    let mut index = repo.index().map_err(git_err)?;
+    let mut filter = |path: &Path, _: &[u8]| -> i32 {
+        if sync::is_tracked(&path.to_string_lossy().replace('\\', "/")) {
+            0
+        } else {
+            1
+        }
+    };
+    index.add_all(["*"], IndexAddOption::DEFAULT, Some(&mut filter)).map_err(git_err)?;
+    index.update_all(["*"], Some(&mut filter)).map_err(git_err)?;
+    index.write().map_err(git_err)?;
+    let tree_id = index.write_tree().map_err(git_err)?;
+
+    // An empty first commit would leave the vault with a history unrelated to
+    // the remote's.
+    let parent = repo.head().ok().and_then(|head| head.peel_to_commit().ok());
+    let unchanged = match &parent {
+        Some(parent) => parent.tree_id() == tree_id,
+        None => index.is_empty(),
+    };
+    if unchanged {
+        return Ok(None);
+    }
+    let tree = repo.find_tree(tree_id).map_err(git_err)?;
+    let parents: Vec<_> = parent.iter().collect();
+    repo.commit(Some("HEAD"), signature, signature, message, &tree, &parents)
+        .map(Some)
+        .map_err(git_err)
+}
+
+fn callbacks(config: &GitConfig) -> RemoteCallbacks<'_> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut attempts = 0;
+    callbacks.credentials(move |url, username, allowed| {
+        attempts += 1;
+        if attempts > MAX_AUTH_ATTEMPTS {
+            return Err(git2::Error::from_str("Authentication failed for the sync remote."));
+        }
+        let username = config.username.as_deref().or(username).unwrap_or("git");
+        if allowed.contains(CredentialType::SSH_KEY) {
+            return Cred::ssh_key_from_agent(username);
+        }
+        if allowed.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            if let Some(token) = &config.token {
+                return Cred::userpass_plaintext(username, token);
+            }
+            let git_config = git2::Config::open_default()?;
+            return Cred::credential_helper(&git_config, url, Some(username));
+        }
+        Cred::default()
+    });
+    callbacks
+}
+
+pub fn ensure_remote(repo: &Repository, url: &str) -> Result<(), String> {
+    match repo.find_remote(REMOTE_NAME) {
+        Ok(remote) if remote.url() == Some(url) => Ok(()),
+        Ok(_) => repo.remote_set_url(REMOTE_NAME, url).map_err(git_err),
+        Err(_) => repo.remote(REMOTE_NAME, url).map(|_| ()).map_err(git_err),
+    }
+}
+
+pub fn fetch(repo: &Repository, config: &GitConfig) -> Result<(), String> {
+    let mut remote = repo.find_remote(REMOTE_NAME).map_err(git_err)?;
+    let mut options = FetchOptions::new();
+    options.remote_callbacks(callbacks(config));
+    remote.fetch(&[] as &[&str], Some(&mut options), None).map_err(git_err)
+}
+
+pub fn push(repo: &Repository, config: &GitConfig) -> Result<(), String> {
+    let mut remote = repo.find_remote(REMOTE_NAME).map_err(git_err)?;
+    let mut rejected: Option<String> = None;
+    {
+        let mut callbacks = callbacks(config);
+        callbacks.push_update_reference(|_, status| {
+            if let Some(status) = status {
+                rejected = Some(status.to_string());
+            }
+            Ok(())
+        });
+        let mut options = PushOptions::new();
+        options.remote_callbacks(callbacks);
+        let refspec = format!("refs/heads/{0}:refs/heads/{0}", config.branch);
+        remote.push(&[refspec.as_str()], Some(&mut options)).map_err(git_err)?;
+    }
+    match rejected {
+        Some(status) => Err(format!("The remote rejected the push ({}). Sync again to merge its changes.", status)),
+        None => Ok(()),
+    }
+}
+
+pub fn remote_branch(branch: &str) -> String {
+    format!("refs/remotes/{}/{}", REMOTE_NAME, branch)
+}
+
+/// An index entry for `path` at stage 0, with the content and mode of `like`.
+fn resolved_entry(like: &IndexEntry, path: &str) -> IndexEntry {
+    IndexEntry {
+        ctime: git2::IndexTime::new(0, 0),
+        mtime: git2::IndexTime::new(0, 0),
+        dev: 0,
+        ino: 0,
+        mode: like.mode,
+        uid: 0,
+        gid: 0,
+        file_size: like.file_size,
+        id: like.id,
+        flags: (path.len().min(0xfff)) as u16,
+        flags_extended: 0,
+        path: path.as_bytes().to_vec(),
+    }
+}
+
+/// Resolves conflicts in a merge index. Text files get a three-way merge
+/// (`sync::merge`); if that fails, the remote version stays at the path and
+/// this device's version is added as a conflict duplicate. When one side
+/// deleted the file, the side that edited it wins.
+fn resolve_conflicts(
+    repo: &Repository,
+    index: &mut git2::Index,
+    device: &str,
+    report: &mut SyncReport,
+) -> Result<(), String> {
+    let root = repo.workdir().ok_or("The sync repository has no working directory")?.to_path_buf();
+    let entries: Vec<_> = index.conflicts().map_err(git_err)?.collect::<Result<_, _>>().map_err(git_err)?;
+    let blob = |entry: &IndexEntry| repo.find_blob(entry.id).map(|blob| blob.content().to_vec()).map_err(git_err);
+    for conflict in entries {
+        let Some(any) = conflict.our.as_ref().or(conflict.their.as_ref()).or(conflict.ancestor.as_ref()) else {
+            continue;
+        };
+        let path = String::from_utf8_lossy(&any.path).to_string();
+        index.conflict_remove(Path::new(&path)).map_err(git_err)?;
+        match (&conflict.our, &conflict.their) {
+            (Some(ours), Some(theirs)) => {
+                if let Some(ancestor) = &conflict.ancestor {
+                    if let Some(merged) = sync::merge::merge_file(&path, &blob(ancestor)?, &blob(ours)?, &blob(theirs)?) {
+                        let mut entry = resolved_entry(theirs, &path);
+                        entry.id = repo.blob(&merged).map_err(git_err)?;
+                        entry.file_size = merged.len() as u32;
+                        index.add(&entry).map_err(git_err)?;
+                        report.merged.push(path);
+                        continue;
+                    }
+                }
+                index.add(&resolved_entry(theirs, &path)).map_err(git_err)?;
+                let conflict_path = sync::conflict_path(&root, &path, device, sync::now_ms());
+                index.add(&resolved_entry(ours, &conflict_path)).map_err(git_err)?;
+                report.conflicts.push(Conflict { path, conflict_path });
+            }
+            (Some(kept), None) | (None, Some(kept)) => {
+                index.add(&resolved_entry(kept, &path)).map_err(git_err)?;
+            }
+            (None, None) => {}
+        }
+    }
+    Ok(())
+}
+
+/// Brings the remote branch into the local one. The working tree matches
+/// HEAD at this point, so checkouts only touch files the remote changed.
+fn merge_remote(
+    repo: &Repository,
+    config: &GitConfig,
+    signature: &Signature,
+    device: &str,
+    report: &mut SyncReport,
+) -> Result<&'static str, String> {
+    let Ok(remote_ref) = repo.find_reference(&remote_branch(&config.branch)) else {
+        return Ok("none");
+    };
+    let remote_commit: AnnotatedCommit = repo.reference_to_annotated_commit(&remote_ref).map_err(git_err)?;
+    let (analysis, _) = repo.merge_analysis(&[&remote_commit]).map_err(git_err)?;
+    let target = repo.find_commit(remote_commit.id()).map_err(git_err)?;
+    let local_branch = format!("refs/heads/{}", config.branch);
+
+    if analysis.is_up_to_date() {
+        return Ok("up-to-date");
+    }
+    if analysis.is_fast_forward() || analysis.is_unborn() {
+        repo.checkout_tree(target.as_object(), Some(CheckoutBuilder::new().safe()))
+            .map_err(git_err)?;
+        repo.reference(&local_branch, target.id(), true, "sync: fast-forward")
+            .map_err(git_err)?;
+        repo.set_head(&local_branch).map_err(git_err)?;
+        return Ok("fast-forward");
+    }
+
+    let local = repo.head().and_then(|head| head.peel_to_commit()).map_err(git_err)?;
+    let mut index = repo.merge_commits(&local, &target, None).map_err(git_err)?;
+    if index.has_conflicts() {
+        resolve_conflicts(repo, &mut index, device, report)?;
+    }
+    let tree_id = index.write_tree_to(repo).map_err(git_err)?;
+    let tree = repo.find_tree(tree_id).map_err(git_err)?;
+    repo.checkout_tree(tree.as_object(), Some(CheckoutBuilder::new().safe()))
+        .map_err(git_err)?;
+    let message = match report.conflicts.len() {
+        0 => "Merge remote changes".to_string(),
+        n => format!("Merge remote changes ({} conflict{})", n, if n == 1 { "" } else { "s" }),
+    };
+    repo.commit(Some("HEAD"), signature, signature, &message, &tree, &[&local, &target])
+        .map_err(git_err)?;
+    Ok("merge")
+}
+
+/// Commits ahead of and behind the remote-tracking branch, as of the last
+/// fetch. Without a remote branch, every local commit is ahead.
+pub fn ahead_behind(repo: &Repository, branch: &str) -> Result<(usize, usize), String> {
+    let Some(local) = repo.head().ok().and_then(|head| head.target()) else {
+        return Ok((0, 0));
+    };
+    match repo.find_reference(&remote_branch(branch)).ok().and_then(|r| r.target()) {
+        Some(remote) => repo.graph_ahead_behind(local, remote).map_err(git_err),
+        None => {
+            let mut walk = repo.revwalk().map_err(git_err)?;
+            walk.push(local).map_err(git_err)?;
+            Ok((walk.count(), 0))
+        }
+    }
+}
+
+/// Synced files that differ from HEAD.
+pub fn uncommitted(repo: &Repository) -> Result<usize, String> {
+    let mut options = StatusOptions::new();
+    options.include_untracked(true).recurse_untracked_dirs(true);
+    let statuses = repo.statuses(Some(&mut options)).map_err(git_err)?;
+    Ok(statuses
+        .iter()
+        .filter(|entry| entry.path().is_some_and(sync::is_tracked))
+        .filter(|entry| !entry.status().is_ignored())
+        .count())
+}
+
+/// Commits local changes without touching the remote.
+pub fn commit(root: &Path, config: &GitConfig, device: &str) -> Result<Option<String>, String> {
+    let repo = open_or_init(root, &config.branch)?;
+    let signature = signature(&repo, config, device)?;
+    let message = format!("Vault changes from {}", device);
+    Ok(commit_changes(&repo, &message, &signature)?.map(|oid| oid.to_string()))
+}
+
+/// One sync cycle of the vault repository: commit, fetch, merge, push.
+/// Sealed configs go through `sealed::sync` instead.
+pub fn sync(root: &Path, config: &GitConfig, device: &str) -> Result<SyncReport, String> {
+    let repo = open_or_init(root, &config.branch)?;
+    let signature = signature(&repo, config, device)?;
+    let mut report = SyncReport {
+        committed: commit_changes(&repo, &format!("Vault changes from {}", device), &signature)?.map(|oid| oid.to_string()),
+        merge: "none",
+        ..SyncReport::default()
+    };
+
+    if let Some(url) = &config.remote {
+        ensure_remote(&repo, url)?;
+        fetch(&repo, config)?;
+        report.merge = merge_remote(&repo, config, &signature, device, &mut report)?;
+        if ahead_behind(&repo, &config.branch)?.0 > 0 {
+            push(&repo, config)?;
+            report.pushed = true;
+        }
+    }
+    (report.ahead, report.behind) = ahead_behind(&repo, &config.branch)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn config(remote: &Path) -> GitConfig {
+        let mut settings = HashMap::new();
+        settings.insert("plugins.core.sync.git.remote".to_string(), Value::from(remote.to_string_lossy()));
+        settings.insert("plugins.core.sync.git.authorName".to_string(), Value::from("Test"));
+        settings.insert("plugins.core.sync.git.authorEmail".to_string(), Value::from("test@example.com"));
+        GitConfig::from_settings(&settings)
+    }
+
+    #[test]
+    fn test_sync_through_bare_remote() {
+        let (remote, a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        Repository::init_bare(remote.path()).unwrap();
+        let config = config(remote.path());
+        let read = |dir: &Path, path: &str| fs::read_to_string(dir.join(path)).unwrap();
+
+        fs::write(a.path().join("note.md"), "one\n").unwrap();
+        fs::create_dir_all(a.path().join(".sync")).unwrap();
+        fs::write(a.path().join(".sync/device.json"), "{}").unwrap();
+        let report = sync(a.path(), &config, "A").unwrap();
+        assert!(report.committed.is_some() && report.pushed);
+        assert_eq!((report.ahead, report.behind), (0, 0));
+
+        let report = sync(b.path(), &config, "B").unwrap();
+        assert_eq!(report.merge, "fast-forward");
+        assert_eq!(read(b.path(), "note.md"), "one\n");
+        assert!(!b.path().join(".sync").exists());
+
+        // Edits to different files merge cleanly.
+        fs::write(a.path().join("other.md"), "a\n").unwrap();
+        fs::write(b.path().join("note.md"), "two\n").unwrap();
+        sync(b.path(), &config, "B").unwrap();
+        let report = sync(a.path(), &config, "A").unwrap();
+        assert_eq!((report.merge, report.conflicts.len()), ("merge", 0));
+        assert_eq!(read(a.path(), "note.md"), "two\n");
+
+        // Both add a tag on the same line: git conflicts, the note merge doesn't.
+        fs::write(a.path().join("note.md"), "---\ntags: [x]\n---\ntwo\n").unwrap();
+        sync(a.path(), &config, "A").unwrap();
+        sync(b.path(), &config, "B").unwrap();
+        fs::write(a.path().join("note.md"), "---\ntags: [x, a]\n---\ntwo\n").unwrap();
+        fs::write(b.path().join("note.md"), "---\ntags: [x, b]\n---\ntwo\n").unwrap();
+        sync(a.path(), &config, "A").unwrap();
+        let report = sync(b.path(), &config, "B").unwrap();
+        assert_eq!((report.merged.len(), report.conflicts.len()), (1, 0));
+        assert_eq!(read(b.path(), "note.md"), "---\ntags: [x, b, a]\n---\ntwo\n");
+        sync(a.path(), &config, "A").unwrap();
+
+        // The same line edited on both: the remote version wins the path.
+        fs::write(a.path().join("note.md"), "from a\n").unwrap();
+        fs::write(b.path().join("note.md"), "from b\n").unwrap();
+        sync(a.path(), &config, "A").unwrap();
+        let report = sync(b.path(), &config, "B").unwrap();
+        assert_eq!(report.conflicts.len(), 1);
+        let conflict = &report.conflicts[0];
+        assert!(conflict.conflict_path.starts_with("note (Conflict — B — "));
+        assert_eq!(read(b.path(), "note.md"), "from a\n");
+        assert_eq!(read(b.path(), &conflict.conflict_path), "from b\n");
+        assert_eq!(read(b.path(), "other.md"), "a\n");
+
+        let report = sync(a.path(), &config, "A").unwrap();
+        assert_eq!(read(a.path(), &conflict.conflict_path), "from b\n");
+        assert_eq!((report.ahead, report.behind), (0, 0));
+        assert_eq!(uncommitted(&open_or_init(a.path(), "main").unwrap()).unwrap(), 0);
+    }
+}
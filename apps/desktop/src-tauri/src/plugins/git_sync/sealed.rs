@@ -0,0 +1,175 @@
+// Git sync with encryption on. The vault is not pushed; a mirror repository
+// in `.sync/git-mirror/` holds the folder provider's sealed layout (keys,
+// sealed manifest, sealed objects and change log) and is pushed instead.
+// Every sync starts from the remote branch and publishes by fast-forward, so
+// the manifest never needs a git merge: a rejected push starts over.
+
+use git2::build::CheckoutBuilder;
+use git2::Repository;
+use serde::Serialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::repo::{self, git_err, GitConfig, PROVIDER};
+use crate::plugins::folder_sync::{engine, store::RemoteFolder};
+use crate::sync::{crypto::KEYS_FILE, SyncCore, SYNC_DIR};
+
+const MIRROR_DIR: &str = "git-mirror";
+/// Only guards a pass on this device, so it is never committed.
+const LOCK_FILE: &str = "sync.lock";
+/// Rounds of fetch, sync and push before giving up on a busy remote.
+const MAX_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Serialize)]
+pub struct SealedReport {
+    #[serde(flatten)]
+    pub files: engine::SyncReport,
+    /// Mirror commit this sync pushed.
+    pub committed: Option<String>,
+}
+
+pub fn mirror_path(root: &Path) -> PathBuf {
+    root.join(SYNC_DIR).join(MIRROR_DIR)
+}
+
+fn open_mirror(root: &Path, branch: &str) -> Result<Repository, String> {
+    let path = mirror_path(root);
+    fs::create_dir_all(&path).map_err(|e| e.to_string())?;
+    let repo = repo::open_or_init(&path, branch)?;
+    let exclude = repo.path().join("info").join("exclude");
+    fs::create_dir_all(repo.path().join("info")).map_err(|e| e.to_string())?;
+    fs::write(exclude, format!("{}\n", LOCK_FILE)).map_err(|e| e.to_string())?;
+    Ok(repo)
+}
+
+/// Moves the mirror to the remote branch, dropping whatever an unpublished
+/// attempt left behind. Without a remote branch the mirror is kept.
+fn reset_to_remote(repo: &Repository, branch: &str) -> Result<(), String> {
+    let Ok(reference) = repo.find_reference(&repo::remote_branch(branch)) else {
+        return Ok(());
+    };
+    let target = reference.peel_to_commit().map_err(git_err)?;
+    repo.checkout_tree(target.as_object(), Some(CheckoutBuilder::new().force().remove_untracked(true)))
+        .map_err(git_err)?;
+    let local_branch = format!("refs/heads/{}", branch);
+    repo.reference(&local_branch, target.id(), true, "sync: reset to remote")
+        .map_err(git_err)?;
+    repo.set_head(&local_branch).map_err(git_err)
+}
+
+/// A branch that already holds files but no keys is a plain vault; sealed
+/// objects are never mixed into it.
+fn check_sealed(dir: &Path) -> Result<(), String> {
+    if dir.join(KEYS_FILE).exists() {
+        return Ok(());
+    }
+    let plain = fs::read_dir(dir)
+        .map_err(|e| e.to_string())?
+        .filter_map(|entry| entry.ok())
+        .any(|entry| entry.file_name() != ".git" && entry.file_name() != LOCK_FILE);
+    if plain {
+        return Err("The Git remote holds unencrypted data; use an empty repository or branch for encrypted sync".to_string());
+    }
+    Ok(())
+}
+
+/// Syncs the vault with the sealed mirror on the remote, both ways. The
+/// provider state is only saved once the push has landed, so a rejected or
+/// failed push leaves every local change to be sent again.
+pub fn sync(root: &Path, config: &GitConfig, device: &str) -> Result<SealedReport, String> {
+    let (Some(url), Some(passphrase)) = (&config.remote, &config.passphrase) else {
+        return Err("Encrypted Git sync needs a remote and a passphrase".to_string());
+    };
+    let repo = open_mirror(root, &config.branch)?;
+    repo::ensure_remote(&repo, url)?;
+    let signature = repo::signature(&repo, config, device)?;
+    let dir = mirror_path(root);
+
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        repo::fetch(&repo, config)?;
+        reset_to_remote(&repo, &config.branch)?;
+        check_sealed(&dir)?;
+        let remote = RemoteFolder::open(&dir, Some(passphrase))?;
+        let (files, state) = engine::exchange(root, &remote, PROVIDER)?;
+        let committed = repo::commit_changes(&repo, &format!("Sealed changes from {}", device), &signature)?;
+        if repo::ahead_behind(&repo, &config.branch)?.0 > 0 {
+            match repo::push(&repo, config) {
+                Ok(()) => {}
+                Err(_) if attempt < MAX_ATTEMPTS => continue,
+                Err(e) => return Err(e),
+            }
+        }
+
+        let core = SyncCore::open(root);
+        core.save_provider_state(PROVIDER, &state)?;
+        core.compact(state.acked)?;
+        return Ok(SealedReport { files, committed: committed.map(|oid| oid.to_string()) });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+    use std::collections::HashMap;
+
+    fn config(remote: &Path, passphrase: Option<&str>) -> GitConfig {
+        let mut settings = HashMap::new();
+        settings.insert("plugins.core.sync.git.remote".to_string(), Value::from(remote.to_string_lossy()));
+        settings.insert("plugins.core.sync.git.authorName".to_string(), Value::from("Test"));
+        settings.insert("plugins.core.sync.git.authorEmail".to_string(), Value::from("test@example.com"));
+        let mut config = GitConfig::from_settings(&settings);
+        config.passphrase = passphrase.map(String::from);
+        config
+    }
+
+    #[test]
+    fn test_sealed_mirror_holds_only_ciphertext() {
+        let (remote, a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        let bare = Repository::init_bare(remote.path()).unwrap();
+        let sealed = config(remote.path(), Some("hunter2"));
+        let read = |dir: &Path, path: &str| fs::read_to_string(dir.join(path)).unwrap();
+
+        fs::create_dir_all(a.path().join("Secret Plans")).unwrap();
+        fs::write(a.path().join("Secret Plans/launch.md"), "# Launch codes\n").unwrap();
+        let report = sync(a.path(), &sealed, "A").unwrap();
+        assert_eq!(report.files.pushed, vec!["Secret Plans/launch.md"]);
+        assert!(report.committed.is_some());
+
+        let tree = bare.find_reference("refs/heads/main").unwrap().peel_to_tree().unwrap();
+        tree.walk(git2::TreeWalkMode::PreOrder, |dir, entry| {
+            let name = format!("{}{}", dir, entry.name().unwrap_or_default());
+            assert!(!name.contains("Secret") && !name.contains("launch") && !name.contains(LOCK_FILE), "{}", name);
+            if let Ok(blob) = bare.find_blob(entry.id()) {
+                assert!(!blob.content().windows(6).any(|w| w == b"Launch" || w == b"Secret"), "{}", name);
+            }
+            git2::TreeWalkResult::Ok
+        })
+        .unwrap();
+
+        let wrong = sync(b.path(), &config(remote.path(), Some("hunter3")), "B").unwrap_err();
+        assert!(wrong.contains("Wrong passphrase"));
+        sync(b.path(), &sealed, "B").unwrap();
+        assert_eq!(read(b.path(), "Secret Plans/launch.md"), "# Launch codes\n");
+
+        // Both sides change: each sync builds on the other's push.
+        fs::write(a.path().join("Secret Plans/launch.md"), "# Launch codes\n\nfrom a\n").unwrap();
+        fs::write(b.path().join("other.md"), "from b\n").unwrap();
+        sync(b.path(), &sealed, "B").unwrap();
+        let report = sync(a.path(), &sealed, "A").unwrap();
+        assert_eq!(report.files.pulled, vec!["other.md"]);
+        assert_eq!(report.files.pushed, vec!["Secret Plans/launch.md"]);
+        sync(b.path(), &sealed, "B").unwrap();
+        assert_eq!(read(b.path(), "Secret Plans/launch.md"), "# Launch codes\n\nfrom a\n");
+        assert_eq!(read(a.path(), "other.md"), "from b\n");
+
+        // A branch holding a plain vault is never mixed with ciphertext.
+        let plain = tempfile::tempdir().unwrap();
+        Repository::init_bare(plain.path()).unwrap();
+        repo::sync(a.path(), &config(plain.path(), None), "A").unwrap();
+        let refused = sync(b.path(), &config(plain.path(), Some("hunter2")), "B").unwrap_err();
+        assert!(refused.contains("unencrypted"));
+    }
+}
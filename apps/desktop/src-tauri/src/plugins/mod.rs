@@ -5,7 +5,11 @@ use serde::Serialize;
 use serde_json::Value;
 
 mod traits;
+pub mod backup;
+pub mod folder_sync;
+pub mod git_sync;
 pub mod tts;
+pub mod webdav_sync;
 
 pub use traits::{NativeBackendPlugin, NativePluginContext, ActivePlugin, Invocable};
 
@@ -1,6 +1,7 @@
-use tauri::{AppHandle, Runtime};
+use tauri::{AppHandle, Manager, Runtime};
 use serde_json::Value;
 use std::future::Future;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -25,6 +26,23 @@ pub struct NativePluginContext<R: Runtime> {
     pub plugin_id: &'static str,
 }
 
+impl<R: Runtime> NativePluginContext<R> {
+    /// Path of the app settings file. Plugins keep their keys under
+    /// `plugins.<pluginId>.*` and re-read it when needed, as the user may
+    /// change settings while the plugin is active.
+    pub fn settings_path(&self) -> Result<PathBuf, String> {
+        let config_dir = self.app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+        Ok(crate::settings::settings_path_in(&config_dir))
+    }
+
+    /// Path of `vault.json`. The open vault can change while a plugin is
+    /// active, so plugins resolve the root per request.
+    pub fn vault_config_path(&self) -> Result<PathBuf, String> {
+        let config_dir = self.app_handle.path().app_config_dir().map_err(|e| e.to_string())?;
+        Ok(crate::vault::vault_config_path_in(&config_dir))
+    }
+}
+
 impl<R: Runtime> Clone for NativePluginContext<R> {
     fn clone(&self) -> Self {
         Self {
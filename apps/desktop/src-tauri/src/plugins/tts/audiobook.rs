@@ -0,0 +1,308 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use super::export::VorbisComments;
+use super::model::now_millis;
+use crate::frontmatter::{self, Frontmatter};
+
+#[derive(Clone, Default)]
+pub struct AudiobookProgress {
+    pub status: String,
+    pub job_id: String,
+    pub chapter: usize,
+    pub total_chapters: usize,
+    pub chapter_title: String,
+    pub encoded_samples: u64,
+    pub total_samples: u64,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Chapter {
+    /// Vault-relative path of the note.
+    pub note: String,
+    pub title: String,
+    /// WAV for the chapter in the job's audio directory, set once it has
+    /// been synthesized.
+    #[serde(default)]
+    pub audio: Option<String>,
+    #[serde(default)]
+    pub samples: u64,
+}
+
+/// A batch job persisted under `jobs/`, so a cancelled or failed run can be
+/// resumed without resending the request.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct AudiobookJob {
+    pub id: String,
+    pub title: String,
+    pub voice: String,
+    pub speed: f32,
+    pub destination: String,
+    pub chapters: Vec<Chapter>,
+    pub status: String,
+    pub created_at: u64,
+}
+
+pub fn jobs_dir(tts_dir: &Path) -> PathBuf {
+    tts_dir.join("jobs")
+}
+
+fn job_path(tts_dir: &Path, id: &str) -> PathBuf {
+    jobs_dir(tts_dir).join(format!("{}.json", id))
+}
+
+/// Chapter audio of a job, kept outside the cache until the book is encoded.
+pub fn job_audio_dir(tts_dir: &Path, id: &str) -> PathBuf {
+    jobs_dir(tts_dir).join(id)
+}
+
+pub fn chapter_audio_path(tts_dir: &Path, id: &str, index: usize) -> PathBuf {
+    job_audio_dir(tts_dir, id).join(format!("{:04}.wav", index))
+}
+
+/// Same notes, voice, speed and destination give the same job, so
+/// resubmitting a request picks up where the previous run stopped.
+pub fn job_id(notes: &[String], voice: &str, speed: f32, destination: &str) -> String {
+    let mut hasher = Sha256::new();
+    for note in notes {
+        hasher.update(note);
+        hasher.update([0]);
+    }
+    hasher.update(voice);
+    hasher.update([0]);
+    hasher.update(speed.to_string());
+    hasher.update([0]);
+    hasher.update(destination);
+    hex::encode(&hasher.finalize()[..8])
+}
+
+impl AudiobookJob {
+    pub fn new(title: String, notes: Vec<String>, voice: String, speed: f32, destination: String) -> Self {
+        let id = job_id(&notes, &voice, speed, &destination);
+        let chapters = notes
+            .into_iter()
+            .map(|note| Chapter {
+                title: note_stem(&note),
+                note,
+                audio: None,
+                samples: 0,
+            })
+            .collect();
+        Self {
+            id,
+            title,
+            voice,
+            speed,
+            destination,
+            chapters,
+            status: "pending".to_string(),
+            created_at: now_millis(),
+        }
+    }
+
+    pub fn load(tts_dir: &Path, id: &str) -> Result<Self, String> {
+        let content = std::fs::read_to_string(job_path(tts_dir, id))
+            .map_err(|_| format!("Audiobook job {} not found", id))?;
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+
+    pub fn save(&self, tts_dir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(jobs_dir(tts_dir)).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        std::fs::write(job_path(tts_dir, &self.id), content).map_err(|e| e.to_string())
+    }
+
+    pub fn list(tts_dir: &Path) -> Vec<Self> {
+        let mut jobs: Vec<Self> = std::fs::read_dir(jobs_dir(tts_dir))
+            .into_iter()
+            .flatten()
+            .flatten()
+            .filter_map(|entry| std::fs::read_to_string(entry.path()).ok())
+            .filter_map(|content| serde_json::from_str(&content).ok())
+            .collect();
+        jobs.sort_by_key(|job| job.created_at);
+        jobs
+    }
+
+    pub fn completed_chapters(&self) -> usize {
+        self.chapters.iter().filter(|c| c.audio.is_some()).count()
+    }
+}
+
+fn note_stem(note: &str) -> String {
+    Path::new(note)
+        .file_stem()
+        .map(|s| s.to_string_lossy().to_string())
+        .unwrap_or_else(|| note.to_string())
+}
+
+/// Markdown notes under `folder`, in path order, skipping hidden entries.
+pub fn collect_notes(vault_root: &Path, folder: &str) -> Result<Vec<String>, String> {
+    let dir = crate::vault::resolve_safe_path(vault_root, folder)?;
+    if !dir.is_dir() {
+        return Err(format!("Folder '{}' does not exist", folder));
+    }
+
+    let mut notes = Vec::new();
+    let walker = WalkDir::new(&dir)
+        .into_iter()
+        .filter_entry(|e| e.path() == dir || !e.file_name().to_string_lossy().starts_with('.'));
+    for entry in walker {
+        let entry = entry.map_err(|e| e.to_string())?;
+        if entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "md") {
+            let relative = entry
+                .path()
+                .strip_prefix(vault_root)
+                .map_err(|e| e.to_string())?
+                .to_string_lossy()
+                .replace('\\', "/");
+            notes.push(relative);
+        }
+    }
+    notes.sort();
+    Ok(notes)
+}
+
+/// Spoken text of a note: the body without frontmatter, prefixed with its title.
+pub fn chapter_text(title: &str, note_text: &str) -> String {
+    let (_, body) = frontmatter::split(note_text);
+    format!("{}.\n\n{}", title, body.trim())
+}
+
+/// Frontmatter `title`, or the chapter's current title (the file name).
+pub fn chapter_title(current: &str, note_text: &str) -> String {
+    Frontmatter::from_note(note_text)
+        .get_str("title")
+        .unwrap_or_else(|| current.to_string())
+}
+
+fn format_timestamp(samples: u64, sample_rate: u32) -> String {
+    let millis = samples * 1000 / sample_rate as u64;
+    format!(
+        "{:02}:{:02}:{:02}.{:03}",
+        millis / 3_600_000,
+        millis / 60_000 % 60,
+        millis / 1000 % 60,
+        millis % 1000
+    )
+}
+
+/// Cue sheet `INDEX` time: minutes, seconds and 1/75 s frames.
+fn format_cue_time(samples: u64, sample_rate: u32) -> String {
+    let frames = samples * 75 / sample_rate as u64;
+    format!("{:02}:{:02}:{:02}", frames / (75 * 60), frames / 75 % 60, frames % 75)
+}
+
+/// Adds `CHAPTERxxx` / `CHAPTERxxxNAME` comments for each chapter start.
+pub fn chapter_comments(comments: &mut VorbisComments, chapters: &[Chapter], sample_rate: u32) {
+    let mut start = 0;
+    for (i, chapter) in chapters.iter().enumerate() {
+        comments.push(&format!("CHAPTER{:03}", i + 1), &format_timestamp(start, sample_rate));
+        comments.push(&format!("CHAPTER{:03}NAME", i + 1), &chapter.title);
+        start += chapter.samples;
+    }
+}
+
+/// Writes `<name>.cue` and `<name>.chapters.json` next to the audio file.
+pub fn write_chapter_maps(destination: &Path, title: &str, chapters: &[Chapter], sample_rate: u32) -> Result<(), String> {
+    let file_name = destination
+        .file_name()
+        .ok_or("Invalid destination")?
+        .to_string_lossy();
+    let quote = |s: &str| s.replace('"', "'");
+
+    let mut cue = format!("TITLE \"{}\"\nFILE \"{}\" WAVE\n", quote(title), quote(&file_name));
+    let mut map = Vec::new();
+    let mut start = 0;
+    for (i, chapter) in chapters.iter().enumerate() {
+        cue.push_str(&format!(
+            "  TRACK {:02} AUDIO\n    TITLE \"{}\"\n    INDEX 01 {}\n",
+            i + 1,
+            quote(&chapter.title),
+            format_cue_time(start, sample_rate)
+        ));
+        map.push(serde_json::json!({
+            "title": chapter.title,
+            "note": chapter.note,
+            "startMs": start * 1000 / sample_rate as u64,
+            "endMs": (start + chapter.samples) * 1000 / sample_rate as u64,
+        }));
+        start += chapter.samples;
+    }
+
+    let content = serde_json::to_string_pretty(&serde_json::json!({
+        "title": title,
+        "audio": file_name,
+        "chapters": map,
+    }))
+    .map_err(|e| e.to_string())?;
+    std::fs::write(destination.with_extension("cue"), cue).map_err(|e| e.to_string())?;
+    std::fs::write(destination.with_extension("chapters.json"), content).map_err(|e| e.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chapter(title: &str, samples: u64) -> Chapter {
+        Chapter {
+            note: format!("{}.md", title),
+            title: title.to_string(),
+            audio: Some(format!("{}.wav", title)),
+            samples,
+        }
+    }
+
+    #[test]
+    fn test_collect_notes_in_path_order() {
+        let dir = tempfile::tempdir().unwrap();
+        let project = dir.path().join("Project");
+        std::fs::create_dir_all(project.join("b")).unwrap();
+        std::fs::create_dir_all(project.join(".hidden")).unwrap();
+        std::fs::write(project.join("02 Second.md"), "").unwrap();
+        std::fs::write(project.join("01 First.md"), "").unwrap();
+        std::fs::write(project.join("b").join("Nested.md"), "").unwrap();
+        std::fs::write(project.join(".hidden").join("Secret.md"), "").unwrap();
+        std::fs::write(project.join("image.png"), "").unwrap();
+
+        let notes = collect_notes(dir.path(), "Project").unwrap();
+        assert_eq!(notes, vec!["Project/01 First.md", "Project/02 Second.md", "Project/b/Nested.md"]);
+        assert!(collect_notes(dir.path(), "../Project").is_err());
+    }
+
+    #[test]
+    fn test_job_id_is_stable_and_resumable() {
+        let dir = tempfile::tempdir().unwrap();
+        let notes = vec!["a.md".to_string(), "b.md".to_string()];
+        let mut job = AudiobookJob::new("Book".into(), notes.clone(), "af_sky".into(), 1.0, "/out/book.flac".into());
+        assert_eq!(job.id, job_id(&notes, "af_sky", 1.0, "/out/book.flac"));
+        assert_ne!(job.id, job_id(&notes, "af_sky", 1.2, "/out/book.flac"));
+
+        job.chapters[0].audio = Some("cache/x.wav".into());
+        job.save(dir.path()).unwrap();
+        let loaded = AudiobookJob::load(dir.path(), &job.id).unwrap();
+        assert_eq!(loaded.completed_chapters(), 1);
+        assert_eq!(loaded.chapters[1].title, "b");
+    }
+
+    #[test]
+    fn test_chapter_maps() {
+        let dir = tempfile::tempdir().unwrap();
+        let destination = dir.path().join("book.flac");
+        // 90.5 s of audio at 24 kHz, then a second chapter.
+        let chapters = vec![chapter("Intro", 2_172_000), chapter("Part \"Two\"", 24_000)];
+        write_chapter_maps(&destination, "Book", &chapters, 24_000).unwrap();
+
+        let cue = std::fs::read_to_string(dir.path().join("book.cue")).unwrap();
+        assert!(cue.contains("FILE \"book.flac\" WAVE"));
+        assert!(cue.contains("TRACK 02 AUDIO\n    TITLE \"Part 'Two'\"\n    INDEX 01 01:30:37"));
+
+        let map: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(dir.path().join("book.chapters.json")).unwrap()).unwrap();
+        assert_eq!(map["chapters"][1]["startMs"], 90_500);
+        assert_eq!(map["chapters"][1]["endMs"], 91_500);
+        assert_eq!(format_timestamp(2_172_000, 24_000), "00:01:30.500");
+    }
+}
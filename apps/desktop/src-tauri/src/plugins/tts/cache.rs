@@ -0,0 +1,427 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime};
+
+use super::model::{now_millis, ModelStamp};
+
+const MANIFEST_FILENAME: &str = "manifest.json";
+pub const CACHE_LIMIT_SETTING: &str = "plugins.core.tts.cacheLimitMb";
+pub const DEFAULT_CACHE_LIMIT_MB: u64 = 1024;
+/// Untracked audio younger than this may belong to a job that has not
+/// stored it yet.
+const UNTRACKED_GRACE: Duration = Duration::from_secs(10 * 60);
+
+/// Serializes manifest updates between concurrent synthesis jobs.
+static LOCK: Mutex<()> = Mutex::new(());
+/// Keys handed out for playback but not yet loaded into the player. They are
+/// never evicted, so a queued track can't vanish before it plays.
+static PINNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Everything besides the request itself that changes the audio we produce.
+/// Audio is only replayed for the pipeline that produced it.
+#[derive(Clone, Debug)]
+pub struct PipelineFingerprint {
+    /// `id:version` of engines other than Kokoro. Kokoro predates engine
+    /// selection and is identified by its model digests alone.
+    pub engine: Option<String>,
+    pub model_sha256: String,
+    pub voices_sha256: String,
+    pub sanitiser_version: u32,
+    /// Pause lengths after sentences and other elements.
+    pub pauses: String,
+    /// SHA-256 of the vault's pronunciation lexicon; empty without one.
+    pub lexicon_sha256: String,
+    /// Voices chosen per language and whether detection is on; empty for
+    /// the defaults.
+    pub languages: String,
+}
+
+impl PipelineFingerprint {
+    pub fn new(stamp: &ModelStamp, sanitiser_version: u32, pauses: &str, lexicon_sha256: &str) -> Self {
+        Self {
+            engine: None,
+            model_sha256: stamp.model_sha256.clone(),
+            voices_sha256: stamp.voices_sha256.clone(),
+            sanitiser_version,
+            pauses: pauses.to_string(),
+            lexicon_sha256: lexicon_sha256.to_string(),
+            languages: String::new(),
+        }
+    }
+
+    /// For engines that ship without downloadable model files.
+    pub fn for_engine(engine: &str, version: &str, sanitiser_version: u32, pauses: &str, lexicon_sha256: &str) -> Self {
+        Self {
+            engine: Some(format!("{}:{}", engine, version)),
+            model_sha256: String::new(),
+            voices_sha256: String::new(),
+            sanitiser_version,
+            pauses: pauses.to_string(),
+            lexicon_sha256: lexicon_sha256.to_string(),
+            languages: String::new(),
+        }
+    }
+
+    pub fn id(&self) -> String {
+        let mut hasher = Sha256::new();
+        hasher.update(format!(
+            "model={}\nvoices={}\nsanitiser={}\npauses={}",
+            self.model_sha256, self.voices_sha256, self.sanitiser_version, self.pauses
+        ));
+        // Only hashed when present, so vaults without a lexicon keep the
+        // audio cached before lexicons existed.
+        if !self.lexicon_sha256.is_empty() {
+            hasher.update(format!("\nlexicon={}", self.lexicon_sha256));
+        }
+        if !self.languages.is_empty() {
+            hasher.update(format!("\nlanguages={}", self.languages));
+        }
+        if let Some(engine) = &self.engine {
+            hasher.update(format!("\nengine={}", engine));
+        }
+        hex::encode(hasher.finalize())
+    }
+}
+
+pub fn cache_key(pipeline_id: &str, text: &str, voice: &str, speed: f32) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(pipeline_id);
+    hasher.update([0]);
+    hasher.update(voice);
+    hasher.update([0]);
+    hasher.update(speed.to_string());
+    hasher.update([0]);
+    hasher.update(text);
+    hex::encode(hasher.finalize())
+}
+
+/// Key for the audio of one sentence, shared by every request that reads it
+/// with the same voice and speed.
+pub fn sentence_key(pipeline_id: &str, engine_text: &str, voice: &str, speed: f32) -> String {
+    cache_key(pipeline_id, engine_text, &format!("sentence:{}", voice), speed)
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CacheEntry {
+    pub pipeline: String,
+    pub created_at: u64,
+    /// Last replay or write; entries without one are evicted first.
+    #[serde(default)]
+    pub last_used: u64,
+    /// Size of the audio and metadata; 0 for entries written before sizes
+    /// were recorded, which are measured when the cache is next evicted.
+    #[serde(default)]
+    pub bytes: u64,
+}
+
+/// Index of cached audio, recording which pipeline produced each entry.
+/// Entries of every pipeline are kept while the app runs, so switching a
+/// lexicon, engine or voice back and forth replays earlier audio; entries the
+/// current pipeline can't replay are deleted at startup and model changes.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct CacheManifest {
+    #[serde(default)]
+    pub entries: BTreeMap<String, CacheEntry>,
+}
+
+pub fn cache_dir(tts_dir: &Path) -> PathBuf {
+    tts_dir.join("cache")
+}
+
+pub fn audio_path(cache_dir: &Path, key: &str) -> PathBuf {
+    cache_dir.join(format!("{}.wav", key))
+}
+
+fn metadata_path(cache_dir: &Path, key: &str) -> PathBuf {
+    cache_dir.join(format!("{}.json", key))
+}
+
+impl CacheManifest {
+    pub fn load(cache_dir: &Path) -> Self {
+        std::fs::read_to_string(cache_dir.join(MANIFEST_FILENAME))
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self, cache_dir: &Path) -> Result<(), String> {
+        std::fs::create_dir_all(cache_dir).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp_path = cache_dir.join(format!("{}.tmp", MANIFEST_FILENAME));
+        std::fs::write(&tmp_path, content).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp_path, cache_dir.join(MANIFEST_FILENAME)).map_err(|e| e.to_string())
+    }
+
+    /// Keys produced by pipelines other than `pipeline_id`.
+    pub fn stale_keys(&self, pipeline_id: &str) -> Vec<String> {
+        self.entries
+            .iter()
+            .filter(|(_, entry)| entry.pipeline != pipeline_id)
+            .map(|(key, _)| key.clone())
+            .collect()
+    }
+
+    /// Removes least recently used entries, never `keep` or a pinned key,
+    /// until the cache fits in `limit_bytes`. Returns the number of entries
+    /// removed.
+    fn evict(&mut self, cache_dir: &Path, limit_bytes: u64, keep: &str) -> usize {
+        let pinned = PINNED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        for (key, entry) in self.entries.iter_mut().filter(|(_, entry)| entry.bytes == 0) {
+            entry.bytes = entry_bytes(cache_dir, key);
+        }
+        let mut total: u64 = self.entries.values().map(|entry| entry.bytes).sum();
+        let mut by_age: Vec<(u64, String)> = self
+            .entries
+            .iter()
+            .filter(|(key, _)| key.as_str() != keep && !pinned.contains(key.as_str()))
+            .map(|(key, entry)| (entry.last_used, key.clone()))
+            .collect();
+        by_age.sort();
+
+        let mut removed = 0;
+        for (_, key) in by_age {
+            if total <= limit_bytes {
+                break;
+            }
+            let _ = std::fs::remove_file(audio_path(cache_dir, &key));
+            let _ = std::fs::remove_file(metadata_path(cache_dir, &key));
+            if let Some(entry) = self.entries.remove(&key) {
+                total = total.saturating_sub(entry.bytes);
+            }
+            removed += 1;
+        }
+        removed
+    }
+}
+
+fn entry_bytes(cache_dir: &Path, key: &str) -> u64 {
+    [audio_path(cache_dir, key), metadata_path(cache_dir, key)]
+        .iter()
+        .filter_map(|path| std::fs::metadata(path).ok())
+        .map(|meta| meta.len())
+        .sum()
+}
+
+/// Keeps the audio at `key` out of eviction until it is unpinned.
+pub fn pin(key: &str) {
+    PINNED.lock().unwrap_or_else(|e| e.into_inner()).insert(key.to_string());
+}
+
+pub fn unpin(key: &str) {
+    PINNED.lock().unwrap_or_else(|e| e.into_inner()).remove(key);
+}
+
+pub fn unpin_all() {
+    PINNED.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// The cache key of a cached audio path.
+pub fn key_of(path: &Path) -> Option<&str> {
+    path.file_stem().and_then(|stem| stem.to_str())
+}
+
+/// The cache size limit from settings, in bytes.
+pub fn limit_bytes(settings: &std::collections::HashMap<String, Value>) -> u64 {
+    let mb = settings.get(CACHE_LIMIT_SETTING).and_then(|v| v.as_u64()).unwrap_or(DEFAULT_CACHE_LIMIT_MB);
+    mb.saturating_mul(1024 * 1024)
+}
+
+/// Deletes cached audio the manifest does not know about, written before the
+/// manifest existed or by a job that stopped before storing it. Run at
+/// startup and model install rather than per synthesis. Returns the number
+/// of files removed.
+pub fn prune_untracked(cache_dir: &Path) -> Result<usize, String> {
+    if !cache_dir.exists() {
+        return Ok(0);
+    }
+
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let manifest = CacheManifest::load(cache_dir);
+    let cutoff = SystemTime::now() - UNTRACKED_GRACE;
+    let mut removed = 0;
+    let entries = std::fs::read_dir(cache_dir).map_err(|e| e.to_string())?;
+    for entry in entries {
+        let path = entry.map_err(|e| e.to_string())?.path();
+        let is_audio = path.extension().map(|ext| ext == "wav").unwrap_or(false);
+        let key = path.file_stem().and_then(|s| s.to_str()).unwrap_or_default();
+        let recent = std::fs::metadata(&path)
+            .and_then(|meta| meta.modified())
+            .map(|modified| modified > cutoff)
+            .unwrap_or(false);
+        if is_audio && !recent && !manifest.entries.contains_key(key) {
+            let _ = std::fs::remove_file(metadata_path(cache_dir, key));
+            std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+            removed += 1;
+        }
+    }
+    Ok(removed)
+}
+
+/// Deletes audio produced by pipelines other than `pipeline_id`, except
+/// pinned entries. Returns the number of entries removed.
+pub fn remove_stale(cache_dir: &Path, pipeline_id: &str) -> Result<usize, String> {
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut manifest = CacheManifest::load(cache_dir);
+    let pinned = PINNED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+    let stale: Vec<String> = manifest.stale_keys(pipeline_id).into_iter().filter(|key| !pinned.contains(key)).collect();
+    if stale.is_empty() {
+        return Ok(0);
+    }
+    for key in &stale {
+        let _ = std::fs::remove_file(audio_path(cache_dir, key));
+        let _ = std::fs::remove_file(metadata_path(cache_dir, key));
+        manifest.entries.remove(key);
+    }
+    manifest.save(cache_dir)?;
+    Ok(stale.len())
+}
+
+/// Returns the stored synthesis metadata when the audio for `key` is cached
+/// and was produced by the current pipeline, and marks the entry as used.
+pub fn lookup(cache_dir: &Path, key: &str, pipeline_id: &str) -> Option<Value> {
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut manifest = CacheManifest::load(cache_dir);
+    let entry = manifest.entries.get_mut(key)?;
+    if entry.pipeline != pipeline_id {
+        return None;
+    }
+    if !audio_path(cache_dir, key).exists() {
+        return None;
+    }
+    let content = std::fs::read_to_string(metadata_path(cache_dir, key)).ok()?;
+    let metadata = serde_json::from_str(&content).ok()?;
+    entry.last_used = now_millis();
+    let _ = manifest.save(cache_dir);
+    Some(metadata)
+}
+
+/// Records a freshly written audio file and its metadata in the manifest,
+/// then evicts the least recently used entries beyond `limit_bytes`.
+pub fn store(cache_dir: &Path, key: &str, pipeline_id: &str, metadata: &Value, limit_bytes: u64) -> Result<(), String> {
+    let content = serde_json::to_string(metadata).map_err(|e| e.to_string())?;
+    std::fs::write(metadata_path(cache_dir, key), content).map_err(|e| e.to_string())?;
+
+    let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let mut manifest = CacheManifest::load(cache_dir);
+    let now = now_millis();
+    manifest.entries.insert(key.to_string(), CacheEntry {
+        pipeline: pipeline_id.to_string(),
+        created_at: now,
+        last_used: now,
+        bytes: entry_bytes(cache_dir, key),
+    });
+    manifest.evict(cache_dir, limit_bytes, key);
+    manifest.save(cache_dir)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fingerprint(model: &str, sanitiser_version: u32) -> PipelineFingerprint {
+        PipelineFingerprint {
+            engine: None,
+            model_sha256: model.to_string(),
+            voices_sha256: "voices".to_string(),
+            sanitiser_version,
+            pauses: "sentence=100".to_string(),
+            lexicon_sha256: String::new(),
+            languages: String::new(),
+        }
+    }
+
+    #[test]
+    fn test_cache_key_covers_pipeline() {
+        let base = fingerprint("model-a", 1).id();
+        assert_eq!(cache_key(&base, "Hello.", "af_sky", 1.0), cache_key(&base, "Hello.", "af_sky", 1.0));
+        assert_ne!(cache_key(&base, "Hello.", "af_sky", 1.0), cache_key(&base, "Hello.", "af_sky", 1.1));
+
+        let other_model = fingerprint("model-b", 1).id();
+        let other_sanitiser = fingerprint("model-a", 2).id();
+        assert_ne!(cache_key(&base, "Hello.", "af_sky", 1.0), cache_key(&other_model, "Hello.", "af_sky", 1.0));
+        assert_ne!(cache_key(&base, "Hello.", "af_sky", 1.0), cache_key(&other_sanitiser, "Hello.", "af_sky", 1.0));
+
+        let mut with_lexicon = fingerprint("model-a", 1);
+        with_lexicon.lexicon_sha256 = "lexicon".to_string();
+        assert_ne!(base, with_lexicon.id());
+        assert_ne!(
+            PipelineFingerprint::for_engine("formant", "1", 1, "", "").id(),
+            PipelineFingerprint::for_engine("formant", "2", 1, "", "").id()
+        );
+
+        let mut longer_pauses = fingerprint("model-a", 1);
+        longer_pauses.pauses = "sentence=250".to_string();
+        assert_ne!(base, longer_pauses.id());
+
+        let mut french_voice = fingerprint("model-a", 1);
+        french_voice.languages = "detect=true,voices=fr=ff_siwis".to_string();
+        assert_ne!(base, french_voice.id());
+    }
+
+    #[test]
+    fn test_prune_untracked_audio() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path();
+        let old = fingerprint("model-a", 1).id();
+        let current = fingerprint("model-b", 1).id();
+        let metadata = serde_json::json!({ "duration_ms": 0, "segments": [] });
+
+        std::fs::write(audio_path(cache_dir, "old"), b"old").unwrap();
+        store(cache_dir, "old", &old, &metadata, u64::MAX).unwrap();
+        std::fs::write(audio_path(cache_dir, "new"), b"new").unwrap();
+        store(cache_dir, "new", &current, &metadata, u64::MAX).unwrap();
+        std::fs::write(audio_path(cache_dir, "pending"), b"pending").unwrap();
+        let legacy = std::fs::File::create(audio_path(cache_dir, "legacy")).unwrap();
+        legacy.set_modified(SystemTime::now() - UNTRACKED_GRACE * 2).unwrap();
+
+        assert_eq!(prune_untracked(cache_dir).unwrap(), 1);
+        assert!(audio_path(cache_dir, "pending").exists());
+        assert!(!audio_path(cache_dir, "legacy").exists());
+        assert!(lookup(cache_dir, "old", &current).is_none());
+        assert!(lookup(cache_dir, "old", &old).is_some());
+        assert!(lookup(cache_dir, "new", &current).is_some());
+
+        assert_eq!(remove_stale(cache_dir, &current).unwrap(), 1);
+        assert!(!audio_path(cache_dir, "old").exists());
+        assert!(lookup(cache_dir, "old", &old).is_none());
+        assert!(lookup(cache_dir, "new", &current).is_some());
+    }
+
+    #[test]
+    fn test_store_evicts_least_recently_used() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path();
+        let pipeline = fingerprint("model-a", 1).id();
+        let metadata = serde_json::json!({});
+        let write = |key: &str| std::fs::write(audio_path(cache_dir, key), [0u8; 100]).unwrap();
+
+        write("a");
+        store(cache_dir, "a", &pipeline, &metadata, 250).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write("b");
+        store(cache_dir, "b", &pipeline, &metadata, 250).unwrap();
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        assert!(lookup(cache_dir, "a", &pipeline).is_some());
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write("c");
+        store(cache_dir, "c", &pipeline, &metadata, 250).unwrap();
+
+        assert!(lookup(cache_dir, "b", &pipeline).is_none());
+        assert!(!audio_path(cache_dir, "b").exists());
+        assert!(lookup(cache_dir, "a", &pipeline).is_some());
+        assert!(lookup(cache_dir, "c", &pipeline).is_some());
+
+        // A pinned entry outlives newer ones until it is unpinned.
+        pin("a");
+        std::thread::sleep(std::time::Duration::from_millis(2));
+        write("d");
+        store(cache_dir, "d", &pipeline, &metadata, 250).unwrap();
+        unpin("a");
+        assert!(lookup(cache_dir, "a", &pipeline).is_some());
+        assert!(lookup(cache_dir, "c", &pipeline).is_none());
+    }
+}
@@ -0,0 +1,341 @@
+use futures::StreamExt;
+use reqwest::header::RANGE;
+use reqwest::StatusCode;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::Mutex;
+
+use super::model::sha256_file;
+
+#[derive(Clone, Default)]
+pub struct InstallProgress {
+    pub status: String,
+    pub phase: String,
+    pub downloaded_bytes: u64,
+    pub total_bytes: u64,
+}
+
+pub async fn set_install_progress(
+    progress: &Arc<Mutex<InstallProgress>>,
+    status: &str,
+    phase: &str,
+    downloaded_bytes: u64,
+    total_bytes: u64,
+) {
+    let mut guard = progress.lock().await;
+    guard.status = status.to_string();
+    guard.phase = phase.to_string();
+    guard.downloaded_bytes = downloaded_bytes;
+    guard.total_bytes = total_bytes;
+}
+
+#[derive(Clone, Copy)]
+pub struct RetryPolicy {
+    pub max_attempts: u32,
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+/// A single file to fetch. `sha256` is checked once the file is complete;
+/// the partial `.download` file is kept between attempts so it can be resumed.
+pub struct Download<'a> {
+    pub url: &'a str,
+    pub path: &'a Path,
+    pub sha256: Option<&'a str>,
+    pub phase: &'a str,
+}
+
+pub struct DownloadContext {
+    client: reqwest::Client,
+    progress: Arc<Mutex<InstallProgress>>,
+    cancel_flag: Arc<AtomicBool>,
+    retry: RetryPolicy,
+}
+
+pub const CANCELLED_MESSAGE: &str = "Install cancelled.";
+
+enum AttemptError {
+    Retryable(String),
+    Fatal(String),
+}
+
+impl DownloadContext {
+    pub fn new(
+        progress: Arc<Mutex<InstallProgress>>,
+        cancel_flag: Arc<AtomicBool>,
+        retry: RetryPolicy,
+    ) -> Result<Self, String> {
+        let client = reqwest::Client::builder()
+            .user_agent("Liminal Notes TTS Installer")
+            .connect_timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { client, progress, cancel_flag, retry })
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancel_flag.load(Ordering::SeqCst)
+    }
+
+    /// Downloads, retrying with exponential backoff and resuming from the
+    /// partial file via HTTP range requests. Returns the final file size.
+    pub async fn download_file(&self, download: &Download<'_>, base_downloaded: u64) -> Result<u64, String> {
+        let tmp_path = download.path.with_extension("download");
+        if download.path.exists() && !tmp_path.exists() {
+            // Left over from an earlier install that failed later on; let the
+            // range request confirm it is complete instead of fetching it again.
+            tokio::fs::rename(download.path, &tmp_path).await.map_err(|e| e.to_string())?;
+        }
+        let mut attempt = 0;
+        let mut backoff = self.retry.initial_backoff;
+
+        loop {
+            attempt += 1;
+            match self.download_attempt(download, &tmp_path, base_downloaded).await {
+                Ok(()) => break,
+                Err(AttemptError::Fatal(message)) => return Err(message),
+                Err(AttemptError::Retryable(message)) => {
+                    if attempt >= self.retry.max_attempts {
+                        return Err(format!("Download failed after {} attempts: {}", attempt, message));
+                    }
+                    {
+                        let mut guard = self.progress.lock().await;
+                        guard.status = "retrying".to_string();
+                    }
+                    tokio::time::sleep(backoff).await;
+                    if self.is_cancelled() {
+                        return Err(CANCELLED_MESSAGE.to_string());
+                    }
+                    backoff *= 2;
+                }
+            }
+        }
+
+        let hash_path = tmp_path.clone();
+        let actual = tokio::task::spawn_blocking(move || sha256_file(&hash_path))
+            .await
+            .map_err(|e| e.to_string())??;
+        if let Some(expected) = download.sha256 {
+            if !actual.eq_ignore_ascii_case(expected) {
+                let _ = tokio::fs::remove_file(&tmp_path).await;
+                return Err(format!(
+                    "Checksum mismatch for {}: expected {}, got {}",
+                    download.phase, expected, actual
+                ));
+            }
+        }
+
+        let size = tokio::fs::metadata(&tmp_path).await.map_err(|e| e.to_string())?.len();
+        tokio::fs::rename(&tmp_path, download.path).await.map_err(|e| e.to_string())?;
+        Ok(size)
+    }
+
+    async fn download_attempt(
+        &self,
+        download: &Download<'_>,
+        tmp_path: &Path,
+        base_downloaded: u64,
+    ) -> Result<(), AttemptError> {
+        if self.is_cancelled() {
+            return Err(AttemptError::Fatal(CANCELLED_MESSAGE.to_string()));
+        }
+
+        let existing = tokio::fs::metadata(tmp_path).await.map(|m| m.len()).unwrap_or(0);
+        let mut request = self.client.get(download.url);
+        if existing > 0 {
+            request = request.header(RANGE, format!("bytes={}-", existing));
+        }
+
+        let response = request.send().await.map_err(|e| AttemptError::Retryable(e.to_string()))?;
+        let status = response.status();
+        if status == StatusCode::RANGE_NOT_SATISFIABLE && existing > 0 {
+            // The partial file is already complete; the checksum decides whether it is usable.
+            return Ok(());
+        }
+        if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(AttemptError::Retryable(format!("Server responded with {}", status)));
+        }
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let snippet: String = body.chars().take(200).collect();
+            return Err(AttemptError::Fatal(format!("Download failed ({}): {}", status, snippet)));
+        }
+
+        let resumed = status == StatusCode::PARTIAL_CONTENT;
+        let mut downloaded = if resumed { existing } else { 0 };
+        let total_bytes = response.content_length().map(|len| len + downloaded).unwrap_or(0);
+
+        let mut file = if resumed {
+            tokio::fs::OpenOptions::new().append(true).open(tmp_path).await
+        } else {
+            tokio::fs::File::create(tmp_path).await
+        }
+        .map_err(|e| AttemptError::Fatal(e.to_string()))?;
+
+        set_install_progress(
+            &self.progress,
+            "downloading",
+            download.phase,
+            base_downloaded + downloaded,
+            base_downloaded + total_bytes,
+        ).await;
+
+        let mut stream = response.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            if self.is_cancelled() {
+                let _ = file.flush().await;
+                return Err(AttemptError::Fatal(CANCELLED_MESSAGE.to_string()));
+            }
+            let chunk = match chunk {
+                Ok(chunk) => chunk,
+                Err(e) => {
+                    let _ = file.flush().await;
+                    return Err(AttemptError::Retryable(e.to_string()));
+                }
+            };
+            file.write_all(&chunk).await.map_err(|e| AttemptError::Fatal(e.to_string()))?;
+            downloaded += chunk.len() as u64;
+            if total_bytes > 0 {
+                set_install_progress(
+                    &self.progress,
+                    "downloading",
+                    download.phase,
+                    base_downloaded + downloaded,
+                    base_downloaded + total_bytes,
+                ).await;
+            }
+        }
+        file.flush().await.map_err(|e| AttemptError::Fatal(e.to_string()))?;
+
+        if total_bytes > 0 && downloaded < total_bytes {
+            return Err(AttemptError::Retryable(format!(
+                "Connection closed after {} of {} bytes",
+                downloaded, total_bytes
+            )));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::sync::atomic::AtomicUsize;
+    use tokio::io::AsyncReadExt;
+    use tokio::net::TcpListener;
+
+    /// Minimal stand-in for the release host. Honours `Range: bytes=N-` and can
+    /// drop the first `failures` connections half way through the body.
+    async fn serve(body: Vec<u8>, failures: usize) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let requests = Arc::new(AtomicUsize::new(0));
+        let counter = requests.clone();
+
+        tokio::spawn(async move {
+            loop {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let n = counter.fetch_add(1, Ordering::SeqCst);
+                let body = body.clone();
+                tokio::spawn(async move {
+                    let mut buf = vec![0u8; 4096];
+                    let read = socket.read(&mut buf).await.unwrap();
+                    let request = String::from_utf8_lossy(&buf[..read]).to_lowercase();
+                    let start = request
+                        .lines()
+                        .find_map(|line| line.strip_prefix("range: bytes="))
+                        .and_then(|range| range.trim_end_matches('-').parse::<usize>().ok());
+
+                    let (status, slice) = match start {
+                        Some(start) if start >= body.len() => ("416 Range Not Satisfiable", &body[0..0]),
+                        Some(start) => ("206 Partial Content", &body[start..]),
+                        None => ("200 OK", &body[..]),
+                    };
+                    let header = format!("HTTP/1.1 {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", status, slice.len());
+                    socket.write_all(header.as_bytes()).await.unwrap();
+                    if n < failures {
+                        let _ = socket.write_all(&slice[..slice.len() / 2]).await;
+                    } else {
+                        let _ = socket.write_all(slice).await;
+                    }
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+
+        (format!("http://{}/kokoro-v1.0.onnx", addr), requests)
+    }
+
+    fn context(cancel_flag: Arc<AtomicBool>) -> DownloadContext {
+        let retry = RetryPolicy { max_attempts: 3, initial_backoff: Duration::from_millis(10) };
+        DownloadContext::new(Arc::new(Mutex::new(InstallProgress::default())), cancel_flag, retry).unwrap()
+    }
+
+    fn body() -> (Vec<u8>, String) {
+        let body: Vec<u8> = (0..64 * 1024).map(|i| (i % 251) as u8).collect();
+        let hash = hex::encode(Sha256::digest(&body));
+        (body, hash)
+    }
+
+    #[tokio::test]
+    async fn test_resumes_after_dropped_connection() {
+        let (body, hash) = body();
+        let (url, requests) = serve(body.clone(), 1).await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+
+        let ctx = context(Arc::new(AtomicBool::new(false)));
+        let size = ctx.download_file(&Download { url: &url, path: &path, sha256: Some(&hash), phase: "Model" }, 0).await.unwrap();
+
+        assert_eq!(size, body.len() as u64);
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[tokio::test]
+    async fn test_rejects_checksum_mismatch() {
+        let (body, _) = body();
+        let (url, _) = serve(body, 0).await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+
+        let ctx = context(Arc::new(AtomicBool::new(false)));
+        let wrong = "0".repeat(64);
+        let result = ctx.download_file(&Download { url: &url, path: &path, sha256: Some(&wrong), phase: "Model" }, 0).await;
+
+        assert!(result.unwrap_err().contains("Checksum mismatch"));
+        assert!(!path.exists());
+        assert!(!path.with_extension("download").exists());
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_download_keeps_partial_file() {
+        let (body, hash) = body();
+        let (url, _) = serve(body.clone(), 0).await;
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("model.onnx");
+        std::fs::write(path.with_extension("download"), &body[..1000]).unwrap();
+
+        let cancel_flag = Arc::new(AtomicBool::new(true));
+        let ctx = context(cancel_flag.clone());
+        let result = ctx.download_file(&Download { url: &url, path: &path, sha256: Some(&hash), phase: "Model" }, 0).await;
+        assert_eq!(result.unwrap_err(), CANCELLED_MESSAGE);
+        assert_eq!(std::fs::metadata(path.with_extension("download")).unwrap().len(), 1000);
+
+        cancel_flag.store(false, Ordering::SeqCst);
+        ctx.download_file(&Download { url: &url, path: &path, sha256: Some(&hash), phase: "Model" }, 0).await.unwrap();
+        assert_eq!(std::fs::read(&path).unwrap(), body);
+    }
+}
@@ -0,0 +1,188 @@
+use kokoro_tts::{KokoroTts, Voice};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::Path;
+use std::pin::Pin;
+
+use super::formant::{self, FormantEngine};
+use super::model::{self, MODEL_FILENAME, VOICES_FILENAME};
+
+/// `"auto"` (default), `"kokoro"` or `"formant"`.
+pub const ENGINE_SETTING: &str = "plugins.core.tts.engine";
+
+#[derive(Clone, Debug, Serialize)]
+pub struct VoiceInfo {
+    pub id: &'static str,
+    pub label: &'static str,
+    pub language: &'static str,
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct EngineCapabilities {
+    pub id: &'static str,
+    pub name: &'static str,
+    /// Changes whenever the engine would produce different audio for the same input.
+    pub version: String,
+    pub sample_rate: u32,
+    pub languages: Vec<&'static str>,
+    pub voices: Vec<VoiceInfo>,
+    /// Whether the engine needs the downloaded model files.
+    pub requires_model: bool,
+}
+
+/// A speech synthesizer behind the TTS plugin. Engines receive one sanitised
+/// sentence at a time and return mono samples in `[-1, 1]` at `sample_rate`.
+/// Unknown voice ids fall back to the engine's first voice.
+pub trait SpeechEngine: Send + Sync {
+    fn capabilities(&self) -> EngineCapabilities;
+    fn synth<'a>(&'a self, text: &'a str, voice: &'a str, speed: f32) -> Pin<Box<dyn Future<Output = Result<Vec<f32>, String>> + Send + 'a>>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum EngineKind {
+    Kokoro,
+    Formant,
+}
+
+impl EngineKind {
+    pub const ALL: [EngineKind; 2] = [EngineKind::Kokoro, EngineKind::Formant];
+
+    pub fn id(self) -> &'static str {
+        match self {
+            EngineKind::Kokoro => "kokoro",
+            EngineKind::Formant => formant::ENGINE_ID,
+        }
+    }
+
+    pub fn capabilities(self) -> EngineCapabilities {
+        match self {
+            EngineKind::Kokoro => KokoroEngine::capabilities(),
+            EngineKind::Formant => FormantEngine::capabilities(),
+        }
+    }
+
+    /// The configured engine. `auto` prefers Kokoro once its model is installed.
+    pub fn select(settings: &HashMap<String, Value>, kokoro_installed: bool) -> Result<Self, String> {
+        match settings.get(ENGINE_SETTING).and_then(|v| v.as_str()).unwrap_or("auto") {
+            "auto" | "" if kokoro_installed => Ok(EngineKind::Kokoro),
+            "auto" | "" => Ok(EngineKind::Formant),
+            other => Self::ALL
+                .into_iter()
+                .find(|kind| kind.id() == other)
+                .ok_or_else(|| format!("Unknown TTS engine '{}'", other)),
+        }
+    }
+
+    pub async fn load(self, tts_dir: &Path) -> Result<Box<dyn SpeechEngine>, String> {
+        match self {
+            EngineKind::Kokoro => {
+                let dir = model::model_dir(tts_dir);
+                let model_path = dir.join(MODEL_FILENAME);
+                let voices_path = dir.join(VOICES_FILENAME);
+                let tts = KokoroTts::new(
+                    model_path.to_str().ok_or("Invalid path")?,
+                    voices_path.to_str().ok_or("Invalid path")?,
+                )
+                .await
+                .map_err(|e| e.to_string())?;
+                Ok(Box::new(KokoroEngine { tts }))
+            }
+            EngineKind::Formant => Ok(Box::new(FormantEngine)),
+        }
+    }
+}
+
+const KOKORO_VOICES: [VoiceInfo; 14] = [
+    VoiceInfo { id: "af_sky", label: "Sky (American Female)", language: "en-us" },
+    VoiceInfo { id: "af_bella", label: "Bella (American Female)", language: "en-us" },
+    VoiceInfo { id: "af_nicole", label: "Nicole (American Female)", language: "en-us" },
+    VoiceInfo { id: "af_sarah", label: "Sarah (American Female)", language: "en-us" },
+    VoiceInfo { id: "am_adam", label: "Adam (American Male)", language: "en-us" },
+    VoiceInfo { id: "am_michael", label: "Michael (American Male)", language: "en-us" },
+    VoiceInfo { id: "bf_emma", label: "Emma (British Female)", language: "en-gb" },
+    VoiceInfo { id: "bf_isabella", label: "Isabella (British Female)", language: "en-gb" },
+    VoiceInfo { id: "bm_george", label: "George (British Male)", language: "en-gb" },
+    VoiceInfo { id: "bm_lewis", label: "Lewis (British Male)", language: "en-gb" },
+    VoiceInfo { id: "zf_xiaobei", label: "Xiaobei (Mandarin Female)", language: "zh" },
+    VoiceInfo { id: "zf_xiaoni", label: "Xiaoni (Mandarin Female)", language: "zh" },
+    VoiceInfo { id: "zm_yunjian", label: "Yunjian (Mandarin Male)", language: "zh" },
+    VoiceInfo { id: "zm_yunxi", label: "Yunxi (Mandarin Male)", language: "zh" },
+];
+
+pub struct KokoroEngine {
+    tts: KokoroTts,
+}
+
+impl KokoroEngine {
+    pub fn capabilities() -> EngineCapabilities {
+        EngineCapabilities {
+            id: "kokoro",
+            name: "Kokoro",
+            version: "1.0".to_string(),
+            sample_rate: 24000,
+            // kokoro-tts only has grapheme-to-phoneme rules for English and
+            // Mandarin, although the model has voices for more languages.
+            languages: vec!["en-us", "en-gb", "zh"],
+            voices: KOKORO_VOICES.to_vec(),
+            requires_model: true,
+        }
+    }
+
+    fn voice(voice: &str, speed: f32) -> Voice {
+        match voice {
+            "af_sky" => Voice::AfSky(speed),
+            "af_bella" => Voice::AfBella(speed),
+            "af_nicole" => Voice::AfNicole(speed),
+            "af_sarah" => Voice::AfSarah(speed),
+            "am_adam" => Voice::AmAdam(speed),
+            "am_michael" => Voice::AmMichael(speed),
+            "bf_emma" => Voice::BfEmma(speed),
+            "bf_isabella" => Voice::BfIsabella(speed),
+            "bm_george" => Voice::BmGeorge(speed),
+            "bm_lewis" => Voice::BmLewis(speed),
+            "zf_xiaobei" => Voice::ZfXiaobei(speed),
+            "zf_xiaoni" => Voice::ZfXiaoni(speed),
+            "zm_yunjian" => Voice::ZmYunjian(speed),
+            "zm_yunxi" => Voice::ZmYunxi(speed),
+            _ => Voice::AfSky(speed),
+        }
+    }
+}
+
+impl SpeechEngine for KokoroEngine {
+    fn capabilities(&self) -> EngineCapabilities {
+        Self::capabilities()
+    }
+
+    fn synth<'a>(&'a self, text: &'a str, voice: &'a str, speed: f32) -> Pin<Box<dyn Future<Output = Result<Vec<f32>, String>> + Send + 'a>> {
+        Box::pin(async move {
+            let (samples, _) = self
+                .tts
+                .synth(text, Self::voice(voice, speed))
+                .await
+                .map_err(|e| e.to_string())?;
+            Ok(samples)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_select_engine() {
+        let mut settings = HashMap::new();
+        assert_eq!(EngineKind::select(&settings, true).unwrap(), EngineKind::Kokoro);
+        assert_eq!(EngineKind::select(&settings, false).unwrap(), EngineKind::Formant);
+
+        settings.insert(ENGINE_SETTING.to_string(), Value::from("formant"));
+        assert_eq!(EngineKind::select(&settings, true).unwrap(), EngineKind::Formant);
+        settings.insert(ENGINE_SETTING.to_string(), Value::from("kokoro"));
+        assert_eq!(EngineKind::select(&settings, false).unwrap(), EngineKind::Kokoro);
+        settings.insert(ENGINE_SETTING.to_string(), Value::from("espeak"));
+        assert!(EngineKind::select(&settings, true).is_err());
+    }
+}
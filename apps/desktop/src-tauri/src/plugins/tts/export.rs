@@ -0,0 +1,299 @@
+use flacenc::bitsink::ByteSink;
+use flacenc::component::{BitRepr, StreamInfo};
+use flacenc::error::Verify;
+use flacenc::source::{Fill, FrameBuf};
+use hound::WavReader;
+use md5::{Digest, Md5};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use crate::frontmatter::Frontmatter;
+
+const VORBIS_COMMENT_BLOCK: u8 = 4;
+const STREAMINFO_LEN: usize = 34;
+
+#[derive(Clone, Default)]
+pub struct ExportProgress {
+    pub status: String,
+    pub encoded_samples: u64,
+    pub total_samples: u64,
+    pub destination: String,
+}
+
+/// `KEY=value` pairs written into the FLAC `VORBIS_COMMENT` block.
+#[derive(Clone, Debug, Default)]
+pub struct VorbisComments {
+    fields: Vec<(String, String)>,
+}
+
+impl VorbisComments {
+    pub fn push(&mut self, key: &str, value: &str) {
+        if !value.trim().is_empty() {
+            self.fields.push((key.to_uppercase(), value.trim().to_string()));
+        }
+    }
+
+    /// Tags derived from a note: the title (frontmatter `title` wins over the
+    /// file name), plus author, date, description and tags when present.
+    pub fn from_note(title: Option<&str>, note_text: Option<&str>) -> Self {
+        let frontmatter = note_text.map(Frontmatter::from_note).unwrap_or_default();
+        let mut comments = Self::default();
+
+        if let Some(title) = frontmatter.get_str("title").as_deref().or(title) {
+            comments.push("TITLE", title);
+        }
+        for key in ["author", "authors"] {
+            for author in frontmatter.get_list(key) {
+                comments.push("ARTIST", &author);
+            }
+        }
+        if let Some(date) = frontmatter.get_str("date").or_else(|| frontmatter.get_str("created")) {
+            comments.push("DATE", &date);
+        }
+        if let Some(description) = frontmatter.get_str("description").or_else(|| frontmatter.get_str("summary")) {
+            comments.push("DESCRIPTION", &description);
+        }
+        let tags = frontmatter.get_list("tags");
+        if !tags.is_empty() {
+            comments.push("KEYWORDS", &tags.join(", "));
+        }
+        comments
+    }
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let vendor = "Liminal Notes";
+        let mut out = Vec::new();
+        out.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+        out.extend_from_slice(vendor.as_bytes());
+        out.extend_from_slice(&(self.fields.len() as u32).to_le_bytes());
+        for (key, value) in &self.fields {
+            let field = format!("{}={}", key, value);
+            out.extend_from_slice(&(field.len() as u32).to_le_bytes());
+            out.extend_from_slice(field.as_bytes());
+        }
+        out
+    }
+}
+
+/// Reads a sequence of WAV files as one continuous signal.
+struct WavChain {
+    readers: Vec<WavReader<BufReader<File>>>,
+    current: usize,
+    channels: usize,
+    bits_per_sample: usize,
+    sample_rate: usize,
+    buffer: Vec<i32>,
+}
+
+impl WavChain {
+    fn open(paths: &[PathBuf]) -> Result<Self, String> {
+        let mut readers = Vec::new();
+        for path in paths {
+            readers.push(WavReader::open(path).map_err(|e| format!("Failed to read {}: {}", path.display(), e))?);
+        }
+        let spec = readers.first().ok_or("Nothing to export.")?.spec();
+        if spec.sample_format != hound::SampleFormat::Int || spec.bits_per_sample != 16 {
+            return Err("Only 16-bit PCM audio can be exported.".to_string());
+        }
+        if readers.iter().any(|r| r.spec() != spec) {
+            return Err("All audio to export must share one sample rate and channel layout.".to_string());
+        }
+        Ok(Self {
+            readers,
+            current: 0,
+            channels: spec.channels as usize,
+            bits_per_sample: spec.bits_per_sample as usize,
+            sample_rate: spec.sample_rate as usize,
+            buffer: Vec::new(),
+        })
+    }
+
+    fn total_samples(&self) -> u64 {
+        self.readers.iter().map(|r| r.duration() as u64).sum()
+    }
+
+    /// Reads up to `block_size` interleaved frames, crossing file boundaries.
+    /// Returns an empty slice once every file is exhausted.
+    fn read_block(&mut self, block_size: usize) -> Result<&[i32], String> {
+        self.buffer.clear();
+        let wanted = block_size * self.channels;
+        while self.buffer.len() < wanted && self.current < self.readers.len() {
+            let before = self.buffer.len();
+            for sample in self.readers[self.current].samples::<i16>().take(wanted - before) {
+                self.buffer.push(sample.map_err(|e| e.to_string())? as i32);
+            }
+            if self.buffer.len() == before {
+                self.current += 1;
+            }
+        }
+        Ok(&self.buffer)
+    }
+}
+
+fn write_block_header(out: &mut impl Write, is_last: bool, block_type: u8, len: usize) -> Result<(), String> {
+    let flag = if is_last { 0x80 } else { 0 };
+    let len = len as u32;
+    out.write_all(&[flag | block_type, (len >> 16) as u8, (len >> 8) as u8, len as u8])
+        .map_err(|e| e.to_string())
+}
+
+/// Encodes `sources` (concatenated) to a FLAC file at `destination`. Frames are
+/// written as they are encoded; `STREAMINFO` is patched in once the MD5 and
+/// sample count are known. The file only appears at `destination` on success.
+pub fn encode_flac(
+    sources: &[PathBuf],
+    destination: &Path,
+    comments: &VorbisComments,
+    cancel_flag: &AtomicBool,
+    mut on_progress: impl FnMut(u64, u64),
+) -> Result<u64, String> {
+    let mut source = WavChain::open(sources)?;
+    let total = source.total_samples();
+
+    if let Some(parent) = destination.parent() {
+        std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    let part_path = destination.with_extension("flac.part");
+    let result = (|| {
+        let mut out = BufWriter::new(File::create(&part_path).map_err(|e| e.to_string())?);
+        let comment_bytes = comments.to_bytes();
+        out.write_all(b"fLaC").map_err(|e| e.to_string())?;
+        write_block_header(&mut out, false, 0, STREAMINFO_LEN)?;
+        out.write_all(&[0u8; STREAMINFO_LEN]).map_err(|e| e.to_string())?;
+        write_block_header(&mut out, true, VORBIS_COMMENT_BLOCK, comment_bytes.len())?;
+        out.write_all(&comment_bytes).map_err(|e| e.to_string())?;
+
+        let config = flacenc::config::Encoder::default()
+            .into_verified()
+            .map_err(|e| format!("{:?}", e))?;
+        let block_size = config.block_size;
+        let channels = source.channels;
+        let mut info = StreamInfo::new(source.sample_rate, channels, source.bits_per_sample)
+            .map_err(|e| e.to_string())?;
+        let mut framebuf = FrameBuf::with_size(channels, block_size).map_err(|e| e.to_string())?;
+        // flacenc's own `Context` zero-pads the final block (and its MD5), so
+        // the digest is computed here over the real samples only.
+        let mut md5 = Md5::new();
+        let mut sink = ByteSink::new();
+        let mut frame_number = 0usize;
+        let mut encoded = 0u64;
+
+        loop {
+            if cancel_flag.load(Ordering::SeqCst) {
+                return Err("Export cancelled.".to_string());
+            }
+            let samples = source.read_block(block_size)?;
+            if samples.is_empty() {
+                break;
+            }
+            let read = samples.len() / channels;
+            for sample in samples {
+                md5.update((*sample as i16).to_le_bytes());
+            }
+            // Only the last frame may be shorter than the block size.
+            if read != framebuf.size() {
+                framebuf.resize(read);
+            }
+            framebuf.fill_interleaved(samples).map_err(|e| e.to_string())?;
+            let frame = flacenc::encode_fixed_size_frame(&config, &framebuf, frame_number, &info)
+                .map_err(|e| format!("{:?}", e))?;
+            info.update_frame_info(&frame);
+            sink.clear();
+            frame.write(&mut sink).map_err(|e| format!("{:?}", e))?;
+            out.write_all(sink.as_slice()).map_err(|e| e.to_string())?;
+
+            frame_number += 1;
+            encoded += read as u64;
+            on_progress(encoded, total);
+        }
+
+        // STREAMINFO's minimum block size excludes the short final frame.
+        if frame_number > 1 {
+            info.set_block_sizes(block_size, block_size).map_err(|e| format!("{:?}", e))?;
+        }
+        info.set_md5_digest(&md5.finalize().into());
+        info.set_total_samples(encoded as usize);
+        sink.clear();
+        info.write(&mut sink).map_err(|e| format!("{:?}", e))?;
+        let mut file = out.into_inner().map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(8)).map_err(|e| e.to_string())?;
+        file.write_all(sink.as_slice()).map_err(|e| e.to_string())?;
+        file.sync_all().map_err(|e| e.to_string())?;
+        Ok(encoded)
+    })();
+
+    match result {
+        Ok(encoded) => {
+            std::fs::rename(&part_path, destination).map_err(|e| e.to_string())?;
+            Ok(encoded)
+        }
+        Err(e) => {
+            let _ = std::fs::remove_file(&part_path);
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn write_wav(path: &Path, samples: usize) {
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 24000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..samples {
+            writer.write_sample(((i as f32 / 10.0).sin() * 8000.0) as i16).unwrap();
+        }
+        writer.finalize().unwrap();
+    }
+
+    #[test]
+    fn test_encode_flac_with_comments() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.wav");
+        let b = dir.path().join("b.wav");
+        write_wav(&a, 10_000);
+        write_wav(&b, 5_000);
+
+        let note = "---\ntitle: Chapter One\nauthor: Sam\ntags: [draft]\n---\nBody";
+        let comments = VorbisComments::from_note(Some("file-name"), Some(note));
+        let destination = dir.path().join("out").join("note.flac");
+        let mut last_progress = (0, 0);
+        let encoded = encode_flac(&[a, b], &destination, &comments, &AtomicBool::new(false), |done, total| {
+            last_progress = (done, total);
+        }).unwrap();
+
+        assert_eq!(encoded, 15_000);
+        assert_eq!(last_progress, (15_000, 15_000));
+        let bytes = std::fs::read(&destination).unwrap();
+        assert_eq!(&bytes[..4], b"fLaC");
+        // STREAMINFO total samples: low nibble of byte 13 plus bytes 14..18.
+        let body = &bytes[8..8 + STREAMINFO_LEN];
+        let total = ((body[13] as u64 & 0x0f) << 32) | u32::from_be_bytes([body[14], body[15], body[16], body[17]]) as u64;
+        assert_eq!(total, 15_000);
+        let text = String::from_utf8_lossy(&bytes);
+        assert!(text.contains("TITLE=Chapter One"));
+        assert!(text.contains("ARTIST=Sam"));
+        assert!(text.contains("KEYWORDS=draft"));
+    }
+
+    #[test]
+    fn test_cancelled_export_leaves_no_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let a = dir.path().join("a.wav");
+        write_wav(&a, 10_000);
+        let destination = dir.path().join("note.flac");
+
+        let result = encode_flac(&[a], &destination, &VorbisComments::default(), &AtomicBool::new(true), |_, _| {});
+        assert!(result.is_err());
+        assert!(!destination.exists());
+        assert!(!destination.with_extension("flac.part").exists());
+    }
+}
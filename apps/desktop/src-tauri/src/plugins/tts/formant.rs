@@ -0,0 +1,486 @@
+// A small rule-based formant synthesizer: English letter-to-sound rules feed a
+// cascade of three resonators driven by a glottal pulse or noise source. It
+// needs no model files and sounds robotic, but it is intelligible and always
+// available. Output is deterministic so it can share the synthesis cache.
+
+use std::f32::consts::PI;
+use std::future::Future;
+use std::pin::Pin;
+
+use super::engine::{EngineCapabilities, SpeechEngine, VoiceInfo};
+
+pub const ENGINE_ID: &str = "formant";
+/// Bump when the rules or synthesis change the audio produced.
+pub const ENGINE_VERSION: u32 = 1;
+const SAMPLE_RATE: u32 = 24000;
+
+struct FormantVoice {
+    id: &'static str,
+    label: &'static str,
+    pitch_hz: f32,
+    formant_scale: f32,
+}
+
+const VOICES: [FormantVoice; 2] = [
+    FormantVoice { id: "formant_low", label: "Formant (low)", pitch_hz: 110.0, formant_scale: 1.0 },
+    FormantVoice { id: "formant_high", label: "Formant (high)", pitch_hz: 195.0, formant_scale: 1.15 },
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Class {
+    Vowel,
+    /// Voiced, formant-shaped consonants: liquids, glides and nasals.
+    Sonorant,
+    Fricative { voiced: bool },
+    Plosive { voiced: bool },
+    Pause,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Phone {
+    name: &'static str,
+    class: Class,
+    formants: [f32; 3],
+    /// Diphthongs glide towards a second target.
+    glide_to: Option<[f32; 3]>,
+    duration_ms: f32,
+    /// Centre frequency and gain of the noise source, for fricatives and bursts.
+    noise: (f32, f32),
+    amplitude: f32,
+}
+
+const fn phone(name: &'static str, class: Class, formants: [f32; 3], duration_ms: f32, noise: (f32, f32), amplitude: f32) -> Phone {
+    Phone { name, class, formants, glide_to: None, duration_ms, noise, amplitude }
+}
+
+const fn diphthong(name: &'static str, from: [f32; 3], to: [f32; 3], duration_ms: f32) -> Phone {
+    Phone { name, class: Class::Vowel, formants: from, glide_to: Some(to), duration_ms, noise: (0.0, 0.0), amplitude: 1.0 }
+}
+
+const V: Class = Class::Vowel;
+const S: Class = Class::Sonorant;
+
+// Formant values after Peterson & Barney, simplified.
+const PHONES: &[Phone] = &[
+    phone("IY", V, [270.0, 2290.0, 3010.0], 120.0, (0.0, 0.0), 1.0),
+    phone("IH", V, [390.0, 1990.0, 2550.0], 85.0, (0.0, 0.0), 1.0),
+    phone("EH", V, [530.0, 1840.0, 2480.0], 95.0, (0.0, 0.0), 1.0),
+    phone("AE", V, [660.0, 1720.0, 2410.0], 115.0, (0.0, 0.0), 1.0),
+    phone("AA", V, [730.0, 1090.0, 2440.0], 125.0, (0.0, 0.0), 1.0),
+    phone("AO", V, [570.0, 840.0, 2410.0], 125.0, (0.0, 0.0), 1.0),
+    phone("UH", V, [440.0, 1020.0, 2240.0], 85.0, (0.0, 0.0), 1.0),
+    phone("UW", V, [300.0, 870.0, 2240.0], 120.0, (0.0, 0.0), 1.0),
+    phone("AH", V, [640.0, 1190.0, 2390.0], 80.0, (0.0, 0.0), 0.9),
+    phone("ER", V, [490.0, 1350.0, 1690.0], 120.0, (0.0, 0.0), 1.0),
+    diphthong("EY", [480.0, 1900.0, 2500.0], [300.0, 2200.0, 2900.0], 150.0),
+    diphthong("AY", [730.0, 1100.0, 2440.0], [300.0, 2100.0, 2800.0], 170.0),
+    diphthong("OW", [550.0, 900.0, 2400.0], [320.0, 800.0, 2300.0], 150.0),
+    diphthong("AW", [730.0, 1100.0, 2440.0], [320.0, 850.0, 2300.0], 170.0),
+    diphthong("OY", [570.0, 840.0, 2410.0], [300.0, 2100.0, 2800.0], 170.0),
+    phone("L", S, [360.0, 1300.0, 2700.0], 65.0, (0.0, 0.0), 0.7),
+    phone("R", S, [420.0, 1300.0, 1600.0], 65.0, (0.0, 0.0), 0.7),
+    phone("W", S, [300.0, 610.0, 2200.0], 55.0, (0.0, 0.0), 0.7),
+    phone("Y", S, [260.0, 2070.0, 3020.0], 55.0, (0.0, 0.0), 0.7),
+    phone("M", S, [250.0, 1100.0, 2200.0], 70.0, (0.0, 0.0), 0.5),
+    phone("N", S, [250.0, 1500.0, 2500.0], 65.0, (0.0, 0.0), 0.5),
+    phone("NG", S, [250.0, 2000.0, 2600.0], 70.0, (0.0, 0.0), 0.5),
+    phone("F", Class::Fricative { voiced: false }, [340.0, 1100.0, 2100.0], 95.0, (5000.0, 0.15), 0.0),
+    phone("TH", Class::Fricative { voiced: false }, [320.0, 1300.0, 2500.0], 95.0, (5500.0, 0.12), 0.0),
+    phone("S", Class::Fricative { voiced: false }, [320.0, 1400.0, 2700.0], 105.0, (6000.0, 0.5), 0.0),
+    phone("SH", Class::Fricative { voiced: false }, [300.0, 1800.0, 2500.0], 105.0, (2800.0, 0.5), 0.0),
+    phone("HH", Class::Fricative { voiced: false }, [500.0, 1500.0, 2500.0], 60.0, (1500.0, 0.2), 0.0),
+    phone("V", Class::Fricative { voiced: true }, [220.0, 1100.0, 2080.0], 70.0, (5000.0, 0.08), 0.5),
+    phone("DH", Class::Fricative { voiced: true }, [270.0, 1290.0, 2540.0], 55.0, (5500.0, 0.06), 0.5),
+    phone("Z", Class::Fricative { voiced: true }, [240.0, 1300.0, 2700.0], 80.0, (6000.0, 0.25), 0.4),
+    phone("ZH", Class::Fricative { voiced: true }, [300.0, 1800.0, 2500.0], 80.0, (2800.0, 0.25), 0.4),
+    phone("P", Class::Plosive { voiced: false }, [400.0, 1100.0, 2150.0], 80.0, (1000.0, 0.35), 0.0),
+    phone("B", Class::Plosive { voiced: true }, [200.0, 1100.0, 2150.0], 60.0, (1000.0, 0.25), 0.3),
+    phone("T", Class::Plosive { voiced: false }, [400.0, 1600.0, 2600.0], 75.0, (4000.0, 0.4), 0.0),
+    phone("D", Class::Plosive { voiced: true }, [200.0, 1600.0, 2600.0], 55.0, (4000.0, 0.3), 0.3),
+    phone("K", Class::Plosive { voiced: false }, [300.0, 1990.0, 2850.0], 85.0, (2000.0, 0.4), 0.0),
+    phone("G", Class::Plosive { voiced: true }, [200.0, 1990.0, 2850.0], 65.0, (2000.0, 0.3), 0.3),
+    phone("_", Class::Pause, [500.0, 1500.0, 2500.0], 180.0, (0.0, 0.0), 0.0),
+];
+
+fn lookup(name: &str) -> Option<&'static Phone> {
+    PHONES.iter().find(|p| p.name == name)
+}
+
+/// Whole-word pronunciations for common words the rules get wrong.
+const EXCEPTIONS: &[(&str, &str)] = &[
+    ("a", "AH"), ("the", "DH AH"), ("to", "T UW"), ("of", "AH V"), ("you", "Y UW"),
+    ("is", "IH Z"), ("was", "W AA Z"), ("are", "AA R"), ("one", "W AH N"), ("do", "D UW"),
+    ("does", "D AH Z"), ("said", "S EH D"), ("have", "HH AE V"), ("give", "G IH V"),
+    ("live", "L IH V"), ("what", "W AH T"), ("who", "HH UW"), ("where", "W EH R"),
+    ("there", "DH EH R"), ("their", "DH EH R"), ("they", "DH EY"), ("i", "AY"),
+    ("be", "B IY"), ("he", "HH IY"), ("she", "SH IY"), ("we", "W IY"), ("me", "M IY"),
+    ("my", "M AY"), ("by", "B AY"), ("as", "AE Z"), ("has", "HH AE Z"), ("his", "HH IH Z"),
+    ("from", "F R AH M"), ("come", "K AH M"), ("some", "S AH M"), ("done", "D AH N"),
+    ("been", "B IH N"), ("any", "EH N IY"), ("many", "M EH N IY"), ("two", "T UW"),
+    ("four", "F AO R"), ("eight", "EY T"), ("once", "W AH N S"), ("could", "K UH D"),
+    ("would", "W UH D"), ("should", "SH UH D"), ("put", "P UH T"), ("our", "AW R"),
+];
+
+/// Letter-to-sound rules, tried longest first at each position.
+const RULES: &[(&str, &str)] = &[
+    ("tion", "SH AH N"), ("sion", "ZH AH N"), ("ough", "AO"), ("augh", "AO"),
+    ("igh", "AY"), ("tch", "CH"), ("dge", "JH"), ("ear", "IH R"), ("air", "EH R"),
+    ("sh", "SH"), ("ch", "CH"), ("th", "TH"), ("ph", "F"), ("wh", "W"), ("ng", "NG"),
+    ("ck", "K"), ("qu", "K W"), ("gh", ""), ("kn", "N"), ("wr", "R"),
+    ("ee", "IY"), ("ea", "IY"), ("ie", "IY"), ("oo", "UW"), ("ou", "AW"), ("oi", "OY"),
+    ("oy", "OY"), ("ai", "EY"), ("ay", "EY"), ("ey", "EY"), ("oa", "OW"), ("au", "AO"),
+    ("aw", "AO"), ("ew", "UW"), ("ue", "UW"), ("ui", "UW"),
+    ("ar", "AA R"), ("or", "AO R"), ("er", "ER"), ("ir", "ER"), ("ur", "ER"),
+    ("a", "AE"), ("e", "EH"), ("i", "IH"), ("o", "AA"), ("u", "AH"),
+    ("b", "B"), ("d", "D"), ("f", "F"), ("g", "G"), ("h", "HH"), ("j", "JH"), ("k", "K"),
+    ("l", "L"), ("m", "M"), ("n", "N"), ("p", "P"), ("r", "R"), ("s", "S"), ("t", "T"),
+    ("v", "V"), ("w", "W"), ("x", "K S"), ("z", "Z"),
+];
+
+fn is_vowel(c: char) -> bool {
+    matches!(c, 'a' | 'e' | 'i' | 'o' | 'u')
+}
+
+/// Phoneme names for one lowercase word.
+fn word_phonemes(word: &str) -> Vec<&'static str> {
+    if let Some((_, phones)) = EXCEPTIONS.iter().find(|(w, _)| *w == word) {
+        return phones.split(' ').collect();
+    }
+
+    let chars: Vec<char> = word.chars().collect();
+    let len = chars.len();
+    // A silent final `e` lengthens the vowel before a single consonant (`make`).
+    let magic_e = len >= 3 && chars[len - 1] == 'e' && !is_vowel(chars[len - 2]) && is_vowel(chars[len - 3]);
+    let end = if len > 2 && chars[len - 1] == 'e' && !is_vowel(chars[len - 2]) { len - 1 } else { len };
+
+    let mut out: Vec<&'static str> = Vec::new();
+    let mut i = 0;
+    while i < end {
+        let c = chars[i];
+        // Double consonants sound once.
+        if i > 0 && chars[i - 1] == c && !is_vowel(c) {
+            i += 1;
+            continue;
+        }
+        if magic_e && i == len - 3 {
+            let long = match c {
+                'a' => "EY",
+                'e' => "IY",
+                'i' => "AY",
+                'o' => "OW",
+                _ => "UW",
+            };
+            out.push(long);
+            i += 1;
+            continue;
+        }
+        match c {
+            'c' => {
+                let soft = chars.get(i + 1).is_some_and(|n| matches!(n, 'e' | 'i' | 'y'));
+                out.push(if soft { "S" } else { "K" });
+                i += 1;
+                continue;
+            }
+            'y' => {
+                out.push(match i {
+                    0 => "Y",
+                    _ if i == len - 1 && len <= 3 => "AY",
+                    _ if i == len - 1 => "IY",
+                    _ => "IH",
+                });
+                i += 1;
+                continue;
+            }
+            _ => {}
+        }
+
+        let rest: String = chars[i..end].iter().collect();
+        match RULES.iter().find(|(graphemes, _)| rest.starts_with(graphemes)) {
+            Some((graphemes, phones)) => {
+                out.extend(phones.split(' ').filter(|p| !p.is_empty()));
+                i += graphemes.chars().count();
+            }
+            None => i += 1,
+        }
+    }
+    out
+}
+
+const ONES: [&str; 20] = [
+    "zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten",
+    "eleven", "twelve", "thirteen", "fourteen", "fifteen", "sixteen", "seventeen", "eighteen", "nineteen",
+];
+const TENS: [&str; 10] = ["", "", "twenty", "thirty", "forty", "fifty", "sixty", "seventy", "eighty", "ninety"];
+
+fn number_words(n: u64) -> String {
+    match n {
+        0..=19 => ONES[n as usize].to_string(),
+        20..=99 if n.is_multiple_of(10) => TENS[(n / 10) as usize].to_string(),
+        20..=99 => format!("{} {}", TENS[(n / 10) as usize], ONES[(n % 10) as usize]),
+        100..=999 if n.is_multiple_of(100) => format!("{} hundred", ONES[(n / 100) as usize]),
+        100..=999 => format!("{} hundred {}", ONES[(n / 100) as usize], number_words(n % 100)),
+        1000..=999_999 if n.is_multiple_of(1000) => format!("{} thousand", number_words(n / 1000)),
+        1000..=999_999 => format!("{} thousand {}", number_words(n / 1000), number_words(n % 1000)),
+        _ => n.to_string().chars().map(|d| ONES[d.to_digit(10).unwrap_or(0) as usize]).collect::<Vec<_>>().join(" "),
+    }
+}
+
+/// Converts text to a phoneme sequence, with `_` for phrase pauses.
+fn text_to_phonemes(text: &str) -> Vec<&'static str> {
+    let mut out = Vec::new();
+    let mut word = String::new();
+    let flush = |word: &mut String, out: &mut Vec<&'static str>| {
+        if word.is_empty() {
+            return;
+        }
+        let spoken = match word.parse::<u64>() {
+            Ok(n) => number_words(n),
+            Err(_) => word.clone(),
+        };
+        for part in spoken.split(' ') {
+            for phoneme in word_phonemes(part) {
+                // Affricates are a stop followed by a fricative.
+                match phoneme {
+                    "CH" => out.extend(["T", "SH"]),
+                    "JH" => out.extend(["D", "ZH"]),
+                    other => out.push(other),
+                }
+            }
+        }
+        word.clear();
+    };
+
+    for c in text.chars() {
+        if c.is_ascii_alphanumeric() || (c == '\'' && !word.is_empty()) {
+            if c != '\'' {
+                word.push(c.to_ascii_lowercase());
+            }
+        } else {
+            flush(&mut word, &mut out);
+            if matches!(c, ',' | ';' | ':' | '.' | '!' | '?' | '(' | ')') && out.last() != Some(&"_") && !out.is_empty() {
+                out.push("_");
+            }
+        }
+    }
+    flush(&mut word, &mut out);
+    while out.last() == Some(&"_") {
+        out.pop();
+    }
+    out
+}
+
+/// Two-pole resonator (Klatt 1980).
+#[derive(Default)]
+struct Resonator {
+    a: f32,
+    b: f32,
+    c: f32,
+    y1: f32,
+    y2: f32,
+}
+
+impl Resonator {
+    fn set(&mut self, frequency: f32, bandwidth: f32) {
+        let t = 1.0 / SAMPLE_RATE as f32;
+        self.c = -(-2.0 * PI * bandwidth * t).exp();
+        self.b = 2.0 * (-PI * bandwidth * t).exp() * (2.0 * PI * frequency * t).cos();
+        self.a = 1.0 - self.b - self.c;
+    }
+
+    /// Rescales for unity gain at `frequency` instead of at DC, so noise
+    /// bands keep their level wherever they are centred.
+    fn normalise_at(&mut self, frequency: f32) {
+        let w = 2.0 * PI * frequency / SAMPLE_RATE as f32;
+        let re = 1.0 - self.b * w.cos() - self.c * (2.0 * w).cos();
+        let im = self.b * w.sin() + self.c * (2.0 * w).sin();
+        self.a = (re * re + im * im).sqrt();
+    }
+
+    fn process(&mut self, x: f32) -> f32 {
+        let y = self.a * x + self.b * self.y1 + self.c * self.y2;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// xorshift32; seeded per utterance so the output is reproducible.
+struct Noise(u32);
+
+impl Noise {
+    fn next(&mut self) -> f32 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 17;
+        self.0 ^= self.0 << 5;
+        (self.0 as f32 / u32::MAX as f32) * 2.0 - 1.0
+    }
+}
+
+fn lerp3(a: [f32; 3], b: [f32; 3], t: f32) -> [f32; 3] {
+    [a[0] + (b[0] - a[0]) * t, a[1] + (b[1] - a[1]) * t, a[2] + (b[2] - a[2]) * t]
+}
+
+const BANDWIDTHS: [f32; 3] = [60.0, 90.0, 150.0];
+const TRANSITION_MS: f32 = 30.0;
+const CONTROL_INTERVAL: usize = 64;
+
+fn render(phonemes: &[&'static Phone], voice: &FormantVoice, speed: f32, question: bool) -> Vec<f32> {
+    let sample_rate = SAMPLE_RATE as f32;
+    let speed = speed.clamp(0.25, 4.0);
+    let total_ms: f32 = phonemes.iter().map(|p| p.duration_ms).sum::<f32>() / speed;
+    let total_samples = (total_ms * sample_rate / 1000.0) as usize;
+
+    let mut out = Vec::with_capacity(total_samples);
+    let mut resonators: [Resonator; 3] = Default::default();
+    let mut noise_filter;
+    let mut noise = Noise(0x9e37_79b9);
+    let mut glottal_phase = 0.0f32;
+    let mut previous_pulse = 0.0f32;
+    let mut current = phonemes.first().map(|p| p.formants).unwrap_or([500.0, 1500.0, 2500.0]);
+
+    for (index, phone) in phonemes.iter().enumerate() {
+        let samples = (phone.duration_ms / speed * sample_rate / 1000.0) as usize;
+        let ramp = ((5.0 * sample_rate / 1000.0) as usize).min(samples / 2).max(1);
+        let transition = (TRANSITION_MS / speed * sample_rate / 1000.0) as usize;
+        let start_formants = current;
+        let next_is_vowel = phonemes.get(index + 1).is_some_and(|p| p.class == Class::Vowel);
+        noise_filter = Resonator::default();
+        if phone.noise.0 > 0.0 {
+            noise_filter.set(phone.noise.0, phone.noise.0 * 0.4);
+            noise_filter.normalise_at(phone.noise.0);
+        }
+
+        for n in 0..samples {
+            let progress = (out.len() as f32 / total_samples.max(1) as f32).min(1.0);
+            // Gentle declination, or a final rise for questions.
+            let contour = if question && progress > 0.75 { 0.95 + (progress - 0.75) * 1.2 } else { 1.1 - 0.2 * progress };
+            let pitch = voice.pitch_hz * contour;
+
+            if n % CONTROL_INTERVAL == 0 {
+                let target = match phone.glide_to {
+                    Some(to) => lerp3(phone.formants, to, n as f32 / samples as f32),
+                    None => phone.formants,
+                };
+                let t = if transition == 0 { 1.0 } else { (n as f32 / transition as f32).min(1.0) };
+                current = lerp3(start_formants, target, t);
+                for (i, resonator) in resonators.iter_mut().enumerate() {
+                    resonator.set(current[i] * voice.formant_scale, BANDWIDTHS[i]);
+                }
+            }
+
+            // Rosenberg glottal pulse, differentiated for lip radiation.
+            glottal_phase = (glottal_phase + pitch / sample_rate).fract();
+            let pulse = if glottal_phase < 0.4 {
+                0.5 * (1.0 - (PI * glottal_phase / 0.4).cos())
+            } else if glottal_phase < 0.56 {
+                (PI * (glottal_phase - 0.4) / 0.32).cos()
+            } else {
+                0.0
+            };
+            let voicing = pulse - previous_pulse;
+            previous_pulse = pulse;
+
+            let envelope = if n < ramp {
+                n as f32 / ramp as f32
+            } else if n + ramp > samples {
+                (samples - n) as f32 / ramp as f32
+            } else {
+                1.0
+            };
+
+            let (voiced_gain, noise_gain) = match phone.class {
+                Class::Vowel | Class::Sonorant => (phone.amplitude, 0.0),
+                Class::Fricative { .. } => (phone.amplitude, phone.noise.1),
+                Class::Plosive { voiced } => {
+                    // Closure, then a short burst; aspiration before vowels.
+                    let closure = samples * 6 / 10;
+                    if n < closure {
+                        (if voiced { 0.15 } else { 0.0 }, 0.0)
+                    } else {
+                        let aspiration = if !voiced && next_is_vowel { 0.5 } else { 0.0 };
+                        (if voiced { phone.amplitude } else { aspiration * 0.3 }, phone.noise.1)
+                    }
+                }
+                Class::Pause => (0.0, 0.0),
+            };
+
+            let mut sample = voicing * 6.0 * voiced_gain;
+            for resonator in resonators.iter_mut() {
+                sample = resonator.process(sample);
+            }
+            if noise_gain > 0.0 {
+                sample += noise_filter.process(noise.next()) * noise_gain;
+            }
+            out.push(sample * envelope);
+        }
+    }
+
+    let peak = out.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+    if peak > 0.0 {
+        let gain = 0.8 / peak;
+        out.iter_mut().for_each(|s| *s *= gain);
+    }
+    out
+}
+
+pub struct FormantEngine;
+
+impl FormantEngine {
+    pub fn capabilities() -> EngineCapabilities {
+        EngineCapabilities {
+            id: ENGINE_ID,
+            name: "Formant (built-in)",
+            version: ENGINE_VERSION.to_string(),
+            sample_rate: SAMPLE_RATE,
+            languages: vec!["en"],
+            voices: VOICES
+                .iter()
+                .map(|v| VoiceInfo { id: v.id, label: v.label, language: "en" })
+                .collect(),
+            requires_model: false,
+        }
+    }
+
+    fn synth_blocking(text: &str, voice: &str, speed: f32) -> Vec<f32> {
+        let voice = VOICES.iter().find(|v| v.id == voice).unwrap_or(&VOICES[0]);
+        let phonemes: Vec<&'static Phone> = text_to_phonemes(text).into_iter().filter_map(lookup).collect();
+        render(&phonemes, voice, speed, text.trim_end().ends_with('?'))
+    }
+}
+
+impl SpeechEngine for FormantEngine {
+    fn capabilities(&self) -> EngineCapabilities {
+        Self::capabilities()
+    }
+
+    fn synth<'a>(&'a self, text: &'a str, voice: &'a str, speed: f32) -> Pin<Box<dyn Future<Output = Result<Vec<f32>, String>> + Send + 'a>> {
+        Box::pin(async move { Ok(Self::synth_blocking(text, voice, speed)) })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_letter_to_sound_rules() {
+        assert_eq!(word_phonemes("ship"), vec!["SH", "IH", "P"]);
+        assert_eq!(word_phonemes("make"), vec!["M", "EY", "K"]);
+        assert_eq!(word_phonemes("city"), vec!["S", "IH", "T", "IY"]);
+        assert_eq!(text_to_phonemes("Hi, 12"), vec!["HH", "IH", "_", "T", "W", "EH", "L", "V"]);
+        assert!(text_to_phonemes("Notes.").iter().all(|p| lookup(p).is_some()));
+    }
+
+    #[test]
+    fn test_synthesis_is_bounded_and_deterministic() {
+        let a = FormantEngine::synth_blocking("The quick brown fox jumps over the lazy dog.", "formant_low", 1.0);
+        let b = FormantEngine::synth_blocking("The quick brown fox jumps over the lazy dog.", "formant_low", 1.0);
+        assert_eq!(a, b);
+        assert!(a.len() > SAMPLE_RATE as usize);
+        assert!(a.iter().all(|s| s.is_finite() && s.abs() <= 0.81));
+
+        let faster = FormantEngine::synth_blocking("The quick brown fox jumps over the lazy dog.", "formant_low", 2.0);
+        assert!(faster.len() < a.len() * 6 / 10);
+        assert!(FormantEngine::synth_blocking("...", "formant_high", 1.0).is_empty());
+    }
+}
@@ -0,0 +1,271 @@
+use regex::Regex;
+use serde_json::Value;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::OnceLock;
+
+use super::engine::EngineCapabilities;
+
+/// Object mapping a language code (`fr`, `en-gb`) to the voice that reads it.
+pub const LANGUAGE_VOICES_SETTING: &str = "plugins.core.tts.languageVoices";
+/// Whether sentences without a hint are checked for another language.
+pub const DETECT_LANGUAGE_SETTING: &str = "plugins.core.tts.detectLanguage";
+
+/// Sentences shorter than this are too short to detect reliably.
+const MIN_DETECT_WORDS: usize = 3;
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct LanguageSettings {
+    pub detect: bool,
+    pub voices: BTreeMap<String, String>,
+}
+
+impl LanguageSettings {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Self {
+        let voices = settings
+            .get(LANGUAGE_VOICES_SETTING)
+            .and_then(|v| v.as_object())
+            .map(|map| {
+                map.iter()
+                    .filter_map(|(lang, voice)| Some((lang.to_lowercase(), voice.as_str()?.to_string())))
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            detect: settings.get(DETECT_LANGUAGE_SETTING).and_then(|v| v.as_bool()).unwrap_or(true),
+            voices,
+        }
+    }
+
+    /// Stable description for the cache fingerprint; empty for the defaults,
+    /// so audio cached before languages were configurable stays valid.
+    pub fn fingerprint(&self) -> String {
+        if self.detect && self.voices.is_empty() {
+            return String::new();
+        }
+        let voices: Vec<String> = self.voices.iter().map(|(lang, voice)| format!("{}={}", lang, voice)).collect();
+        format!("detect={},voices={}", self.detect, voices.join(","))
+    }
+}
+
+/// `fr-CA` and `fr_ca` both become `fr`.
+pub fn base(lang: &str) -> String {
+    lang.split(['-', '_']).next().unwrap_or(lang).to_lowercase()
+}
+
+fn same_language(a: &str, b: &str) -> bool {
+    base(a) == base(b)
+}
+
+/// `<!-- lang fr -->` switches the language of the text after it;
+/// `<!-- lang auto -->` returns to detection.
+fn hint_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"(?i)<!--\s*lang(?:uage)?\s*:?\s*(auto|[a-z]{2,3}(?:[-_][a-z0-9]{2,8})?)\s*-->").expect("valid hint pattern")
+    })
+}
+
+/// Language hints in `text`, as byte offsets where they take effect. `None`
+/// means back to automatic.
+pub fn hints(text: &str) -> Vec<(usize, Option<String>)> {
+    hint_regex()
+        .captures_iter(text)
+        .map(|caps| {
+            let lang = caps[1].to_lowercase();
+            let end = caps.get(0).map_or(0, |m| m.end());
+            (end, (lang != "auto").then_some(lang))
+        })
+        .collect()
+}
+
+/// The hint in force at `offset`: the last one before it, if any.
+pub fn hint_at(hints: &[(usize, Option<String>)], offset: usize) -> Option<&str> {
+    hints
+        .iter()
+        .take_while(|(start, _)| *start <= offset)
+        .last()
+        .and_then(|(_, lang)| lang.as_deref())
+}
+
+const STOPWORDS: [(&str, &[&str]); 6] = [
+    ("en", &["the", "and", "of", "to", "is", "in", "that", "it", "with", "for", "was", "this", "you", "are", "not", "have", "what", "which", "be", "on"]),
+    ("fr", &["le", "la", "les", "des", "est", "et", "une", "un", "du", "dans", "que", "qui", "pour", "pas", "avec", "sur", "ce", "cette", "nous", "vous", "je", "il", "elle", "sont", "au", "aux", "mais", "très"]),
+    ("es", &["el", "los", "las", "es", "y", "una", "del", "que", "en", "por", "con", "para", "no", "se", "está", "muy", "pero", "como"]),
+    ("de", &["der", "die", "das", "und", "ist", "nicht", "ein", "eine", "mit", "ich", "sie", "auf", "für", "den", "dem", "zu", "auch", "sehr"]),
+    ("it", &["il", "lo", "gli", "è", "e", "una", "che", "di", "non", "per", "con", "sono", "della", "anche", "molto", "questo"]),
+    ("pt", &["o", "os", "é", "e", "uma", "que", "não", "para", "com", "em", "do", "da", "são", "muito", "também", "mas"]),
+];
+
+/// Letters that single out a language among those above.
+const MARKERS: [(&str, &[char]); 5] = [
+    ("fr", &['ç', 'è', 'ê', 'œ', 'à', 'ù', 'î', 'û']),
+    ("es", &['ñ', '¿', '¡']),
+    ("de", &['ß', 'ä', 'ö', 'ü']),
+    ("pt", &['ã', 'õ']),
+    ("it", &['ì', 'ò']),
+];
+
+/// Guesses the language of one sentence from its script, or for Latin text
+/// from common words. Returns `None` unless it is fairly sure.
+pub fn detect(sentence: &str) -> Option<&'static str> {
+    let mut han = 0;
+    let mut kana = 0;
+    let mut hangul = 0;
+    let mut cyrillic = 0;
+    let mut latin = 0;
+    for c in sentence.chars() {
+        match c {
+            '\u{3040}'..='\u{30FF}' => kana += 1,
+            '\u{4E00}'..='\u{9FFF}' => han += 1,
+            '\u{AC00}'..='\u{D7AF}' => hangul += 1,
+            '\u{0400}'..='\u{04FF}' => cyrillic += 1,
+            c if c.is_alphabetic() => latin += 1,
+            _ => {}
+        }
+    }
+    let script = [(kana, "ja"), (hangul, "ko"), (cyrillic, "ru")]
+        .into_iter()
+        .find(|(count, _)| *count > 0 && *count * 2 >= latin);
+    if let Some((_, lang)) = script {
+        return Some(lang);
+    }
+    if han > 0 && han * 2 >= latin {
+        return Some("zh");
+    }
+
+    let words: Vec<String> = sentence
+        .split(|c: char| !c.is_alphabetic() && c != '\'')
+        .filter(|w| !w.is_empty())
+        .map(|w| w.to_lowercase())
+        .collect();
+    if words.len() < MIN_DETECT_WORDS {
+        return None;
+    }
+    let mut scores: Vec<(&'static str, usize)> = STOPWORDS
+        .iter()
+        .map(|(lang, stopwords)| {
+            let mut score = words.iter().filter(|w| stopwords.contains(&w.as_str())).count();
+            // Elisions such as l'homme and qu'il.
+            if *lang == "fr" {
+                score += words.iter().filter(|w| w.contains('\'') && w.len() > 2).count();
+            }
+            if let Some((_, markers)) = MARKERS.iter().find(|(marker_lang, _)| marker_lang == lang) {
+                if sentence.to_lowercase().chars().any(|c| markers.contains(&c)) {
+                    score += 2;
+                }
+            }
+            (*lang, score)
+        })
+        .collect();
+    scores.sort_by_key(|(_, score)| std::cmp::Reverse(*score));
+    let (best, best_score) = scores[0];
+    (best_score >= 2 && best_score > scores[1].1).then_some(best)
+}
+
+/// The voice for one sentence and, when it had to fall back, why.
+pub struct VoiceChoice {
+    pub voice: String,
+    pub lang: Option<String>,
+    pub warning: Option<String>,
+}
+
+fn voice_language<'a>(capabilities: &'a EngineCapabilities, voice: &str) -> Option<&'a str> {
+    capabilities
+        .voices
+        .iter()
+        .find(|v| v.id == voice)
+        .or(capabilities.voices.first())
+        .map(|v| v.language)
+}
+
+/// Picks the voice for a sentence in `lang`. Sentences in the requested
+/// voice's own language keep it; others use the configured voice for their
+/// language, or any engine voice that speaks it.
+pub fn choose_voice(lang: Option<&str>, requested_voice: &str, settings: &LanguageSettings, capabilities: &EngineCapabilities) -> VoiceChoice {
+    let keep = |warning: Option<String>| VoiceChoice {
+        voice: requested_voice.to_string(),
+        lang: lang.map(String::from),
+        warning,
+    };
+    let Some(lang) = lang else {
+        return keep(None);
+    };
+    if voice_language(capabilities, requested_voice).is_some_and(|own| same_language(own, lang)) {
+        return keep(None);
+    }
+    if !capabilities.languages.iter().any(|supported| same_language(supported, lang)) {
+        return keep(Some(format!(
+            "{} does not support language '{}'; reading it with {}",
+            capabilities.name, lang, requested_voice
+        )));
+    }
+
+    let configured = settings.voices.get(&lang.to_lowercase()).or_else(|| settings.voices.get(&base(lang)));
+    if let Some(voice) = configured {
+        if capabilities.voices.iter().any(|v| v.id == voice) {
+            return VoiceChoice {
+                voice: voice.clone(),
+                lang: Some(lang.to_string()),
+                warning: None,
+            };
+        }
+        return keep(Some(format!(
+            "Voice '{}' configured for '{}' is not available in {}; reading it with {}",
+            voice, lang, capabilities.name, requested_voice
+        )));
+    }
+    match capabilities.voices.iter().find(|v| same_language(v.language, lang)) {
+        Some(voice) => VoiceChoice {
+            voice: voice.id.to_string(),
+            lang: Some(lang.to_string()),
+            warning: None,
+        },
+        None => keep(Some(format!(
+            "{} has no voice for language '{}'; reading it with {}",
+            capabilities.name, lang, requested_voice
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use super::super::engine::EngineKind;
+
+    #[test]
+    fn test_detect() {
+        assert_eq!(detect("The cat sat on the mat with a hat."), Some("en"));
+        assert_eq!(detect("Nous sommes allés à la plage avec les enfants."), Some("fr"));
+        assert_eq!(detect("Der Hund ist sehr müde und schläft."), Some("de"));
+        assert_eq!(detect("今天天气很好。"), Some("zh"));
+        assert_eq!(detect("OK then."), None);
+    }
+
+    #[test]
+    fn test_hints() {
+        let text = "Intro. <!-- lang fr --> Bonjour. <!-- lang: auto --> Bye.";
+        let hints = hints(text);
+        assert_eq!(hint_at(&hints, 0), None);
+        assert_eq!(hint_at(&hints, text.find("Bonjour").unwrap()), Some("fr"));
+        assert_eq!(hint_at(&hints, text.find("Bye").unwrap()), None);
+    }
+
+    #[test]
+    fn test_choose_voice() {
+        let kokoro = EngineKind::Kokoro.capabilities();
+        let mut settings = LanguageSettings::from_settings(&HashMap::new());
+
+        let english = choose_voice(Some("en-gb"), "af_sky", &settings, &kokoro);
+        assert_eq!(english.voice, "af_sky");
+        assert!(english.warning.is_none());
+
+        let chinese = choose_voice(Some("zh"), "af_sky", &settings, &kokoro);
+        assert_eq!(chinese.voice, "zf_xiaobei");
+        settings.voices.insert("zh".into(), "zm_yunjian".into());
+        assert_eq!(choose_voice(Some("zh-CN"), "af_sky", &settings, &kokoro).voice, "zm_yunjian");
+
+        let french = choose_voice(Some("fr"), "af_sky", &settings, &kokoro);
+        assert_eq!(french.voice, "af_sky");
+        assert!(french.warning.unwrap().contains("'fr'"));
+    }
+}
@@ -0,0 +1,195 @@
+use regex::{Regex, RegexBuilder};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+
+/// Location of the pronunciation lexicon, relative to the vault root.
+pub const LEXICON_PATH: &str = ".liminal/tts/lexicon.json";
+
+#[derive(Debug, Deserialize)]
+struct LexiconFile {
+    #[serde(default)]
+    entries: Vec<LexiconEntry>,
+}
+
+/// One lexicon rule. `match` is a literal word unless `regex` is set, in
+/// which case `say` may refer to capture groups (`$1`).
+#[derive(Debug, Deserialize)]
+struct LexiconEntry {
+    #[serde(rename = "match")]
+    pattern: String,
+    #[serde(default)]
+    say: Option<String>,
+    #[serde(default)]
+    phonemes: Option<String>,
+    #[serde(default)]
+    regex: bool,
+    #[serde(default)]
+    case_sensitive: bool,
+    #[serde(default = "default_whole_word")]
+    whole_word: bool,
+}
+
+fn default_whole_word() -> bool {
+    true
+}
+
+struct Rule {
+    regex: Regex,
+    replacement: String,
+}
+
+/// Compiled pronunciation overrides, applied in file order to each sentence
+/// after sanitising.
+#[derive(Default)]
+pub struct Lexicon {
+    rules: Vec<Rule>,
+    /// Entries that were not applied, with the reason.
+    pub skipped: Vec<String>,
+    /// SHA-256 of the lexicon file; empty when there is none.
+    pub digest: String,
+    pub path: Option<PathBuf>,
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// `\b` only holds next to word characters, so terms like `C++` get a
+/// boundary on the side that has one.
+fn literal_pattern(word: &str, whole_word: bool) -> String {
+    let escaped = regex::escape(word);
+    if !whole_word {
+        return escaped;
+    }
+    let start = if word.starts_with(is_word_char) { r"\b" } else { "" };
+    let end = if word.ends_with(is_word_char) { r"\b" } else { "" };
+    format!("{}{}{}", start, escaped, end)
+}
+
+impl Lexicon {
+    pub fn parse(content: &str) -> Result<Self, String> {
+        let file: LexiconFile =
+            serde_json::from_str(content).map_err(|e| format!("Pronunciation lexicon is invalid: {}", e))?;
+        let mut lexicon = Self {
+            digest: hex::encode(Sha256::digest(content.as_bytes())),
+            ..Default::default()
+        };
+
+        for (index, entry) in file.entries.into_iter().enumerate() {
+            let Some(replacement) = entry.say else {
+                if entry.phonemes.is_some() {
+                    // kokoro-tts runs its own G2P on the text it is given, so
+                    // there is no way to hand it phonemes directly.
+                    lexicon.skipped.push(format!(
+                        "'{}': phoneme overrides are not supported by this engine; use \"say\" with a respelling",
+                        entry.pattern
+                    ));
+                } else {
+                    lexicon.skipped.push(format!("'{}': missing \"say\"", entry.pattern));
+                }
+                continue;
+            };
+            if entry.pattern.is_empty() {
+                lexicon.skipped.push(format!("Entry {}: empty \"match\"", index + 1));
+                continue;
+            }
+
+            let pattern = if entry.regex {
+                entry.pattern.clone()
+            } else {
+                literal_pattern(&entry.pattern, entry.whole_word)
+            };
+            let regex = match RegexBuilder::new(&pattern).case_insensitive(!entry.case_sensitive).build() {
+                Ok(regex) => regex,
+                Err(e) => {
+                    lexicon.skipped.push(format!("'{}': not a valid pattern: {}", entry.pattern, e));
+                    continue;
+                }
+            };
+            // Literal replacements must not expand `$`.
+            let replacement = if entry.regex { replacement } else { replacement.replace('$', "$$") };
+            lexicon.rules.push(Rule { regex, replacement });
+        }
+        Ok(lexicon)
+    }
+
+    /// Reads `.liminal/tts/lexicon.json` from the vault; a missing file is an
+    /// empty lexicon.
+    pub fn load(vault_root: &Path) -> Result<Self, String> {
+        let path = vault_root.join(LEXICON_PATH);
+        let content = match std::fs::read_to_string(&path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e.to_string()),
+        };
+        let mut lexicon = Self::parse(&content)?;
+        lexicon.path = Some(path);
+        Ok(lexicon)
+    }
+
+    pub fn rule_count(&self) -> usize {
+        self.rules.len()
+    }
+
+    pub fn apply(&self, text: &str) -> String {
+        let mut out = text.to_string();
+        for rule in &self.rules {
+            out = rule.regex.replace_all(&out, rule.replacement.as_str()).into_owned();
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_whole_word_and_case_rules() {
+        let lexicon = Lexicon::parse(
+            r#"{ "entries": [
+                { "match": "SQL", "say": "sequel", "case_sensitive": true },
+                { "match": "nginx", "say": "engine x" },
+                { "match": "C++", "say": "C plus plus" },
+                { "match": "$HOME", "say": "home", "whole_word": false }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(lexicon.apply("SQL and sql, NoSQL."), "sequel and sql, NoSQL.");
+        assert_eq!(lexicon.apply("Nginx fronts nginxes."), "engine x fronts nginxes.");
+        assert_eq!(lexicon.apply("Write C++ daily."), "Write C plus plus daily.");
+        assert_eq!(lexicon.apply("cd $HOME"), "cd home");
+    }
+
+    #[test]
+    fn test_regex_entries_and_skipped_phonemes() {
+        let lexicon = Lexicon::parse(
+            r#"{ "entries": [
+                { "match": "\\bv(\\d+)\\b", "say": "version $1", "regex": true },
+                { "match": "Nguyen", "phonemes": "wɪn" }
+            ] }"#,
+        )
+        .unwrap();
+
+        assert_eq!(lexicon.apply("Ship v2 today"), "Ship version 2 today");
+        assert_eq!(lexicon.rule_count(), 1);
+        assert_eq!(lexicon.skipped.len(), 1);
+
+        let broken = Lexicon::parse(
+            r#"{ "entries": [ { "match": "(", "say": "x", "regex": true }, { "match": "k8s", "say": "kubernetes" } ] }"#,
+        )
+        .unwrap();
+        assert_eq!(broken.rule_count(), 1);
+        assert!(broken.skipped[0].contains("not a valid pattern"));
+    }
+
+    #[test]
+    fn test_missing_lexicon_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let lexicon = Lexicon::load(dir.path()).unwrap();
+        assert_eq!(lexicon.rule_count(), 0);
+        assert!(lexicon.digest.is_empty());
+    }
+}
@@ -1,20 +1,47 @@
 use super::traits::{NativeBackendPlugin, NativePluginContext, ActivePlugin, Invocable};
+use crate::frontmatter::Frontmatter;
+use audiobook::{AudiobookJob, AudiobookProgress};
+use cache::PipelineFingerprint;
+use export::{ExportProgress, VorbisComments};
+use download::{set_install_progress, Download, DownloadContext, InstallProgress, RetryPolicy};
+use engine::EngineKind;
+use language::LanguageSettings;
+use lexicon::Lexicon;
+use model::{MODEL_FILENAME, VOICES_FILENAME};
+use pauses::PauseSettings;
+use playback::{AudioSink, EventSink, NullSink, Player, SinkKind, Track};
+use queue::{JobHandle, JobPriority, JobQueue};
+use residency::{EngineSlot, LoadedEngine};
 use serde_json::Value;
 use std::sync::Arc;
 use std::sync::atomic::{AtomicBool, Ordering};
 use tokio::sync::Mutex;
 use std::future::Future;
 use std::pin::Pin;
-use tauri::{Runtime, Manager};
+use tauri::{http, Emitter, Manager, Runtime, UriSchemeContext, UriSchemeResponder};
 use std::path::{Path, PathBuf};
-use kokoro_tts::{KokoroTts, Voice};
 use hound::{WavSpec, WavWriter};
-use sha2::{Sha256, Digest};
-use unicode_segmentation::UnicodeSegmentation;
-use futures::StreamExt;
-use tokio::io::AsyncWriteExt;
 use base64::Engine;
 
+mod audiobook;
+mod cache;
+mod download;
+mod engine;
+mod export;
+mod formant;
+mod language;
+mod lexicon;
+mod model;
+mod output;
+mod pack;
+mod pauses;
+mod playback;
+mod queue;
+mod residency;
+mod source;
+mod stream;
+mod timing;
+
 pub struct TtsPlugin;
 
 impl<R: Runtime> NativeBackendPlugin<R> for TtsPlugin {
@@ -26,9 +53,15 @@ impl<R: Runtime> NativeBackendPlugin<R> for TtsPlugin {
         let app_data_dir = ctx.app_handle.path().app_data_dir().map_err(|e| e.to_string())?;
         let tts_dir = app_data_dir.join("tts");
         std::fs::create_dir_all(&tts_dir).map_err(|e| e.to_string())?;
+        let settings_path = ctx.settings_path()?;
+        let vault_config_path = ctx.vault_config_path()?;
+        let app_handle = ctx.app_handle.clone();
+        let events: EventSink = Arc::new(move |event: &str, payload: Value| {
+            let _ = app_handle.emit(event, payload);
+        });
 
         Ok(ActivePlugin {
-            instance: Arc::new(TtsInstance::new(tts_dir)),
+            instance: Arc::new(TtsInstance::new(tts_dir, settings_path, vault_config_path, events)),
         })
     }
 
@@ -37,41 +70,69 @@ impl<R: Runtime> NativeBackendPlugin<R> for TtsPlugin {
     }
 }
 
+pub use stream::SCHEME as AUDIO_SCHEME;
+
+/// Serves cached audio over the `tts-audio` scheme, with range requests, off
+/// the main thread.
+pub fn audio_protocol<R: Runtime>(ctx: UriSchemeContext<'_, R>, request: http::Request<Vec<u8>>, responder: UriSchemeResponder) {
+    let cache_dir = ctx.app_handle().path().app_data_dir().map(|dir| cache::cache_dir(&dir.join("tts")));
+    tauri::async_runtime::spawn_blocking(move || {
+        let response = match cache_dir {
+            Ok(cache_dir) => {
+                let range = request.headers().get(http::header::RANGE).and_then(|v| v.to_str().ok());
+                stream::serve(&cache_dir, request.uri().path(), range)
+            }
+            Err(_) => stream::empty(http::StatusCode::NOT_FOUND),
+        };
+        responder.respond(response);
+    });
+}
+
+#[derive(Clone)]
+pub struct SynthesisRequest {
+    pub text: String,
+    pub voice: String,
+    pub speed: f32,
+    /// Include estimated per-word timings in the response.
+    pub word_timings: bool,
+    /// Language of the text, e.g. from the note's frontmatter. Sentences
+    /// that are not detected as another language are read in it.
+    pub lang: Option<String>,
+    /// UTF-16 `(start, end)` of the part of `text` to read; offsets in the
+    /// response stay in the coordinates of the whole text.
+    pub range: Option<(usize, usize)>,
+}
+
 pub struct TtsInstance {
     tts_dir: PathBuf,
-    engine: Arc<Mutex<Option<KokoroTts>>>,
-    cancel_flag: Arc<AtomicBool>,
+    settings_path: PathBuf,
+    vault_config_path: PathBuf,
+    engine: EngineSlot,
+    jobs: Arc<JobQueue>,
+    player: Arc<Player>,
+    install_cancel: Arc<AtomicBool>,
     install_progress: Arc<Mutex<InstallProgress>>,
+    export_cancel: Arc<AtomicBool>,
+    export_progress: Arc<Mutex<ExportProgress>>,
+    audiobook_cancel: Arc<AtomicBool>,
+    audiobook_progress: Arc<Mutex<AudiobookProgress>>,
 }
 
-const MODEL_FILENAME: &str = "kokoro-v1.0.onnx";
-const VOICES_FILENAME: &str = "voices.bin";
+/// Bump whenever `sanitise_sentence` changes the text it produces, so audio
+/// cached from the old output is invalidated.
+const SANITISER_VERSION: u32 = 1;
+/// Sentences synthesized ahead after a request that asks to continue.
+const PREFETCH_SENTENCES: usize = 8;
 
-#[derive(Clone, Default)]
-struct InstallProgress {
-    status: String,
-    phase: String,
-    downloaded_bytes: u64,
-    total_bytes: u64,
-}
 
-async fn set_install_progress(
-    progress: &Arc<Mutex<InstallProgress>>,
-    status: &str,
-    phase: &str,
-    downloaded_bytes: u64,
-    total_bytes: u64,
-) {
-    let mut guard = progress.lock().await;
-    guard.status = status.to_string();
-    guard.phase = phase.to_string();
-    guard.downloaded_bytes = downloaded_bytes;
-    guard.total_bytes = total_bytes;
+fn check_model_files(tts_dir: &Path) -> Result<(), String> {
+    check_model_dir(&tts_dir.join("models"))
 }
 
-fn check_model_files(tts_dir: &Path) -> Result<(), String> {
-    let model_path = tts_dir.join("models").join(MODEL_FILENAME);
-    let voices_path = tts_dir.join("models").join(VOICES_FILENAME);
+/// Checks model files in `dir` before they are installed, or once they are.
+fn check_model_dir(dir: &Path) -> Result<(), String> {
+    let model_path = dir.join(MODEL_FILENAME);
+    let voices_path = dir.join(VOICES_FILENAME);
     let model_meta = std::fs::metadata(&model_path)
         .map_err(|_| "Model file missing".to_string())?;
     let voices_meta = std::fs::metadata(&voices_path)
@@ -101,13 +162,16 @@ fn remove_model_files(tts_dir: &Path) -> Result<Value, String> {
     if model_dir.exists() {
         std::fs::remove_dir_all(&model_dir).map_err(|e| e.to_string())?;
     }
+    let tmp_dir = tts_dir.join("tmp");
+    if tmp_dir.exists() {
+        std::fs::remove_dir_all(&tmp_dir).map_err(|e| e.to_string())?;
+    }
     Ok(serde_json::json!({ "status": "model_removed" }))
 }
 
+/// Copies the files into a staging folder and only replaces the installed
+/// model once they pass the checks.
 fn import_local_files(tts_dir: &Path, source_dir: &Path) -> Result<Value, String> {
-    let model_dir = tts_dir.join("models");
-    std::fs::create_dir_all(&model_dir).map_err(|e| e.to_string())?;
-
     let source_model = source_dir.join(MODEL_FILENAME);
     let source_voices = source_dir.join(VOICES_FILENAME);
     let fallback_model = source_dir.join("kokoro-v1.0.int8.onnx");
@@ -129,11 +193,13 @@ fn import_local_files(tts_dir: &Path, source_dir: &Path) -> Result<Value, String
         return Err("Local voices file not found in the provided folder.".to_string());
     };
 
-    let target_model = model_dir.join(MODEL_FILENAME);
-    let target_voices = model_dir.join(VOICES_FILENAME);
-    std::fs::copy(&model_source, &target_model).map_err(|e| e.to_string())?;
-    std::fs::copy(&voices_source, &target_voices).map_err(|e| e.to_string())?;
-    check_model_files(tts_dir)?;
+    std::fs::create_dir_all(tts_dir).map_err(|e| e.to_string())?;
+    let staging = tempfile::Builder::new().prefix("import").tempdir_in(tts_dir).map_err(|e| e.to_string())?;
+    std::fs::copy(&model_source, staging.path().join(MODEL_FILENAME)).map_err(|e| e.to_string())?;
+    std::fs::copy(&voices_source, staging.path().join(VOICES_FILENAME)).map_err(|e| e.to_string())?;
+    check_model_dir(staging.path())?;
+    model::replace_model_files(tts_dir, staging.path())?;
+    model::write_model_stamp(tts_dir, None, "local")?;
 
     Ok(serde_json::json!({ "status": "imported" }))
 }
@@ -152,6 +218,27 @@ fn read_audio_file(tts_dir: &Path, path: &Path) -> Result<Value, String> {
     Ok(serde_json::json!({ "base64": encoded }))
 }
 
+/// Resolves cached audio paths for export, rejecting anything outside the cache.
+fn cached_audio_paths(tts_dir: &Path, payload: &Value) -> Result<Vec<PathBuf>, String> {
+    let cache_dir = tts_dir.join("cache").canonicalize().map_err(|e| e.to_string())?;
+    let raw: Vec<&str> = match payload.get("paths").and_then(|v| v.as_array()) {
+        Some(paths) => paths.iter().filter_map(|v| v.as_str()).collect(),
+        None => vec![payload["path"].as_str().ok_or("Missing path")?],
+    };
+    let mut paths = Vec::new();
+    for path in raw {
+        let target = Path::new(path).canonicalize().map_err(|e| e.to_string())?;
+        if !target.starts_with(&cache_dir) {
+            return Err("Invalid audio path.".to_string());
+        }
+        paths.push(target);
+    }
+    if paths.is_empty() {
+        return Err("Nothing to export.".to_string());
+    }
+    Ok(paths)
+}
+
 fn dir_stats(path: &Path) -> Result<(u64, u64), String> {
     if !path.exists() {
         return Ok((0, 0));
@@ -178,65 +265,523 @@ fn dir_stats(path: &Path) -> Result<(u64, u64), String> {
     Ok((total_bytes, total_files))
 }
 
-fn cache_stats(tts_dir: &Path) -> Result<Value, String> {
+fn current_pipeline(tts_dir: &Path, config: &SynthesisConfig, lexicon: &Lexicon) -> Result<PipelineFingerprint, String> {
+    let pauses = config.pauses.fingerprint();
+    let mut fingerprint = match config.kind {
+        EngineKind::Kokoro => {
+            let stamp = model::read_model_stamp(tts_dir)?;
+            PipelineFingerprint::new(&stamp, SANITISER_VERSION, &pauses, &lexicon.digest)
+        }
+        kind => {
+            let capabilities = kind.capabilities();
+            PipelineFingerprint::for_engine(
+                capabilities.id,
+                &capabilities.version,
+                SANITISER_VERSION,
+                &pauses,
+                &lexicon.digest,
+            )
+        }
+    };
+    fingerprint.languages = config.languages.fingerprint();
+    Ok(fingerprint)
+}
+
+/// The engine chosen in settings, given whether the Kokoro model is installed.
+fn selected_engine(tts_dir: &Path, settings_path: &Path) -> Result<EngineKind, String> {
+    let settings = crate::settings::read_settings_file(settings_path)?;
+    EngineKind::select(&settings, check_model_files(tts_dir).is_ok())
+}
+
+/// Settings that shape the audio of a request, read once when it starts.
+#[derive(Clone)]
+struct SynthesisConfig {
+    kind: EngineKind,
+    pauses: PauseSettings,
+    languages: LanguageSettings,
+    cache_limit_bytes: u64,
+}
+
+fn synthesis_config(tts_dir: &Path, settings_path: &Path) -> Result<SynthesisConfig, String> {
+    let settings = crate::settings::read_settings_file(settings_path)?;
+    Ok(SynthesisConfig {
+        kind: EngineKind::select(&settings, check_model_files(tts_dir).is_ok())?,
+        pauses: PauseSettings::from_settings(&settings),
+        languages: LanguageSettings::from_settings(&settings),
+        cache_limit_bytes: cache::limit_bytes(&settings),
+    })
+}
+
+/// The part of a request's text to read, in bytes and in UTF-16 units.
+struct TextRange {
+    start: usize,
+    end: usize,
+    start_char: usize,
+    end_char: usize,
+    total_chars: usize,
+}
+
+impl TextRange {
+    /// Clamps a UTF-16 `(start, end)` to `text`; `None` covers all of it.
+    fn resolve(text: &str, range: Option<(usize, usize)>) -> Self {
+        let total_chars = timing::utf16_len(text);
+        let (start_char, end_char) = match range {
+            Some((start, end)) => (start.min(total_chars), end.clamp(start.min(total_chars), total_chars)),
+            None => (0, total_chars),
+        };
+        Self {
+            start: timing::byte_offset(text, start_char),
+            end: timing::byte_offset(text, end_char),
+            start_char,
+            end_char,
+            total_chars,
+        }
+    }
+
+    /// Adds `range` to a response and, when text follows it, `next`: where
+    /// a continuation starts.
+    fn describe(&self, result: &mut Value) {
+        result["range"] = serde_json::json!({ "start": self.start_char, "end": self.end_char });
+        if self.end_char < self.total_chars {
+            result["next"] = Value::from(self.end_char);
+        }
+    }
+}
+
+struct PlannedSentence {
+    start: usize,
+    end: usize,
+    pause_after_ms: u32,
+    engine_text: String,
+    voice: String,
+    lang: Option<String>,
+}
+
+/// Synthesizes single sentences through the sentence cache, so overlapping
+/// requests and prefetching share audio.
+struct SentenceSynth<'a> {
+    tts_dir: &'a Path,
+    config: &'a SynthesisConfig,
+    cache_dir: &'a Path,
+    pipeline_id: &'a str,
+    speed: f32,
+}
+
+impl SentenceSynth<'_> {
+    async fn audio(&self, job: &JobHandle, sentence: &PlannedSentence, display_text: &str) -> Result<Vec<f32>, String> {
+        let key = cache::sentence_key(self.pipeline_id, &sentence.engine_text, &sentence.voice, self.speed);
+        let path = cache::audio_path(self.cache_dir, &key);
+        if cache::lookup(self.cache_dir, &key, self.pipeline_id).is_some() {
+            if let Ok(samples) = read_wav(&path) {
+                if job.is_cancelled() {
+                    return Err("Synthesis cancelled.".to_string());
+                }
+                return Ok(samples);
+            }
+        }
+
+        // The engine may have been unloaded since the last sentence.
+        let kind = self.config.kind;
+        let mut guard = job.turn().await?;
+        if guard.as_ref().map(|loaded| loaded.kind) != Some(kind) {
+            *guard = Some(LoadedEngine::load(kind, self.tts_dir).await?);
+        }
+        let loaded = guard.as_mut().ok_or("TTS engine is not loaded")?;
+        let samples = loaded.engine.synth(&sentence.engine_text, &sentence.voice, self.speed).await
+            .map_err(|e| {
+                if e.contains("Utf8") {
+                    "Model files appear corrupt. Reinstall the TTS model.".to_string()
+                } else {
+                    format!("Synthesis failed for segment '{}': {}", display_text, e)
+                }
+            })?;
+        loaded.touch();
+        drop(guard);
+
+        std::fs::create_dir_all(self.cache_dir).map_err(|e| e.to_string())?;
+        write_wav(&path, &samples, kind.capabilities().sample_rate)?;
+        cache::store(self.cache_dir, &key, self.pipeline_id, &serde_json::json!({ "sentence": true }), self.config.cache_limit_bytes)?;
+        Ok(samples)
+    }
+}
+
+fn write_wav(path: &Path, samples: &[f32], sample_rate: u32) -> Result<(), String> {
+    let spec = WavSpec {
+        channels: 1,
+        sample_rate,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+
+    let mut writer = WavWriter::create(path, spec).map_err(|e| e.to_string())?;
+    for sample in samples {
+        let clamped = sample.clamp(-1.0, 1.0);
+        let scaled = (clamped * i16::MAX as f32) as i16;
+        writer.write_sample(scaled).map_err(|e| e.to_string())?;
+    }
+    writer.finalize().map_err(|e| e.to_string())
+}
+
+fn read_wav(path: &Path) -> Result<Vec<f32>, String> {
+    let mut reader = hound::WavReader::open(path).map_err(|e| e.to_string())?;
+    reader
+        .samples::<i16>()
+        .map(|s| s.map(|s| s as f32 / i16::MAX as f32).map_err(|e| e.to_string()))
+        .collect()
+}
+
+fn engine_status(tts_dir: &Path) -> Vec<Value> {
+    let installed = check_model_files(tts_dir).is_ok();
+    EngineKind::ALL
+        .into_iter()
+        .map(|kind| {
+            let capabilities = kind.capabilities();
+            let mut value = serde_json::to_value(&capabilities).unwrap_or_default();
+            value["available"] = Value::Bool(!capabilities.requires_model || installed);
+            value
+        })
+        .collect()
+}
+
+/// The output chosen in settings, opened when playback starts.
+fn audio_sink(settings_path: &Path) -> Result<Box<dyn AudioSink>, String> {
+    let settings = crate::settings::read_settings_file(settings_path)?;
+    Ok(match SinkKind::from_settings(&settings)? {
+        SinkKind::Device => Box::new(output::DeviceSink),
+        SinkKind::Null => Box::new(NullSink),
+    })
+}
+
+/// Tracks from `items` of `{ path, offset?, note? }`, where `offset` is the
+/// UTF-16 offset of the synthesized text within the note.
+fn playback_tracks(tts_dir: &Path, payload: &Value) -> Result<Vec<Track>, String> {
+    let items = payload.get("items").and_then(|v| v.as_array()).ok_or("Missing items")?;
+    let cache_dir = cache::cache_dir(tts_dir);
+    items
+        .iter()
+        .map(|item| {
+            let path = item["path"].as_str().ok_or("Missing path")?;
+            let offset = item.get("offset").and_then(|v| v.as_u64()).unwrap_or(0) as usize;
+            let note = item.get("note").and_then(|v| v.as_str()).map(String::from);
+            let track = Track::load(&cache_dir, Path::new(path), offset, note)?;
+            // Loaded tracks hold their samples, so the cache may drop them.
+            if let Some(key) = cache::key_of(Path::new(path)) {
+                cache::unpin(key);
+            }
+            Ok(track)
+        })
+        .collect()
+}
+
+/// The open vault's pronunciation lexicon; empty when no vault is configured.
+fn load_lexicon(vault_config_path: &Path) -> Result<Lexicon, String> {
+    match crate::vault::read_vault_root(vault_config_path) {
+        Ok(vault_root) => Lexicon::load(&vault_root),
+        Err(_) => Ok(Lexicon::default()),
+    }
+}
+
+/// Deletes cached audio the manifest doesn't track, and audio the current
+/// pipeline can't replay. Run at startup and after the model changes.
+fn prune_cache(tts_dir: &Path, settings_path: &Path, vault_config_path: &Path) -> Result<(), String> {
+    let cache_dir = cache::cache_dir(tts_dir);
+    if !cache_dir.exists() {
+        return Ok(());
+    }
+    cache::prune_untracked(&cache_dir)?;
+    let config = synthesis_config(tts_dir, settings_path)?;
+    let lexicon = load_lexicon(vault_config_path)?;
+    // Without an installed model there is no current pipeline to compare to.
+    if let Ok(pipeline) = current_pipeline(tts_dir, &config, &lexicon) {
+        cache::remove_stale(&cache_dir, &pipeline.id())?;
+    }
+    Ok(())
+}
+
+fn cache_stats(tts_dir: &Path, config: &SynthesisConfig, lexicon: &Lexicon) -> Result<Value, String> {
     let model_dir = tts_dir.join("models");
     let cache_dir = tts_dir.join("cache");
     let (model_bytes, model_files) = dir_stats(&model_dir)?;
     let (cache_bytes, cache_files) = dir_stats(&cache_dir)?;
+    let stale_entries = match current_pipeline(tts_dir, config, lexicon) {
+        Ok(pipeline) => cache::CacheManifest::load(&cache_dir).stale_keys(&pipeline.id()).len(),
+        Err(_) => 0,
+    };
 
     Ok(serde_json::json!({
         "model_bytes": model_bytes,
         "model_files": model_files,
         "cache_bytes": cache_bytes,
         "cache_files": cache_files,
+        "stale_entries": stale_entries,
         "model_dir": model_dir.to_string_lossy(),
         "cache_dir": cache_dir.to_string_lossy()
     }))
 }
 
 impl TtsInstance {
-    pub fn new(tts_dir: PathBuf) -> Self {
+    pub fn new(tts_dir: PathBuf, settings_path: PathBuf, vault_config_path: PathBuf, events: EventSink) -> Self {
+        let engine: EngineSlot = Arc::new(Mutex::new(None));
+        residency::spawn_idle_watcher(&engine, settings_path.clone());
+        let jobs = Arc::new(JobQueue::new(engine.clone()));
+        let player = Arc::new(Player::new(events));
+        playback::spawn_ticker(&player);
+        let (dir, settings, vault_config) = (tts_dir.clone(), settings_path.clone(), vault_config_path.clone());
+        std::thread::spawn(move || prune_cache(&dir, &settings, &vault_config));
         Self {
             tts_dir,
-            engine: Arc::new(Mutex::new(None)),
-            cancel_flag: Arc::new(AtomicBool::new(false)),
+            settings_path,
+            vault_config_path,
+            engine,
+            jobs,
+            player,
+            install_cancel: Arc::new(AtomicBool::new(false)),
             install_progress: Arc::new(Mutex::new(InstallProgress::default())),
+            export_cancel: Arc::new(AtomicBool::new(false)),
+            export_progress: Arc::new(Mutex::new(ExportProgress::default())),
+            audiobook_cancel: Arc::new(AtomicBool::new(false)),
+            audiobook_progress: Arc::new(Mutex::new(AudiobookProgress::default())),
         }
     }
 
-    // Helper to run installation logic (async)
-    async fn install_logic(
+    async fn export_logic(
         tts_dir: PathBuf,
-        engine: Arc<Mutex<Option<KokoroTts>>>,
-        install_progress: Arc<Mutex<InstallProgress>>,
+        export_progress: Arc<Mutex<ExportProgress>>,
+        export_cancel: Arc<AtomicBool>,
+        payload: Value,
     ) -> Result<Value, String> {
-        let model_dir = tts_dir.join("models");
-        if model_dir.exists() {
-            std::fs::remove_dir_all(&model_dir).map_err(|e| e.to_string())?;
+        let format = payload.get("format").and_then(|v| v.as_str()).unwrap_or("flac");
+        if format != "flac" {
+            return Err(format!("Unsupported export format: {}", format));
         }
-        std::fs::create_dir_all(&model_dir).map_err(|e| e.to_string())?;
+        let sources = cached_audio_paths(&tts_dir, &payload)?;
+        let destination = PathBuf::from(payload["destination"].as_str().ok_or("Missing destination")?);
+        let comments = VorbisComments::from_note(
+            payload.get("title").and_then(|v| v.as_str()),
+            payload.get("note_text").and_then(|v| v.as_str()),
+        );
 
-        let model_url = "https://github.com/mzdk100/kokoro/releases/download/V1.0/kokoro-v1.0.onnx";
-        let voices_url = "https://github.com/mzdk100/kokoro/releases/download/V1.0/voices.bin";
+        export_cancel.store(false, Ordering::SeqCst);
+        *export_progress.lock().await = ExportProgress {
+            status: "encoding".to_string(),
+            destination: destination.to_string_lossy().to_string(),
+            ..Default::default()
+        };
 
-        let model_path = model_dir.join(MODEL_FILENAME);
-        let voices_path = model_dir.join(VOICES_FILENAME);
+        let progress = export_progress.clone();
+        let cancel = export_cancel.clone();
+        let target = destination.clone();
+        let result = tokio::task::spawn_blocking(move || {
+            export::encode_flac(&sources, &target, &comments, &cancel, |done, total| {
+                let mut p = progress.blocking_lock();
+                p.encoded_samples = done;
+                p.total_samples = total;
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())?;
 
-        set_install_progress(&install_progress, "downloading", "Model", 0, 0).await;
-        let model_size = download_file(model_url, &model_path, install_progress.clone(), 0, "Model").await?;
-        set_install_progress(&install_progress, "downloading", "Voices", model_size, model_size).await;
-        let voices_size = download_file(voices_url, &voices_path, install_progress.clone(), model_size, "Voices").await?;
-        let total_size = model_size + voices_size;
-        set_install_progress(&install_progress, "verifying", "Checking files", total_size, total_size).await;
-        check_model_files(&tts_dir)?;
+        let mut progress = export_progress.lock().await;
+        match result {
+            Ok(samples) => {
+                progress.status = "complete".to_string();
+                Ok(serde_json::json!({
+                    "status": "complete",
+                    "path": destination.to_string_lossy(),
+                    "format": format,
+                    "samples": samples
+                }))
+            }
+            Err(e) => {
+                progress.status = if export_cancel.load(Ordering::SeqCst) { "cancelled" } else { "error" }.to_string();
+                Err(e)
+            }
+        }
+    }
+
+    /// Loads the job named by `job_id`, or builds one from `folder`/`notes`.
+    /// A request matching an earlier job resumes it.
+    fn audiobook_job(tts_dir: &Path, vault_config_path: &Path, payload: &Value) -> Result<AudiobookJob, String> {
+        if let Some(id) = payload.get("job_id").and_then(|v| v.as_str()) {
+            return AudiobookJob::load(tts_dir, id);
+        }
+
+        let vault_root = crate::vault::read_vault_root(vault_config_path)?;
+        let notes: Vec<String> = match payload.get("notes").and_then(|v| v.as_array()) {
+            Some(notes) => notes.iter().filter_map(|v| v.as_str().map(str::to_string)).collect(),
+            None => audiobook::collect_notes(&vault_root, payload["folder"].as_str().ok_or("Missing folder or notes")?)?,
+        };
+        if notes.is_empty() {
+            return Err("No notes to read.".to_string());
+        }
+        let destination = payload["destination"].as_str().ok_or("Missing destination")?.to_string();
+        let voice = payload.get("voice").and_then(|v| v.as_str()).unwrap_or("af_sky").to_string();
+        let speed = payload.get("speed").and_then(|v| v.as_f64()).unwrap_or(1.0) as f32;
+
+        let id = audiobook::job_id(&notes, &voice, speed, &destination);
+        if let Ok(job) = AudiobookJob::load(tts_dir, &id) {
+            return Ok(job);
+        }
+        let title = match payload.get("title").and_then(|v| v.as_str()) {
+            Some(title) => title.to_string(),
+            None => Path::new(&destination)
+                .file_stem()
+                .map(|s| s.to_string_lossy().to_string())
+                .unwrap_or_default(),
+        };
+        Ok(AudiobookJob::new(title, notes, voice, speed, destination))
+    }
+
+    async fn audiobook_logic(
+        tts_dir: PathBuf,
+        settings_path: PathBuf,
+        vault_config_path: PathBuf,
+        jobs: Arc<JobQueue>,
+        audiobook_progress: Arc<Mutex<AudiobookProgress>>,
+        audiobook_cancel: Arc<AtomicBool>,
+        payload: Value,
+    ) -> Result<Value, String> {
+        let mut job = Self::audiobook_job(&tts_dir, &vault_config_path, &payload)?;
+        let config = synthesis_config(&tts_dir, &settings_path)?;
+        audiobook_cancel.store(false, Ordering::SeqCst);
+        job.status = "running".to_string();
+        job.save(&tts_dir)?;
+
+        let result = Self::run_audiobook(&tts_dir, &vault_config_path, &jobs, &config, &audiobook_progress, &audiobook_cancel, &mut job).await;
+        job.status = match &result {
+            Ok(_) => "complete",
+            Err(_) if audiobook_cancel.load(Ordering::SeqCst) => "cancelled",
+            Err(_) => "error",
+        }
+        .to_string();
+        job.save(&tts_dir)?;
+        audiobook_progress.lock().await.status = job.status.clone();
+
+        let samples = result?;
+        Ok(serde_json::json!({
+            "status": "complete",
+            "job_id": job.id,
+            "path": job.destination,
+            "chapters": job.chapters.len(),
+            "samples": samples
+        }))
+    }
+
+    async fn run_audiobook(
+        tts_dir: &Path,
+        vault_config_path: &Path,
+        jobs: &Arc<JobQueue>,
+        config: &SynthesisConfig,
+        audiobook_progress: &Arc<Mutex<AudiobookProgress>>,
+        audiobook_cancel: &Arc<AtomicBool>,
+        job: &mut AudiobookJob,
+    ) -> Result<u64, String> {
+        let vault_root = crate::vault::read_vault_root(vault_config_path)?;
+        let lexicon = Lexicon::load(&vault_root)?;
+        let total_chapters = job.chapters.len();
+
+        for index in 0..total_chapters {
+            if audiobook_cancel.load(Ordering::SeqCst) {
+                return Err("Audiobook cancelled.".to_string());
+            }
+            let note_path = crate::vault::resolve_safe_path(&vault_root, &job.chapters[index].note)?;
+            let note_text = std::fs::read_to_string(&note_path)
+                .map_err(|e| format!("Failed to read {}: {}", job.chapters[index].note, e))?;
+            let title = audiobook::chapter_title(&job.chapters[index].title, &note_text);
+            *audiobook_progress.lock().await = AudiobookProgress {
+                status: "synthesizing".to_string(),
+                job_id: job.id.clone(),
+                chapter: index + 1,
+                total_chapters,
+                chapter_title: title.clone(),
+                ..Default::default()
+            };
+
+            // Chapters finished by an earlier run come straight from the cache,
+            // unless the note has been edited since.
+            let text = audiobook::chapter_text(&title, &note_text);
+            let request = SynthesisRequest {
+                text,
+                voice: job.voice.clone(),
+                speed: job.speed,
+                word_timings: false,
+                lang: Frontmatter::from_note(&note_text).get_str("lang"),
+                range: None,
+            };
+            let chapter_job = jobs.submit(None, "audiobook", JobPriority::Batch, audiobook_cancel.clone());
+            let result = Self::synthesize_logic(tts_dir.to_path_buf(), config, chapter_job, &lexicon, request).await?;
+            let cached = result["path"].as_str().ok_or("Synthesis returned no audio")?;
+            // Copied out of the cache, which may evict it before the book
+            // is encoded.
+            let audio = audiobook::chapter_audio_path(tts_dir, &job.id, index);
+            std::fs::create_dir_all(audio.parent().ok_or("Invalid job directory")?).map_err(|e| e.to_string())?;
+            std::fs::copy(cached, &audio).map_err(|e| e.to_string())?;
+            let samples = hound::WavReader::open(&audio).map_err(|e| e.to_string())?.duration() as u64;
+            let audio = audio.to_string_lossy().to_string();
+
+            let chapter = &mut job.chapters[index];
+            chapter.title = title;
+            chapter.audio = Some(audio);
+            chapter.samples = samples;
+            job.save(tts_dir)?;
+        }
+
+        let destination = PathBuf::from(&job.destination);
+        let sources: Vec<PathBuf> = job.chapters.iter().filter_map(|c| c.audio.as_ref().map(PathBuf::from)).collect();
+        let sample_rate = hound::WavReader::open(&sources[0]).map_err(|e| e.to_string())?.spec().sample_rate;
+        let mut comments = VorbisComments::default();
+        comments.push("TITLE", &job.title);
+        comments.push("ALBUM", &job.title);
+        audiobook::chapter_comments(&mut comments, &job.chapters, sample_rate);
+        audiobook_progress.lock().await.status = "encoding".to_string();
+
+        let progress = audiobook_progress.clone();
+        let cancel = audiobook_cancel.clone();
+        let target = destination.clone();
+        let samples = tokio::task::spawn_blocking(move || {
+            export::encode_flac(&sources, &target, &comments, &cancel, |done, total| {
+                let mut p = progress.blocking_lock();
+                p.encoded_samples = done;
+                p.total_samples = total;
+            })
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+        audiobook::write_chapter_maps(&destination, &job.title, &job.chapters, sample_rate)?;
+        let _ = std::fs::remove_dir_all(audiobook::job_audio_dir(tts_dir, &job.id));
+        Ok(samples)
+    }
+
+    // Helper to run installation logic (async)
+    async fn install_logic(
+        tts_dir: PathBuf,
+        settings_path: PathBuf,
+        engine: EngineSlot,
+        install_progress: Arc<Mutex<InstallProgress>>,
+        install_cancel: Arc<AtomicBool>,
+    ) -> Result<Value, String> {
+        install_cancel.store(false, Ordering::SeqCst);
+        let result = async {
+            let settings = crate::settings::read_settings_file(&settings_path)?;
+            let model_source = source::resolve(&settings).await?;
+            Self::download_model_files(&tts_dir, &model_source, install_progress.clone(), install_cancel.clone()).await
+        }.await;
+        let (model_size, voices_size) = match result {
+            Ok(sizes) => sizes,
+            Err(e) => {
+                let status = if install_cancel.load(Ordering::SeqCst) { "cancelled" } else { "error" };
+                set_install_progress(&install_progress, status, &e, 0, 0).await;
+                return Err(e);
+            }
+        };
+
+        let model_dir = tts_dir.join("models");
 
         // Initialise engine
         {
             let mut engine_guard = engine.lock().await;
-            let tts = KokoroTts::new(
-                model_path.to_str().ok_or("Invalid model path")?,
-                voices_path.to_str().ok_or("Invalid voices path")?,
-            ).await.map_err(|e| {
+            let tts = LoadedEngine::load(EngineKind::Kokoro, &tts_dir).await.map_err(|e| {
                 let message = e.to_string();
                 let _ = std::fs::remove_dir_all(&model_dir);
                 if message.contains("Utf8") {
@@ -253,14 +798,49 @@ impl TtsInstance {
         Ok(serde_json::json!({ "status": "installed" }))
     }
 
+    /// Downloads into `tmp/` so partial files survive for resuming, and only
+    /// replaces the installed model once both files have been verified.
+    async fn download_model_files(
+        tts_dir: &Path,
+        model_source: &source::ModelSource,
+        install_progress: Arc<Mutex<InstallProgress>>,
+        install_cancel: Arc<AtomicBool>,
+    ) -> Result<(u64, u64), String> {
+        let tmp_dir = tts_dir.join("tmp");
+        std::fs::create_dir_all(&tmp_dir).map_err(|e| e.to_string())?;
+        let model_file = model_source.file(MODEL_FILENAME)?;
+        let voices_file = model_source.file(VOICES_FILENAME)?;
+
+        let ctx = DownloadContext::new(install_progress.clone(), install_cancel, RetryPolicy::default())?;
+        set_install_progress(&install_progress, "downloading", "Model", 0, 0).await;
+        let model_size = ctx.download_file(&Download {
+            url: &model_file.url,
+            path: &tmp_dir.join(MODEL_FILENAME),
+            sha256: Some(&model_file.sha256),
+            phase: "Model",
+        }, 0).await?;
+        set_install_progress(&install_progress, "downloading", "Voices", model_size, model_size).await;
+        let voices_size = ctx.download_file(&Download {
+            url: &voices_file.url,
+            path: &tmp_dir.join(VOICES_FILENAME),
+            sha256: Some(&voices_file.sha256),
+            phase: "Voices",
+        }, model_size).await?;
+
+        let total_size = model_size + voices_size;
+        set_install_progress(&install_progress, "verifying", "Checking files", total_size, total_size).await;
+        check_model_dir(&tmp_dir)?;
+        model::replace_model_files(tts_dir, &tmp_dir)?;
+        model::write_model_stamp(tts_dir, model_source.version.as_deref(), &model_source.origin)?;
+
+        Ok((model_size, voices_size))
+    }
+
     fn sanitise_sentence(sentence: &str) -> String {
         sentence
-            .replace('‘', "'")
-            .replace('’', "'")
-            .replace('“', "\"")
-            .replace('”', "\"")
-            .replace('—', "-")
-            .replace('–', "-")
+            .replace(['‘', '’'], "'")
+            .replace(['“', '”'], "\"")
+            .replace(['—', '–'], "-")
             .replace("…", "...")
             .replace('«', "(")
             .replace('»', ")")
@@ -274,192 +854,247 @@ impl TtsInstance {
             .to_string()
     }
 
-    async fn synthesize_logic(tts_dir: PathBuf, engine: Arc<Mutex<Option<KokoroTts>>>, cancel_flag: Arc<AtomicBool>, text: String, voice: String, speed: f32) -> Result<Value, String> {
-        cancel_flag.store(false, Ordering::SeqCst);
-        // Ensure loaded
-        {
-            let mut guard = engine.lock().await;
-            if guard.is_none() {
-                 let model_path = tts_dir.join("models").join(MODEL_FILENAME);
-                 let voices_path = tts_dir.join("models").join(VOICES_FILENAME);
-                 check_model_files(&tts_dir)?;
-
-                 let tts = KokoroTts::new(
-                    model_path.to_str().ok_or("Invalid path")?,
-                    voices_path.to_str().ok_or("Invalid path")?
-                ).await.map_err(|e| e.to_string())?;
-                *guard = Some(tts);
+    /// The exact text handed to the engine for one sentence.
+    fn engine_text(sentence: &str, lexicon: &Lexicon) -> String {
+        lexicon.apply(&Self::sanitise_sentence(sentence))
+    }
+
+    fn preview_pronunciation(text: &str, lexicon: &Lexicon, pauses: &PauseSettings) -> Value {
+        let plan = pauses::plan(text, pauses);
+        let segments: Vec<Value> = plan
+            .utterances
+            .iter()
+            .map(|utterance| {
+                let sentence = &text[utterance.start..utterance.end];
+                let start_char = timing::utf16_len(&text[0..utterance.start]);
+                serde_json::json!({
+                    "startChar": start_char,
+                    "endChar": start_char + timing::utf16_len(sentence),
+                    "text": sentence,
+                    "engine_text": Self::engine_text(sentence, lexicon),
+                    "pause_after_ms": utterance.pause_after_ms
+                })
+            })
+            .collect();
+
+        serde_json::json!({
+            "segments": segments,
+            "leading_pause_ms": plan.leading_pause_ms,
+            "lexicon": {
+                "path": lexicon.path.as_ref().map(|p| p.to_string_lossy().to_string()),
+                "rules": lexicon.rule_count(),
+                "skipped": lexicon.skipped
             }
+        })
+    }
+
+    async fn synthesize_logic(tts_dir: PathBuf, config: &SynthesisConfig, job: JobHandle, lexicon: &Lexicon, request: SynthesisRequest) -> Result<Value, String> {
+        let result = Self::run_synthesis(&tts_dir, config, &job, lexicon, request).await;
+        job.finish(&result);
+        let mut value = result?;
+        value["job_id"] = Value::from(job.id.clone());
+        Ok(value)
+    }
+
+    /// Takes the engine lock one sentence at a time, so other jobs can
+    /// interleave and interactive jobs overtake batch ones.
+    async fn run_synthesis(tts_dir: &Path, config: &SynthesisConfig, job: &JobHandle, lexicon: &Lexicon, request: SynthesisRequest) -> Result<Value, String> {
+        let kind = config.kind;
+        if kind == EngineKind::Kokoro {
+            check_model_files(tts_dir)?;
         }
 
-        let selected_voice = match voice.as_str() {
-            "af_sky" => Voice::AfSky(speed),
-            "af_bella" => Voice::AfBella(speed),
-            "af_nicole" => Voice::AfNicole(speed),
-            "af_sarah" => Voice::AfSarah(speed),
-            "am_adam" => Voice::AmAdam(speed),
-            "am_michael" => Voice::AmMichael(speed),
-            "bf_emma" => Voice::BfEmma(speed),
-            "bf_isabella" => Voice::BfIsabella(speed),
-            "bm_george" => Voice::BmGeorge(speed),
-            "bm_lewis" => Voice::BmLewis(speed),
-            _ => Voice::AfSky(speed),
-        };
+        let pipeline_id = current_pipeline(tts_dir, config, lexicon)?.id();
+        let cache_dir = cache::cache_dir(tts_dir);
+
+        let text = &request.text;
+        let range = TextRange::resolve(text, request.range);
+        let mut variant = request.voice.clone();
+        if let Some(lang) = &request.lang {
+            variant.push_str(&format!("@{}", lang));
+        }
+        if request.range.is_some() {
+            variant.push_str(&format!("#{}-{}", range.start_char, range.end_char));
+        }
+        let key = cache::cache_key(&pipeline_id, text, &variant, request.speed);
+        let output_path = cache::audio_path(&cache_dir, &key);
+        // Entries written before word timings count characters rather than
+        // UTF-16 code units, and entries written before language detection
+        // read every sentence with one voice, so both are synthesized again.
+        let current = |m: &Value| m.get("words").is_some() && m.get("warnings").is_some();
+        if let Some(metadata) = cache::lookup(&cache_dir, &key, &pipeline_id).filter(current) {
+            let mut result = serde_json::json!({
+                "path": output_path,
+                "duration_ms": metadata["duration_ms"],
+                "segments": metadata["segments"],
+                "warnings": metadata["warnings"],
+                "cached": true
+            });
+            if request.word_timings {
+                result["words"] = metadata["words"].clone();
+            }
+            range.describe(&mut result);
+            return Ok(result);
+        }
 
         // Segmentation and Synthesis
         let mut all_samples: Vec<f32> = Vec::new();
         let mut segments: Vec<Value> = Vec::new();
-        let mut current_ms = 0.0;
-
-        // Use unicode segmentation to find sentences
-        // Note: sentence indices are byte indices, we need char indices for frontend?
-        // Frontend uses CodeMirror which counts chars (UTF-16 code units usually in JS, but CM handles it).
-        // Rust string indices are bytes.
-        // We need to map byte offsets to char offsets if we want char precision.
-        // But for simplicity, we can just assume 1-1 mapping for ASCII, but we should do it right.
-        //
-        // We'll iterate by sentences.
-        let sentence_bounds = text.split_sentence_bound_indices();
-
-        // We need the engine lock for the duration of synthesis
-        let guard = engine.lock().await;
-        let tts = guard.as_ref().unwrap();
-
-        for (byte_start, sentence) in sentence_bounds {
-            if cancel_flag.load(Ordering::SeqCst) {
-                return Err("Synthesis cancelled.".to_string());
-            }
-
-            let sentence_trimmed = sentence.trim();
-            if sentence_trimmed.is_empty() {
-                continue;
-            }
+        let mut words: Vec<timing::WordTiming> = Vec::new();
+        let mut warnings: Vec<String> = Vec::new();
+        let sample_rate = kind.capabilities().sample_rate;
+        let silence = |ms: u32| std::iter::repeat_n(0.0, (sample_rate as f64 * ms as f64 / 1000.0) as usize);
 
-            let cleaned = Self::sanitise_sentence(sentence_trimmed);
+        // Sentences, headings and list items, each with the pause after it.
+        let (leading_pause_ms, sentences) = Self::plan_sentences(&request, &range, config, lexicon, &mut warnings);
+        job.set_total(sentences.len());
+        all_samples.extend(silence(leading_pause_ms));
+        let mut current_ms = leading_pause_ms as f64;
+        let synth = SentenceSynth {
+            tts_dir,
+            config,
+            cache_dir: &cache_dir,
+            pipeline_id: &pipeline_id,
+            speed: request.speed,
+        };
 
-            // Synthesize segment
-            let (samples, _) = tts.synth(&cleaned, selected_voice).await
-                .map_err(|e| {
-                    let message = e.to_string();
-                    if message.contains("Utf8") {
-                        "Model files appear corrupt. Reinstall the TTS model.".to_string()
-                    } else {
-                        format!("Synthesis failed for segment '{}': {}", sentence_trimmed, e)
-                    }
-                })?;
+        for sentence in &sentences {
+            let sentence_trimmed = &text[sentence.start..sentence.end];
+            let samples = synth.audio(job, sentence, sentence_trimmed).await?;
+            job.advance();
 
-            let segment_duration_ms = (samples.len() as f64 / 24000.0) * 1000.0;
+            let to_ms = |samples: usize| samples as f64 / sample_rate as f64 * 1000.0;
+            let segment_duration_ms = to_ms(samples.len());
+            let (voiced_start, voiced_end) = timing::voiced_span(&samples);
+            words.extend(timing::estimate_words(
+                text,
+                sentence.start,
+                sentence.end,
+                current_ms + to_ms(voiced_start),
+                current_ms + to_ms(voiced_end),
+            ));
 
             all_samples.extend(samples);
 
-            // Add small silence (0.1s) between sentences
-            let silence_samples = (24000.0 * 0.1) as usize;
-            all_samples.extend(std::iter::repeat(0.0).take(silence_samples));
-            let silence_ms = 100.0;
-
-            // Record segment info
-            // Calculate char offsets
-            // We need start/end in CHARS (Unicode Scalar Values) for frontend consistency if using string.length?
-            // JS string.length counts UTF-16 code units.
-            // Rust String chars().count() counts Unicode Scalar Values.
-            // CodeMirror usually works with char offsets (UTF-16).
-            // For MVP, assume mostly BMP.
-            // Correct approach: Count chars up to byte_start.
-            // Calculate char offsets using byte_start to map back to text
-            let start_char = text[0..byte_start].chars().count();
-            let end_char = start_char + sentence.chars().count();
+            all_samples.extend(silence(sentence.pause_after_ms));
+
+            // Offsets are UTF-16 code units, matching JS string indices.
+            let start_char = timing::utf16_len(&text[0..sentence.start]);
+            let end_char = start_char + timing::utf16_len(sentence_trimmed);
 
             segments.push(serde_json::json!({
                 "startChar": start_char,
                 "endChar": end_char,
                 "startMs": current_ms,
-                "endMs": current_ms + segment_duration_ms
+                "endMs": current_ms + segment_duration_ms,
+                "lang": sentence.lang,
+                "voice": sentence.voice
             }));
 
-            current_ms += segment_duration_ms + silence_ms;
+            current_ms += segment_duration_ms + sentence.pause_after_ms as f64;
         }
 
          // Write to WAV
-        let cache_dir = tts_dir.join("cache");
         std::fs::create_dir_all(&cache_dir).map_err(|e| e.to_string())?;
+        write_wav(&output_path, &all_samples, sample_rate)?;
 
-        let mut hasher = Sha256::new();
-        hasher.update(format!("{}-{}-{}", text, voice, speed));
-        let hash = hex::encode(hasher.finalize());
-        let output_path = cache_dir.join(format!("{}.wav", hash));
-
-        let spec = WavSpec {
-            channels: 1,
-            sample_rate: 24000,
-            bits_per_sample: 16,
-            sample_format: hound::SampleFormat::Int,
-        };
-
-        let mut writer = WavWriter::create(&output_path, spec).map_err(|e| e.to_string())?;
-        for sample in all_samples {
-            let clamped = sample.clamp(-1.0, 1.0);
-            let scaled = (clamped * i16::MAX as f32) as i16;
-            writer.write_sample(scaled).map_err(|e| e.to_string())?;
-        }
-        writer.finalize().map_err(|e| e.to_string())?;
+        let metadata = serde_json::json!({
+            "duration_ms": current_ms,
+            "segments": segments,
+            "words": words,
+            "warnings": warnings,
+            "voice": request.voice,
+            "lang": request.lang,
+            "speed": request.speed
+        });
+        cache::store(&cache_dir, &key, &pipeline_id, &metadata, config.cache_limit_bytes)?;
 
-        Ok(serde_json::json!({
+        let mut result = serde_json::json!({
             "path": output_path,
             "duration_ms": current_ms,
-            "segments": segments
-        }))
+            "segments": segments,
+            "warnings": warnings,
+            "cached": false
+        });
+        if request.word_timings {
+            result["words"] = metadata["words"].clone();
+        }
+        range.describe(&mut result);
+        Ok(result)
     }
-}
 
-async fn download_file(
-    url: &str,
-    path: &Path,
-    install_progress: Arc<Mutex<InstallProgress>>,
-    base_downloaded: u64,
-    phase: &str,
-) -> Result<u64, String> {
-    let client = reqwest::Client::builder()
-        .user_agent("Liminal Notes TTS Installer")
-        .build()
-        .map_err(|e| e.to_string())?;
-    let response = client.get(url).send().await.map_err(|e| e.to_string())?;
-    let status = response.status();
-    if !status.is_success() {
-        let body = response.text().await.unwrap_or_default();
-        let snippet: String = body.chars().take(200).collect();
-        return Err(format!("Download failed ({}): {}", status, snippet));
-    }
-
-    let total_bytes = response.content_length().unwrap_or(0);
-    set_install_progress(
-        &install_progress,
-        "downloading",
-        phase,
-        base_downloaded,
-        base_downloaded + total_bytes,
-    ).await;
-
-    let tmp_path = path.with_extension("download");
-    let mut file = tokio::fs::File::create(&tmp_path).await.map_err(|e| e.to_string())?;
-    let mut stream = response.bytes_stream();
-    let mut downloaded = 0u64;
-    while let Some(chunk) = stream.next().await {
-        let chunk = chunk.map_err(|e| e.to_string())?;
-        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
-        downloaded += chunk.len() as u64;
-        if total_bytes > 0 {
-            set_install_progress(
-                &install_progress,
-                "downloading",
-                phase,
-                base_downloaded + downloaded,
-                base_downloaded + total_bytes,
-            ).await;
+    /// The sentences of `request` within `range`, each with its engine text
+    /// and voice, and the silence before the first one.
+    fn plan_sentences(
+        request: &SynthesisRequest,
+        range: &TextRange,
+        config: &SynthesisConfig,
+        lexicon: &Lexicon,
+        warnings: &mut Vec<String>,
+    ) -> (u32, Vec<PlannedSentence>) {
+        let text = &request.text;
+        let plan = pauses::plan(text, &config.pauses).within(text, range.start, range.end);
+        let capabilities = config.kind.capabilities();
+        let hints = language::hints(text);
+
+        let sentences = plan
+            .utterances
+            .iter()
+            .map(|utterance| {
+                let sentence = &text[utterance.start..utterance.end];
+                // An inline hint wins; otherwise detect the sentence's language,
+                // falling back to the language of the whole text.
+                let sentence_lang = match language::hint_at(&hints, utterance.start) {
+                    Some(hint) => Some(hint),
+                    None if config.languages.detect => language::detect(sentence).or(request.lang.as_deref()),
+                    None => request.lang.as_deref(),
+                };
+                let choice = language::choose_voice(sentence_lang, &request.voice, &config.languages, &capabilities);
+                if let Some(warning) = choice.warning {
+                    if !warnings.contains(&warning) {
+                        warnings.push(warning);
+                    }
+                }
+                PlannedSentence {
+                    start: utterance.start,
+                    end: utterance.end,
+                    pause_after_ms: utterance.pause_after_ms,
+                    engine_text: Self::engine_text(sentence, lexicon),
+                    voice: choice.voice,
+                    lang: choice.lang,
+                }
+            })
+            .collect();
+        (plan.leading_pause_ms, sentences)
+    }
+
+    /// Fills the sentence cache for up to `PREFETCH_SENTENCES` sentences of
+    /// `request`, so a following request for them starts without waiting.
+    async fn prefetch_logic(tts_dir: PathBuf, config: SynthesisConfig, job: JobHandle, lexicon: Lexicon, request: SynthesisRequest) -> Result<(), String> {
+        let result = async {
+            let pipeline_id = current_pipeline(&tts_dir, &config, &lexicon)?.id();
+            let cache_dir = cache::cache_dir(&tts_dir);
+            let range = TextRange::resolve(&request.text, request.range);
+            let (_, sentences) = Self::plan_sentences(&request, &range, &config, &lexicon, &mut Vec::new());
+            let synth = SentenceSynth {
+                tts_dir: &tts_dir,
+                config: &config,
+                cache_dir: &cache_dir,
+                pipeline_id: &pipeline_id,
+                speed: request.speed,
+            };
+            let sentences = &sentences[..sentences.len().min(PREFETCH_SENTENCES)];
+            job.set_total(sentences.len());
+            for sentence in sentences {
+                synth.audio(&job, sentence, &request.text[sentence.start..sentence.end]).await?;
+                job.advance();
+            }
+            Ok(())
         }
+        .await;
+        job.finish(&result);
+        result
     }
-    file.flush().await.map_err(|e| e.to_string())?;
-    tokio::fs::rename(&tmp_path, path).await.map_err(|e| e.to_string())?;
-    Ok(downloaded)
 }
 
 impl Invocable for TtsInstance {
@@ -467,21 +1102,69 @@ impl Invocable for TtsInstance {
         let method = method.to_string();
         let tts_dir = self.tts_dir.clone();
         let engine = self.engine.clone();
-        let cancel_flag = self.cancel_flag.clone();
+        let jobs = self.jobs.clone();
+        let player = self.player.clone();
         let install_progress = self.install_progress.clone();
+        let install_cancel = self.install_cancel.clone();
+        let settings_path = self.settings_path.clone();
+        let export_progress = self.export_progress.clone();
+        let export_cancel = self.export_cancel.clone();
+        let vault_config_path = self.vault_config_path.clone();
+        let audiobook_progress = self.audiobook_progress.clone();
+        let audiobook_cancel = self.audiobook_cancel.clone();
 
         Box::pin(async move {
             match method.as_str() {
                 "status" => {
                     let installed = check_model_files(&tts_dir).is_ok();
-                    let loaded = engine.lock().await.is_some();
+                    let settings = crate::settings::read_settings_file(&settings_path).unwrap_or_default();
+                    let loaded = match engine.lock().await.as_ref() {
+                        Some(loaded) => serde_json::json!({
+                            "engine": loaded.engine.capabilities().id,
+                            "load_ms": loaded.load_ms,
+                            "loaded_at": loaded.loaded_at,
+                            "memory_bytes": loaded.memory_bytes,
+                            "idle_seconds": loaded.idle_for().as_secs(),
+                        }),
+                        None => Value::Null,
+                    };
+                    let loaded_engine = loaded.get("engine").cloned();
+                    let selected = selected_engine(&tts_dir, &settings_path);
+                    // The selected engine can synthesize without further setup.
+                    let ready = match &selected {
+                        Ok(kind) => !kind.capabilities().requires_model || installed,
+                        Err(_) => false,
+                    };
                     Ok(serde_json::json!({
                         "installed": installed,
-                        "loaded": loaded
+                        "loaded": loaded_engine.is_some(),
+                        "loaded_engine": loaded_engine,
+                        "engine": selected.as_ref().map(|kind| kind.id()).ok(),
+                        "engine_error": selected.err(),
+                        "ready": ready,
+                        "engines": engine_status(&tts_dir),
+                        "residency": loaded,
+                        "process_memory_bytes": residency::process_memory_bytes(),
+                        "idle_unload_seconds": residency::idle_timeout(&settings).map(|timeout| timeout.as_secs()),
+                    }))
+                }
+                "unload" => {
+                    let unloaded = engine.lock().await.take();
+                    Ok(serde_json::json!({
+                        "status": "unloaded",
+                        "was_loaded": unloaded.is_some()
                     }))
                 }
                 "install" => {
-                    TtsInstance::install_logic(tts_dir, engine, install_progress).await
+                    let result = TtsInstance::install_logic(tts_dir.clone(), settings_path.clone(), engine, install_progress, install_cancel).await?;
+                    prune_cache(&tts_dir, &settings_path, &vault_config_path)?;
+                    Ok(result)
+                }
+                "cancel_install" => {
+                    install_cancel.store(true, Ordering::SeqCst);
+                    Ok(serde_json::json!({
+                        "status": "cancelling"
+                    }))
                 }
                 "install_progress" => {
                     let progress = install_progress.lock().await.clone();
@@ -493,7 +1176,9 @@ impl Invocable for TtsInstance {
                     }))
                 }
                 "cache_stats" => {
-                    cache_stats(&tts_dir)
+                    let lexicon = load_lexicon(&vault_config_path)?;
+                    let config = synthesis_config(&tts_dir, &settings_path)?;
+                    cache_stats(&tts_dir, &config, &lexicon)
                 }
                 "clear_cache" => {
                     clear_cache_files(&tts_dir)
@@ -504,13 +1189,91 @@ impl Invocable for TtsInstance {
                 "import_local" => {
                     let source = payload["path"].as_str().ok_or("Missing path")?;
                     let source_dir = PathBuf::from(source);
-                    import_local_files(&tts_dir, &source_dir)
+                    let result = import_local_files(&tts_dir, &source_dir)?;
+                    prune_cache(&tts_dir, &settings_path, &vault_config_path)?;
+                    *engine.lock().await = None;
+                    Ok(result)
+                }
+                "import_pack" => {
+                    let archive = PathBuf::from(payload["path"].as_str().ok_or("Missing path")?);
+                    let pack_dir = tts_dir.clone();
+                    let manifest = tokio::task::spawn_blocking(move || pack::import_model_pack(&pack_dir, &archive))
+                        .await
+                        .map_err(|e| e.to_string())??;
+                    prune_cache(&tts_dir, &settings_path, &vault_config_path)?;
+                    *engine.lock().await = None;
+                    Ok(serde_json::json!({
+                        "status": "imported",
+                        "version": manifest.version
+                    }))
+                }
+                "model_source" => {
+                    let settings = crate::settings::read_settings_file(&settings_path)?;
+                    let mut described = source::describe(&settings);
+                    if let Ok(stamp) = model::read_model_stamp(&tts_dir) {
+                        described["installed_version"] = serde_json::json!(stamp.version);
+                        described["installed_source"] = serde_json::json!(stamp.source);
+                    }
+                    Ok(described)
                 }
                 "read_audio" => {
                     let path = payload["path"].as_str().ok_or("Missing path")?;
                     let audio_path = PathBuf::from(path);
                     read_audio_file(&tts_dir, &audio_path)
                 }
+                "export" => {
+                    TtsInstance::export_logic(tts_dir, export_progress, export_cancel, payload).await
+                }
+                "export_progress" => {
+                    let progress = export_progress.lock().await.clone();
+                    Ok(serde_json::json!({
+                        "status": progress.status,
+                        "encoded_samples": progress.encoded_samples,
+                        "total_samples": progress.total_samples,
+                        "destination": progress.destination,
+                    }))
+                }
+                "cancel_export" => {
+                    export_cancel.store(true, Ordering::SeqCst);
+                    Ok(serde_json::json!({
+                        "status": "cancelling"
+                    }))
+                }
+                "audiobook" => {
+                    TtsInstance::audiobook_logic(tts_dir, settings_path, vault_config_path, jobs, audiobook_progress, audiobook_cancel, payload).await
+                }
+                "audiobook_progress" => {
+                    let progress = audiobook_progress.lock().await.clone();
+                    Ok(serde_json::json!({
+                        "status": progress.status,
+                        "job_id": progress.job_id,
+                        "chapter": progress.chapter,
+                        "total_chapters": progress.total_chapters,
+                        "chapter_title": progress.chapter_title,
+                        "encoded_samples": progress.encoded_samples,
+                        "total_samples": progress.total_samples,
+                    }))
+                }
+                "audiobook_jobs" => {
+                    let jobs: Vec<Value> = AudiobookJob::list(&tts_dir)
+                        .into_iter()
+                        .map(|job| serde_json::json!({
+                            "job_id": job.id,
+                            "title": job.title,
+                            "status": job.status,
+                            "destination": job.destination,
+                            "completed_chapters": job.completed_chapters(),
+                            "total_chapters": job.chapters.len(),
+                        }))
+                        .collect();
+                    Ok(serde_json::json!({ "jobs": jobs }))
+                }
+                "cancel_audiobook" => {
+                    audiobook_cancel.store(true, Ordering::SeqCst);
+                    Ok(serde_json::json!({
+                        "status": "cancelling"
+                    }))
+                }
                 "model_dir" => {
                     let model_dir = tts_dir.join("models");
                     Ok(serde_json::json!({
@@ -521,14 +1284,96 @@ impl Invocable for TtsInstance {
                     let text = payload["text"].as_str().ok_or("Missing text")?.to_string();
                     let voice = payload.get("voice").and_then(|v| v.as_str()).unwrap_or("af_sky").to_string();
                     let speed = payload.get("speed").and_then(|v| v.as_f64()).unwrap_or(1.0) as f32;
-                    TtsInstance::synthesize_logic(tts_dir, engine, cancel_flag, text, voice, speed).await
+                    let priority = JobPriority::parse(payload.get("priority").and_then(|v| v.as_str()))?;
+                    let job_id = payload.get("job_id").and_then(|v| v.as_str()).map(String::from);
+                    let lexicon = load_lexicon(&vault_config_path)?;
+                    let config = synthesis_config(&tts_dir, &settings_path)?;
+                    let job = jobs.submit(job_id, "synthesize", priority, Arc::new(AtomicBool::new(false)));
+                    let word_timings = payload.get("word_timings").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let lang = payload
+                        .get("lang")
+                        .and_then(|v| v.as_str())
+                        .map(String::from)
+                        .or_else(|| Frontmatter::from_note(&text).get_str("lang"));
+                    let range = payload.get("range").and_then(|r| {
+                        let start = r.get("start")?.as_u64()? as usize;
+                        let end = r.get("end").and_then(|v| v.as_u64()).map_or(usize::MAX, |v| v as usize);
+                        Some((start, end))
+                    });
+                    let continues = payload.get("continue").and_then(|v| v.as_bool()).unwrap_or(false);
+                    let request = SynthesisRequest { text, voice, speed, word_timings, lang, range };
+                    let result = TtsInstance::synthesize_logic(tts_dir.clone(), &config, job, &lexicon, request.clone()).await?;
+                    // Kept until the player loads it, which may be after
+                    // later chunks have been stored.
+                    if let Some(key) = result["path"].as_str().and_then(|path| cache::key_of(Path::new(path))) {
+                        cache::pin(key);
+                    }
+                    if let Some(next) = result.get("next").and_then(|v| v.as_u64()).filter(|_| continues) {
+                        // Warm the sentence cache for what the reader asks for next.
+                        jobs.cancel_kind("prefetch");
+                        let job = jobs.submit(None, "prefetch", JobPriority::Batch, Arc::new(AtomicBool::new(false)));
+                        let request = SynthesisRequest { range: Some((next as usize, usize::MAX)), word_timings: false, ..request };
+                        tauri::async_runtime::spawn(async move {
+                            let _ = TtsInstance::prefetch_logic(tts_dir, config, job, lexicon, request).await;
+                        });
+                    }
+                    Ok(result)
+                }
+                "preview_pronunciation" => {
+                    let text = payload["text"].as_str().ok_or("Missing text")?;
+                    let lexicon = load_lexicon(&vault_config_path)?;
+                    let settings = crate::settings::read_settings_file(&settings_path)?;
+                    let pauses = PauseSettings::from_settings(&settings);
+                    Ok(TtsInstance::preview_pronunciation(text, &lexicon, &pauses))
                 }
                 "cancel" => {
-                    cancel_flag.store(true, Ordering::SeqCst);
+                    // Without a job id, cancels every read-aloud job.
+                    let cancelled = match payload.get("job_id").and_then(|v| v.as_str()) {
+                        Some(job_id) => usize::from(jobs.cancel(job_id)),
+                        None => jobs.cancel_kind("synthesize") + jobs.cancel_kind("prefetch"),
+                    };
                     Ok(serde_json::json!({
-                        "status": "cancelled"
+                        "status": "cancelled",
+                        "cancelled": cancelled
                     }))
                 }
+                "jobs" => {
+                    Ok(serde_json::json!({ "jobs": jobs.list() }))
+                }
+                "playback_load" => {
+                    let tracks = playback_tracks(&tts_dir, &payload)?;
+                    if payload.get("play").and_then(|v| v.as_bool()).unwrap_or(true) {
+                        let sink = audio_sink(&settings_path)?;
+                        player.load(tracks, Some(&*sink))
+                    } else {
+                        player.load(tracks, None)
+                    }
+                }
+                "playback_enqueue" => {
+                    let tracks = playback_tracks(&tts_dir, &payload)?;
+                    player.enqueue(tracks, &*audio_sink(&settings_path)?)
+                }
+                "playback_play" => player.play(&*audio_sink(&settings_path)?),
+                "playback_pause" => Ok(player.pause()),
+                "playback_stop" => {
+                    cache::unpin_all();
+                    Ok(player.stop())
+                }
+                "playback_seek" => match payload.get("char").and_then(|v| v.as_u64()) {
+                    Some(char) => player.seek_char(char as usize),
+                    None => {
+                        let ms = payload["ms"].as_f64().ok_or("Missing ms")?;
+                        let track = payload.get("track").and_then(|v| v.as_u64()).map(|t| t as usize);
+                        player.seek(track, ms)
+                    }
+                },
+                "playback_rate" => {
+                    let rate = payload["rate"].as_f64().ok_or("Missing rate")?;
+                    player.set_rate(rate as f32)
+                }
+                "playback_next" => Ok(player.skip(1)),
+                "playback_previous" => Ok(player.skip(-1)),
+                "playback_status" => Ok(player.status()),
                 _ => Err(format!("Method {} not found", method)),
             }
         })
@@ -0,0 +1,190 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+pub const MODEL_FILENAME: &str = "kokoro-v1.0.onnx";
+pub const VOICES_FILENAME: &str = "voices.bin";
+const STAMP_FILENAME: &str = "model.json";
+
+/// Identity of the installed model files, recorded at install time so the
+/// synthesis cache can tell audio produced by different models apart.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct ModelStamp {
+    pub model_sha256: String,
+    pub voices_sha256: String,
+    pub installed_at: u64,
+    #[serde(default)]
+    pub version: Option<String>,
+    #[serde(default)]
+    pub source: Option<String>,
+}
+
+/// Lists model files with their digests. Published by a mirror next to the
+/// files, or bundled as `manifest.json` at the root of an offline model pack.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ModelManifest {
+    pub version: String,
+    pub files: Vec<ManifestFile>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ManifestFile {
+    pub name: String,
+    pub sha256: String,
+    #[serde(default)]
+    pub size: Option<u64>,
+    /// Download location, relative to the manifest URL. Defaults to `name`.
+    #[serde(default)]
+    pub url: Option<String>,
+}
+
+impl ModelManifest {
+    pub fn file(&self, name: &str) -> Option<&ManifestFile> {
+        self.files.iter().find(|f| f.name == name)
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        for required in [MODEL_FILENAME, VOICES_FILENAME] {
+            if self.file(required).is_none() {
+                return Err(format!("Model manifest does not list {}.", required));
+            }
+        }
+        for file in &self.files {
+            if file.name != MODEL_FILENAME && file.name != VOICES_FILENAME {
+                return Err(format!("Model manifest lists unexpected file '{}'.", file.name));
+            }
+            if file.sha256.len() != 64 || !file.sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+                return Err(format!("Model manifest has an invalid SHA-256 for {}.", file.name));
+            }
+        }
+        Ok(())
+    }
+}
+
+pub fn model_dir(tts_dir: &Path) -> PathBuf {
+    tts_dir.join("models")
+}
+
+fn stamp_path(tts_dir: &Path) -> PathBuf {
+    model_dir(tts_dir).join(STAMP_FILENAME)
+}
+
+pub fn now_millis() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut file = std::fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1024 * 1024];
+    loop {
+        let read = file.read(&mut buf).map_err(|e| e.to_string())?;
+        if read == 0 {
+            break;
+        }
+        hasher.update(&buf[..read]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Hashes the installed files and records the result next to them.
+pub fn write_model_stamp(tts_dir: &Path, version: Option<&str>, source: &str) -> Result<ModelStamp, String> {
+    let dir = model_dir(tts_dir);
+    let stamp = ModelStamp {
+        model_sha256: sha256_file(&dir.join(MODEL_FILENAME))?,
+        voices_sha256: sha256_file(&dir.join(VOICES_FILENAME))?,
+        installed_at: now_millis(),
+        version: version.map(str::to_string),
+        source: Some(source.to_string()),
+    };
+    let content = serde_json::to_string_pretty(&stamp).map_err(|e| e.to_string())?;
+    std::fs::write(stamp_path(tts_dir), content).map_err(|e| e.to_string())?;
+    Ok(stamp)
+}
+
+/// Returns the recorded stamp, creating it for installs that predate stamping.
+pub fn read_model_stamp(tts_dir: &Path) -> Result<ModelStamp, String> {
+    let path = stamp_path(tts_dir);
+    if let Ok(content) = std::fs::read_to_string(&path) {
+        if let Ok(stamp) = serde_json::from_str(&content) {
+            return Ok(stamp);
+        }
+    }
+    write_model_stamp(tts_dir, None, "unknown")
+}
+
+/// Moves verified files from a staging directory over the installed model.
+/// The new files are assembled next to the installed directory, which is only
+/// renamed aside for the swap and removed once the new one is in place.
+pub fn replace_model_files(tts_dir: &Path, staging_dir: &Path) -> Result<(), String> {
+    let dir = model_dir(tts_dir);
+    let incoming = tts_dir.join("models.new");
+    let previous = tts_dir.join("models.old");
+    if !dir.exists() && previous.exists() {
+        // An earlier swap stopped between its two renames.
+        std::fs::rename(&previous, &dir).map_err(|e| e.to_string())?;
+    }
+    for leftover in [&incoming, &previous] {
+        if leftover.exists() {
+            std::fs::remove_dir_all(leftover).map_err(|e| e.to_string())?;
+        }
+    }
+
+    std::fs::create_dir_all(&incoming).map_err(|e| e.to_string())?;
+    for name in [MODEL_FILENAME, VOICES_FILENAME] {
+        if let Err(e) = std::fs::rename(staging_dir.join(name), incoming.join(name)) {
+            let _ = std::fs::remove_dir_all(&incoming);
+            return Err(e.to_string());
+        }
+    }
+
+    if dir.exists() {
+        std::fs::rename(&dir, &previous).map_err(|e| e.to_string())?;
+    }
+    if let Err(e) = std::fs::rename(&incoming, &dir) {
+        if previous.exists() {
+            let _ = std::fs::rename(&previous, &dir);
+        }
+        return Err(e.to_string());
+    }
+    if previous.exists() {
+        let _ = std::fs::remove_dir_all(&previous);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stage(dir: &Path, model: &str) {
+        std::fs::create_dir_all(dir).unwrap();
+        std::fs::write(dir.join(MODEL_FILENAME), model).unwrap();
+        std::fs::write(dir.join(VOICES_FILENAME), "voices").unwrap();
+    }
+
+    #[test]
+    fn test_replace_keeps_installed_model_on_failure() {
+        let dir = tempfile::tempdir().unwrap();
+        let tts_dir = dir.path();
+        stage(&model_dir(tts_dir), "old");
+
+        let staging = tts_dir.join("tmp");
+        std::fs::create_dir_all(&staging).unwrap();
+        std::fs::write(staging.join(MODEL_FILENAME), "new").unwrap();
+        assert!(replace_model_files(tts_dir, &staging).is_err());
+        let installed = std::fs::read_to_string(model_dir(tts_dir).join(MODEL_FILENAME)).unwrap();
+        assert_eq!(installed, "old");
+
+        stage(&staging, "new");
+        replace_model_files(tts_dir, &staging).unwrap();
+        let installed = std::fs::read_to_string(model_dir(tts_dir).join(MODEL_FILENAME)).unwrap();
+        assert_eq!(installed, "new");
+        assert!(!tts_dir.join("models.old").exists());
+        assert!(!tts_dir.join("models.new").exists());
+    }
+}
@@ -0,0 +1,71 @@
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{FromSample, SampleFormat, SizedSample, StreamConfig};
+use std::sync::mpsc;
+
+use super::playback::{AudioSink, Renderer};
+
+/// The system's default audio output.
+pub struct DeviceSink;
+
+impl AudioSink for DeviceSink {
+    fn open(&self, renderer: Renderer) -> Result<Box<dyn Send>, String> {
+        let (ready_tx, ready_rx) = mpsc::channel::<Result<(), String>>();
+        let (stop_tx, stop_rx) = mpsc::channel::<()>();
+        // cpal streams are not `Send`, so each one lives on its own thread
+        // until the returned sender is dropped.
+        std::thread::spawn(move || {
+            let stream = match build_stream(renderer) {
+                Ok(stream) => stream,
+                Err(e) => {
+                    let _ = ready_tx.send(Err(e));
+                    return;
+                }
+            };
+            let _ = ready_tx.send(Ok(()));
+            let _ = stop_rx.recv();
+            drop(stream);
+        });
+        ready_rx.recv().map_err(|e| e.to_string())??;
+        Ok(Box::new(stop_tx))
+    }
+}
+
+fn build_stream(renderer: Renderer) -> Result<cpal::Stream, String> {
+    let device = cpal::default_host()
+        .default_output_device()
+        .ok_or("No audio output device found.")?;
+    let supported = device.default_output_config().map_err(|e| e.to_string())?;
+    let format = supported.sample_format();
+    let config: StreamConfig = supported.into();
+    let stream = match format {
+        SampleFormat::F32 => output_stream::<f32>(&device, &config, renderer),
+        SampleFormat::I16 => output_stream::<i16>(&device, &config, renderer),
+        SampleFormat::U16 => output_stream::<u16>(&device, &config, renderer),
+        other => Err(format!("Unsupported output sample format {:?}", other)),
+    }?;
+    stream.play().map_err(|e| e.to_string())?;
+    Ok(stream)
+}
+
+fn output_stream<T>(device: &cpal::Device, config: &StreamConfig, renderer: Renderer) -> Result<cpal::Stream, String>
+where
+    T: SizedSample + FromSample<f32>,
+{
+    let channels = config.channels as usize;
+    let sample_rate = config.sample_rate.0;
+    let mut buffer: Vec<f32> = Vec::new();
+    device
+        .build_output_stream(
+            config,
+            move |data: &mut [T], _| {
+                buffer.resize(data.len(), 0.0);
+                renderer.render(&mut buffer, channels, sample_rate);
+                for (out, sample) in data.iter_mut().zip(&buffer) {
+                    *out = T::from_sample(*sample);
+                }
+            },
+            |e| eprintln!("TTS audio output error: {}", e),
+            None,
+        )
+        .map_err(|e| e.to_string())
+}
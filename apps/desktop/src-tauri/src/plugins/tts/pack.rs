@@ -0,0 +1,207 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::{Component, Path};
+
+use super::model::{self, sha256_file, ModelManifest, MODEL_FILENAME, VOICES_FILENAME};
+
+const PACK_MANIFEST: &str = "manifest.json";
+
+enum PackFormat {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+fn pack_format(path: &Path) -> Result<PackFormat, String> {
+    let name = path
+        .file_name()
+        .and_then(|n| n.to_str())
+        .unwrap_or_default()
+        .to_lowercase();
+    if name.ends_with(".zip") {
+        Ok(PackFormat::Zip)
+    } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+        Ok(PackFormat::TarGz)
+    } else if name.ends_with(".tar") {
+        Ok(PackFormat::Tar)
+    } else {
+        Err("Unsupported model pack; expected a .zip, .tar or .tar.gz file.".to_string())
+    }
+}
+
+/// Maps an archive entry to one of the files a pack may contain. Entries may
+/// sit at the archive root or inside a single top-level folder; anything else
+/// (including `..` components) is ignored.
+fn pack_member(entry_path: &Path) -> Option<&'static str> {
+    let components: Vec<Component> = entry_path.components().collect();
+    if components.is_empty() || components.len() > 2 {
+        return None;
+    }
+    if !components.iter().all(|c| matches!(c, Component::Normal(_))) {
+        return None;
+    }
+    let name = entry_path.file_name()?.to_str()?;
+    [PACK_MANIFEST, MODEL_FILENAME, VOICES_FILENAME]
+        .into_iter()
+        .find(|member| *member == name)
+}
+
+fn extract_entry(reader: &mut impl Read, staging_dir: &Path, member: &str) -> Result<(), String> {
+    let mut out = File::create(staging_dir.join(member)).map_err(|e| e.to_string())?;
+    std::io::copy(reader, &mut out).map_err(|e| e.to_string())?;
+    Ok(())
+}
+
+fn extract_tar(reader: impl Read, staging_dir: &Path) -> Result<(), String> {
+    let mut archive = tar::Archive::new(reader);
+    for entry in archive.entries().map_err(|e| e.to_string())? {
+        let mut entry = entry.map_err(|e| e.to_string())?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let entry_path = entry.path().map_err(|e| e.to_string())?.into_owned();
+        if let Some(member) = pack_member(&entry_path) {
+            extract_entry(&mut entry, staging_dir, member)?;
+        }
+    }
+    Ok(())
+}
+
+fn extract_zip(file: File, staging_dir: &Path) -> Result<(), String> {
+    let mut archive = zip::ZipArchive::new(file).map_err(|e| e.to_string())?;
+    for i in 0..archive.len() {
+        let mut entry = archive.by_index(i).map_err(|e| e.to_string())?;
+        if !entry.is_file() {
+            continue;
+        }
+        let Some(entry_path) = entry.enclosed_name() else {
+            continue;
+        };
+        if let Some(member) = pack_member(&entry_path) {
+            extract_entry(&mut entry, staging_dir, member)?;
+        }
+    }
+    Ok(())
+}
+
+/// Checks the extracted files against the pack manifest.
+fn verify_staged(staging_dir: &Path) -> Result<ModelManifest, String> {
+    let content = std::fs::read_to_string(staging_dir.join(PACK_MANIFEST))
+        .map_err(|_| "Model pack is missing manifest.json.".to_string())?;
+    let manifest: ModelManifest = serde_json::from_str(&content)
+        .map_err(|e| format!("Model pack manifest is invalid: {}", e))?;
+    manifest.validate()?;
+
+    for file in &manifest.files {
+        let path = staging_dir.join(&file.name);
+        let meta = std::fs::metadata(&path)
+            .map_err(|_| format!("Model pack is missing {}.", file.name))?;
+        if let Some(size) = file.size {
+            if meta.len() != size {
+                return Err(format!(
+                    "{} in the model pack is {} bytes, expected {}.",
+                    file.name, meta.len(), size
+                ));
+            }
+        }
+        let actual = sha256_file(&path)?;
+        if !actual.eq_ignore_ascii_case(&file.sha256) {
+            return Err(format!("Checksum mismatch for {} in the model pack.", file.name));
+        }
+    }
+    Ok(manifest)
+}
+
+/// Extracts a model pack into `tmp/pack`, verifies it, and only then replaces
+/// the installed model. The installed model is untouched if anything fails.
+pub fn import_model_pack(tts_dir: &Path, archive_path: &Path) -> Result<ModelManifest, String> {
+    let format = pack_format(archive_path)?;
+    let staging_dir = tts_dir.join("tmp").join("pack");
+    if staging_dir.exists() {
+        std::fs::remove_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+    }
+    std::fs::create_dir_all(&staging_dir).map_err(|e| e.to_string())?;
+
+    let result = (|| {
+        let file = File::open(archive_path).map_err(|e| e.to_string())?;
+        match format {
+            PackFormat::Zip => extract_zip(file, &staging_dir)?,
+            PackFormat::Tar => extract_tar(file, &staging_dir)?,
+            PackFormat::TarGz => extract_tar(flate2::read::GzDecoder::new(file), &staging_dir)?,
+        }
+        let manifest = verify_staged(&staging_dir)?;
+        model::replace_model_files(tts_dir, &staging_dir)?;
+        model::write_model_stamp(tts_dir, Some(&manifest.version), "pack")?;
+        Ok(manifest)
+    })();
+
+    let _ = std::fs::remove_dir_all(&staging_dir);
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sha2::{Digest, Sha256};
+    use std::io::Write;
+
+    fn write_pack(path: &Path, voices_hash: Option<&str>) {
+        let model = b"model-bytes".to_vec();
+        let voices = b"voices-bytes".to_vec();
+        let manifest = serde_json::json!({
+            "version": "1.0-test",
+            "files": [
+                { "name": MODEL_FILENAME, "sha256": hex::encode(Sha256::digest(&model)), "size": model.len() },
+                { "name": VOICES_FILENAME, "sha256": voices_hash.map(str::to_string).unwrap_or_else(|| hex::encode(Sha256::digest(&voices))) }
+            ]
+        });
+
+        let mut zip = zip::ZipWriter::new(File::create(path).unwrap());
+        let options = zip::write::SimpleFileOptions::default();
+        for (name, bytes) in [
+            ("kokoro/manifest.json", serde_json::to_vec(&manifest).unwrap()),
+            ("kokoro/kokoro-v1.0.onnx", model),
+            ("kokoro/voices.bin", voices),
+        ] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(&bytes).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    #[test]
+    fn test_import_valid_pack() {
+        let dir = tempfile::tempdir().unwrap();
+        let pack = dir.path().join("kokoro.zip");
+        write_pack(&pack, None);
+
+        let manifest = import_model_pack(dir.path(), &pack).unwrap();
+        assert_eq!(manifest.version, "1.0-test");
+        assert_eq!(std::fs::read(model::model_dir(dir.path()).join(MODEL_FILENAME)).unwrap(), b"model-bytes");
+        assert_eq!(model::read_model_stamp(dir.path()).unwrap().version.as_deref(), Some("1.0-test"));
+    }
+
+    #[test]
+    fn test_invalid_pack_keeps_installed_model() {
+        let dir = tempfile::tempdir().unwrap();
+        let model_dir = model::model_dir(dir.path());
+        std::fs::create_dir_all(&model_dir).unwrap();
+        std::fs::write(model_dir.join(MODEL_FILENAME), b"installed").unwrap();
+
+        let pack = dir.path().join("kokoro.zip");
+        write_pack(&pack, Some(&"0".repeat(64)));
+
+        let err = import_model_pack(dir.path(), &pack).unwrap_err();
+        assert!(err.contains("Checksum mismatch"));
+        assert_eq!(std::fs::read(model_dir.join(MODEL_FILENAME)).unwrap(), b"installed");
+    }
+
+    #[test]
+    fn test_pack_member_rejects_nested_and_parent_paths() {
+        assert_eq!(pack_member(Path::new("voices.bin")), Some(VOICES_FILENAME));
+        assert_eq!(pack_member(Path::new("kokoro/voices.bin")), Some(VOICES_FILENAME));
+        assert_eq!(pack_member(Path::new("a/b/voices.bin")), None);
+        assert_eq!(pack_member(Path::new("../voices.bin")), None);
+        assert_eq!(pack_member(Path::new("kokoro/readme.txt")), None);
+    }
+}
@@ -0,0 +1,357 @@
+use regex::Regex;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+use unicode_segmentation::UnicodeSegmentation;
+
+pub const SENTENCE_PAUSE_SETTING: &str = "plugins.core.tts.pauseSentenceMs";
+pub const PARAGRAPH_PAUSE_SETTING: &str = "plugins.core.tts.pauseParagraphMs";
+pub const HEADING_PAUSE_SETTING: &str = "plugins.core.tts.pauseHeadingMs";
+pub const LIST_ITEM_PAUSE_SETTING: &str = "plugins.core.tts.pauseListItemMs";
+
+/// Longest pause a note can ask for.
+const MAX_PAUSE_MS: u32 = 10_000;
+
+/// Silence after each structural element, in milliseconds.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PauseSettings {
+    pub sentence: u32,
+    pub paragraph: u32,
+    pub heading: u32,
+    pub list_item: u32,
+}
+
+impl Default for PauseSettings {
+    fn default() -> Self {
+        Self {
+            sentence: 100,
+            paragraph: 400,
+            heading: 600,
+            list_item: 300,
+        }
+    }
+}
+
+impl PauseSettings {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Self {
+        let defaults = Self::default();
+        let read = |key: &str, default: u32| {
+            settings
+                .get(key)
+                .and_then(|v| v.as_u64())
+                .map(|ms| (ms as u32).min(MAX_PAUSE_MS))
+                .unwrap_or(default)
+        };
+        Self {
+            sentence: read(SENTENCE_PAUSE_SETTING, defaults.sentence),
+            paragraph: read(PARAGRAPH_PAUSE_SETTING, defaults.paragraph),
+            heading: read(HEADING_PAUSE_SETTING, defaults.heading),
+            list_item: read(LIST_ITEM_PAUSE_SETTING, defaults.list_item),
+        }
+    }
+
+    /// Stable description for the cache fingerprint.
+    pub fn fingerprint(&self) -> String {
+        format!(
+            "sentence={},paragraph={},heading={},list_item={}",
+            self.sentence, self.paragraph, self.heading, self.list_item
+        )
+    }
+}
+
+/// One piece of speech: a byte range of the request text and the silence
+/// that follows it.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Utterance {
+    pub start: usize,
+    pub end: usize,
+    pub pause_after_ms: u32,
+}
+
+#[derive(Debug, Default, PartialEq)]
+pub struct SpeechPlan {
+    /// Silence before the first utterance, from a directive at the very start.
+    pub leading_pause_ms: u32,
+    pub utterances: Vec<Utterance>,
+}
+
+impl SpeechPlan {
+    /// The part of the plan inside `text[start..end]`. Utterances crossing
+    /// either edge are cut there; the leading pause only applies from the
+    /// start of the text.
+    pub fn within(self, text: &str, start: usize, end: usize) -> SpeechPlan {
+        let utterances = self
+            .utterances
+            .into_iter()
+            .filter_map(|utterance| {
+                let from = utterance.start.max(start);
+                let to = utterance.end.min(end);
+                let slice = text.get(from..to)?;
+                let trimmed = slice.trim();
+                if trimmed.is_empty() {
+                    return None;
+                }
+                let start = from + (slice.len() - slice.trim_start().len());
+                Some(Utterance {
+                    start,
+                    end: start + trimmed.len(),
+                    pause_after_ms: utterance.pause_after_ms,
+                })
+            })
+            .collect();
+        SpeechPlan {
+            leading_pause_ms: if start == 0 { self.leading_pause_ms } else { 0 },
+            utterances,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum BlockKind {
+    Paragraph,
+    Heading,
+    ListItem,
+}
+
+struct Block {
+    kind: BlockKind,
+    start: usize,
+    end: usize,
+}
+
+enum DirectiveKind {
+    /// `None` for a bare `<break/>`, which takes the paragraph pause.
+    Pause(Option<u32>),
+    /// Any other Markdown comment: not rendered, so not spoken either.
+    Comment,
+}
+
+struct Directive {
+    start: usize,
+    end: usize,
+    kind: DirectiveKind,
+}
+
+/// `<break time="500ms"/>`, `<break time="1.5s">`, `<break/>`, the Markdown
+/// comment form `<!-- pause 800ms -->`, and other comments.
+fn directive_regex() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(
+            r#"(?i)<break(?:\s+time\s*=\s*["']?\s*([0-9]+(?:\.[0-9]+)?)\s*(ms|s)?\s*["']?)?\s*/?>|<!--\s*pause\s*:?\s*(?:([0-9]+(?:\.[0-9]+)?)\s*(ms|s)?)?\s*-->|(<!--.*?-->)"#,
+        )
+        .expect("valid directive pattern")
+    })
+}
+
+fn parse_duration(value: &str, unit: Option<&str>) -> Option<u32> {
+    let value: f64 = value.parse().ok()?;
+    let ms = match unit.map(|u| u.to_ascii_lowercase()) {
+        Some(unit) if unit == "s" => value * 1000.0,
+        _ => value,
+    };
+    Some((ms.round() as u32).min(MAX_PAUSE_MS))
+}
+
+fn find_directives(text: &str) -> Vec<Directive> {
+    directive_regex()
+        .captures_iter(text)
+        .map(|caps| {
+            let whole = caps.get(0).expect("match");
+            let (value, unit) = match caps.get(1) {
+                Some(value) => (Some(value), caps.get(2)),
+                None => (caps.get(3), caps.get(4)),
+            };
+            let kind = if caps.get(5).is_some() {
+                DirectiveKind::Comment
+            } else {
+                DirectiveKind::Pause(value.and_then(|v| parse_duration(v.as_str(), unit.map(|u| u.as_str()))))
+            };
+            Directive {
+                start: whole.start(),
+                end: whole.end(),
+                kind,
+            }
+        })
+        .collect()
+}
+
+/// Length of a heading or list marker at the start of `line`, including
+/// indentation and a task checkbox.
+fn line_marker(line: &str) -> Option<(BlockKind, usize)> {
+    let indent = line.len() - line.trim_start().len();
+    let rest = &line[indent..];
+
+    let hashes = rest.bytes().take_while(|&b| b == b'#').count();
+    if (1..=6).contains(&hashes) && rest[hashes..].starts_with(' ') {
+        return Some((BlockKind::Heading, indent + hashes + 1));
+    }
+
+    let bullet = if rest.starts_with("- ") || rest.starts_with("* ") || rest.starts_with("+ ") {
+        2
+    } else {
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        if digits > 0 && (rest[digits..].starts_with(". ") || rest[digits..].starts_with(") ")) {
+            digits + 2
+        } else {
+            return None;
+        }
+    };
+    let task = ["[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find(|box_| rest[bullet..].starts_with(*box_))
+        .map_or(0, |box_| box_.len());
+    Some((BlockKind::ListItem, indent + bullet + task))
+}
+
+/// Splits Markdown into paragraphs, headings and list items. Blank lines end
+/// a block; indented lines continue the list item above them.
+fn blocks(text: &str) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut open = false;
+    let mut offset = 0;
+
+    for line in text.split_inclusive('\n') {
+        let content = line.trim_end_matches(['\n', '\r']);
+        let line_start = offset;
+        offset += line.len();
+
+        if content.trim().is_empty() {
+            open = false;
+            continue;
+        }
+        let line_end = line_start + content.len();
+
+        if let Some((kind, marker)) = line_marker(content) {
+            blocks.push(Block { kind, start: line_start + marker, end: line_end });
+            open = kind == BlockKind::ListItem;
+            continue;
+        }
+
+        let continues = open
+            && blocks.last().is_some_and(|block| {
+                block.kind == BlockKind::Paragraph || content.starts_with(char::is_whitespace)
+            });
+        if continues {
+            if let Some(block) = blocks.last_mut() {
+                block.end = line_end;
+            }
+        } else {
+            blocks.push(Block { kind: BlockKind::Paragraph, start: line_start, end: line_end });
+            open = true;
+        }
+    }
+    blocks
+}
+
+/// Splits `text` into utterances, each followed by the pause for the element
+/// it ends. Directives replace the pause before them; neither they nor other
+/// comments are spoken.
+pub fn plan(text: &str, pauses: &PauseSettings) -> SpeechPlan {
+    let directives = find_directives(text);
+    let mut plan = SpeechPlan::default();
+
+    for block in blocks(text) {
+        let block_pause = match block.kind {
+            BlockKind::Paragraph => pauses.paragraph,
+            BlockKind::Heading => pauses.heading,
+            BlockKind::ListItem => pauses.list_item,
+        };
+        let block_first = plan.utterances.len();
+        // Whether the block ends on a directive, which then wins over the block pause.
+        let mut explicit_end = false;
+        let mut cursor = block.start;
+
+        let inside = directives.iter().filter(|d| d.start >= block.start && d.end <= block.end);
+        for span_end in inside.map(Some).chain(std::iter::once(None)) {
+            let end = span_end.map_or(block.end, |d| d.start);
+            for (offset, sentence) in text[cursor..end].split_sentence_bound_indices() {
+                let trimmed = sentence.trim();
+                if trimmed.is_empty() {
+                    continue;
+                }
+                let start = cursor + offset + (sentence.len() - sentence.trim_start().len());
+                plan.utterances.push(Utterance {
+                    start,
+                    end: start + trimmed.len(),
+                    pause_after_ms: pauses.sentence,
+                });
+                explicit_end = false;
+            }
+
+            if let Some(directive) = span_end {
+                if let DirectiveKind::Pause(pause_ms) = directive.kind {
+                    let pause = pause_ms.unwrap_or(pauses.paragraph);
+                    match plan.utterances.last_mut() {
+                        Some(utterance) => utterance.pause_after_ms = pause,
+                        None => plan.leading_pause_ms = pause,
+                    }
+                    explicit_end = true;
+                }
+                cursor = directive.end;
+            }
+        }
+
+        if plan.utterances.len() > block_first && !explicit_end {
+            if let Some(last) = plan.utterances.last_mut() {
+                last.pause_after_ms = last.pause_after_ms.max(block_pause);
+            }
+        }
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spoken<'a>(text: &'a str, plan: &SpeechPlan) -> Vec<(&'a str, u32)> {
+        plan.utterances
+            .iter()
+            .map(|u| (&text[u.start..u.end], u.pause_after_ms))
+            .collect()
+    }
+
+    #[test]
+    fn test_structural_pauses() {
+        let text = "# Groceries\n\nWe need a few things. Soon.\nReally soon.\n\n- [ ] Milk\n- Eggs\n  from the farm\n1. Bread";
+        let plan = plan(text, &PauseSettings::default());
+        assert_eq!(
+            spoken(text, &plan),
+            vec![
+                ("Groceries", 600),
+                ("We need a few things.", 100),
+                ("Soon.", 100),
+                ("Really soon.", 400),
+                ("Milk", 300),
+                ("Eggs", 100),
+                ("from the farm", 300),
+                ("Bread", 300),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_break_directives() {
+        let text = "<break time=\"2s\"/>Wait for it. <break time='750ms'> Now!<!-- pause -->\n\nDone <BREAK/> here.<!-- todo -->";
+        let plan = plan(text, &PauseSettings::default());
+        assert_eq!(plan.leading_pause_ms, 2000);
+        assert_eq!(
+            spoken(text, &plan),
+            vec![("Wait for it.", 750), ("Now!", 400), ("Done", 400), ("here.", 400)]
+        );
+
+        let from = text.find("for").unwrap();
+        let clipped = plan.within(text, from, text.find("here").unwrap());
+        assert_eq!(clipped.leading_pause_ms, 0);
+        assert_eq!(spoken(text, &clipped), vec![("for it.", 750), ("Now!", 400), ("Done", 400)]);
+
+        let pauses = PauseSettings::from_settings(&HashMap::from([
+            (SENTENCE_PAUSE_SETTING.to_string(), Value::from(250)),
+            (HEADING_PAUSE_SETTING.to_string(), Value::from(60_000)),
+        ]));
+        assert_eq!(pauses.sentence, 250);
+        assert_eq!(pauses.heading, MAX_PAUSE_MS);
+        assert_eq!(parse_duration("1.5", Some("S")), Some(1500));
+    }
+}
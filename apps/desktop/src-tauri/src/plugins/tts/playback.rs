@@ -0,0 +1,649 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+/// `device` (default) plays through the system's default output; `null`
+/// discards the audio but keeps time, for running headless.
+pub const AUDIO_SINK_SETTING: &str = "plugins.core.tts.audioSink";
+
+pub const POSITION_EVENT: &str = "tts://playback-position";
+pub const SEGMENT_EVENT: &str = "tts://playback-segment";
+pub const STATE_EVENT: &str = "tts://playback-state";
+
+const TICK_INTERVAL: Duration = Duration::from_millis(100);
+/// `previous` restarts the current track when it is further in than this.
+const RESTART_THRESHOLD_MS: f64 = 3000.0;
+const MIN_RATE: f32 = 0.25;
+const MAX_RATE: f32 = 4.0;
+/// Grains overlap by half, so this is also half a grain.
+const STRETCH_HOP_MS: f64 = 20.0;
+const NULL_SINK_RATE: u32 = 48_000;
+const NULL_SINK_PERIOD: Duration = Duration::from_millis(20);
+
+/// Delivers a plugin event to the frontend.
+pub type EventSink = Arc<dyn Fn(&str, Value) + Send + Sync>;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SinkKind {
+    Device,
+    Null,
+}
+
+impl SinkKind {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Result<Self, String> {
+        match settings.get(AUDIO_SINK_SETTING).and_then(|v| v.as_str()).unwrap_or("device") {
+            "device" => Ok(SinkKind::Device),
+            "null" => Ok(SinkKind::Null),
+            other => Err(format!("Unknown audio sink '{}'", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Segment {
+    pub start_char: usize,
+    pub end_char: usize,
+    pub start_ms: f64,
+    pub end_ms: f64,
+}
+
+/// One queued piece of audio, usually a chunk of a note.
+pub struct Track {
+    pub id: String,
+    /// Vault path of the note being read, passed back in events so the UI
+    /// only highlights that note.
+    pub note: Option<String>,
+    pub segments: Vec<Segment>,
+    samples: Vec<f32>,
+    sample_rate: u32,
+}
+
+impl Track {
+    pub fn new(id: &str, note: Option<String>, samples: Vec<f32>, sample_rate: u32, segments: Vec<Segment>) -> Self {
+        Self {
+            id: id.to_string(),
+            note,
+            segments,
+            samples,
+            sample_rate,
+        }
+    }
+
+    /// Loads cached audio and the segments stored beside it. `char_offset`
+    /// is added to the segment offsets, for audio of a chunk of a note.
+    pub fn load(cache_dir: &Path, path: &Path, char_offset: usize, note: Option<String>) -> Result<Self, String> {
+        let cache_dir = cache_dir.canonicalize().map_err(|e| e.to_string())?;
+        let target = path.canonicalize().map_err(|e| e.to_string())?;
+        if !target.starts_with(&cache_dir) {
+            return Err("Invalid audio path.".to_string());
+        }
+
+        let mut reader = hound::WavReader::open(&target).map_err(|e| e.to_string())?;
+        let spec = reader.spec();
+        let interleaved: Vec<f32> = match spec.sample_format {
+            hound::SampleFormat::Int => {
+                let scale = (1u32 << (spec.bits_per_sample - 1)) as f32;
+                reader.samples::<i32>().map(|s| s.map(|s| s as f32 / scale)).collect::<Result<_, _>>()
+            }
+            hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>(),
+        }
+        .map_err(|e| e.to_string())?;
+        let channels = spec.channels.max(1) as usize;
+        let samples = interleaved
+            .chunks(channels)
+            .map(|frame| frame.iter().sum::<f32>() / channels as f32)
+            .collect();
+
+        let segments = std::fs::read_to_string(target.with_extension("json"))
+            .ok()
+            .and_then(|content| serde_json::from_str::<Value>(&content).ok())
+            .and_then(|metadata| serde_json::from_value::<Vec<Segment>>(metadata["segments"].clone()).ok())
+            .unwrap_or_default()
+            .into_iter()
+            .map(|segment| Segment {
+                start_char: segment.start_char + char_offset,
+                end_char: segment.end_char + char_offset,
+                ..segment
+            })
+            .collect();
+        let id = target.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+        Ok(Self::new(&id, note, samples, spec.sample_rate, segments))
+    }
+
+    pub fn duration_ms(&self) -> f64 {
+        self.samples.len() as f64 * 1000.0 / self.sample_rate as f64
+    }
+
+    fn segment_at(&self, ms: f64) -> Option<usize> {
+        self.segments.iter().position(|s| ms >= s.start_ms && ms < s.end_ms)
+    }
+
+    fn hop(&self) -> f64 {
+        self.sample_rate as f64 * STRETCH_HOP_MS / 1000.0
+    }
+
+    /// Linear interpolation between samples; silence outside the track.
+    fn sample_at(&self, position: f64) -> f32 {
+        if position < 0.0 {
+            return 0.0;
+        }
+        let index = position as usize;
+        let Some(&a) = self.samples.get(index) else {
+            return 0.0;
+        };
+        let b = self.samples.get(index + 1).copied().unwrap_or(a);
+        a + (b - a) * (position - index as f64) as f32
+    }
+}
+
+struct Transport {
+    queue: Vec<Track>,
+    current: usize,
+    /// Position in the current track, in its own sample frames.
+    position: f64,
+    playing: bool,
+    rate: f32,
+    /// Where the current and the previous grain start in the track, and how
+    /// far into the current grain playback is, in the track's sample frames.
+    grain_start: f64,
+    previous_start: f64,
+    grain_offset: f64,
+}
+
+impl Transport {
+    fn track(&self) -> Option<&Track> {
+        self.queue.get(self.current)
+    }
+
+    /// Played past the last track; more may still be enqueued.
+    fn ended(&self) -> bool {
+        self.current >= self.queue.len()
+    }
+
+    fn position_ms(&self) -> f64 {
+        self.track().map_or(0.0, |track| self.position * 1000.0 / track.sample_rate as f64)
+    }
+
+    fn queue_ms(&self) -> f64 {
+        self.queue.iter().take(self.current).map(Track::duration_ms).sum::<f64>() + self.position_ms()
+    }
+
+    fn jump(&mut self, track: usize, ms: f64) {
+        self.current = track;
+        self.position = self
+            .track()
+            .map_or(0.0, |t| (ms.max(0.0) * t.sample_rate as f64 / 1000.0).min(t.samples.len() as f64));
+        self.restart_grains();
+    }
+
+    /// Starts grains at the current position. The previous grain lines up
+    /// with the current one, so playback resumes without a fade.
+    fn restart_grains(&mut self) {
+        let hop = self.track().map_or(0.0, Track::hop);
+        self.grain_start = self.position;
+        self.previous_start = self.position - hop;
+        self.grain_offset = 0.0;
+    }
+
+    fn render(&mut self, out: &mut [f32], channels: usize, out_rate: u32) {
+        for frame in out.chunks_mut(channels.max(1)) {
+            let sample = if self.playing { self.next_sample(out_rate) } else { 0.0 };
+            frame.fill(sample);
+        }
+    }
+
+    /// Applies the rate by overlap-add time-stretching, so pitch is kept:
+    /// Hann-windowed grains of two hops are read at the original speed, and
+    /// a new grain starts every hop at wherever `rate` has moved the
+    /// position. At 1x the grains line up and the track plays unchanged.
+    fn next_sample(&mut self, out_rate: u32) -> f32 {
+        loop {
+            let Some(track) = self.queue.get(self.current) else {
+                return 0.0;
+            };
+            if self.position >= track.samples.len() as f64 {
+                self.current += 1;
+                self.position = 0.0;
+                self.restart_grains();
+                continue;
+            }
+            let hop = track.hop();
+            let fade = 0.5 - 0.5 * (std::f64::consts::PI * self.grain_offset / hop).cos();
+            let sample = fade as f32 * track.sample_at(self.grain_start + self.grain_offset)
+                + (1.0 - fade) as f32 * track.sample_at(self.previous_start + hop + self.grain_offset);
+
+            let step = track.sample_rate as f64 / out_rate as f64;
+            self.position += self.rate as f64 * step;
+            self.grain_offset += step;
+            if self.grain_offset >= hop {
+                self.grain_offset -= hop;
+                self.previous_start = self.grain_start;
+                self.grain_start = self.position - self.grain_offset * self.rate as f64;
+            }
+            return sample;
+        }
+    }
+}
+
+/// Handed to a sink so it can pull audio from the player.
+#[derive(Clone)]
+pub struct Renderer(Arc<Mutex<Transport>>);
+
+impl Renderer {
+    /// Fills `out` (interleaved, `channels` per frame) at `sample_rate`.
+    pub fn render(&self, out: &mut [f32], channels: usize, sample_rate: u32) {
+        self.0.lock().unwrap().render(out, channels, sample_rate);
+    }
+}
+
+/// Somewhere to play audio. `open` starts pulling from the renderer and
+/// returns a handle that stops the output when dropped.
+pub trait AudioSink {
+    fn open(&self, renderer: Renderer) -> Result<Box<dyn Send>, String>;
+}
+
+/// Consumes audio in real time without playing it.
+pub struct NullSink;
+
+struct NullOutput(Arc<AtomicBool>);
+
+impl Drop for NullOutput {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+}
+
+impl AudioSink for NullSink {
+    fn open(&self, renderer: Renderer) -> Result<Box<dyn Send>, String> {
+        let stopped = Arc::new(AtomicBool::new(false));
+        let flag = stopped.clone();
+        std::thread::spawn(move || {
+            let started = Instant::now();
+            let mut rendered = 0usize;
+            let mut buffer = Vec::new();
+            while !flag.load(Ordering::SeqCst) {
+                std::thread::sleep(NULL_SINK_PERIOD);
+                let due = (started.elapsed().as_secs_f64() * NULL_SINK_RATE as f64) as usize;
+                buffer.resize(due - rendered, 0.0);
+                renderer.render(&mut buffer, 1, NULL_SINK_RATE);
+                rendered = due;
+            }
+        });
+        Ok(Box::new(NullOutput(stopped)))
+    }
+}
+
+#[derive(Clone, Copy, Default, PartialEq)]
+struct Snapshot {
+    track: usize,
+    segment: Option<usize>,
+    playing: bool,
+    ended: bool,
+    rate: f32,
+    tracks: usize,
+}
+
+/// Plays a queue of tracks through a sink, independently of any window.
+/// The output is open only while something is playing.
+pub struct Player {
+    transport: Arc<Mutex<Transport>>,
+    output: Mutex<Option<Box<dyn Send>>>,
+    events: EventSink,
+    last: Mutex<Snapshot>,
+}
+
+impl Player {
+    pub fn new(events: EventSink) -> Self {
+        Self {
+            transport: Arc::new(Mutex::new(Transport {
+                queue: Vec::new(),
+                current: 0,
+                position: 0.0,
+                playing: false,
+                rate: 1.0,
+                grain_start: 0.0,
+                previous_start: 0.0,
+                grain_offset: 0.0,
+            })),
+            output: Mutex::new(None),
+            events,
+            last: Mutex::new(Snapshot::default()),
+        }
+    }
+
+    /// Replaces the queue; playback starts from its first track.
+    pub fn load(&self, tracks: Vec<Track>, sink: Option<&dyn AudioSink>) -> Result<Value, String> {
+        {
+            let mut transport = self.transport.lock().unwrap();
+            transport.queue = tracks;
+            transport.jump(0, 0.0);
+            transport.playing = sink.is_some();
+        }
+        self.sync_output(sink)?;
+        Ok(self.status())
+    }
+
+    /// Appends to the queue. If playback had run out of tracks, it carries
+    /// on with these.
+    pub fn enqueue(&self, tracks: Vec<Track>, sink: &dyn AudioSink) -> Result<Value, String> {
+        self.transport.lock().unwrap().queue.extend(tracks);
+        self.sync_output(Some(sink))?;
+        Ok(self.status())
+    }
+
+    pub fn play(&self, sink: &dyn AudioSink) -> Result<Value, String> {
+        {
+            let mut transport = self.transport.lock().unwrap();
+            if transport.queue.is_empty() {
+                return Err("Nothing to play.".to_string());
+            }
+            if transport.ended() {
+                transport.jump(0, 0.0);
+            }
+            transport.playing = true;
+        }
+        self.sync_output(Some(sink))?;
+        Ok(self.status())
+    }
+
+    pub fn pause(&self) -> Value {
+        self.transport.lock().unwrap().playing = false;
+        let _ = self.sync_output(None);
+        self.status()
+    }
+
+    /// Stops and clears the queue.
+    pub fn stop(&self) -> Value {
+        {
+            let mut transport = self.transport.lock().unwrap();
+            transport.playing = false;
+            transport.queue.clear();
+            transport.jump(0, 0.0);
+        }
+        let _ = self.sync_output(None);
+        self.status()
+    }
+
+    /// Moves to `ms` within `track`, or within the whole queue without one.
+    pub fn seek(&self, track: Option<usize>, ms: f64) -> Result<Value, String> {
+        {
+            let mut transport = self.transport.lock().unwrap();
+            match track {
+                Some(index) if index >= transport.queue.len() => return Err(format!("No track {}", index)),
+                Some(index) => transport.jump(index, ms),
+                None => {
+                    let mut remaining = ms.max(0.0);
+                    let mut index = 0;
+                    while let Some(track) = transport.queue.get(index) {
+                        if remaining < track.duration_ms() || index + 1 == transport.queue.len() {
+                            break;
+                        }
+                        remaining -= track.duration_ms();
+                        index += 1;
+                    }
+                    transport.jump(index, remaining);
+                }
+            }
+        }
+        Ok(self.status())
+    }
+
+    /// Moves to the start of the segment containing UTF-16 offset `char`.
+    pub fn seek_char(&self, char: usize) -> Result<Value, String> {
+        let target = {
+            let transport = self.transport.lock().unwrap();
+            transport.queue.iter().enumerate().find_map(|(index, track)| {
+                track
+                    .segments
+                    .iter()
+                    .find(|s| char >= s.start_char && char < s.end_char)
+                    .map(|s| (index, s.start_ms))
+            })
+        };
+        let (track, ms) = target.ok_or("No segment at that position.")?;
+        self.seek(Some(track), ms)
+    }
+
+    /// Moves `delta` tracks. `previous` from well into a track restarts it.
+    pub fn skip(&self, delta: isize) -> Value {
+        {
+            let mut transport = self.transport.lock().unwrap();
+            let current = transport.current.min(transport.queue.len().saturating_sub(1));
+            let target = if delta < 0 && transport.position_ms() > RESTART_THRESHOLD_MS && !transport.ended() {
+                current
+            } else {
+                current.saturating_add_signed(delta).min(transport.queue.len())
+            };
+            transport.jump(target, 0.0);
+        }
+        let _ = self.sync_output(None);
+        self.status()
+    }
+
+    pub fn set_rate(&self, rate: f32) -> Result<Value, String> {
+        if !(MIN_RATE..=MAX_RATE).contains(&rate) {
+            return Err(format!("Rate must be between {} and {}", MIN_RATE, MAX_RATE));
+        }
+        self.transport.lock().unwrap().rate = rate;
+        Ok(self.status())
+    }
+
+    pub fn status(&self) -> Value {
+        let transport = self.transport.lock().unwrap();
+        let ms = transport.position_ms();
+        let tracks: Vec<Value> = transport
+            .queue
+            .iter()
+            .map(|track| {
+                serde_json::json!({
+                    "id": track.id,
+                    "note": track.note,
+                    "durationMs": track.duration_ms(),
+                    "segments": track.segments.len()
+                })
+            })
+            .collect();
+        serde_json::json!({
+            "playing": transport.playing,
+            "ended": transport.ended(),
+            "rate": transport.rate,
+            "track": transport.current,
+            "ms": ms,
+            "queueMs": transport.queue_ms(),
+            "durationMs": transport.queue.iter().map(Track::duration_ms).sum::<f64>(),
+            "segment": transport.track().and_then(|t| t.segment_at(ms)),
+            "tracks": tracks
+        })
+    }
+
+    /// Opens the output when there is something to play and closes it
+    /// otherwise. Without a sink it can only close.
+    fn sync_output(&self, sink: Option<&dyn AudioSink>) -> Result<(), String> {
+        let wanted = {
+            let transport = self.transport.lock().unwrap();
+            transport.playing && !transport.ended()
+        };
+        let mut output = self.output.lock().unwrap();
+        match (wanted, output.is_some(), sink) {
+            (true, false, Some(sink)) => match sink.open(Renderer(self.transport.clone())) {
+                Ok(handle) => *output = Some(handle),
+                Err(e) => {
+                    self.transport.lock().unwrap().playing = false;
+                    return Err(e);
+                }
+            },
+            (false, true, _) => *output = None,
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Emits position while playing, and segment and state changes. Runs
+    /// every tick; also closes the output once the queue has played out.
+    pub fn tick(&self) {
+        let (snapshot, position, segment) = {
+            let transport = self.transport.lock().unwrap();
+            let ms = transport.position_ms();
+            let snapshot = Snapshot {
+                track: transport.current,
+                segment: transport.track().and_then(|t| t.segment_at(ms)),
+                playing: transport.playing,
+                ended: transport.ended(),
+                rate: transport.rate,
+                tracks: transport.queue.len(),
+            };
+            let position = serde_json::json!({
+                "track": transport.current,
+                "note": transport.track().and_then(|t| t.note.clone()),
+                "ms": ms,
+                "queueMs": transport.queue_ms()
+            });
+            let segment = snapshot
+                .segment
+                .and_then(|index| transport.track().map(|t| (t.note.clone(), t.segments[index].clone())));
+            (snapshot, position, segment)
+        };
+        let last = std::mem::replace(&mut *self.last.lock().unwrap(), snapshot);
+
+        if snapshot.playing && !snapshot.ended {
+            (self.events)(POSITION_EVENT, position);
+        }
+        if (snapshot.track, snapshot.segment) != (last.track, last.segment) {
+            let (note, segment) = segment.map_or((None, None), |(note, segment)| (note, Some(segment)));
+            (self.events)(
+                SEGMENT_EVENT,
+                serde_json::json!({
+                    "track": snapshot.track,
+                    "index": snapshot.segment,
+                    "note": note,
+                    "segment": segment
+                }),
+            );
+        }
+        if snapshot != last {
+            if snapshot.ended {
+                let _ = self.sync_output(None);
+            }
+            (self.events)(STATE_EVENT, self.status());
+        }
+    }
+}
+
+/// Emits playback events every tick; the task ends with the player.
+pub fn spawn_ticker(player: &Arc<Player>) {
+    let player: Weak<Player> = Arc::downgrade(player);
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(TICK_INTERVAL).await;
+            let Some(player) = player.upgrade() else {
+                break;
+            };
+            player.tick();
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type EventLog = Arc<Mutex<Vec<(String, Value)>>>;
+
+    fn recorder() -> (EventSink, EventLog) {
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let log = events.clone();
+        let sink: EventSink = Arc::new(move |name: &str, payload: Value| log.lock().unwrap().push((name.to_string(), payload)));
+        (sink, events)
+    }
+
+    /// One second of audio at 1 kHz with two half-second segments.
+    fn track(id: &str, char_offset: usize) -> Track {
+        let segments = vec![
+            Segment { start_char: char_offset, end_char: char_offset + 5, start_ms: 0.0, end_ms: 500.0 },
+            Segment { start_char: char_offset + 6, end_char: char_offset + 10, start_ms: 500.0, end_ms: 1000.0 },
+        ];
+        Track::new(id, Some("note.md".into()), vec![0.5; 1000], 1000, segments)
+    }
+
+    fn render_ms(player: &Player, ms: usize) {
+        let mut buffer = vec![0.0; ms];
+        Renderer(player.transport.clone()).render(&mut buffer, 1, 1000);
+    }
+
+    #[test]
+    fn test_queue_rate_and_seek() {
+        let (events, log) = recorder();
+        let player = Player::new(events);
+        player.load(vec![track("a", 0), track("b", 11)], None).unwrap();
+        player.transport.lock().unwrap().playing = true;
+
+        render_ms(&player, 600);
+        player.tick();
+        let status = player.status();
+        assert_eq!(status["track"], 0);
+        assert_eq!(status["segment"], 1);
+
+        player.set_rate(2.0).unwrap();
+        render_ms(&player, 300);
+        assert_eq!(player.status()["track"], 1);
+        assert!(player.set_rate(10.0).is_err());
+
+        player.seek(None, 1250.0).unwrap();
+        assert_eq!(player.status()["ms"], 250.0);
+        player.seek_char(12).unwrap();
+        assert_eq!(player.status()["queueMs"], 1000.0);
+        assert_eq!(player.skip(-1)["track"], 0);
+
+        render_ms(&player, 2000);
+        player.tick();
+        assert_eq!(player.status()["ended"], true);
+        let names: Vec<String> = log.lock().unwrap().iter().map(|(name, _)| name.clone()).collect();
+        assert!(names.contains(&POSITION_EVENT.to_string()));
+        assert!(names.contains(&SEGMENT_EVENT.to_string()));
+        assert_eq!(names.last().unwrap(), STATE_EVENT);
+    }
+
+    #[test]
+    fn test_rate_keeps_pitch() {
+        let (events, _) = recorder();
+        let player = Player::new(events);
+        let tone = (0..8000).map(|i| (i as f32 * 2.0 * std::f32::consts::PI * 400.0 / 8000.0).sin()).collect();
+        player.load(vec![Track::new("tone", None, tone, 8000, Vec::new())], None).unwrap();
+        player.transport.lock().unwrap().playing = true;
+        player.set_rate(2.0).unwrap();
+
+        let mut buffer = vec![0.0; 2000];
+        Renderer(player.transport.clone()).render(&mut buffer, 1, 8000);
+        let crossings = buffer.windows(2).filter(|w| w[0] < 0.0 && w[1] >= 0.0).count();
+        // A quarter second of a 400 Hz tone, not 800 Hz as resampling gives.
+        assert!((95..=105).contains(&crossings), "{} crossings", crossings);
+        assert_eq!(player.status()["ms"], 500.0);
+    }
+
+    #[test]
+    fn test_null_sink_plays_headless() {
+        let (events, _) = recorder();
+        let player = Player::new(events);
+        let short = Track::new("short", None, vec![0.1; 100], 1000, Vec::new());
+        player.load(vec![short], Some(&NullSink)).unwrap();
+        assert!(player.output.lock().unwrap().is_some());
+
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while player.status()["ended"] != true && Instant::now() < deadline {
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        player.tick();
+        assert_eq!(player.status()["ended"], true);
+        assert!(player.output.lock().unwrap().is_none());
+
+        player.enqueue(vec![track("more", 0)], &NullSink).unwrap();
+        assert!(player.output.lock().unwrap().is_some());
+        player.pause();
+        assert!(player.output.lock().unwrap().is_none());
+    }
+}
@@ -0,0 +1,272 @@
+use serde::Serialize;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::{MutexGuard, Notify};
+
+use super::model::now_millis;
+use super::residency::{EngineSlot, LoadedEngine};
+
+/// Finished jobs kept for `jobs`; older ones are forgotten.
+const FINISHED_JOBS_KEPT: usize = 50;
+/// How often a waiting batch job re-checks its cancel flag.
+const CANCEL_POLL: Duration = Duration::from_millis(200);
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum JobPriority {
+    /// Someone is waiting to hear it: read-aloud and voice previews.
+    Interactive,
+    /// Background work such as audiobook chapters; yields to interactive jobs.
+    Batch,
+}
+
+impl JobPriority {
+    pub fn parse(value: Option<&str>) -> Result<Self, String> {
+        match value.unwrap_or("interactive") {
+            "interactive" => Ok(JobPriority::Interactive),
+            "batch" => Ok(JobPriority::Batch),
+            other => Err(format!("Unknown job priority '{}'", other)),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobInfo {
+    pub id: String,
+    /// What submitted the job, e.g. `synthesize` or `audiobook`.
+    pub kind: String,
+    pub priority: JobPriority,
+    /// `queued`, `running`, `complete`, `cancelled` or `error`.
+    pub status: String,
+    pub done_sentences: usize,
+    pub total_sentences: usize,
+    pub created_at: u64,
+    pub started_at: Option<u64>,
+    pub finished_at: Option<u64>,
+    pub error: Option<String>,
+}
+
+struct JobRecord {
+    info: JobInfo,
+    cancel: Arc<AtomicBool>,
+}
+
+/// Synthesis jobs, in submission order. The queue does not run jobs itself:
+/// each job takes the engine one sentence at a time, and batch jobs wait
+/// between sentences while any interactive job is outstanding.
+pub struct JobQueue {
+    engine: EngineSlot,
+    jobs: Mutex<VecDeque<JobRecord>>,
+    changed: Notify,
+    next_id: AtomicU64,
+}
+
+impl JobQueue {
+    pub fn new(engine: EngineSlot) -> Self {
+        Self {
+            engine,
+            jobs: Mutex::new(VecDeque::new()),
+            changed: Notify::new(),
+            next_id: AtomicU64::new(0),
+        }
+    }
+
+    /// Registers a job. `id` lets the caller name the job so it can cancel
+    /// it before `synthesize` returns.
+    pub fn submit(self: &Arc<Self>, id: Option<String>, kind: &str, priority: JobPriority, cancel: Arc<AtomicBool>) -> JobHandle {
+        let id = id.unwrap_or_else(|| format!("job-{}", self.next_id.fetch_add(1, Ordering::SeqCst) + 1));
+        let info = JobInfo {
+            id: id.clone(),
+            kind: kind.to_string(),
+            priority,
+            status: "queued".to_string(),
+            done_sentences: 0,
+            total_sentences: 0,
+            created_at: now_millis(),
+            started_at: None,
+            finished_at: None,
+            error: None,
+        };
+        self.jobs.lock().unwrap().push_back(JobRecord { info, cancel: cancel.clone() });
+        JobHandle {
+            queue: self.clone(),
+            id,
+            priority,
+            cancel,
+        }
+    }
+
+    pub fn list(&self) -> Vec<JobInfo> {
+        self.jobs.lock().unwrap().iter().map(|record| record.info.clone()).collect()
+    }
+
+    /// Cancels the job with `id`; returns whether it was still active.
+    pub fn cancel(&self, id: &str) -> bool {
+        self.cancel_matching(|info| info.id == id) > 0
+    }
+
+    /// Cancels every active job of `kind`, returning how many there were.
+    pub fn cancel_kind(&self, kind: &str) -> usize {
+        self.cancel_matching(|info| info.kind == kind)
+    }
+
+    fn cancel_matching(&self, matches: impl Fn(&JobInfo) -> bool) -> usize {
+        let jobs = self.jobs.lock().unwrap();
+        let mut cancelled = 0;
+        for record in jobs.iter().filter(|r| r.info.finished_at.is_none() && matches(&r.info)) {
+            record.cancel.store(true, Ordering::SeqCst);
+            cancelled += 1;
+        }
+        drop(jobs);
+        self.changed.notify_waiters();
+        cancelled
+    }
+
+    fn interactive_pending(&self) -> bool {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .any(|r| r.info.priority == JobPriority::Interactive && r.info.finished_at.is_none())
+    }
+
+    fn update(&self, id: &str, f: impl FnOnce(&mut JobInfo)) {
+        if let Some(record) = self.jobs.lock().unwrap().iter_mut().find(|r| r.info.id == id) {
+            f(&mut record.info);
+        }
+    }
+
+    fn finish(&self, id: &str, status: &str, error: Option<String>) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(record) = jobs.iter_mut().find(|r| r.info.id == id && r.info.finished_at.is_none()) {
+            record.info.status = status.to_string();
+            record.info.error = error;
+            record.info.finished_at = Some(now_millis());
+        }
+        let finished = jobs.iter().filter(|r| r.info.finished_at.is_some()).count();
+        for _ in FINISHED_JOBS_KEPT..finished {
+            if let Some(index) = jobs.iter().position(|r| r.info.finished_at.is_some()) {
+                jobs.remove(index);
+            }
+        }
+        drop(jobs);
+        self.changed.notify_waiters();
+    }
+}
+
+/// A submitted job. Dropping the handle without calling `finish` records
+/// the job as cancelled or failed, so an early return never leaves it running.
+pub struct JobHandle {
+    queue: Arc<JobQueue>,
+    pub id: String,
+    pub priority: JobPriority,
+    cancel: Arc<AtomicBool>,
+}
+
+impl JobHandle {
+    pub fn is_cancelled(&self) -> bool {
+        self.cancel.load(Ordering::SeqCst)
+    }
+
+    pub fn set_total(&self, total_sentences: usize) {
+        self.queue.update(&self.id, |info| info.total_sentences = total_sentences);
+    }
+
+    pub fn advance(&self) {
+        self.queue.update(&self.id, |info| info.done_sentences += 1);
+    }
+
+    /// Waits until this job may use the engine for its next sentence and
+    /// returns the locked slot. Batch jobs wait for outstanding interactive
+    /// jobs to finish.
+    pub async fn turn(&self) -> Result<MutexGuard<'_, Option<LoadedEngine>>, String> {
+        if self.priority == JobPriority::Batch {
+            loop {
+                let changed = self.queue.changed.notified();
+                if self.is_cancelled() || !self.queue.interactive_pending() {
+                    break;
+                }
+                let _ = tokio::time::timeout(CANCEL_POLL, changed).await;
+            }
+        }
+        if self.is_cancelled() {
+            return Err("Synthesis cancelled.".to_string());
+        }
+        self.queue.update(&self.id, |info| {
+            if info.started_at.is_none() {
+                info.status = "running".to_string();
+                info.started_at = Some(now_millis());
+            }
+        });
+        Ok(self.queue.engine.lock().await)
+    }
+
+    pub fn finish<T>(&self, result: &Result<T, String>) {
+        match result {
+            Ok(_) => self.queue.finish(&self.id, "complete", None),
+            Err(_) if self.is_cancelled() => self.queue.finish(&self.id, "cancelled", None),
+            Err(e) => self.queue.finish(&self.id, "error", Some(e.clone())),
+        }
+    }
+}
+
+impl Drop for JobHandle {
+    fn drop(&mut self) {
+        let status = if self.is_cancelled() { "cancelled" } else { "error" };
+        self.queue.finish(&self.id, status, None);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn flag() -> Arc<AtomicBool> {
+        Arc::new(AtomicBool::new(false))
+    }
+
+    fn queue() -> Arc<JobQueue> {
+        Arc::new(JobQueue::new(Arc::new(tokio::sync::Mutex::new(None))))
+    }
+
+    #[tokio::test]
+    async fn test_batch_waits_for_interactive() {
+        let queue = queue();
+        let batch = queue.submit(None, "audiobook", JobPriority::Batch, flag());
+        let preview = queue.submit(Some("preview".into()), "synthesize", JobPriority::Interactive, flag());
+
+        drop(preview.turn().await.unwrap());
+        let waiting = tokio::spawn(async move {
+            drop(batch.turn().await.unwrap());
+            batch
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(!waiting.is_finished());
+
+        preview.finish(&Ok::<(), String>(()));
+        let batch = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        let jobs = queue.list();
+        assert_eq!(jobs[0].status, "running");
+        assert_eq!(jobs[1].id, "preview");
+        assert_eq!(jobs[1].status, "complete");
+        drop(batch);
+        assert_eq!(queue.list()[0].status, "error");
+    }
+
+    #[tokio::test]
+    async fn test_cancel_releases_waiting_job() {
+        let queue = queue();
+        let _preview = queue.submit(None, "synthesize", JobPriority::Interactive, flag());
+        let batch = queue.submit(None, "audiobook", JobPriority::Batch, flag());
+        let id = batch.id.clone();
+
+        let waiting = tokio::spawn(async move { batch.turn().await.is_ok() });
+        assert!(queue.cancel(&id));
+        let acquired = tokio::time::timeout(Duration::from_secs(1), waiting).await.unwrap().unwrap();
+        assert!(!acquired);
+        assert_eq!(queue.list()[1].status, "cancelled");
+        assert!(!queue.cancel(&id));
+    }
+}
@@ -0,0 +1,131 @@
+use serde_json::Value;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+
+use super::engine::{EngineKind, SpeechEngine};
+use super::model::now_millis;
+
+/// Seconds without synthesis before the engine is dropped; `0` keeps it loaded.
+pub const IDLE_UNLOAD_SETTING: &str = "plugins.core.tts.idleUnloadSeconds";
+pub const DEFAULT_IDLE_UNLOAD_SECS: u64 = 300;
+const IDLE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// A loaded engine and what it cost to load.
+pub struct LoadedEngine {
+    pub engine: Box<dyn SpeechEngine>,
+    pub kind: EngineKind,
+    pub load_ms: u64,
+    /// Growth of the process's resident memory while loading. Approximate:
+    /// other threads allocate too, but model weights dominate for Kokoro.
+    pub memory_bytes: Option<u64>,
+    pub loaded_at: u64,
+    last_used: Instant,
+}
+
+pub type EngineSlot = Arc<Mutex<Option<LoadedEngine>>>;
+
+impl LoadedEngine {
+    pub async fn load(kind: EngineKind, tts_dir: &Path) -> Result<Self, String> {
+        let memory_before = process_memory_bytes();
+        let started = Instant::now();
+        let engine = kind.load(tts_dir).await?;
+        let load_ms = started.elapsed().as_millis() as u64;
+        let memory_bytes = match (memory_before, process_memory_bytes()) {
+            (Some(before), Some(after)) => Some(after.saturating_sub(before)),
+            _ => None,
+        };
+        Ok(Self {
+            engine,
+            kind,
+            load_ms,
+            memory_bytes,
+            loaded_at: now_millis(),
+            last_used: Instant::now(),
+        })
+    }
+
+    pub fn touch(&mut self) {
+        self.last_used = Instant::now();
+    }
+
+    pub fn idle_for(&self) -> Duration {
+        self.last_used.elapsed()
+    }
+}
+
+pub fn process_memory_bytes() -> Option<u64> {
+    memory_stats::memory_stats().map(|stats| stats.physical_mem as u64)
+}
+
+/// `None` when idle unloading is disabled.
+pub fn idle_timeout(settings: &HashMap<String, Value>) -> Option<Duration> {
+    let secs = settings
+        .get(IDLE_UNLOAD_SETTING)
+        .and_then(|v| v.as_u64())
+        .unwrap_or(DEFAULT_IDLE_UNLOAD_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+/// Drops the engine if it has been idle for `timeout`. A slot that is locked
+/// is in use, so it is left alone.
+pub fn unload_if_idle(slot: &Mutex<Option<LoadedEngine>>, timeout: Duration) -> bool {
+    let Ok(mut guard) = slot.try_lock() else {
+        return false;
+    };
+    if guard.as_ref().is_some_and(|loaded| loaded.idle_for() >= timeout) {
+        *guard = None;
+        return true;
+    }
+    false
+}
+
+/// Periodically unloads the engine once it has been idle for the configured
+/// time. The setting is re-read on every check; the task ends with the plugin.
+pub fn spawn_idle_watcher(slot: &EngineSlot, settings_path: PathBuf) {
+    let slot: Weak<Mutex<Option<LoadedEngine>>> = Arc::downgrade(slot);
+    tauri::async_runtime::spawn(async move {
+        loop {
+            tokio::time::sleep(IDLE_CHECK_INTERVAL).await;
+            let Some(slot) = slot.upgrade() else {
+                break;
+            };
+            let settings = crate::settings::read_settings_file(&settings_path).unwrap_or_default();
+            if let Some(timeout) = idle_timeout(&settings) {
+                unload_if_idle(&slot, timeout);
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_idle_timeout_setting() {
+        let mut settings = HashMap::new();
+        assert_eq!(idle_timeout(&settings), Some(Duration::from_secs(DEFAULT_IDLE_UNLOAD_SECS)));
+        settings.insert(IDLE_UNLOAD_SETTING.to_string(), Value::from(60));
+        assert_eq!(idle_timeout(&settings), Some(Duration::from_secs(60)));
+        settings.insert(IDLE_UNLOAD_SETTING.to_string(), Value::from(0));
+        assert_eq!(idle_timeout(&settings), None);
+    }
+
+    #[tokio::test]
+    async fn test_unload_if_idle() {
+        let dir = tempfile::tempdir().unwrap();
+        let slot = Mutex::new(Some(LoadedEngine::load(EngineKind::Formant, dir.path()).await.unwrap()));
+        assert!(!unload_if_idle(&slot, Duration::from_secs(60)));
+
+        // Busy engines are never unloaded.
+        let guard = slot.lock().await;
+        assert!(!unload_if_idle(&slot, Duration::ZERO));
+        drop(guard);
+
+        assert!(unload_if_idle(&slot, Duration::ZERO));
+        assert!(slot.lock().await.is_none());
+    }
+}
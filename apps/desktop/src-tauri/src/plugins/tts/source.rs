@@ -0,0 +1,171 @@
+use reqwest::Url;
+use serde_json::Value;
+use std::collections::HashMap;
+use std::time::Duration;
+
+use super::model::{ModelManifest, MODEL_FILENAME, VOICES_FILENAME};
+
+/// Directory URL serving `kokoro-v1.0.onnx` and `voices.bin`, e.g. an internal mirror.
+pub const BASE_URL_SETTING: &str = "plugins.core.tts.modelBaseUrl";
+/// URL of a `ModelManifest` JSON; takes precedence over the base URL.
+pub const MANIFEST_URL_SETTING: &str = "plugins.core.tts.modelManifestUrl";
+/// SHA-256 of the files served from the base URL; required with it.
+pub const MODEL_SHA256_SETTING: &str = "plugins.core.tts.modelSha256";
+pub const VOICES_SHA256_SETTING: &str = "plugins.core.tts.voicesSha256";
+
+const DEFAULT_BASE_URL: &str = "https://github.com/mzdk100/kokoro/releases/download/V1.0/";
+const DEFAULT_VERSION: &str = "1.0";
+// SHA-256 digests of the V1.0 release assets. None are recorded yet, so
+// installing from the public release fails until they are filled in from the
+// published files; a download that doesn't match a pin fails as well.
+const MODEL_SHA256: Option<&str> = None;
+const VOICES_SHA256: Option<&str> = None;
+
+pub struct SourceFile {
+    pub name: &'static str,
+    pub url: String,
+    pub sha256: String,
+}
+
+/// Where the installer fetches the model from, resolved from settings.
+pub struct ModelSource {
+    pub origin: String,
+    pub version: Option<String>,
+    pub files: Vec<SourceFile>,
+}
+
+impl ModelSource {
+    pub fn file(&self, name: &str) -> Result<&SourceFile, String> {
+        self.files
+            .iter()
+            .find(|f| f.name == name)
+            .ok_or_else(|| format!("Model source does not provide {}", name))
+    }
+}
+
+fn setting_str<'a>(settings: &'a HashMap<String, Value>, key: &str) -> Option<&'a str> {
+    settings
+        .get(key)
+        .and_then(|v| v.as_str())
+        .map(str::trim)
+        .filter(|v| !v.is_empty())
+}
+
+fn parse_directory_url(raw: &str) -> Result<Url, String> {
+    // Without a trailing slash, `Url::join` would replace the last path segment.
+    let raw = if raw.ends_with('/') { raw.to_string() } else { format!("{}/", raw) };
+    Url::parse(&raw).map_err(|e| format!("Invalid model base URL '{}': {}", raw, e))
+}
+
+/// The model files under `base`. Every file needs a digest to be checked
+/// against; `hint` says where one can come from.
+fn from_base_url(
+    base: &Url,
+    origin: String,
+    version: Option<String>,
+    digests: [Option<&str>; 2],
+    hint: &str,
+) -> Result<ModelSource, String> {
+    let mut files = Vec::new();
+    for (name, sha256) in [MODEL_FILENAME, VOICES_FILENAME].into_iter().zip(digests) {
+        let url = base.join(name).map_err(|e| e.to_string())?;
+        let sha256 = sha256.ok_or_else(|| format!("No SHA-256 digest for {}; {}", name, hint))?;
+        if sha256.len() != 64 || !sha256.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(format!("Invalid SHA-256 for {}", name));
+        }
+        files.push(SourceFile { name, url: url.to_string(), sha256: sha256.to_string() });
+    }
+    Ok(ModelSource { origin, version, files })
+}
+
+fn from_manifest(manifest_url: &Url, manifest: &ModelManifest) -> Result<ModelSource, String> {
+    manifest.validate()?;
+    let mut files = Vec::new();
+    for name in [MODEL_FILENAME, VOICES_FILENAME] {
+        let entry = manifest.file(name).ok_or_else(|| format!("Model manifest does not list {}", name))?;
+        let relative = entry.url.as_deref().unwrap_or(name);
+        let url = manifest_url.join(relative).map_err(|e| e.to_string())?;
+        files.push(SourceFile {
+            name,
+            url: url.to_string(),
+            sha256: entry.sha256.clone(),
+        });
+    }
+    Ok(ModelSource {
+        origin: manifest_url.to_string(),
+        version: Some(manifest.version.clone()),
+        files,
+    })
+}
+
+/// Resolves the configured source: a manifest URL, then a base URL, then the
+/// public release. A base URL may serve a different build than the public
+/// release, so its digests come from settings rather than the pins.
+pub async fn resolve(settings: &HashMap<String, Value>) -> Result<ModelSource, String> {
+    if let Some(raw) = setting_str(settings, MANIFEST_URL_SETTING) {
+        let manifest_url = Url::parse(raw).map_err(|e| format!("Invalid model manifest URL '{}': {}", raw, e))?;
+        let client = reqwest::Client::builder()
+            .user_agent("Liminal Notes TTS Installer")
+            .timeout(Duration::from_secs(30))
+            .build()
+            .map_err(|e| e.to_string())?;
+        let response = client.get(manifest_url.clone()).send().await.map_err(|e| e.to_string())?;
+        if !response.status().is_success() {
+            return Err(format!("Fetching model manifest failed ({})", response.status()));
+        }
+        let manifest: ModelManifest = response
+            .json()
+            .await
+            .map_err(|e| format!("Model manifest is not valid JSON: {}", e))?;
+        return from_manifest(&manifest_url, &manifest);
+    }
+
+    if let Some(raw) = setting_str(settings, BASE_URL_SETTING) {
+        let base = parse_directory_url(raw)?;
+        let digests = [setting_str(settings, MODEL_SHA256_SETTING), setting_str(settings, VOICES_SHA256_SETTING)];
+        let hint = format!(
+            "set {} and {}, or install from a model manifest ({})",
+            MODEL_SHA256_SETTING, VOICES_SHA256_SETTING, MANIFEST_URL_SETTING
+        );
+        return from_base_url(&base, base.to_string(), None, digests, &hint);
+    }
+
+    let base = parse_directory_url(DEFAULT_BASE_URL)?;
+    let hint = format!("none is pinned in this build; install from a model manifest ({}) or an offline pack", MANIFEST_URL_SETTING);
+    from_base_url(&base, base.to_string(), Some(DEFAULT_VERSION.to_string()), [MODEL_SHA256, VOICES_SHA256], &hint)
+}
+
+/// Describes the configured source without touching the network.
+pub fn describe(settings: &HashMap<String, Value>) -> Value {
+    serde_json::json!({
+        "manifest_url": setting_str(settings, MANIFEST_URL_SETTING),
+        "base_url": setting_str(settings, BASE_URL_SETTING),
+        "model_sha256": setting_str(settings, MODEL_SHA256_SETTING),
+        "voices_sha256": setting_str(settings, VOICES_SHA256_SETTING),
+        "default_base_url": DEFAULT_BASE_URL,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_public_release_is_always_pinned() {
+        let base = parse_directory_url(DEFAULT_BASE_URL).unwrap();
+        let is_digest = |d: &str| d.len() == 64 && d.bytes().all(|b| b.is_ascii_hexdigit());
+        assert!([MODEL_SHA256, VOICES_SHA256].into_iter().flatten().all(is_digest));
+        match from_base_url(&base, base.to_string(), None, [MODEL_SHA256, VOICES_SHA256], "") {
+            Ok(source) => assert!(source.files.iter().all(|f| is_digest(&f.sha256))),
+            Err(e) => assert!(e.contains("No SHA-256 digest")),
+        }
+
+        let mirror = parse_directory_url("https://models.example.com/kokoro").unwrap();
+        let digest = "ab".repeat(32);
+        assert!(from_base_url(&mirror, mirror.to_string(), None, [Some(&digest), None], "").is_err());
+        assert!(from_base_url(&mirror, mirror.to_string(), None, [Some(&digest), Some("abc")], "").is_err());
+        let custom = from_base_url(&mirror, mirror.to_string(), None, [Some(&digest), Some(&digest)], "").unwrap();
+        assert_eq!(custom.files[1].url, "https://models.example.com/kokoro/voices.bin");
+        assert!(custom.files.iter().all(|f| f.sha256 == digest));
+    }
+}
@@ -0,0 +1,160 @@
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use tauri::http::{header, Response, StatusCode};
+
+/// URI scheme serving cached audio to `<audio>` elements, so playback streams
+/// from disk instead of arriving base64-encoded over IPC.
+pub const SCHEME: &str = "tts-audio";
+
+/// Most bytes sent for an open-ended range such as `bytes=0-`, or for a
+/// request without a range; the media element asks for the rest as it plays.
+const MAX_CHUNK_BYTES: u64 = 1024 * 1024;
+
+/// `Range: bytes=...` as an inclusive byte range within a file of `len`
+/// bytes. Only the first range of a multi-range request is honoured. Without
+/// a header, files larger than `MAX_CHUNK_BYTES` are answered in part too,
+/// so no request reads a whole file into memory.
+#[derive(Debug, PartialEq)]
+pub enum ByteRange {
+    Full,
+    Partial(u64, u64),
+    Unsatisfiable,
+}
+
+pub fn parse_range(header: Option<&str>, len: u64) -> ByteRange {
+    let Some(spec) = header.and_then(|h| h.trim().strip_prefix("bytes=")) else {
+        if len > MAX_CHUNK_BYTES {
+            return ByteRange::Partial(0, MAX_CHUNK_BYTES - 1);
+        }
+        return ByteRange::Full;
+    };
+    let first = spec.split(',').next().unwrap_or("").trim();
+    let Some((start, end)) = first.split_once('-') else {
+        return ByteRange::Unsatisfiable;
+    };
+    let (start, end) = match (start.trim().parse::<u64>().ok(), end.trim().parse::<u64>().ok()) {
+        // `bytes=-500`: the last 500 bytes.
+        (None, Some(suffix)) if start.trim().is_empty() && suffix > 0 => (len.saturating_sub(suffix), len.saturating_sub(1)),
+        (Some(start), None) if end.trim().is_empty() => (start, len.saturating_sub(1).min(start.saturating_add(MAX_CHUNK_BYTES - 1))),
+        (Some(start), Some(end)) if start <= end => (start, end.min(len.saturating_sub(1))),
+        _ => return ByteRange::Unsatisfiable,
+    };
+    if len == 0 || start >= len {
+        return ByteRange::Unsatisfiable;
+    }
+    ByteRange::Partial(start, end)
+}
+
+/// Decodes the request path and confines it to the cache directory, the same
+/// way `read_audio` does.
+fn resolve(cache_dir: &Path, uri_path: &str) -> Option<PathBuf> {
+    let decoded = percent_encoding::percent_decode_str(uri_path.trim_start_matches('/'))
+        .decode_utf8()
+        .ok()?;
+    let cache_dir = cache_dir.canonicalize().ok()?;
+    let target = Path::new(decoded.as_ref()).canonicalize().ok()?;
+    (target.starts_with(&cache_dir) && target.is_file()).then_some(target)
+}
+
+fn content_type(path: &Path) -> &'static str {
+    match path.extension().and_then(|e| e.to_str()) {
+        Some("wav") => "audio/wav",
+        Some("flac") => "audio/flac",
+        _ => "application/octet-stream",
+    }
+}
+
+pub fn empty(code: StatusCode) -> Response<Vec<u8>> {
+    Response::builder()
+        .status(code)
+        .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+        .body(Vec::new())
+        .expect("valid response")
+}
+
+/// Answers one request for `uri_path` (a percent-encoded absolute path, as
+/// produced by `convertFileSrc`) with the requested byte range.
+pub fn serve(cache_dir: &Path, uri_path: &str, range: Option<&str>) -> Response<Vec<u8>> {
+    let Some(path) = resolve(cache_dir, uri_path) else {
+        return empty(StatusCode::NOT_FOUND);
+    };
+    let read = || -> std::io::Result<Response<Vec<u8>>> {
+        let mut file = std::fs::File::open(&path)?;
+        let len = file.metadata()?.len();
+        let builder = Response::builder()
+            .header(header::ACCESS_CONTROL_ALLOW_ORIGIN, "*")
+            .header(header::ACCEPT_RANGES, "bytes")
+            .header(header::CONTENT_TYPE, content_type(&path));
+
+        let response = match parse_range(range, len) {
+            ByteRange::Full => {
+                let mut body = Vec::with_capacity(len as usize);
+                file.read_to_end(&mut body)?;
+                builder.status(StatusCode::OK).header(header::CONTENT_LENGTH, body.len()).body(body)
+            }
+            ByteRange::Partial(start, end) => {
+                let mut body = Vec::with_capacity((end - start + 1) as usize);
+                file.seek(SeekFrom::Start(start))?;
+                file.take(end - start + 1).read_to_end(&mut body)?;
+                builder
+                    .status(StatusCode::PARTIAL_CONTENT)
+                    .header(header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, len))
+                    .header(header::CONTENT_LENGTH, body.len())
+                    .body(body)
+            }
+            ByteRange::Unsatisfiable => builder
+                .status(StatusCode::RANGE_NOT_SATISFIABLE)
+                .header(header::CONTENT_RANGE, format!("bytes */{}", len))
+                .body(Vec::new()),
+        };
+        Ok(response.expect("valid response"))
+    };
+    read().unwrap_or_else(|_| empty(StatusCode::INTERNAL_SERVER_ERROR))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_range() {
+        assert_eq!(parse_range(None, 100), ByteRange::Full);
+        assert_eq!(parse_range(None, 10 * MAX_CHUNK_BYTES), ByteRange::Partial(0, MAX_CHUNK_BYTES - 1));
+        assert_eq!(parse_range(Some("bytes=10-19"), 100), ByteRange::Partial(10, 19));
+        assert_eq!(parse_range(Some("bytes=90-200"), 100), ByteRange::Partial(90, 99));
+        assert_eq!(parse_range(Some("bytes=-30"), 100), ByteRange::Partial(70, 99));
+        assert_eq!(parse_range(Some("bytes=0-"), 100), ByteRange::Partial(0, 99));
+        assert_eq!(
+            parse_range(Some("bytes=0-"), 10 * MAX_CHUNK_BYTES),
+            ByteRange::Partial(0, MAX_CHUNK_BYTES - 1)
+        );
+        assert_eq!(parse_range(Some("bytes=100-"), 100), ByteRange::Unsatisfiable);
+        assert_eq!(parse_range(Some("bytes=5-2"), 100), ByteRange::Unsatisfiable);
+    }
+
+    #[test]
+    fn test_serve_is_confined_to_cache() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache_dir = dir.path().join("cache");
+        std::fs::create_dir_all(&cache_dir).unwrap();
+        let audio = cache_dir.join("a b.wav");
+        std::fs::write(&audio, b"0123456789").unwrap();
+        std::fs::write(dir.path().join("secret.txt"), b"secret").unwrap();
+        let uri = |path: &Path| {
+            format!("/{}", percent_encoding::utf8_percent_encode(path.to_str().unwrap(), percent_encoding::NON_ALPHANUMERIC))
+        };
+
+        let response = serve(&cache_dir, &uri(&audio), Some("bytes=2-5"));
+        assert_eq!(response.status(), StatusCode::PARTIAL_CONTENT);
+        assert_eq!(response.headers()[header::CONTENT_RANGE], "bytes 2-5/10");
+        assert_eq!(response.body().as_slice(), b"2345");
+        assert_eq!(serve(&cache_dir, &uri(&audio), None).body().len(), 10);
+
+        let escape = uri(&cache_dir.join("..").join("secret.txt"));
+        assert_eq!(serve(&cache_dir, &escape, None).status(), StatusCode::NOT_FOUND);
+        assert_eq!(
+            serve(&cache_dir, &uri(&audio), Some("bytes=20-")).status(),
+            StatusCode::RANGE_NOT_SATISFIABLE
+        );
+    }
+}
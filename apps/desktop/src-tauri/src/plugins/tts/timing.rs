@@ -0,0 +1,151 @@
+use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
+
+/// Samples quieter than this fraction of the sentence's peak count as the
+/// silence engines leave around speech.
+const SILENCE_THRESHOLD: f32 = 0.02;
+/// Weight of a clause break (`,` `;` `:` and dashes) relative to one letter.
+const CLAUSE_PAUSE_WEIGHT: f64 = 3.0;
+
+/// Where a word sits in the request text (UTF-16 code units, as the editor
+/// counts them) and in the audio.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WordTiming {
+    pub start_char: usize,
+    pub end_char: usize,
+    pub start_ms: f64,
+    pub end_ms: f64,
+}
+
+pub fn utf16_len(text: &str) -> usize {
+    text.encode_utf16().count()
+}
+
+/// Byte offset of UTF-16 offset `utf16` in `text`, clamped to its length.
+/// An offset inside a surrogate pair moves past the character.
+pub fn byte_offset(text: &str, utf16: usize) -> usize {
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= utf16 {
+            return index;
+        }
+        units += c.len_utf16();
+    }
+    text.len()
+}
+
+/// First and one-past-last sample of audible speech.
+pub fn voiced_span(samples: &[f32]) -> (usize, usize) {
+    let peak = samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()));
+    if peak == 0.0 {
+        return (0, samples.len());
+    }
+    let threshold = peak * SILENCE_THRESHOLD;
+    let start = samples.iter().position(|s| s.abs() >= threshold).unwrap_or(0);
+    let end = samples.iter().rposition(|s| s.abs() >= threshold).map_or(samples.len(), |i| i + 1);
+    (start, end)
+}
+
+/// Rough relative speaking time: digits are read as number words, other
+/// characters roughly one unit each.
+fn word_weight(word: &str) -> f64 {
+    let weight: f64 = word
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .map(|c| if c.is_ascii_digit() { 3.0 } else { 1.0 })
+        .sum();
+    weight.max(2.0)
+}
+
+/// Spreads `[start_ms, end_ms)` over the words of `text[start..end]` in
+/// proportion to their length, leaving gaps at clause breaks. Neither engine
+/// reports durations, so this is an estimate; it is exact at sentence edges.
+pub fn estimate_words(text: &str, start: usize, end: usize, start_ms: f64, end_ms: f64) -> Vec<WordTiming> {
+    enum Token {
+        Word(usize, usize, f64),
+        Gap(f64),
+    }
+
+    let sentence = &text[start..end];
+    let mut tokens = Vec::new();
+    for (offset, token) in sentence.split_word_bound_indices() {
+        if token.chars().any(|c| c.is_alphanumeric()) {
+            tokens.push(Token::Word(start + offset, start + offset + token.len(), word_weight(token)));
+        } else if token.chars().any(|c| matches!(c, ',' | ';' | ':' | '—' | '–')) {
+            tokens.push(Token::Gap(CLAUSE_PAUSE_WEIGHT));
+        }
+    }
+    // Trailing punctuation is already covered by the pause after the sentence.
+    while matches!(tokens.last(), Some(Token::Gap(_))) {
+        tokens.pop();
+    }
+
+    let total: f64 = tokens
+        .iter()
+        .map(|t| match t {
+            Token::Word(_, _, weight) | Token::Gap(weight) => *weight,
+        })
+        .sum();
+    if total == 0.0 {
+        return Vec::new();
+    }
+    let ms_per_unit = (end_ms - start_ms).max(0.0) / total;
+
+    let mut words = Vec::new();
+    let mut cursor_ms = start_ms;
+    let mut cursor_byte = 0;
+    let mut cursor_utf16 = 0;
+    for token in tokens {
+        match token {
+            Token::Word(word_start, word_end, weight) => {
+                cursor_utf16 += utf16_len(&text[cursor_byte..word_start]);
+                let start_char = cursor_utf16;
+                cursor_utf16 += utf16_len(&text[word_start..word_end]);
+                cursor_byte = word_end;
+                words.push(WordTiming {
+                    start_char,
+                    end_char: cursor_utf16,
+                    start_ms: cursor_ms,
+                    end_ms: cursor_ms + weight * ms_per_unit,
+                });
+                cursor_ms += weight * ms_per_unit;
+            }
+            Token::Gap(weight) => cursor_ms += weight * ms_per_unit,
+        }
+    }
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_word_offsets_are_utf16() {
+        let text = "🎉 Party time, friends.";
+        let start = text.find('P').unwrap();
+        let words = estimate_words(text, start, text.len(), 1000.0, 2300.0);
+
+        let spans: Vec<(usize, usize)> = words.iter().map(|w| (w.start_char, w.end_char)).collect();
+        // The emoji is two UTF-16 code units.
+        assert_eq!(spans, vec![(3, 8), (9, 13), (15, 22)]);
+        assert_eq!(byte_offset(text, 3), start);
+        assert_eq!(byte_offset(text, 1), start - 1);
+        assert_eq!(byte_offset(text, 100), text.len());
+        assert_eq!(words[0].start_ms, 1000.0);
+        assert!((words[2].end_ms - 2300.0).abs() < 1e-6);
+        // The comma leaves a gap between "time" and "friends".
+        assert!(words[2].start_ms > words[1].end_ms);
+    }
+
+    #[test]
+    fn test_voiced_span() {
+        let mut samples = vec![0.0; 100];
+        samples[20] = 0.5;
+        samples[70] = -0.4;
+        samples[90] = 0.001;
+        assert_eq!(voiced_span(&samples), (20, 71));
+        assert_eq!(voiced_span(&[0.0; 4]), (0, 4));
+    }
+}
@@ -0,0 +1,315 @@
+use percent_encoding::{percent_decode_str, utf8_percent_encode, AsciiSet, CONTROLS};
+use reqwest::header::{HeaderMap, CONTENT_TYPE, ETAG, IF_MATCH, IF_NONE_MATCH};
+use reqwest::{Client, Method, RequestBuilder, StatusCode, Url};
+use std::time::Duration;
+
+use crate::sync::crypto::{KeyParams, VaultKey, KEYS_FILE};
+
+/// Characters escaped in a path segment.
+const SEGMENT: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'"')
+    .add(b'#')
+    .add(b'%')
+    .add(b'<')
+    .add(b'>')
+    .add(b'?')
+    .add(b'[')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+const PROPFIND_BODY: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/><d:resourcetype/><d:getcontentlength/></d:prop></d:propfind>"#;
+
+/// A member of a collection, by path relative to the root collection.
+/// Folder paths end in `/`; the root is `""`.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DavEntry {
+    pub path: String,
+    pub etag: Option<String>,
+    pub is_dir: bool,
+}
+
+/// Why a conditional write was refused.
+#[derive(Debug)]
+pub enum DavError {
+    /// The resource changed since the given ETag (412).
+    Changed,
+    Other(String),
+}
+
+impl From<reqwest::Error> for DavError {
+    fn from(e: reqwest::Error) -> Self {
+        DavError::Other(e.to_string())
+    }
+}
+
+impl From<DavError> for String {
+    fn from(e: DavError) -> Self {
+        match e {
+            DavError::Changed => "The file changed on the server during sync".to_string(),
+            DavError::Other(message) => message,
+        }
+    }
+}
+
+pub struct DavClient {
+    client: Client,
+    base: Url,
+    username: Option<String>,
+    password: Option<String>,
+    /// Set on an encrypted collection: paths and bodies are sealed with it.
+    key: Option<VaultKey>,
+}
+
+impl DavClient {
+    pub fn new(url: &str, username: Option<String>, password: Option<String>) -> Result<Self, String> {
+        let mut base = Url::parse(url).map_err(|e| format!("Invalid WebDAV URL: {}", e))?;
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let client = Client::builder()
+            .connect_timeout(Duration::from_secs(15))
+            .timeout(Duration::from_secs(120))
+            .build()
+            .map_err(|e| e.to_string())?;
+        Ok(Self { client, base, username, password, key: None })
+    }
+
+    /// Reads the collection's key file. With a passphrase, an empty
+    /// collection becomes encrypted; an encrypted one needs the passphrase it
+    /// was created with.
+    pub async fn unlock(&mut self, passphrase: Option<&str>) -> Result<(), String> {
+        let keys = self.fetch(self.raw_url(KEYS_FILE)?).await?;
+        self.key = match (keys, passphrase) {
+            (Some((bytes, _)), Some(passphrase)) => {
+                let params: KeyParams = serde_json::from_slice(&bytes).map_err(|e| format!("Invalid {}: {}", KEYS_FILE, e))?;
+                Some(VaultKey::unlock(passphrase, &params)?)
+            }
+            (Some(_), None) => return Err("The WebDAV folder is encrypted; set the sync passphrase".to_string()),
+            (None, Some(passphrase)) => {
+                if self.list("").await?.len() > 1 {
+                    return Err("The WebDAV folder holds unencrypted files; use an empty folder for encrypted sync".to_string());
+                }
+                let (key, params) = VaultKey::create(passphrase)?;
+                let content = serde_json::to_vec_pretty(&params).map_err(|e| e.to_string())?;
+                match self.upload(self.raw_url(KEYS_FILE)?, content, None).await {
+                    Ok(_) => Some(key),
+                    Err(DavError::Changed) => return Err("Another device is setting up encryption; sync again".to_string()),
+                    Err(e) => return Err(e.into()),
+                }
+            }
+            (None, None) => None,
+        };
+        Ok(())
+    }
+
+    /// URL of a path as stored on the server.
+    fn raw_url(&self, path: &str) -> Result<Url, String> {
+        let encoded: Vec<String> = path.split('/').map(|s| utf8_percent_encode(s, SEGMENT).to_string()).collect();
+        self.base.join(&encoded.join("/")).map_err(|e| e.to_string())
+    }
+
+    fn url(&self, path: &str) -> Result<Url, String> {
+        match &self.key {
+            Some(key) => self.raw_url(&key.encrypt_path(path)?),
+            None => self.raw_url(path),
+        }
+    }
+
+    fn request(&self, method: Method, url: Url) -> RequestBuilder {
+        let request = self.client.request(method, url);
+        match &self.username {
+            Some(username) => request.basic_auth(username, self.password.as_ref()),
+            None => request,
+        }
+    }
+
+    /// The collection at `path` (first) and its direct members. On an
+    /// encrypted collection, members whose names do not decrypt are left out.
+    pub async fn list(&self, path: &str) -> Result<Vec<DavEntry>, String> {
+        let response = self
+            .request(Method::from_bytes(b"PROPFIND").map_err(|e| e.to_string())?, self.url(path)?)
+            .header("Depth", "1")
+            .header(CONTENT_TYPE, "application/xml; charset=utf-8")
+            .body(PROPFIND_BODY)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        if response.status() == StatusCode::NOT_FOUND && path.is_empty() {
+            return Err(format!("WebDAV folder {} not found", self.base));
+        }
+        if response.status() != StatusCode::MULTI_STATUS {
+            return Err(format!("PROPFIND {} failed: {}", path, response.status()));
+        }
+        let body = response.text().await.map_err(|e| e.to_string())?;
+        let mut entries = parse_multistatus(&body, self.base.path())?;
+        if let Some(key) = &self.key {
+            entries = entries
+                .into_iter()
+                .filter_map(|entry| Some(DavEntry { path: key.decrypt_path(&entry.path)?, ..entry }))
+                .collect();
+        }
+        entries.sort_by_key(|entry| entry.path != path);
+        Ok(entries)
+    }
+
+    async fn fetch(&self, url: Url) -> Result<Option<(Vec<u8>, Option<String>)>, String> {
+        let response = self.request(Method::GET, url.clone()).send().await.map_err(|e| e.to_string())?;
+        if response.status() == StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("GET {} failed: {}", url.path(), response.status()));
+        }
+        let etag = etag(response.headers());
+        let bytes = response.bytes().await.map_err(|e| e.to_string())?;
+        Ok(Some((bytes.to_vec(), etag)))
+    }
+
+    /// The file's content and ETag, or `None` once deleted.
+    pub async fn get(&self, path: &str) -> Result<Option<(Vec<u8>, Option<String>)>, String> {
+        let fetched = self.fetch(self.url(path)?).await?;
+        match (fetched, &self.key) {
+            (Some((bytes, etag)), Some(key)) => Ok(Some((key.open(&bytes, path.as_bytes())?, etag))),
+            (fetched, _) => Ok(fetched),
+        }
+    }
+
+    async fn upload(&self, url: Url, bytes: Vec<u8>, expected: Option<&str>) -> Result<Option<String>, DavError> {
+        let request = self.request(Method::PUT, url.clone());
+        let request = match expected {
+            Some(etag) => request.header(IF_MATCH, etag),
+            None => request.header(IF_NONE_MATCH, "*"),
+        };
+        let response = request.body(bytes).send().await?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Err(DavError::Changed),
+            status if status.is_success() => Ok(etag(response.headers())),
+            status => Err(DavError::Other(format!("PUT {} failed: {}", url.path(), status))),
+        }
+    }
+
+    /// Uploads a file if the server still has `expected` (an ETag, or no file
+    /// for `None`), and returns the new ETag.
+    pub async fn put(&self, path: &str, bytes: Vec<u8>, expected: Option<&str>) -> Result<Option<String>, DavError> {
+        let bytes = match &self.key {
+            Some(key) => key.seal(&bytes, path.as_bytes()).map_err(DavError::Other)?,
+            None => bytes,
+        };
+        match self.upload(self.url(path).map_err(DavError::Other)?, bytes, expected).await? {
+            Some(etag) => Ok(Some(etag)),
+            // Some servers only report the ETag on request.
+            None => Ok(self.list(path).await.map_err(DavError::Other)?.first().and_then(|e| e.etag.clone())),
+        }
+    }
+
+    /// Deletes a file if the server still has `expected`.
+    pub async fn delete(&self, path: &str, expected: &str) -> Result<(), DavError> {
+        let url = self.url(path).map_err(DavError::Other)?;
+        let response = self.request(Method::DELETE, url).header(IF_MATCH, expected).send().await?;
+        match response.status() {
+            StatusCode::PRECONDITION_FAILED => Err(DavError::Changed),
+            StatusCode::NOT_FOUND => Ok(()),
+            status if status.is_success() => Ok(()),
+            status => Err(DavError::Other(format!("DELETE {} failed: {}", path, status))),
+        }
+    }
+
+    /// Creates a collection; one that already exists is fine.
+    pub async fn mkcol(&self, path: &str) -> Result<(), String> {
+        let response = self
+            .request(Method::from_bytes(b"MKCOL").map_err(|e| e.to_string())?, self.url(path)?)
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        match response.status() {
+            StatusCode::METHOD_NOT_ALLOWED => Ok(()),
+            status if status.is_success() => Ok(()),
+            status => Err(format!("MKCOL {} failed: {}", path, status)),
+        }
+    }
+}
+
+fn etag(headers: &HeaderMap) -> Option<String> {
+    headers.get(ETAG).and_then(|v| v.to_str().ok()).map(String::from)
+}
+
+/// Entries of a PROPFIND response, with hrefs made relative to `base_path`.
+fn parse_multistatus(body: &str, base_path: &str) -> Result<Vec<DavEntry>, String> {
+    let doc = roxmltree::Document::parse(body).map_err(|e| format!("Invalid PROPFIND response: {}", e))?;
+    let base_path = percent_decode_str(base_path).decode_utf8_lossy().to_string();
+    let dav = |node: &roxmltree::Node, name: &str| node.is_element() && node.has_tag_name(("DAV:", name));
+    let mut entries = Vec::new();
+    for response in doc.descendants().filter(|n| dav(n, "response")) {
+        let Some(href) = response.children().find(|n| dav(n, "href")).and_then(|n| n.text()) else {
+            continue;
+        };
+        // Hrefs may be absolute URLs or absolute paths.
+        let href_path = Url::parse(href).map(|url| url.path().to_string()).unwrap_or_else(|_| href.to_string());
+        let href_path = percent_decode_str(&href_path).decode_utf8_lossy().to_string();
+        let Some(relative) = href_path.strip_prefix(&base_path) else {
+            continue;
+        };
+        // Properties the server could not return come in a non-200 propstat.
+        let props: Vec<_> = response
+            .children()
+            .filter(|n| dav(n, "propstat"))
+            .filter(|propstat| {
+                propstat
+                    .children()
+                    .find(|n| dav(n, "status"))
+                    .and_then(|n| n.text())
+                    .is_none_or(|status| status.contains(" 200 "))
+            })
+            .flat_map(|propstat| propstat.descendants())
+            .collect();
+        let is_dir = props.iter().any(|n| dav(n, "collection"));
+        let etag = props.iter().find(|n| dav(n, "getetag")).and_then(|n| n.text()).map(|t| t.trim().to_string());
+        let mut path = relative.trim_matches('/').to_string();
+        if is_dir && !path.is_empty() {
+            path.push('/');
+        }
+        entries.push(DavEntry { path, etag: etag.filter(|e| !e.is_empty()), is_dir });
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_multistatus() {
+        let body = r#"<?xml version="1.0"?>
+<d:multistatus xmlns:d="DAV:" xmlns:oc="http://owncloud.org/ns">
+  <d:response>
+    <d:href>/remote.php/dav/files/me/My%20Notes/</d:href>
+    <d:propstat><d:prop><d:getetag>"root1"</d:getetag><d:resourcetype><d:collection/></d:resourcetype></d:prop>
+      <d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+  </d:response>
+  <d:response>
+    <d:href>https://cloud.example.com/remote.php/dav/files/me/My%20Notes/Daily/</d:href>
+    <d:propstat><d:prop><d:resourcetype><d:collection/></d:resourcetype></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>
+    <d:propstat><d:prop><d:getetag/></d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>
+  </d:response>
+  <D:response xmlns:D="DAV:">
+    <D:href>/remote.php/dav/files/me/My%20Notes/caf%C3%A9.md</D:href>
+    <D:propstat><D:prop><D:getetag>W/"abc"</D:getetag><D:resourcetype/></D:prop><D:status>HTTP/1.1 200 OK</D:status></D:propstat>
+  </D:response>
+</d:multistatus>"#;
+        let entries = parse_multistatus(body, "/remote.php/dav/files/me/My%20Notes/").unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                DavEntry { path: "".into(), etag: Some("\"root1\"".into()), is_dir: true },
+                DavEntry { path: "Daily/".into(), etag: None, is_dir: true },
+                DavEntry { path: "café.md".into(), etag: Some("W/\"abc\"".into()), is_dir: false },
+            ]
+        );
+    }
+}
@@ -0,0 +1,535 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+
+use super::client::{DavClient, DavError};
+use crate::sync::provider::{ProviderConfig, ProviderSettings};
+use crate::sync::{self, BaseRef, ConflictRecord, ProviderState, Reconciled, SyncCore};
+
+/// Name used for the provider's state file, settings and conflict records.
+pub const PROVIDER: &str = "webdav";
+
+/// `plugins.core.sync.webdav.*` settings.
+#[derive(Clone, Debug)]
+pub struct WebDavConfig {
+    pub enabled: bool,
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    /// Set when sync encryption is on.
+    pub passphrase: Option<String>,
+    pub debounce_secs: u64,
+    pub interval_secs: u64,
+}
+
+impl WebDavConfig {
+    pub fn from_settings(settings: &HashMap<String, Value>) -> Self {
+        let read = ProviderSettings::new(settings, PROVIDER);
+        Self {
+            enabled: read.enabled(),
+            url: read.text("url"),
+            username: read.text("username"),
+            password: read.text("password"),
+            passphrase: sync::crypto::passphrase_from_settings(settings),
+            debounce_secs: read.secs("debounceSecs", 30),
+            interval_secs: read.secs("intervalSecs", 300),
+        }
+    }
+}
+
+impl ProviderConfig for WebDavConfig {
+    fn ready(&self) -> bool {
+        self.enabled && self.url.is_some()
+    }
+
+    fn debounce_secs(&self) -> u64 {
+        self.debounce_secs
+    }
+
+    fn interval_secs(&self) -> Option<u64> {
+        Some(self.interval_secs)
+    }
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct SyncReport {
+    pub pulled: Vec<String>,
+    pub pushed: Vec<String>,
+    /// Files both sides changed that merged cleanly.
+    pub merged: Vec<String>,
+    pub conflicts: Vec<ConflictRecord>,
+}
+
+/// What the server holds: file ETags, folder ETags, and every known folder.
+#[derive(Default)]
+struct RemoteTree {
+    files: BTreeMap<String, String>,
+    folders: BTreeMap<String, String>,
+    dirs: HashSet<String>,
+}
+
+impl RemoteTree {
+    /// Takes everything under `prefix` from the last sync, unchanged.
+    fn carry(&mut self, state: &ProviderState, prefix: &str) {
+        for (path, base) in state.synced.iter().filter(|(path, _)| path.starts_with(prefix)) {
+            self.files.insert(path.clone(), base.provider_rev.clone().unwrap_or_default());
+        }
+        for (dir, etag) in state.folders.iter().filter(|(dir, _)| dir.starts_with(prefix)) {
+            self.folders.insert(dir.clone(), etag.clone());
+            self.dirs.insert(dir.clone());
+        }
+    }
+}
+
+/// Lists the collection, descending only into folders whose ETag changed
+/// since the last sync. Servers without folder ETags are listed in full.
+async fn remote_tree(client: &DavClient, state: &ProviderState) -> Result<RemoteTree, String> {
+    let mut tree = RemoteTree::default();
+    let mut pending = vec![String::new()];
+    while let Some(dir) = pending.pop() {
+        let mut entries = client.list(&dir).await?.into_iter();
+        let this = entries.next().filter(|entry| entry.path == dir);
+        tree.dirs.insert(dir.clone());
+        if let Some(etag) = this.and_then(|entry| entry.etag) {
+            if dir.is_empty() && state.folders.get("") == Some(&etag) {
+                tree.carry(state, "");
+                return Ok(tree);
+            }
+            tree.folders.insert(dir.clone(), etag);
+        }
+        for entry in entries {
+            if !entry.is_dir {
+                tree.files.insert(entry.path, entry.etag.unwrap_or_default());
+            } else if entry.etag.is_some() && state.folders.get(&entry.path) == entry.etag.as_ref() {
+                tree.carry(state, &entry.path);
+            } else {
+                pending.push(entry.path);
+            }
+        }
+    }
+    Ok(tree)
+}
+
+/// One sync pass: the server and the provider state, updated together.
+struct Pass<'a> {
+    root: &'a Path,
+    core: &'a SyncCore,
+    client: &'a DavClient,
+    state: ProviderState,
+    dirs: HashSet<String>,
+}
+
+impl Pass<'_> {
+    fn read_local(&self, path: &str) -> Result<Vec<u8>, String> {
+        fs::read(self.root.join(path)).map_err(|e| e.to_string())
+    }
+
+    fn mark_synced(&mut self, path: &str, bytes: &[u8], etag: Option<String>) -> Result<(), String> {
+        let hash = self.core.save_base(path, bytes)?;
+        let base = BaseRef { provider_rev: etag, content_hash: Some(hash) };
+        self.state.synced.insert(path.to_string(), base);
+        Ok(())
+    }
+
+    async fn ensure_parents(&mut self, path: &str) -> Result<(), String> {
+        let mut dir = String::new();
+        for segment in path.split('/').rev().skip(1).collect::<Vec<_>>().into_iter().rev() {
+            dir.push_str(segment);
+            dir.push('/');
+            if self.dirs.insert(dir.clone()) {
+                self.client.mkcol(&dir).await?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Uploads `bytes` to `path` if the server still has `expected`.
+    async fn put(&mut self, path: &str, bytes: Vec<u8>, expected: Option<&str>) -> Result<(), DavError> {
+        self.ensure_parents(path).await.map_err(DavError::Other)?;
+        let etag = self.client.put(path, bytes.clone(), expected).await?;
+        self.mark_synced(path, &bytes, etag).map_err(DavError::Other)
+    }
+
+    /// Publishes this device's version of `path`, or its deletion.
+    async fn push(&mut self, path: &str, exists: bool, expected: Option<&str>) -> Result<(), DavError> {
+        if exists {
+            let bytes = self.read_local(path).map_err(DavError::Other)?;
+            return self.put(path, bytes, expected).await;
+        }
+        if let Some(etag) = expected {
+            self.client.delete(path, etag).await?;
+        }
+        self.state.synced.remove(path);
+        Ok(())
+    }
+}
+
+/// Syncs the vault at `root` with the configured WebDAV collection, both
+/// ways. Vault paths map to the same paths under the collection; ETags are
+/// the base revisions, and base content is kept in `.sync/bases/` for
+/// merging. Writes are conditional, so a file changed on the server during
+/// a sync is merged rather than overwritten. With a passphrase the server
+/// sees only encrypted names and contents.
+pub async fn sync(root: &Path, config: &WebDavConfig) -> Result<SyncReport, String> {
+    let url = config.url.as_deref().ok_or("No WebDAV URL is set")?;
+    let mut client = DavClient::new(url, config.username.clone(), config.password.clone())?;
+    client.unlock(config.passphrase.as_deref()).await?;
+    let core = SyncCore::open(root);
+    let scan_root = root.to_path_buf();
+    tokio::task::spawn_blocking(move || SyncCore::open(&scan_root).scan())
+        .await
+        .map_err(|e| e.to_string())??;
+
+    let mut state = core.provider_state(PROVIDER)?;
+    let fresh = state.remote_id.as_deref() != Some(url);
+    if fresh {
+        state = ProviderState { remote_id: Some(url.to_string()), ..Default::default() };
+    }
+    let tree = remote_tree(&client, &state).await?;
+    let index = core.index()?;
+    let (local_paths, acked) = core.changed_since(state.acked)?;
+    let synced_rev = |path: &str| state.synced.get(path).and_then(|base| base.provider_rev.clone());
+    let mut paths: BTreeSet<String> = tree
+        .files
+        .iter()
+        .filter(|(path, etag)| synced_rev(path).as_ref() != Some(*etag))
+        .map(|(path, _)| path.clone())
+        .collect();
+    paths.extend(state.synced.keys().filter(|path| !tree.files.contains_key(*path)).cloned());
+    if fresh {
+        paths.extend(index.files.keys().cloned());
+    } else {
+        paths.extend(local_paths);
+    }
+
+    let mut pass = Pass { root, core: &core, client: &client, state, dirs: tree.dirs };
+    let mut report = SyncReport::default();
+    for path in paths.into_iter().filter(|path| sync::is_tracked(path)) {
+        let local = index.files.get(&path).map(|record| record.hash.clone());
+        let base = pass.state.synced.get(&path).cloned().unwrap_or_default();
+        let remote_rev = tree.files.get(&path).cloned();
+        let local_changed = local != base.content_hash;
+        if remote_rev == base.provider_rev {
+            if !local_changed {
+                continue;
+            }
+            match pass.push(&path, local.is_some(), base.provider_rev.as_deref()).await {
+                Ok(()) => {
+                    report.pushed.push(path);
+                    continue;
+                }
+                // Changed on the server since the listing: handled below.
+                Err(DavError::Changed) => {}
+                Err(e) => return Err(e.into()),
+            }
+        }
+
+        let fetched = client.get(&path).await?;
+        let remote_hash = fetched.as_ref().map(|(bytes, _)| sync::content_hash(bytes));
+        match fetched {
+            Some((bytes, etag)) if remote_hash == local => pass.mark_synced(&path, &bytes, etag)?,
+            None if local.is_none() => {
+                pass.state.synced.remove(&path);
+            }
+            // Only the server changed, or it changed a file deleted here: the edit wins.
+            Some((bytes, etag)) if !local_changed || local.is_none() => {
+                core.apply_write(&path, &bytes)?;
+                pass.mark_synced(&path, &bytes, etag)?;
+                report.pulled.push(path);
+            }
+            None if !local_changed => {
+                core.apply_delete(&path)?;
+                pass.state.synced.remove(&path);
+                report.pulled.push(path);
+            }
+            // Deleted on the server but edited here.
+            None => {
+                pass.push(&path, true, None).await?;
+                report.pushed.push(path);
+            }
+            Some((bytes, etag)) => {
+                let base_bytes = base.content_hash.as_deref().and_then(|hash| core.load_base(hash));
+                let local_bytes = pass.read_local(&path)?;
+                match core.reconcile(&path, base_bytes.as_deref(), &local_bytes, &bytes, PROVIDER)? {
+                    Reconciled::Merged => {
+                        let merged = pass.read_local(&path)?;
+                        pass.put(&path, merged, etag.as_deref()).await?;
+                        report.merged.push(path);
+                    }
+                    Reconciled::Conflict(record) => {
+                        pass.mark_synced(&path, &bytes, etag)?;
+                        let copy = pass.read_local(&record.conflict_path)?;
+                        pass.put(&record.conflict_path, copy, None).await?;
+                        report.conflicts.push(record);
+                    }
+                }
+            }
+        }
+    }
+
+    let mut state = pass.state;
+    state.folders = tree.folders;
+    state.acked = acked;
+    core.save_provider_state(PROVIDER, &state)?;
+    core.prune_bases(&state)?;
+    core.compact(acked)?;
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::crypto::KEYS_FILE;
+    use percent_encoding::percent_decode_str;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+
+    /// In-memory WebDAV collection under `/dav/`. Folder keys end in `/`;
+    /// a change bumps the ETags of every folder above it, as Nextcloud does.
+    #[derive(Default)]
+    struct Dav {
+        files: BTreeMap<String, (Vec<u8>, String)>,
+        folders: BTreeMap<String, String>,
+        next_etag: usize,
+    }
+
+    impl Dav {
+        fn etag(&mut self) -> String {
+            self.next_etag += 1;
+            format!("\"e{}\"", self.next_etag)
+        }
+
+        fn touch(&mut self, path: &str) {
+            let mut dir = path.trim_end_matches('/').to_string();
+            loop {
+                dir = dir.rsplit_once('/').map(|(parent, _)| format!("{}/", parent)).unwrap_or_default();
+                let etag = self.etag();
+                self.folders.insert(dir.clone(), etag);
+                if dir.is_empty() {
+                    break;
+                }
+                dir.pop();
+            }
+        }
+
+        fn parent_exists(&self, path: &str) -> bool {
+            let parent = path.trim_end_matches('/').rsplit_once('/').map(|(p, _)| format!("{}/", p)).unwrap_or_default();
+            self.folders.contains_key(&parent)
+        }
+
+        fn handle(&mut self, method: &str, path: &str, headers: &HashMap<String, String>, body: Vec<u8>) -> (u16, Vec<(String, String)>, Vec<u8>) {
+            let current = self.files.get(path).map(|(_, etag)| etag.clone());
+            let precondition = match (headers.get("if-match"), headers.get("if-none-match")) {
+                (Some(expected), _) => current.as_ref() == Some(expected),
+                (_, Some(_)) => current.is_none(),
+                _ => true,
+            };
+            match method {
+                "PROPFIND" => {
+                    let folder = if path.is_empty() || path.ends_with('/') { path.to_string() } else { format!("{}/", path) };
+                    let mut members = Vec::new();
+                    if let Some(etag) = self.folders.get(&folder) {
+                        members.push((folder.clone(), etag.clone(), true));
+                        for (child, etag) in &self.folders {
+                            if child.len() > folder.len() && child.starts_with(&folder) && !child[folder.len()..child.len() - 1].contains('/') {
+                                members.push((child.clone(), etag.clone(), true));
+                            }
+                        }
+                        for (child, (_, etag)) in &self.files {
+                            if child.starts_with(&folder) && !child[folder.len()..].contains('/') {
+                                members.push((child.clone(), etag.clone(), false));
+                            }
+                        }
+                    } else if let Some(etag) = current {
+                        members.push((path.to_string(), etag, false));
+                    } else {
+                        return (404, Vec::new(), Vec::new());
+                    }
+                    let responses: String = members
+                        .iter()
+                        .map(|(member, etag, is_dir)| {
+                            let kind = if *is_dir { "<d:collection/>" } else { "" };
+                            let href = member.replace(' ', "%20");
+                            format!(
+                                "<d:response><d:href>/dav/{}</d:href><d:propstat><d:prop><d:getetag>{}</d:getetag><d:resourcetype>{}</d:resourcetype></d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat></d:response>",
+                                href, etag.replace('"', "&quot;"), kind
+                            )
+                        })
+                        .collect();
+                    let xml = format!("<?xml version=\"1.0\"?><d:multistatus xmlns:d=\"DAV:\">{}</d:multistatus>", responses);
+                    (207, Vec::new(), xml.into_bytes())
+                }
+                "GET" => match self.files.get(path) {
+                    Some((bytes, etag)) => (200, vec![("ETag".into(), etag.clone())], bytes.clone()),
+                    None => (404, Vec::new(), Vec::new()),
+                },
+                "PUT" if !precondition => (412, Vec::new(), Vec::new()),
+                "PUT" if !self.parent_exists(path) => (409, Vec::new(), Vec::new()),
+                "PUT" => {
+                    let etag = self.etag();
+                    self.files.insert(path.to_string(), (body, etag.clone()));
+                    self.touch(path);
+                    (201, vec![("ETag".into(), etag)], Vec::new())
+                }
+                "DELETE" if !precondition => (412, Vec::new(), Vec::new()),
+                "DELETE" => {
+                    self.files.remove(path);
+                    self.touch(path);
+                    (204, Vec::new(), Vec::new())
+                }
+                "MKCOL" if self.folders.contains_key(path) => (405, Vec::new(), Vec::new()),
+                "MKCOL" => {
+                    let etag = self.etag();
+                    self.folders.insert(path.to_string(), etag);
+                    self.touch(path);
+                    (201, Vec::new(), Vec::new())
+                }
+                _ => (405, Vec::new(), Vec::new()),
+            }
+        }
+    }
+
+    async fn respond(mut socket: TcpStream, dav: Arc<Mutex<Dav>>, propfinds: Arc<AtomicUsize>) {
+        let mut request = Vec::new();
+        let mut buf = [0u8; 8192];
+        let header_end = loop {
+            let read = socket.read(&mut buf).await.unwrap();
+            if read == 0 {
+                return;
+            }
+            request.extend_from_slice(&buf[..read]);
+            if let Some(end) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break end + 4;
+            }
+        };
+        let head = String::from_utf8_lossy(&request[..header_end]).to_string();
+        let mut lines = head.lines();
+        let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+        let (method, target) = (request_line.next().unwrap_or_default(), request_line.next().unwrap_or_default());
+        let headers: HashMap<String, String> = lines
+            .filter_map(|line| line.split_once(':'))
+            .map(|(name, value)| (name.trim().to_lowercase(), value.trim().to_string()))
+            .collect();
+        let length: usize = headers.get("content-length").and_then(|v| v.parse().ok()).unwrap_or(0);
+        while request.len() < header_end + length {
+            let read = socket.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..read]);
+        }
+        let body = request[header_end..header_end + length].to_vec();
+        let path = percent_decode_str(target.trim_start_matches("/dav/")).decode_utf8_lossy().to_string();
+        if method == "PROPFIND" {
+            propfinds.fetch_add(1, Ordering::SeqCst);
+        }
+
+        let (status, extra, body) = dav.lock().unwrap().handle(method, &path, &headers, body);
+        let mut response = format!("HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n", status, body.len());
+        for (name, value) in extra {
+            response.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        response.push_str("\r\n");
+        socket.write_all(response.as_bytes()).await.unwrap();
+        socket.write_all(&body).await.unwrap();
+        let _ = socket.shutdown().await;
+    }
+
+    async fn serve() -> (WebDavConfig, Arc<AtomicUsize>, Arc<Mutex<Dav>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let dav = Arc::new(Mutex::new(Dav::default()));
+        dav.lock().unwrap().folders.insert(String::new(), "\"e0\"".into());
+        let propfinds = Arc::new(AtomicUsize::new(0));
+        let (counter, server) = (propfinds.clone(), dav.clone());
+        tokio::spawn(async move {
+            loop {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(respond(socket, server.clone(), counter.clone()));
+            }
+        });
+
+        let mut settings = HashMap::new();
+        settings.insert("plugins.core.sync.webdav.url".to_string(), Value::from(format!("http://{}/dav", addr)));
+        (WebDavConfig::from_settings(&settings), propfinds, dav)
+    }
+
+    fn read(dir: &Path, path: &str) -> String {
+        fs::read_to_string(dir.join(path)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_sync_two_vaults_through_webdav() {
+        let (config, propfinds, _) = serve().await;
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::create_dir_all(a.path().join("Daily Notes")).unwrap();
+        fs::write(a.path().join("Daily Notes/one.md"), "# One\n\nfirst\n\nsecond\n").unwrap();
+        fs::write(a.path().join("gone.md"), "bye\n").unwrap();
+        let report = sync(a.path(), &config).await.unwrap();
+        assert_eq!(report.pushed, vec!["Daily Notes/one.md", "gone.md"]);
+
+        let report = sync(b.path(), &config).await.unwrap();
+        assert_eq!(report.pulled, vec!["Daily Notes/one.md", "gone.md"]);
+        assert_eq!(read(b.path(), "Daily Notes/one.md"), "# One\n\nfirst\n\nsecond\n");
+        // Nothing changed: only the root is listed.
+        let before = propfinds.load(Ordering::SeqCst);
+        let report = sync(b.path(), &config).await.unwrap();
+        assert!(report.pulled.is_empty() && report.pushed.is_empty());
+        assert_eq!(propfinds.load(Ordering::SeqCst) - before, 1);
+
+        // Different paragraphs merge against the stored base.
+        fs::write(a.path().join("Daily Notes/one.md"), "# One\n\nFIRST\n\nsecond\n").unwrap();
+        fs::write(b.path().join("Daily Notes/one.md"), "# One\n\nfirst\n\nSECOND\n").unwrap();
+        sync(a.path(), &config).await.unwrap();
+        let report = sync(b.path(), &config).await.unwrap();
+        assert_eq!(report.merged, vec!["Daily Notes/one.md"]);
+        sync(a.path(), &config).await.unwrap();
+        assert_eq!(read(a.path(), "Daily Notes/one.md"), "# One\n\nFIRST\n\nSECOND\n");
+
+        // A delete travels.
+        fs::remove_file(a.path().join("gone.md")).unwrap();
+        sync(a.path(), &config).await.unwrap();
+        let report = sync(b.path(), &config).await.unwrap();
+        assert_eq!(report.pulled, vec!["gone.md"]);
+        assert!(!b.path().join("gone.md").exists());
+
+        // The same line on both sides: a conflict duplicate, pushed too.
+        fs::write(a.path().join("Daily Notes/one.md"), "from a\n").unwrap();
+        fs::write(b.path().join("Daily Notes/one.md"), "from b\n").unwrap();
+        sync(a.path(), &config).await.unwrap();
+        let report = sync(b.path(), &config).await.unwrap();
+        let conflict = &report.conflicts[0];
+        assert_eq!(read(b.path(), "Daily Notes/one.md"), "from a\n");
+        assert_eq!(read(b.path(), &conflict.conflict_path), "from b\n");
+        assert_eq!(SyncCore::open(b.path()).conflicts().unwrap(), report.conflicts);
+        sync(a.path(), &config).await.unwrap();
+        assert_eq!(read(a.path(), &conflict.conflict_path), "from b\n");
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_webdav_holds_only_ciphertext() {
+        let (mut config, _, dav) = serve().await;
+        config.passphrase = Some("correct horse".to_string());
+        let (a, b) = (tempfile::tempdir().unwrap(), tempfile::tempdir().unwrap());
+        fs::create_dir_all(a.path().join("Private")).unwrap();
+        fs::write(a.path().join("Private/secret.md"), "top secret\n").unwrap();
+        sync(a.path(), &config).await.unwrap();
+        let report = sync(b.path(), &config).await.unwrap();
+        assert_eq!(report.pulled, vec!["Private/secret.md"]);
+        assert_eq!(read(b.path(), "Private/secret.md"), "top secret\n");
+
+        {
+            let dav = dav.lock().unwrap();
+            let names = dav.files.keys().chain(dav.folders.keys());
+            assert!(names.filter(|name| *name != KEYS_FILE).all(|name| !name.contains("Private") && !name.contains("secret")));
+            assert!(dav.files.values().all(|(bytes, _)| !bytes.windows(6).any(|w| w == b"secret")));
+        }
+
+        let mut wrong = config.clone();
+        wrong.passphrase = Some("wrong horse".to_string());
+        assert!(sync(b.path(), &wrong).await.unwrap_err().contains("Wrong passphrase"));
+        wrong.passphrase = None;
+        assert!(sync(b.path(), &wrong).await.unwrap_err().contains("encrypted"));
+    }
+}
@@ -0,0 +1,94 @@
+use super::traits::{NativeBackendPlugin, NativePluginContext, ActivePlugin, Invocable};
+use crate::sync::provider::ProviderRunner;
+use crate::sync::{ConflictStatus, SyncCore};
+use engine::{WebDavConfig, PROVIDER};
+use serde_json::Value;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+use tauri::Runtime;
+
+mod client;
+mod engine;
+
+pub struct WebDavSyncPlugin;
+
+impl<R: Runtime> NativeBackendPlugin<R> for WebDavSyncPlugin {
+    fn id(&self) -> &'static str {
+        "core.sync.webdav"
+    }
+
+    fn activate(&self, ctx: NativePluginContext<R>) -> Result<ActivePlugin, String> {
+        let runner = ProviderRunner::new(
+            PROVIDER,
+            ctx.settings_path()?,
+            ctx.vault_config_path()?,
+            WebDavConfig::from_settings,
+            Box::new(|root, config| Box::pin(WebDavSyncInstance::sync_logic(root, config))),
+        );
+        runner.spawn_debouncer();
+        Ok(ActivePlugin { instance: Arc::new(WebDavSyncInstance { runner }) })
+    }
+
+    fn deactivate(&self, _handle: ActivePlugin) -> Result<(), String> {
+        crate::sync::set_provider_enabled(PROVIDER, false);
+        Ok(())
+    }
+}
+
+pub struct WebDavSyncInstance {
+    runner: Arc<ProviderRunner<WebDavConfig>>,
+}
+
+impl WebDavSyncInstance {
+    async fn sync_logic(root: PathBuf, config: WebDavConfig) -> Result<Value, String> {
+        let report = engine::sync(&root, &config).await?;
+        serde_json::to_value(report).map_err(|e| e.to_string())
+    }
+
+    async fn status_logic(root: PathBuf, config: WebDavConfig, runner: &ProviderRunner<WebDavConfig>) -> Result<Value, String> {
+        let (pending, conflicts) = tokio::task::spawn_blocking(move || {
+            let core = SyncCore::open(&root);
+            let provider_state = core.provider_state(PROVIDER)?;
+            let pending = core.pending(provider_state.acked, usize::MAX)?.ops.len();
+            let conflicts: Vec<_> = core
+                .conflicts()?
+                .into_iter()
+                .filter(|c| c.status == ConflictStatus::Unresolved)
+                .collect();
+            Ok::<_, String>((pending, conflicts))
+        })
+        .await
+        .map_err(|e| e.to_string())??;
+
+        let last = runner.last_sync().await;
+        Ok(serde_json::json!({
+            "enabled": config.enabled,
+            "url": config.url,
+            "username": config.username,
+            "encrypted": config.passphrase.is_some(),
+            "pending": pending,
+            "last_sync_ms": last.last_sync_ms,
+            "last_error": last.last_error,
+            "conflicts": conflicts,
+        }))
+    }
+}
+
+impl Invocable for WebDavSyncInstance {
+    fn invoke(&self, method: &str, _payload: Value) -> Pin<Box<dyn Future<Output = Result<Value, String>> + Send>> {
+        let method = method.to_string();
+        let runner = self.runner.clone();
+
+        Box::pin(async move {
+            let config = runner.config()?;
+            let root = runner.root()?;
+            match method.as_str() {
+                "status" => WebDavSyncInstance::status_logic(root, config, &runner).await,
+                "sync" => runner.sync(root, config).await,
+                _ => Err(format!("Method {} not found", method)),
+            }
+        })
+    }
+}
@@ -1,21 +1,24 @@
 use serde_json::Value;
 use std::fs;
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use tauri::{AppHandle, Manager};
 
 // Helper to get path to settings.json
-fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
+pub fn get_settings_path(app: &AppHandle) -> Result<PathBuf, String> {
     let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
     if !config_dir.exists() {
         fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
     }
-    Ok(config_dir.join("settings.json"))
+    Ok(settings_path_in(&config_dir))
 }
 
-#[tauri::command]
-pub fn get_settings(app: AppHandle) -> Result<HashMap<String, Value>, String> {
-    let path = get_settings_path(&app)?;
+pub fn settings_path_in(config_dir: &Path) -> PathBuf {
+    config_dir.join("settings.json")
+}
+
+// Reads settings.json directly, for backend code that only has the path
+pub fn read_settings_file(path: &Path) -> Result<HashMap<String, Value>, String> {
     if !path.exists() {
         return Ok(HashMap::new());
     }
@@ -24,6 +27,17 @@ pub fn get_settings(app: AppHandle) -> Result<HashMap<String, Value>, String> {
     Ok(settings)
 }
 
+pub fn write_settings_file(path: &Path, settings: &HashMap<String, Value>) -> Result<(), String> {
+    let content = serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?;
+    fs::write(path, content).map_err(|e| e.to_string())
+}
+
+#[tauri::command]
+pub fn get_settings(app: AppHandle) -> Result<HashMap<String, Value>, String> {
+    let path = get_settings_path(&app)?;
+    read_settings_file(&path)
+}
+
 #[tauri::command]
 pub fn set_setting(app: AppHandle, key: String, value: Value) -> Result<(), String> {
     let path = get_settings_path(&app)?;
@@ -35,8 +49,5 @@ pub fn set_setting(app: AppHandle, key: String, value: Value) -> Result<(), Stri
     };
 
     settings.insert(key, value);
-
-    let content = serde_json::to_string_pretty(&settings).map_err(|e| e.to_string())?;
-    fs::write(path, content).map_err(|e| e.to_string())?;
-    Ok(())
+    write_settings_file(&path, &settings)
 }
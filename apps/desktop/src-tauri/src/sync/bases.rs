@@ -0,0 +1,43 @@
+use std::collections::HashSet;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use super::files;
+
+const BASES_DIR: &str = "bases";
+
+/// `.sync/bases/<sha256>`: content of synced revisions, kept for providers
+/// that cannot return an old revision to merge against.
+fn base_path(sync_dir: &Path, hash: &str) -> PathBuf {
+    sync_dir.join(BASES_DIR).join(hash)
+}
+
+pub fn save(sync_dir: &Path, bytes: &[u8]) -> Result<String, String> {
+    let hash = files::content_hash(bytes);
+    let path = base_path(sync_dir, &hash);
+    if !path.exists() {
+        fs::create_dir_all(sync_dir.join(BASES_DIR)).map_err(|e| e.to_string())?;
+        fs::write(&path, bytes).map_err(|e| e.to_string())?;
+    }
+    Ok(hash)
+}
+
+pub fn load(sync_dir: &Path, hash: &str) -> Option<Vec<u8>> {
+    if !hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    fs::read(base_path(sync_dir, hash)).ok()
+}
+
+/// Deletes every base not in `keep`.
+pub fn retain(sync_dir: &Path, keep: &HashSet<&str>) -> Result<(), String> {
+    let Ok(entries) = fs::read_dir(sync_dir.join(BASES_DIR)) else {
+        return Ok(());
+    };
+    for entry in entries.filter_map(|e| e.ok()) {
+        if !keep.contains(entry.file_name().to_string_lossy().as_ref()) {
+            fs::remove_file(entry.path()).map_err(|e| e.to_string())?;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+const CONFLICTS_FILE: &str = "conflicts.json";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConflictStatus {
+    Unresolved,
+    Resolved,
+}
+
+/// One entry of `.sync/conflicts.json`. `conflict_path` holds this device's
+/// version; `path` holds the version that came from the provider.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConflictRecord {
+    pub id: String,
+    pub path: String,
+    pub conflict_path: String,
+    pub provider: String,
+    pub created_at: String,
+    pub status: ConflictStatus,
+}
+
+pub fn load(sync_dir: &Path) -> Result<Vec<ConflictRecord>, String> {
+    let path = sync_dir.join(CONFLICTS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", CONFLICTS_FILE, e))
+}
+
+pub fn save(sync_dir: &Path, conflicts: &[ConflictRecord]) -> Result<(), String> {
+    fs::create_dir_all(sync_dir).map_err(|e| e.to_string())?;
+    let content = serde_json::to_string_pretty(conflicts).map_err(|e| e.to_string())?;
+    let tmp = sync_dir.join(format!("{}.tmp", CONFLICTS_FILE));
+    fs::write(&tmp, content).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, sync_dir.join(CONFLICTS_FILE)).map_err(|e| e.to_string())
+}
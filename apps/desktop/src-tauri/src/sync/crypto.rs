@@ -0,0 +1,266 @@
+// End-to-end encryption for sync payloads. Providers store what this module
+// seals; the vault itself stays plain. See docs/SYNC.md.
+
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use chacha20poly1305::aead::{Aead, KeyInit, Payload};
+use chacha20poly1305::{XChaCha20Poly1305, XNonce};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// Name of the key parameters file at the root of an encrypted remote.
+pub const KEYS_FILE: &str = "liminal-keys.json";
+const KEYS_VERSION: u32 = 1;
+const KDF: &str = "argon2id";
+/// Prefix of sealed file contents.
+const MAGIC: &[u8] = b"LNE1";
+const NONCE_LEN: usize = 24;
+const TAG_LEN: usize = 16;
+const CHECK_PLAINTEXT: &[u8] = b"liminal-notes sync key check";
+const BASE32: &[u8; 32] = b"abcdefghijklmnopqrstuvwxyz234567";
+
+/// Derived keys, by passphrase and parameters, so a sync does not pay for
+/// Argon2 every time.
+static DERIVED: Mutex<Vec<([u8; 32], VaultKey)>> = Mutex::new(Vec::new());
+
+/// `plugins.core.sync.encryption.*`: the passphrase, when encryption is on.
+pub fn passphrase_from_settings(settings: &HashMap<String, Value>) -> Option<String> {
+    let enabled = settings
+        .get("plugins.core.sync.encryption.enabled")
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    settings
+        .get("plugins.core.sync.encryption.passphrase")
+        .and_then(|v| v.as_str())
+        .filter(|passphrase| enabled && !passphrase.is_empty())
+        .map(String::from)
+}
+
+/// The key file: how to derive the key from the passphrase, and a value
+/// sealed with it to tell a wrong passphrase from a right one.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct KeyParams {
+    pub version: u32,
+    pub kdf: String,
+    pub salt: String,
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+    pub check: String,
+}
+
+#[derive(Clone)]
+pub struct VaultKey {
+    content: [u8; 32],
+    names: [u8; 32],
+}
+
+impl VaultKey {
+    /// A key for a new remote, with a random salt.
+    pub fn create(passphrase: &str) -> Result<(Self, KeyParams), String> {
+        let mut salt = [0u8; 16];
+        getrandom::getrandom(&mut salt).map_err(|e| e.to_string())?;
+        let mut params = KeyParams {
+            version: KEYS_VERSION,
+            kdf: KDF.to_string(),
+            salt: STANDARD.encode(salt),
+            memory_kib: 64 * 1024,
+            iterations: 3,
+            parallelism: 1,
+            check: String::new(),
+        };
+        let key = Self::derive(passphrase, &params)?;
+        params.check = STANDARD.encode(key.seal(CHECK_PLAINTEXT, b"check")?);
+        Ok((key, params))
+    }
+
+    /// The key of an existing remote; fails on a wrong passphrase.
+    pub fn unlock(passphrase: &str, params: &KeyParams) -> Result<Self, String> {
+        if params.version > KEYS_VERSION || params.kdf != KDF {
+            return Err(format!("Unsupported encryption ({} version {})", params.kdf, params.version));
+        }
+        let key = Self::derive(passphrase, params)?;
+        let check = STANDARD.decode(&params.check).map_err(|e| e.to_string())?;
+        match key.open(&check, b"check") {
+            Ok(plaintext) if plaintext == CHECK_PLAINTEXT => Ok(key),
+            _ => Err("Wrong passphrase for the encrypted sync remote".to_string()),
+        }
+    }
+
+    fn derive(passphrase: &str, params: &KeyParams) -> Result<Self, String> {
+        let salt = STANDARD.decode(&params.salt).map_err(|e| e.to_string())?;
+        let mut id = Sha256::new();
+        id.update(passphrase.as_bytes());
+        id.update(serde_json::to_vec(&(&params.salt, params.memory_kib, params.iterations, params.parallelism)).unwrap_or_default());
+        let id: [u8; 32] = id.finalize().into();
+        let mut derived = DERIVED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some((_, key)) = derived.iter().find(|(known, _)| *known == id) {
+            return Ok(key.clone());
+        }
+
+        let argon_params = Params::new(params.memory_kib, params.iterations, params.parallelism, Some(64))
+            .map_err(|e| e.to_string())?;
+        let mut output = [0u8; 64];
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, argon_params)
+            .hash_password_into(passphrase.as_bytes(), &salt, &mut output)
+            .map_err(|e| e.to_string())?;
+        let mut key = Self { content: [0; 32], names: [0; 32] };
+        key.content.copy_from_slice(&output[..32]);
+        key.names.copy_from_slice(&output[32..]);
+        if derived.len() >= 4 {
+            derived.remove(0);
+        }
+        derived.push((id, key.clone()));
+        Ok(key)
+    }
+
+    fn mac(&self, parts: &[&[u8]]) -> [u8; 32] {
+        let mut mac = <Hmac<Sha256> as Mac>::new_from_slice(&self.names).expect("HMAC takes any key length");
+        for part in parts {
+            mac.update(&(part.len() as u64).to_le_bytes());
+            mac.update(part);
+        }
+        mac.finalize().into_bytes().into()
+    }
+
+    fn seal_with(&self, nonce: &[u8], plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let cipher = XChaCha20Poly1305::new((&self.content).into());
+        let ciphertext = cipher
+            .encrypt(XNonce::from_slice(nonce), Payload { msg: plaintext, aad })
+            .map_err(|_| "Encryption failed".to_string())?;
+        Ok([nonce, &ciphertext].concat())
+    }
+
+    fn open_raw(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        if sealed.len() < NONCE_LEN + TAG_LEN {
+            return Err("Encrypted data is truncated".to_string());
+        }
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let cipher = XChaCha20Poly1305::new((&self.content).into());
+        cipher
+            .decrypt(XNonce::from_slice(nonce), Payload { msg: ciphertext, aad })
+            .map_err(|_| "Encrypted data failed authentication".to_string())
+    }
+
+    /// Encrypts with a random nonce. `aad` binds the result to its context,
+    /// such as the file's path, so it cannot be swapped for another.
+    pub fn seal(&self, plaintext: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        getrandom::getrandom(&mut nonce).map_err(|e| e.to_string())?;
+        Ok([MAGIC, &self.seal_with(&nonce, plaintext, aad)?].concat())
+    }
+
+    pub fn open(&self, sealed: &[u8], aad: &[u8]) -> Result<Vec<u8>, String> {
+        let sealed = sealed.strip_prefix(MAGIC).ok_or("Data is not encrypted for sync")?;
+        self.open_raw(sealed, aad)
+    }
+
+    /// Encrypts one path segment. The nonce comes from the name, so a name
+    /// always encrypts the same way and paths stay addressable.
+    pub fn encrypt_name(&self, name: &str) -> Result<String, String> {
+        let nonce = self.mac(&[b"name", name.as_bytes()]);
+        Ok(base32(&self.seal_with(&nonce[..NONCE_LEN], name.as_bytes(), b"name")?))
+    }
+
+    pub fn decrypt_name(&self, encrypted: &str) -> Option<String> {
+        let plaintext = self.open_raw(&unbase32(encrypted)?, b"name").ok()?;
+        String::from_utf8(plaintext).ok()
+    }
+
+    /// Encrypts each segment of a `/`-separated path, keeping a trailing `/`.
+    pub fn encrypt_path(&self, path: &str) -> Result<String, String> {
+        let (path, slash) = match path.strip_suffix('/') {
+            Some(path) => (path, "/"),
+            None => (path, ""),
+        };
+        if path.is_empty() {
+            return Ok(String::new());
+        }
+        let segments: Vec<String> = path.split('/').map(|s| self.encrypt_name(s)).collect::<Result<_, _>>()?;
+        Ok(format!("{}{}", segments.join("/"), slash))
+    }
+
+    /// Decrypts a path from `encrypt_path`; `None` for anything else.
+    pub fn decrypt_path(&self, path: &str) -> Option<String> {
+        let (path, slash) = match path.strip_suffix('/') {
+            Some(path) => (path, "/"),
+            None => (path, ""),
+        };
+        if path.is_empty() {
+            return Some(String::new());
+        }
+        let segments: Vec<String> = path.split('/').map(|s| self.decrypt_name(s)).collect::<Option<_>>()?;
+        Some(format!("{}{}", segments.join("/"), slash))
+    }
+
+    /// Storage name for content with this hash, which does not reveal the hash.
+    pub fn object_name(&self, hash: &str) -> String {
+        hex::encode(self.mac(&[b"object", hash.as_bytes()]))
+    }
+}
+
+/// RFC 4648 base32, lowercase and unpadded, so names survive
+/// case-insensitive servers.
+fn base32(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for &byte in bytes {
+        buffer = (buffer << 8) | u32::from(byte);
+        bits += 8;
+        while bits >= 5 {
+            bits -= 5;
+            out.push(BASE32[((buffer >> bits) & 31) as usize] as char);
+        }
+    }
+    if bits > 0 {
+        out.push(BASE32[((buffer << (5 - bits)) & 31) as usize] as char);
+    }
+    out
+}
+
+fn unbase32(text: &str) -> Option<Vec<u8>> {
+    let mut out = Vec::new();
+    let (mut buffer, mut bits) = (0u32, 0);
+    for c in text.bytes() {
+        let value = BASE32.iter().position(|&b| b == c.to_ascii_lowercase())? as u32;
+        buffer = (buffer << 5) | value;
+        bits += 5;
+        if bits >= 8 {
+            bits -= 8;
+            out.push((buffer >> bits) as u8);
+        }
+    }
+    Some(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_names_and_wrong_passphrase() {
+        let (key, params) = VaultKey::create("correct horse").unwrap();
+        let sealed = key.seal(b"# Secret\n", b"notes/a.md").unwrap();
+        assert!(!sealed.windows(6).any(|w| w == b"Secret"));
+        assert_eq!(key.open(&sealed, b"notes/a.md").unwrap(), b"# Secret\n");
+        assert!(key.open(&sealed, b"notes/b.md").is_err());
+
+        let path = key.encrypt_path("Daily Notes/2025-01-01.md").unwrap();
+        assert!(!path.contains("Daily") && path.contains('/'));
+        assert_eq!(path, key.encrypt_path("Daily Notes/2025-01-01.md").unwrap());
+        assert_eq!(path.to_lowercase(), path);
+        assert_eq!(key.decrypt_path(&path).unwrap(), "Daily Notes/2025-01-01.md");
+        assert_eq!(key.decrypt_path("plain.md"), None);
+
+        let unlocked = VaultKey::unlock("correct horse", &params).unwrap();
+        assert_eq!(unlocked.open(&sealed, b"notes/a.md").unwrap(), b"# Secret\n");
+        let wrong = VaultKey::unlock("wrong horse", &params).err().unwrap();
+        assert!(wrong.contains("Wrong passphrase"));
+    }
+}
@@ -0,0 +1,45 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use super::files;
+
+const DEVICE_FILE: &str = "device.json";
+
+/// `.sync/device.json`: this device's identity within the vault.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Device {
+    pub device_id: String,
+    pub device_name: String,
+    pub created_at: u64,
+}
+
+impl Device {
+    pub fn load_or_create(sync_dir: &Path) -> Result<Self, String> {
+        let path = sync_dir.join(DEVICE_FILE);
+        if let Ok(content) = fs::read_to_string(&path) {
+            return serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", DEVICE_FILE, e));
+        }
+        let device_name = default_device_name();
+        let device = Device {
+            device_id: files::new_file_id(&device_name).replacen("f_", "d_", 1),
+            device_name,
+            created_at: super::now_ms(),
+        };
+        fs::create_dir_all(sync_dir).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string_pretty(&device).map_err(|e| e.to_string())?;
+        fs::write(&path, content).map_err(|e| e.to_string())?;
+        Ok(device)
+    }
+}
+
+fn default_device_name() -> String {
+    std::env::var("COMPUTERNAME")
+        .or_else(|_| std::env::var("HOSTNAME"))
+        .ok()
+        .or_else(|| fs::read_to_string("/etc/hostname").ok())
+        .map(|name| name.trim().to_string())
+        .filter(|name| !name.is_empty())
+        .unwrap_or_else(|| "Device".to_string())
+}
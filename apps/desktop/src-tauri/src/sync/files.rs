@@ -0,0 +1,99 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const FILES_FILE: &str = "files.json";
+
+/// What the last successful sync knew of a file, so a later edit can be
+/// merged against it.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct BaseRef {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider_rev: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content_hash: Option<String>,
+}
+
+/// A tracked file. `size` and `mtime` let scans skip hashing unchanged files.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileRecord {
+    pub file_id: String,
+    pub hash: String,
+    pub size: u64,
+    pub mtime: u64,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub base: Option<BaseRef>,
+}
+
+/// `.sync/files.json`: tracked files by vault-relative path, and the last
+/// sequence number given to an op.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FileIndex {
+    pub last_seq: u64,
+    pub files: BTreeMap<String, FileRecord>,
+}
+
+impl FileIndex {
+    pub fn load(sync_dir: &Path) -> Result<Self, String> {
+        let path = sync_dir.join(FILES_FILE);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid {}: {}", FILES_FILE, e))
+    }
+
+    pub fn save(&self, sync_dir: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp = sync_dir.join(format!("{}.tmp", FILES_FILE));
+        fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, sync_dir.join(FILES_FILE)).map_err(|e| e.to_string())
+    }
+
+    /// When the index was last saved, in ms since the epoch.
+    pub fn saved_at(sync_dir: &Path) -> u64 {
+        fs::metadata(sync_dir.join(FILES_FILE))
+            .and_then(|m| m.modified())
+            .ok()
+            .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as u64)
+            .unwrap_or_default()
+    }
+
+    /// Paths of `path` itself and, for a folder, of every file under it.
+    pub fn paths_under(&self, path: &str) -> Vec<String> {
+        let prefix = format!("{}/", path);
+        self.files
+            .keys()
+            .filter(|p| *p == path || p.starts_with(&prefix))
+            .cloned()
+            .collect()
+    }
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+    hex::encode(Sha256::digest(bytes))
+}
+
+/// A new file id. Ids only need to be unique within a vault's sync history,
+/// so the path, the clock and a counter are enough.
+pub fn new_file_id(path: &str) -> String {
+    static COUNTER: AtomicU64 = AtomicU64::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_nanos())
+        .unwrap_or_default();
+    let mut hasher = Sha256::new();
+    hasher.update(path.as_bytes());
+    hasher.update(nanos.to_le_bytes());
+    hasher.update(COUNTER.fetch_add(1, Ordering::SeqCst).to_le_bytes());
+    hasher.update(std::process::id().to_le_bytes());
+    format!("f_{}", &hex::encode(hasher.finalize())[..16])
+}
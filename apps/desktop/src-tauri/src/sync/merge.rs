@@ -0,0 +1,272 @@
+// Three-way merge for notes and small text files. Markdown bodies are merged
+// block by block (paragraphs, list runs, headings), falling back to lines
+// where both sides touched the same blocks; frontmatter is merged key by key.
+
+use crate::frontmatter::{self, Frontmatter};
+
+/// Larger files are never merged.
+pub const MERGE_LIMIT_BYTES: usize = 1024 * 1024;
+/// Largest LCS table built; bigger regions count as a conflict.
+const MAX_LCS_CELLS: usize = 4_000_000;
+
+const TEXT_EXTENSIONS: &[&str] = &["txt", "json", "yml", "yaml", "css", "csv"];
+
+/// Merges a region both sides changed: base, local and remote units.
+type Refine<'a> = &'a dyn Fn(&[String], &[String], &[String]) -> Option<String>;
+
+fn extension(path: &str) -> String {
+    path.rsplit_once('.').map(|(_, ext)| ext.to_ascii_lowercase()).unwrap_or_default()
+}
+
+/// Whether a file of this path and size is ever merged.
+pub fn can_merge(path: &str, len: usize) -> bool {
+    let extension = extension(path);
+    len <= MERGE_LIMIT_BYTES && (extension == "md" || TEXT_EXTENSIONS.contains(&extension.as_str()))
+}
+
+/// Merges `local` and `remote` edits of `base`, or `None` when they conflict.
+pub fn merge_file(path: &str, base: &[u8], local: &[u8], remote: &[u8]) -> Option<Vec<u8>> {
+    if [base, local, remote].iter().any(|bytes| !can_merge(path, bytes.len())) {
+        return None;
+    }
+    let (base, local, remote) = (
+        std::str::from_utf8(base).ok()?,
+        std::str::from_utf8(local).ok()?,
+        std::str::from_utf8(remote).ok()?,
+    );
+    let merged = if extension(path) == "md" {
+        merge_note(base, local, remote)
+    } else {
+        merge_lines(base, local, remote)
+    };
+    merged.map(String::into_bytes)
+}
+
+pub fn merge_note(base: &str, local: &str, remote: &str) -> Option<String> {
+    let (base_yaml, base_body) = frontmatter::split(base);
+    let (local_yaml, local_body) = frontmatter::split(local);
+    let (remote_yaml, remote_body) = frontmatter::split(remote);
+
+    let body = merge_units(&blocks(base_body), &blocks(local_body), &blocks(remote_body), &|b, l, r| {
+        merge_lines(&b.concat(), &l.concat(), &r.concat())
+    })?;
+    if local_yaml.is_none() && remote_yaml.is_none() {
+        return Some(body);
+    }
+    let parse = |yaml: Option<&str>| yaml.map(Frontmatter::parse).unwrap_or_default();
+    let yaml = merge_frontmatter(&parse(base_yaml), &parse(local_yaml), &parse(remote_yaml))?;
+    Some(format!("---\n{}---\n{}", yaml.render(), body))
+}
+
+/// Line-level merge.
+pub fn merge_lines(base: &str, local: &str, remote: &str) -> Option<String> {
+    let lines = |text: &str| text.split_inclusive('\n').map(String::from).collect::<Vec<_>>();
+    merge_units(&lines(base), &lines(local), &lines(remote), &|_, _, _| None)
+}
+
+/// Splits Markdown into blocks: runs of non-blank lines together with the
+/// blank lines that follow them.
+fn blocks(text: &str) -> Vec<String> {
+    let mut blocks: Vec<String> = Vec::new();
+    let mut current = String::new();
+    let mut in_gap = false;
+    for line in text.split_inclusive('\n') {
+        let blank = line.trim().is_empty();
+        if !blank && in_gap {
+            blocks.push(std::mem::take(&mut current));
+            in_gap = false;
+        }
+        in_gap |= blank && !current.trim().is_empty();
+        current.push_str(line);
+    }
+    if !current.is_empty() {
+        blocks.push(current);
+    }
+    blocks
+}
+
+/// For each item of `a`, the index of its partner in `b` along a longest
+/// common subsequence.
+pub(crate) fn lcs_matches(a: &[String], b: &[String]) -> Option<Vec<Option<usize>>> {
+    let mut matches = vec![None; a.len()];
+    let prefix = a.iter().zip(b).take_while(|(x, y)| x == y).count();
+    let suffix = a[prefix..]
+        .iter()
+        .rev()
+        .zip(b[prefix..].iter().rev())
+        .take_while(|(x, y)| x == y)
+        .count();
+    for (i, slot) in matches.iter_mut().enumerate().take(prefix) {
+        *slot = Some(i);
+    }
+    for k in 0..suffix {
+        matches[a.len() - 1 - k] = Some(b.len() - 1 - k);
+    }
+
+    let (a_mid, b_mid) = (&a[prefix..a.len() - suffix], &b[prefix..b.len() - suffix]);
+    let (n, m) = (a_mid.len(), b_mid.len());
+    if n == 0 || m == 0 {
+        return Some(matches);
+    }
+    if (n + 1) * (m + 1) > MAX_LCS_CELLS {
+        return None;
+    }
+    let mut table = vec![0u32; (n + 1) * (m + 1)];
+    let at = |i: usize, j: usize| i * (m + 1) + j;
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            table[at(i, j)] = if a_mid[i] == b_mid[j] {
+                table[at(i + 1, j + 1)] + 1
+            } else {
+                table[at(i + 1, j)].max(table[at(i, j + 1)])
+            };
+        }
+    }
+    let (mut i, mut j) = (0, 0);
+    while i < n && j < m {
+        if a_mid[i] == b_mid[j] {
+            matches[prefix + i] = Some(prefix + j);
+            i += 1;
+            j += 1;
+        } else if table[at(i + 1, j)] >= table[at(i, j + 1)] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    Some(matches)
+}
+
+/// diff3 over units. Regions changed on only one side take that side; regions
+/// changed identically on both are taken once; other regions go to
+/// `refine`, and the merge fails if it cannot resolve them.
+fn merge_units(
+    base: &[String],
+    local: &[String],
+    remote: &[String],
+    refine: Refine,
+) -> Option<String> {
+    let local_matches = lcs_matches(base, local)?;
+    let remote_matches = lcs_matches(base, remote)?;
+    let mut merged = String::new();
+    let (mut b, mut l, mut r) = (0, 0, 0);
+    loop {
+        // The next base unit both sides kept.
+        let stable = (b..base.len()).find_map(|j| Some((j, local_matches[j]?, remote_matches[j]?)));
+        let (jb, jl, jr) = stable.unwrap_or((base.len(), local.len(), remote.len()));
+        let (base_chunk, local_chunk, remote_chunk) = (&base[b..jb], &local[l..jl], &remote[r..jr]);
+        if local_chunk == base_chunk || local_chunk == remote_chunk {
+            merged.push_str(&remote_chunk.concat());
+        } else if remote_chunk == base_chunk {
+            merged.push_str(&local_chunk.concat());
+        } else {
+            merged.push_str(&refine(base_chunk, local_chunk, remote_chunk)?);
+        }
+        let Some((jb, jl, jr)) = stable else {
+            break;
+        };
+        merged.push_str(&base[jb]);
+        (b, l, r) = (jb + 1, jl + 1, jr + 1);
+    }
+    Some(merged)
+}
+
+/// Merges frontmatter key by key. Keys keep the local order, with keys only
+/// the remote added after them. Lists changed on both sides are merged as
+/// sets; any other key changed differently on both sides is a conflict.
+fn merge_frontmatter(base: &Frontmatter, local: &Frontmatter, remote: &Frontmatter) -> Option<Frontmatter> {
+    let mut keys: Vec<&str> = local.entries().iter().map(|(k, _)| k.as_str()).collect();
+    for (key, _) in remote.entries() {
+        if !keys.contains(&key.as_str()) {
+            keys.push(key);
+        }
+    }
+
+    let mut entries = Vec::new();
+    for key in keys {
+        let (b, l, r) = (base.raw(key), local.raw(key), remote.raw(key));
+        let value = if l == r || r == b {
+            l
+        } else if l == b {
+            r
+        } else {
+            let merged = merge_list(key, base, local, remote)?;
+            entries.push((key.to_string(), merged));
+            continue;
+        };
+        if let Some(value) = value {
+            entries.push((key.to_string(), value.to_string()));
+        }
+    }
+    Some(Frontmatter::from_entries(entries))
+}
+
+/// Both sides' additions, minus what either side removed, written in the
+/// local side's list style.
+fn merge_list(key: &str, base: &Frontmatter, local: &Frontmatter, remote: &Frontmatter) -> Option<String> {
+    let is_list = |fm: &Frontmatter| {
+        fm.raw(key)
+            .is_some_and(|raw| raw.starts_with('[') || raw.trim_start().starts_with('-') || raw.is_empty())
+    };
+    if !is_list(local) || !is_list(remote) {
+        return None;
+    }
+    let (b, l, r) = (base.get_list(key), local.get_list(key), remote.get_list(key));
+    let removed = |side: &Vec<String>| b.iter().filter(|item| !side.contains(item)).cloned().collect::<Vec<_>>();
+    let (removed_locally, removed_remotely) = (removed(&l), removed(&r));
+    let mut items: Vec<String> = Vec::new();
+    for item in l.iter().chain(&r) {
+        if !items.contains(item) && !removed_locally.contains(item) && !removed_remotely.contains(item) {
+            items.push(item.clone());
+        }
+    }
+    let inline = local.raw(key).is_some_and(|raw| raw.starts_with('['));
+    Some(if inline {
+        format!("[{}]", items.join(", "))
+    } else {
+        items.iter().map(|item| format!("\n  - {}", item)).collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merges_blocks_and_lines() {
+        let base = "# Title\n\nFirst paragraph.\n\n- one\n- two\n- three\n\nLast paragraph.\n";
+        let local = "# Title\n\nFirst paragraph, edited here.\n\n- one\n- two\n- three\n\nLast paragraph.\n";
+        let remote = "# Title\n\nFirst paragraph.\n\n- one\n- two\n- three\n\nLast paragraph, edited there.\n";
+        assert_eq!(
+            merge_note(base, local, remote).unwrap(),
+            "# Title\n\nFirst paragraph, edited here.\n\n- one\n- two\n- three\n\nLast paragraph, edited there.\n"
+        );
+
+        // Both sides changed the list block, on different lines.
+        let local = "# Title\n\nFirst paragraph.\n\n- ONE\n- two\n- three\n\nLast paragraph.\n";
+        let remote = "# Title\n\nFirst paragraph.\n\n- one\n- two\n- THREE\n\nLast paragraph.\n";
+        assert_eq!(
+            merge_note(base, local, remote).unwrap(),
+            "# Title\n\nFirst paragraph.\n\n- ONE\n- two\n- THREE\n\nLast paragraph.\n"
+        );
+
+        // The same line changed differently.
+        let remote = "# Title\n\nFirst paragraph.\n\n- uno\n- two\n- three\n\nLast paragraph.\n";
+        assert_eq!(merge_note(base, local, remote), None);
+        assert_eq!(merge_file("a.png", b"x", b"y", b"x"), None);
+    }
+
+    #[test]
+    fn test_merges_frontmatter_by_key() {
+        let base = "---\ntitle: Note\ntags: [a, b]\nstatus: draft\n---\nBody\n";
+        let local = "---\ntitle: Renamed\ntags: [a, b, c]\nstatus: draft\n---\nBody\n";
+        let remote = "---\ntitle: Note\ntags: [b, d]\nstatus: draft\nsource: web\n---\nBody\n";
+        assert_eq!(
+            merge_note(base, local, remote).unwrap(),
+            "---\ntitle: Renamed\ntags: [b, c, d]\nstatus: draft\nsource: web\n---\nBody\n"
+        );
+
+        let remote = "---\ntitle: Other\ntags: [a, b]\nstatus: draft\n---\nBody\n";
+        assert_eq!(merge_note(base, local, remote), None);
+    }
+}
@@ -0,0 +1,764 @@
+// SyncCore: stable file ids and the local operation log that sync providers
+// push from. See docs/SYNC.md for the `.sync/` layout.
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tauri::AppHandle;
+use walkdir::WalkDir;
+
+use crate::vault::resolve_safe_path;
+
+mod bases;
+mod conflicts;
+pub mod crypto;
+mod device;
+mod files;
+pub mod merge;
+mod ops;
+pub mod provider;
+mod state;
+
+pub use conflicts::{ConflictRecord, ConflictStatus};
+pub use device::Device;
+pub use files::{content_hash, new_file_id, BaseRef, FileIndex, FileRecord};
+pub use ops::{OpKind, OpsPage, SyncOp};
+pub use state::ProviderState;
+
+pub const SYNC_DIR: &str = ".sync";
+/// Hidden folders that still sync with the vault.
+const SYNCED_DOT_DIRS: &[&str] = &[".plugins/", ".liminal/spellcheck/"];
+const SCAN_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_PAGE_SIZE: usize = 500;
+/// Files modified this close to the index being saved are hashed even when
+/// size and mtime match; covers coarse mtimes such as FAT's 2 s.
+const RACY_WINDOW_MS: u64 = 2000;
+
+/// Serializes index and log updates between vault commands and the watcher.
+static LOCK: Mutex<()> = Mutex::new(());
+
+/// Providers currently enabled. Vault changes are only recorded and scanned
+/// for while there is one; the first scan after enabling catches up.
+static ENABLED: Mutex<BTreeSet<&'static str>> = Mutex::new(BTreeSet::new());
+
+/// Called by each provider whenever it reads its settings.
+pub fn set_provider_enabled(provider: &'static str, enabled: bool) {
+    let mut providers = ENABLED.lock().unwrap_or_else(|e| e.into_inner());
+    if enabled {
+        providers.insert(provider);
+    } else {
+        providers.remove(provider);
+    }
+}
+
+fn any_provider_enabled() -> bool {
+    !ENABLED.lock().unwrap_or_else(|e| e.into_inner()).is_empty()
+}
+
+/// Whether a vault-relative path is synced. Hidden files and folders are
+/// not, apart from vault-scoped plugin settings and spellcheck words.
+pub fn is_tracked(path: &str) -> bool {
+    let rest = SYNCED_DOT_DIRS.iter().find_map(|dir| path.strip_prefix(dir)).unwrap_or(path);
+    !rest.is_empty() && !rest.split('/').any(|c| c.is_empty() || c.starts_with('.'))
+}
+
+fn may_contain_tracked(dir: &str) -> bool {
+    let prefix = format!("{}/", dir);
+    is_tracked(&format!("{}file", prefix)) || SYNCED_DOT_DIRS.iter().any(|synced| synced.starts_with(&prefix))
+}
+
+/// `a\b`, `./a//b` and `a/b/` all become `a/b`.
+pub fn normalize(path: &str) -> String {
+    path.split(['/', '\\'])
+        .filter(|c| !c.is_empty() && *c != ".")
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+pub fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+fn stat(path: &Path) -> Result<(u64, u64), String> {
+    let metadata = fs::metadata(path).map_err(|e| e.to_string())?;
+    let mtime = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default();
+    Ok((metadata.len(), mtime))
+}
+
+/// UTC date and time of `ms`: year, month, day, hour, minute, second.
+fn civil(ms: u64) -> (i64, i64, i64, u64, u64, u64) {
+    let secs = ms / 1000;
+    let days = (secs / 86_400) as i64;
+    let (hour, minute, second) = ((secs % 86_400) / 3600, (secs % 3600) / 60, secs % 60);
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day, hour, minute, second)
+}
+
+/// `YYYY-MM-DD HHmm` in UTC.
+fn format_minute(ms: u64) -> String {
+    let (year, month, day, hour, minute, _) = civil(ms);
+    format!("{:04}-{:02}-{:02} {:02}{:02}", year, month, day, hour, minute)
+}
+
+/// RFC 3339 in UTC, to the second.
+pub fn format_iso(ms: u64) -> String {
+    let (year, month, day, hour, minute, second) = civil(ms);
+    format!("{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z", year, month, day, hour, minute, second)
+}
+
+/// Where to keep a conflicting copy of `path`:
+/// `Note (Conflict — <device> — YYYY-MM-DD HHmm).md`, numbered when that
+/// name is already taken in the vault.
+pub fn conflict_path(root: &Path, path: &str, device: &str, at_ms: u64) -> String {
+    let (dir, name) = match path.rsplit_once('/') {
+        Some((dir, name)) => (format!("{}/", dir), name),
+        None => (String::new(), path),
+    };
+    let (stem, ext) = match name.rsplit_once('.') {
+        Some((stem, ext)) if !stem.is_empty() => (stem, format!(".{}", ext)),
+        _ => (name, String::new()),
+    };
+    let device: String = device.chars().filter(|c| !matches!(c, '/' | '\\' | ':')).collect();
+    let label = format!("Conflict — {} — {}", device, format_minute(at_ms));
+    let mut candidate = format!("{}{} ({}){}", dir, stem, label, ext);
+    let mut n = 2;
+    while root.join(&candidate).exists() {
+        candidate = format!("{}{} ({} {}){}", dir, stem, label, n, ext);
+        n += 1;
+    }
+    candidate
+}
+
+/// A file as found on disk during a scan or after a write.
+struct Observed {
+    hash: String,
+    size: u64,
+    mtime: u64,
+}
+
+impl Observed {
+    fn read(path: &Path) -> Result<Self, String> {
+        let (size, mtime) = stat(path)?;
+        let bytes = fs::read(path).map_err(|e| e.to_string())?;
+        Ok(Self { hash: files::content_hash(&bytes), size, mtime })
+    }
+}
+
+/// How a file changed both locally and by a provider was reconciled.
+#[derive(Debug)]
+pub enum Reconciled {
+    Merged,
+    Conflict(ConflictRecord),
+}
+
+pub struct SyncCore {
+    root: PathBuf,
+    dir: PathBuf,
+}
+
+impl SyncCore {
+    pub fn open(root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            dir: root.join(SYNC_DIR),
+        }
+    }
+
+    pub fn device(&self) -> Result<Device, String> {
+        Device::load_or_create(&self.dir)
+    }
+
+    pub fn index(&self) -> Result<FileIndex, String> {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        FileIndex::load(&self.dir)
+    }
+
+    /// Loads the index, lets `change` edit it and describe the ops it made,
+    /// then numbers, logs and saves them.
+    fn update<F>(&self, change: F) -> Result<Vec<SyncOp>, String>
+    where
+        F: FnOnce(&mut FileIndex) -> Result<Vec<(String, OpKind)>, String>,
+    {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        fs::create_dir_all(&self.dir).map_err(|e| e.to_string())?;
+        let mut index = FileIndex::load(&self.dir)?;
+        let changes = change(&mut index)?;
+        let at = now_ms();
+        let recorded: Vec<SyncOp> = changes
+            .into_iter()
+            .map(|(file_id, kind)| {
+                index.last_seq += 1;
+                SyncOp { seq: index.last_seq, file_id, at, kind }
+            })
+            .collect();
+        ops::append(&self.dir, &recorded)?;
+        index.save(&self.dir)?;
+        Ok(recorded)
+    }
+
+    /// Records a file written at `path`: a create for a new file, an update
+    /// when its content changed, nothing otherwise.
+    pub fn record_write(&self, path: &str) -> Result<Vec<SyncOp>, String> {
+        let path = normalize(path);
+        if !is_tracked(&path) {
+            return Ok(Vec::new());
+        }
+        let observed = Observed::read(&self.root.join(&path))?;
+        self.update(|index| Ok(write_change(index, &path, observed).into_iter().collect()))
+    }
+
+    /// Records a rename of a file or folder; every tracked file under a
+    /// folder keeps its id.
+    pub fn record_move(&self, from: &str, to: &str) -> Result<Vec<SyncOp>, String> {
+        let (from, to) = (normalize(from), normalize(to));
+        let target = self.root.join(&to);
+        let moved = self.update(|index| {
+            let mut changes = Vec::new();
+            for old in index.paths_under(&from) {
+                let new = format!("{}{}", to, &old[from.len()..]);
+                let Some(record) = index.files.remove(&old) else {
+                    continue;
+                };
+                if !is_tracked(&new) {
+                    changes.push((record.file_id, OpKind::Delete { path: old }));
+                    continue;
+                }
+                changes.push((record.file_id.clone(), OpKind::Move { from: old, to: new.clone() }));
+                index.files.insert(new, record);
+            }
+            Ok(changes)
+        })?;
+        // Moving an untracked file into the synced part of the vault creates it.
+        if moved.is_empty() && target.is_file() {
+            return self.record_write(&to);
+        }
+        Ok(moved)
+    }
+
+    /// Records the deletion of a file or of a folder and everything in it.
+    pub fn record_delete(&self, path: &str) -> Result<Vec<SyncOp>, String> {
+        let path = normalize(path);
+        self.update(|index| {
+            Ok(index
+                .paths_under(&path)
+                .into_iter()
+                .filter_map(|old| {
+                    let record = index.files.remove(&old)?;
+                    Some((record.file_id, OpKind::Delete { path: old }))
+                })
+                .collect())
+        })
+    }
+
+    /// Compares the vault with the index and records what changed outside the
+    /// app's own commands. A deleted file and a new file with the same
+    /// content are recorded as a move.
+    pub fn scan(&self) -> Result<Vec<SyncOp>, String> {
+        let index = self.index()?;
+        let saved_at = FileIndex::saved_at(&self.dir);
+        let mut seen: HashMap<String, Option<Observed>> = HashMap::new();
+        let walker = WalkDir::new(&self.root).into_iter().filter_entry(|entry| {
+            let relative = entry.path().strip_prefix(&self.root).map(|p| p.to_string_lossy().replace('\\', "/"));
+            match relative {
+                Ok(relative) if relative.is_empty() => true,
+                Ok(relative) if entry.file_type().is_dir() => may_contain_tracked(&relative),
+                Ok(relative) => is_tracked(&relative),
+                Err(_) => false,
+            }
+        });
+        for entry in walker.filter_map(|e| e.ok()).filter(|e| e.file_type().is_file()) {
+            let Ok(relative) = entry.path().strip_prefix(&self.root) else {
+                continue;
+            };
+            let relative = normalize(&relative.to_string_lossy());
+            // Unchanged size and mtime, recorded well before the index was
+            // saved: skip hashing.
+            let unchanged = match index.files.get(&relative) {
+                Some(record) => stat(entry.path()).is_ok_and(|(size, mtime)| {
+                    size == record.size && mtime == record.mtime && mtime + RACY_WINDOW_MS < saved_at
+                }),
+                None => false,
+            };
+            let observed = if unchanged { None } else { Some(Observed::read(entry.path())?) };
+            seen.insert(relative, observed);
+        }
+
+        self.update(|index| {
+            let missing: Vec<String> = index.files.keys().filter(|p| !seen.contains_key(*p)).cloned().collect();
+            let mut changes = Vec::new();
+            let mut created = Vec::new();
+            let mut paths: Vec<_> = seen.into_iter().filter_map(|(path, observed)| Some((path, observed?))).collect();
+            paths.sort_by(|a, b| a.0.cmp(&b.0));
+            for (path, observed) in paths {
+                if index.files.contains_key(&path) {
+                    changes.extend(write_change(index, &path, observed));
+                } else {
+                    created.push((path, observed));
+                }
+            }
+
+            let mut deleted: Vec<(String, FileRecord)> = missing
+                .into_iter()
+                .filter_map(|path| index.files.remove(&path).map(|record| (path, record)))
+                .collect();
+            for (path, observed) in created {
+                match deleted.iter().position(|(_, record)| record.hash == observed.hash) {
+                    Some(position) => {
+                        let (from, mut record) = deleted.remove(position);
+                        record.size = observed.size;
+                        record.mtime = observed.mtime;
+                        changes.push((record.file_id.clone(), OpKind::Move { from, to: path.clone() }));
+                        index.files.insert(path, record);
+                    }
+                    None => changes.extend(write_change(index, &path, observed)),
+                }
+            }
+            changes.extend(deleted.into_iter().map(|(path, record)| (record.file_id, OpKind::Delete { path })));
+            Ok(changes)
+        })
+    }
+
+    /// Records a conflict duplicate for the UI to list.
+    pub fn record_conflict(&self, path: &str, conflict_path: &str, provider: &str) -> Result<ConflictRecord, String> {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut all = conflicts::load(&self.dir)?;
+        let record = ConflictRecord {
+            id: files::new_file_id(conflict_path).replacen("f_", "conf_", 1),
+            path: normalize(path),
+            conflict_path: normalize(conflict_path),
+            provider: provider.to_string(),
+            created_at: format_iso(now_ms()),
+            status: ConflictStatus::Unresolved,
+        };
+        all.push(record.clone());
+        conflicts::save(&self.dir, &all)?;
+        Ok(record)
+    }
+
+    pub fn conflicts(&self) -> Result<Vec<ConflictRecord>, String> {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        conflicts::load(&self.dir)
+    }
+
+    /// Marks a conflict resolved and, with `delete_copy`, deletes the
+    /// duplicate.
+    pub fn resolve_conflict(&self, id: &str, delete_copy: bool) -> Result<ConflictRecord, String> {
+        let record = {
+            let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+            let mut all = conflicts::load(&self.dir)?;
+            let record = all.iter_mut().find(|c| c.id == id).ok_or("Conflict not found")?;
+            record.status = ConflictStatus::Resolved;
+            let record = record.clone();
+            conflicts::save(&self.dir, &all)?;
+            record
+        };
+        let copy = self.root.join(&record.conflict_path);
+        if delete_copy && copy.is_file() {
+            fs::remove_file(&copy).map_err(|e| e.to_string())?;
+            self.record_delete(&record.conflict_path)?;
+        }
+        Ok(record)
+    }
+
+    /// Reconciles a file that changed locally and at a provider since `base`.
+    /// A clean three-way merge is written to `path`. Otherwise the provider's
+    /// version goes to `path`, the local one to a conflict duplicate, and the
+    /// conflict is recorded.
+    pub fn reconcile(
+        &self,
+        path: &str,
+        base: Option<&[u8]>,
+        local: &[u8],
+        remote: &[u8],
+        provider: &str,
+    ) -> Result<Reconciled, String> {
+        let path = normalize(path);
+        let target = resolve_safe_path(&self.root, &path)?;
+        if let Some(merged) = base.and_then(|base| merge::merge_file(&path, base, local, remote)) {
+            fs::write(&target, merged).map_err(|e| e.to_string())?;
+            self.record_write(&path)?;
+            return Ok(Reconciled::Merged);
+        }
+
+        let device = self.device()?;
+        let conflict_path = conflict_path(&self.root, &path, &device.device_name, now_ms());
+        fs::write(self.root.join(&conflict_path), local).map_err(|e| e.to_string())?;
+        fs::write(&target, remote).map_err(|e| e.to_string())?;
+        self.record_write(&conflict_path)?;
+        self.record_write(&path)?;
+        Ok(Reconciled::Conflict(self.record_conflict(&path, &conflict_path, provider)?))
+    }
+
+    /// Writes a file received from a provider and records it.
+    pub fn apply_write(&self, path: &str, bytes: &[u8]) -> Result<(), String> {
+        let path = normalize(path);
+        let target = resolve_safe_path(&self.root, &path)?;
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&target, bytes).map_err(|e| e.to_string())?;
+        self.record_write(&path).map(|_| ())
+    }
+
+    /// Deletes a file a provider reported deleted, and records it.
+    pub fn apply_delete(&self, path: &str) -> Result<(), String> {
+        let path = normalize(path);
+        let target = resolve_safe_path(&self.root, &path)?;
+        if target.is_file() {
+            fs::remove_file(&target).map_err(|e| e.to_string())?;
+        }
+        self.record_delete(&path).map(|_| ())
+    }
+
+    pub fn provider_state(&self, provider: &str) -> Result<ProviderState, String> {
+        ProviderState::load(&self.dir, provider)
+    }
+
+    pub fn save_provider_state(&self, provider: &str, state: &ProviderState) -> Result<(), String> {
+        state.save(&self.dir, provider)
+    }
+
+    /// Paths touched by ops after `acked`, and the cursor to ack next.
+    pub fn changed_since(&self, acked: u64) -> Result<(BTreeSet<String>, u64), String> {
+        let page = self.pending(acked, usize::MAX)?;
+        let mut paths = BTreeSet::new();
+        for op in page.ops {
+            match op.kind {
+                OpKind::Create { path, .. } | OpKind::Update { path, .. } | OpKind::Delete { path } => {
+                    paths.insert(path);
+                }
+                OpKind::Move { from, to } => {
+                    paths.insert(from);
+                    paths.insert(to);
+                }
+            }
+        }
+        Ok((paths, page.next_cursor))
+    }
+
+    /// Keeps a copy of a synced revision to merge against later, if it is a
+    /// file that can be merged. Returns its hash.
+    pub fn save_base(&self, path: &str, bytes: &[u8]) -> Result<String, String> {
+        if merge::can_merge(path, bytes.len()) {
+            bases::save(&self.dir, bytes)
+        } else {
+            Ok(files::content_hash(bytes))
+        }
+    }
+
+    pub fn load_base(&self, hash: &str) -> Option<Vec<u8>> {
+        bases::load(&self.dir, hash)
+    }
+
+    /// Drops base copies no path of `state` refers to.
+    pub fn prune_bases(&self, state: &ProviderState) -> Result<(), String> {
+        let keep: HashSet<&str> = state.synced.values().filter_map(|base| base.content_hash.as_deref()).collect();
+        bases::retain(&self.dir, &keep)
+    }
+
+    /// Ops recorded after `cursor`, oldest first.
+    pub fn pending(&self, cursor: u64, limit: usize) -> Result<OpsPage, String> {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        ops::since(&self.dir, cursor, limit)
+    }
+
+    /// Drops ops up to `acked` that every enabled provider has acknowledged
+    /// too, and coalesces those no provider has taken yet (see
+    /// `ops::compact`). The log is shared, so ops stay until the enabled
+    /// provider furthest behind has taken them, unchanged. A disabled
+    /// provider does not hold them back: its state is removed once ops it
+    /// has not taken are dropped, so it starts afresh when enabled again.
+    /// Returns the number of ops left.
+    pub fn compact(&self, acked: u64) -> Result<usize, String> {
+        let _lock = LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let enabled = ENABLED.lock().unwrap_or_else(|e| e.into_inner()).clone();
+        let (mut floor, mut ceiling) = (acked, acked);
+        let providers = ProviderState::acked_by_provider(&self.dir)?;
+        let (active, inactive): (Vec<_>, Vec<_>) =
+            providers.into_iter().partition(|(provider, _)| enabled.contains(provider.as_str()));
+        for (_, acked) in active {
+            (floor, ceiling) = (floor.min(acked), ceiling.max(acked));
+        }
+        for (provider, acked) in inactive {
+            if acked < floor {
+                ProviderState::remove(&self.dir, &provider)?;
+            } else {
+                ceiling = ceiling.max(acked);
+            }
+        }
+        let compacted = ops::compact(ops::read(&self.dir)?, floor, ceiling);
+        ops::rewrite(&self.dir, &compacted)?;
+        Ok(compacted.len())
+    }
+}
+
+/// Updates the record for `path` and returns the op for it, if any.
+fn write_change(index: &mut FileIndex, path: &str, observed: Observed) -> Option<(String, OpKind)> {
+    match index.files.get_mut(path) {
+        Some(record) => {
+            record.size = observed.size;
+            record.mtime = observed.mtime;
+            if record.hash == observed.hash {
+                return None;
+            }
+            record.hash = observed.hash.clone();
+            Some((
+                record.file_id.clone(),
+                OpKind::Update {
+                    path: path.to_string(),
+                    hash: observed.hash,
+                    base: record.base.clone(),
+                },
+            ))
+        }
+        None => {
+            let record = FileRecord {
+                file_id: files::new_file_id(path),
+                hash: observed.hash.clone(),
+                size: observed.size,
+                mtime: observed.mtime,
+                base: None,
+            };
+            let file_id = record.file_id.clone();
+            index.files.insert(path.to_string(), record);
+            Some((file_id, OpKind::Create { path: path.to_string(), hash: observed.hash }))
+        }
+    }
+}
+
+/// Records a change made by a vault command, while a provider is enabled.
+/// Failures are logged rather than returned: the command itself succeeded,
+/// and the next scan records it.
+pub fn observe<F>(root: &Path, record: F)
+where
+    F: FnOnce(&SyncCore) -> Result<Vec<SyncOp>, String>,
+{
+    if !any_provider_enabled() {
+        return;
+    }
+    if let Err(e) = record(&SyncCore::open(root)) {
+        eprintln!("Failed to record sync operation: {}", e);
+    }
+}
+
+/// Scans the open vault periodically for changes made outside the app, such
+/// as by another editor or a file sync tool, while a provider is enabled. The
+/// vault is re-read on every scan, as it can change while the app runs.
+pub fn spawn_watcher(vault_config_path: PathBuf) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(SCAN_INTERVAL);
+        if !any_provider_enabled() {
+            continue;
+        }
+        if let Ok(root) = crate::vault::read_vault_root(&vault_config_path) {
+            if let Err(e) = SyncCore::open(&root).scan() {
+                eprintln!("Sync scan failed: {}", e);
+            }
+        }
+    });
+}
+
+fn vault_core(app: &AppHandle) -> Result<SyncCore, String> {
+    let config = crate::vault::get_vault_config(app.clone()).ok_or("No vault configured".to_string())?;
+    let root = Path::new(&config.root_path);
+    if !root.exists() {
+        return Err("Vault root does not exist".to_string());
+    }
+    Ok(SyncCore::open(root))
+}
+
+#[tauri::command]
+pub fn sync_pending_ops(app: AppHandle, cursor: Option<u64>, limit: Option<usize>) -> Result<OpsPage, String> {
+    vault_core(&app)?.pending(cursor.unwrap_or(0), limit.unwrap_or(DEFAULT_PAGE_SIZE))
+}
+
+#[tauri::command]
+pub fn sync_compact(app: AppHandle, acked: u64) -> Result<usize, String> {
+    vault_core(&app)?.compact(acked)
+}
+
+#[tauri::command]
+pub fn sync_conflicts(app: AppHandle, include_resolved: Option<bool>) -> Result<Vec<ConflictRecord>, String> {
+    let conflicts = vault_core(&app)?.conflicts()?;
+    Ok(conflicts
+        .into_iter()
+        .filter(|c| include_resolved.unwrap_or(false) || c.status == ConflictStatus::Unresolved)
+        .collect())
+}
+
+#[tauri::command]
+pub fn sync_resolve_conflict(app: AppHandle, id: String, delete_copy: Option<bool>) -> Result<ConflictRecord, String> {
+    vault_core(&app)?.resolve_conflict(&id, delete_copy.unwrap_or(false))
+}
+
+#[tauri::command]
+pub fn sync_scan(app: AppHandle) -> Result<Vec<SyncOp>, String> {
+    vault_core(&app)?.scan()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(ops: &[SyncOp]) -> Vec<OpKind> {
+        ops.iter().map(|op| op.kind.clone()).collect()
+    }
+
+    #[test]
+    fn test_is_tracked() {
+        assert!(is_tracked("notes/a.md"));
+        assert!(is_tracked(".plugins/core.tts.json"));
+        assert!(is_tracked(".liminal/spellcheck/words.txt"));
+        assert!(!is_tracked(".sync/files.json"));
+        assert!(!is_tracked(".liminal/history/a.md"));
+        assert!(!is_tracked("notes/.DS_Store"));
+        assert!(may_contain_tracked(".liminal"));
+        assert!(!may_contain_tracked(".obsidian"));
+        assert_eq!(normalize(".\\notes//a.md/"), "notes/a.md");
+    }
+
+    #[test]
+    fn test_conflict_path() {
+        let dir = tempfile::tempdir().unwrap();
+        let at = 1_765_921_320_000;
+        let path = conflict_path(dir.path(), "notes/Note.md", "Pixel/Laptop", at);
+        assert_eq!(path, "notes/Note (Conflict — PixelLaptop — 2025-12-16 2142).md");
+        fs::create_dir_all(dir.path().join("notes")).unwrap();
+        fs::write(dir.path().join(&path), "").unwrap();
+        assert_eq!(
+            conflict_path(dir.path(), "notes/Note.md", "PixelLaptop", at),
+            "notes/Note (Conflict — PixelLaptop — 2025-12-16 2142 2).md"
+        );
+        assert_eq!(conflict_path(dir.path(), "README", "A", 0), "README (Conflict — A — 1970-01-01 0000)");
+        assert_eq!(format_iso(at + 5_000), "2025-12-16T21:42:05Z");
+    }
+
+    #[test]
+    fn test_records_and_resolves_conflicts() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = SyncCore::open(dir.path());
+        fs::write(dir.path().join("a.md"), "theirs\n").unwrap();
+        let copy = conflict_path(dir.path(), "a.md", "Laptop", 0);
+        fs::write(dir.path().join(&copy), "mine\n").unwrap();
+        core.record_write("a.md").unwrap();
+        core.record_write(&copy).unwrap();
+
+        let record = core.record_conflict("a.md", &copy, "git").unwrap();
+        assert_eq!(record.status, ConflictStatus::Unresolved);
+        assert_eq!(core.conflicts().unwrap(), vec![record.clone()]);
+
+        let resolved = core.resolve_conflict(&record.id, true).unwrap();
+        assert_eq!(resolved.status, ConflictStatus::Resolved);
+        assert!(!dir.path().join(&copy).exists());
+        assert!(core.index().unwrap().files.contains_key("a.md"));
+        assert!(!core.index().unwrap().files.contains_key(&copy));
+    }
+
+    #[test]
+    fn test_records_commands_and_scans() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = SyncCore::open(dir.path());
+        fs::create_dir_all(dir.path().join("notes")).unwrap();
+        fs::write(dir.path().join("notes/a.md"), "one").unwrap();
+
+        let created = core.record_write("notes/a.md").unwrap();
+        let file_id = created[0].file_id.clone();
+        assert!(matches!(&created[0].kind, OpKind::Create { path, .. } if path == "notes/a.md"));
+        assert!(core.record_write("notes/a.md").unwrap().is_empty());
+
+        fs::rename(dir.path().join("notes"), dir.path().join("archive")).unwrap();
+        let moved = core.record_move("notes", "archive").unwrap();
+        assert_eq!(kinds(&moved), vec![OpKind::Move { from: "notes/a.md".into(), to: "archive/a.md".into() }]);
+        assert_eq!(moved[0].file_id, file_id);
+
+        // Outside the app: a rename, a new file and an edit.
+        fs::rename(dir.path().join("archive/a.md"), dir.path().join("archive/b.md")).unwrap();
+        fs::write(dir.path().join("c.md"), "three").unwrap();
+        fs::write(dir.path().join(".sync/ignored"), "x").unwrap();
+        let scanned = core.scan().unwrap();
+        assert_eq!(scanned.len(), 2);
+        assert_eq!(scanned[0].kind, OpKind::Move { from: "archive/a.md".into(), to: "archive/b.md".into() });
+        assert_eq!(scanned[0].file_id, file_id);
+        assert!(matches!(&scanned[1].kind, OpKind::Create { path, .. } if path == "c.md"));
+        assert!(core.scan().unwrap().is_empty());
+
+        fs::remove_file(dir.path().join("c.md")).unwrap();
+        assert_eq!(kinds(&core.record_delete("c.md").unwrap()), vec![OpKind::Delete { path: "c.md".into() }]);
+
+        let page = core.pending(1, 10).unwrap();
+        assert_eq!(page.ops.iter().map(|op| op.seq).collect::<Vec<_>>(), vec![2, 3, 4, 5]);
+        // The new file was created and deleted again, so only the move is left.
+        assert_eq!(core.compact(1).unwrap(), 1);
+        assert_eq!(
+            kinds(&core.pending(0, 10).unwrap().ops),
+            vec![OpKind::Move { from: "notes/a.md".into(), to: "archive/b.md".into() }]
+        );
+    }
+
+    #[test]
+    fn test_compact_keeps_ops_other_providers_need() {
+        let dir = tempfile::tempdir().unwrap();
+        let core = SyncCore::open(dir.path());
+        for name in ["a.md", "b.md", "c.md"] {
+            fs::write(dir.path().join(name), name).unwrap();
+            core.record_write(name).unwrap();
+        }
+        let state = |acked| ProviderState { acked, ..Default::default() };
+        set_provider_enabled("folder", true);
+        set_provider_enabled("webdav", true);
+        core.save_provider_state("folder", &state(3)).unwrap();
+        core.save_provider_state("webdav", &state(1)).unwrap();
+
+        assert_eq!(core.compact(3).unwrap(), 2);
+        assert_eq!(core.pending(1, 10).unwrap().ops.len(), 2);
+        core.save_provider_state("webdav", &state(3)).unwrap();
+        assert_eq!(core.compact(3).unwrap(), 0);
+
+        // The folder provider has taken the create and the first move; the
+        // delete and the second move must still reach it after the WebDAV
+        // provider, further behind, compacts.
+        fs::write(dir.path().join("d.md"), "d").unwrap();
+        core.record_write("d.md").unwrap();
+        fs::rename(dir.path().join("a.md"), dir.path().join("x.md")).unwrap();
+        core.record_move("a.md", "x.md").unwrap();
+        core.save_provider_state("folder", &state(5)).unwrap();
+        fs::remove_file(dir.path().join("d.md")).unwrap();
+        core.record_delete("d.md").unwrap();
+        fs::rename(dir.path().join("x.md"), dir.path().join("y.md")).unwrap();
+        core.record_move("x.md", "y.md").unwrap();
+        core.compact(3).unwrap();
+        assert_eq!(
+            kinds(&core.pending(5, 10).unwrap().ops),
+            vec![OpKind::Delete { path: "d.md".into() }, OpKind::Move { from: "x.md".into(), to: "y.md".into() }]
+        );
+        assert_eq!(core.pending(3, 10).unwrap().ops.len(), 4);
+
+        // A disabled provider behind the others holds nothing back, and
+        // forgets its place.
+        core.save_provider_state("git", &state(1)).unwrap();
+        core.save_provider_state("webdav", &state(7)).unwrap();
+        core.save_provider_state("folder", &state(7)).unwrap();
+        assert_eq!(core.compact(7).unwrap(), 0);
+        assert!(!dir.path().join(".sync/providers/git.json").exists());
+        assert!(dir.path().join(".sync/providers/folder.json").exists());
+    }
+}
@@ -0,0 +1,281 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+
+use super::files::BaseRef;
+
+const OPS_FILE: &str = "ops.log";
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum OpKind {
+    Create {
+        path: String,
+        hash: String,
+    },
+    Update {
+        path: String,
+        hash: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        base: Option<BaseRef>,
+    },
+    Delete {
+        path: String,
+    },
+    Move {
+        from: String,
+        to: String,
+    },
+}
+
+/// One line of `.sync/ops.log`. `seq` increases by one per recorded op and
+/// is the cursor providers pull from; `at` is in ms since the epoch.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncOp {
+    pub seq: u64,
+    pub file_id: String,
+    pub at: u64,
+    #[serde(flatten)]
+    pub kind: OpKind,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct OpsPage {
+    pub ops: Vec<SyncOp>,
+    /// Cursor to pass next time: the last returned `seq`, or the given cursor
+    /// when there is nothing new.
+    pub next_cursor: u64,
+    pub has_more: bool,
+}
+
+pub fn append(sync_dir: &Path, ops: &[SyncOp]) -> Result<(), String> {
+    if ops.is_empty() {
+        return Ok(());
+    }
+    let lines = to_lines(ops)?;
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(sync_dir.join(OPS_FILE))
+        .map_err(|e| e.to_string())?;
+    file.write_all(lines.as_bytes()).map_err(|e| e.to_string())
+}
+
+/// Every op in the log. A line that does not parse, such as one torn by a
+/// crash mid-write, is skipped; the next scan records the change again.
+pub fn read(sync_dir: &Path) -> Result<Vec<SyncOp>, String> {
+    let path = sync_dir.join(OPS_FILE);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    Ok(content
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// `seq` of a log line, read without parsing the rest: it is always written
+/// first.
+fn line_seq(line: &str) -> Option<u64> {
+    let rest = line.strip_prefix("{\"seq\":")?;
+    let end = rest.find(|c: char| !c.is_ascii_digit())?;
+    rest[..end].parse().ok()
+}
+
+/// Ops after `cursor`, oldest first. Lines at or before the cursor are
+/// skipped without being parsed, and reading stops after `limit`.
+pub fn since(sync_dir: &Path, cursor: u64, limit: usize) -> Result<OpsPage, String> {
+    let path = sync_dir.join(OPS_FILE);
+    if !path.exists() {
+        return Ok(OpsPage { ops: Vec::new(), next_cursor: cursor, has_more: false });
+    }
+    let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+    let mut ops: Vec<SyncOp> = content
+        .lines()
+        .filter(|line| line_seq(line).is_none_or(|seq| seq > cursor))
+        .filter_map(|line| serde_json::from_str::<SyncOp>(line).ok())
+        .filter(|op| op.seq > cursor)
+        .take(limit.saturating_add(1))
+        .collect();
+    let has_more = ops.len() > limit;
+    ops.truncate(limit);
+    let next_cursor = ops.last().map_or(cursor, |op| op.seq);
+    Ok(OpsPage { ops, next_cursor, has_more })
+}
+
+pub fn rewrite(sync_dir: &Path, ops: &[SyncOp]) -> Result<(), String> {
+    let tmp = sync_dir.join(format!("{}.tmp", OPS_FILE));
+    fs::write(&tmp, to_lines(ops)?).map_err(|e| e.to_string())?;
+    fs::rename(&tmp, sync_dir.join(OPS_FILE)).map_err(|e| e.to_string())
+}
+
+fn to_lines(ops: &[SyncOp]) -> Result<String, String> {
+    let mut lines = String::new();
+    for op in ops {
+        lines.push_str(&serde_json::to_string(op).map_err(|e| e.to_string())?);
+        lines.push('\n');
+    }
+    Ok(lines)
+}
+
+/// Drops ops up to `floor`, keeps those up to `ceiling` as they are, and
+/// reduces the rest to their net effect per file: a create followed by
+/// updates is one create, a file created and deleted again disappears, and
+/// chained moves become one. Ops some reader has already taken (up to
+/// `ceiling`) are never coalesced, as that reader would miss the rest of
+/// the chain. Surviving ops keep the latest sequence numbers of their file,
+/// so pending cursors stay valid.
+pub fn compact(ops: Vec<SyncOp>, floor: u64, ceiling: u64) -> Vec<SyncOp> {
+    let mut kept: Vec<SyncOp> = Vec::new();
+    let mut order: Vec<String> = Vec::new();
+    let mut groups: HashMap<String, Vec<SyncOp>> = HashMap::new();
+    for op in ops.into_iter().filter(|op| op.seq > floor) {
+        if op.seq <= ceiling {
+            kept.push(op);
+            continue;
+        }
+        if !groups.contains_key(&op.file_id) {
+            order.push(op.file_id.clone());
+        }
+        groups.entry(op.file_id.clone()).or_default().push(op);
+    }
+
+    let mut compacted: Vec<SyncOp> = order
+        .into_iter()
+        .flat_map(|file_id| {
+            let group = groups.remove(&file_id).unwrap_or_default();
+            net_effect(&group)
+        })
+        .collect();
+    kept.append(&mut compacted);
+    kept.sort_by_key(|op| op.seq);
+    kept
+}
+
+fn net_effect(group: &[SyncOp]) -> Vec<SyncOp> {
+    let Some(first) = group.first() else {
+        return Vec::new();
+    };
+    let existed = !matches!(first.kind, OpKind::Create { .. });
+    let original = match &first.kind {
+        OpKind::Create { path, .. } | OpKind::Update { path, .. } | OpKind::Delete { path } => path.clone(),
+        OpKind::Move { from, .. } => from.clone(),
+    };
+
+    let mut current = Some(original.clone());
+    let mut hash = None;
+    let mut base = None;
+    let mut updated = false;
+    for op in group {
+        match &op.kind {
+            OpKind::Create { path, hash: h } => {
+                current = Some(path.clone());
+                hash = Some(h.clone());
+            }
+            OpKind::Update { path, hash: h, base: b } => {
+                if !updated {
+                    base = b.clone();
+                }
+                updated = true;
+                current = Some(path.clone());
+                hash = Some(h.clone());
+            }
+            OpKind::Delete { .. } => current = None,
+            OpKind::Move { to, .. } => current = Some(to.clone()),
+        }
+    }
+
+    let kinds = match (existed, current) {
+        (false, None) => Vec::new(),
+        (false, Some(path)) => vec![OpKind::Create { path, hash: hash.unwrap_or_default() }],
+        (true, None) => vec![OpKind::Delete { path: original }],
+        (true, Some(path)) => {
+            let mut kinds = Vec::new();
+            if path != original {
+                kinds.push(OpKind::Move { from: original, to: path.clone() });
+            }
+            if let (true, Some(hash)) = (updated, hash) {
+                kinds.push(OpKind::Update { path, hash, base });
+            }
+            kinds
+        }
+    };
+
+    let tail = &group[group.len() - kinds.len()..];
+    tail.iter()
+        .zip(kinds)
+        .map(|(op, kind)| SyncOp { seq: op.seq, file_id: op.file_id.clone(), at: op.at, kind })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn op(seq: u64, file_id: &str, kind: OpKind) -> SyncOp {
+        SyncOp { seq, file_id: file_id.to_string(), at: seq * 1000, kind }
+    }
+
+    fn update(path: &str, hash: &str) -> OpKind {
+        OpKind::Update { path: path.into(), hash: hash.into(), base: None }
+    }
+
+    #[test]
+    fn test_compact_reduces_ops_to_net_effect() {
+        let ops = vec![
+            op(1, "a", OpKind::Create { path: "a.md".into(), hash: "1".into() }),
+            op(2, "b", update("b.md", "1")),
+            op(3, "a", update("a.md", "2")),
+            op(4, "c", OpKind::Create { path: "tmp.md".into(), hash: "1".into() }),
+            op(5, "b", OpKind::Move { from: "b.md".into(), to: "x/b.md".into() }),
+            op(6, "c", OpKind::Delete { path: "tmp.md".into() }),
+            op(7, "b", update("x/b.md", "2")),
+            op(8, "d", OpKind::Move { from: "d.md".into(), to: "e.md".into() }),
+            op(9, "d", OpKind::Move { from: "e.md".into(), to: "d.md".into() }),
+        ];
+
+        assert_eq!(
+            compact(ops.clone(), 0, 0),
+            vec![
+                op(3, "a", OpKind::Create { path: "a.md".into(), hash: "2".into() }),
+                op(5, "b", OpKind::Move { from: "b.md".into(), to: "x/b.md".into() }),
+                op(7, "b", update("x/b.md", "2")),
+            ]
+        );
+        // Acknowledged ops are dropped before coalescing.
+        assert_eq!(compact(ops.clone(), 6, 6), vec![op(7, "b", update("x/b.md", "2"))]);
+        // A reader at 5 has taken the create of `c`, so its delete stays
+        // even though another reader at 3 is behind both.
+        let compacted = compact(ops, 3, 5);
+        let seqs: Vec<u64> = compacted.iter().map(|op| op.seq).collect();
+        assert_eq!(seqs, vec![4, 5, 6, 7]);
+    }
+
+    #[test]
+    fn test_log_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let ops = vec![
+            op(1, "a", OpKind::Create { path: "a.md".into(), hash: "1".into() }),
+            op(2, "a", OpKind::Delete { path: "a.md".into() }),
+        ];
+        append(dir.path(), &ops).unwrap();
+        std::fs::OpenOptions::new()
+            .append(true)
+            .open(dir.path().join(OPS_FILE))
+            .unwrap()
+            .write_all(b"{\"seq\":3,\"fil")
+            .unwrap();
+
+        assert_eq!(read(dir.path()).unwrap(), ops);
+        assert_eq!(line_seq(&serde_json::to_string(&ops[1]).unwrap()), Some(2));
+        let page = since(dir.path(), 0, 1).unwrap();
+        assert_eq!((page.ops.len(), page.next_cursor, page.has_more), (1, 1, true));
+        let page = since(dir.path(), 2, 10).unwrap();
+        assert_eq!((page.ops.len(), page.next_cursor, page.has_more), (0, 2, false));
+    }
+}
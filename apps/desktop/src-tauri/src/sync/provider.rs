@@ -0,0 +1,196 @@
+// Settings, scheduling and bookkeeping shared by the sync provider plugins.
+// Each provider supplies a config reader and a sync closure; the runner
+// debounces local changes, serializes syncs and remembers the outcome.
+
+use serde_json::Value;
+use std::collections::HashMap;
+use std::future::Future;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::{Arc, Weak};
+use std::time::{Duration, Instant};
+use tokio::sync::{Mutex, MutexGuard};
+
+use super::SyncCore;
+
+/// How often the debouncer looks at the op log.
+const CHECK_INTERVAL: Duration = Duration::from_secs(5);
+
+/// `plugins.core.sync.<provider>.*` settings.
+pub struct ProviderSettings<'a> {
+    settings: &'a HashMap<String, Value>,
+    provider: &'a str,
+}
+
+impl<'a> ProviderSettings<'a> {
+    pub fn new(settings: &'a HashMap<String, Value>, provider: &'a str) -> Self {
+        Self { settings, provider }
+    }
+
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.settings.get(&format!("plugins.core.sync.{}.{}", self.provider, key))
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.get("enabled").and_then(|v| v.as_bool()).unwrap_or(false)
+    }
+
+    /// A trimmed string; `None` when unset or blank.
+    pub fn text(&self, key: &str) -> Option<String> {
+        self.get(key)
+            .and_then(|v| v.as_str())
+            .map(|s| s.trim().to_string())
+            .filter(|s| !s.is_empty())
+    }
+
+    pub fn secs(&self, key: &str, default: u64) -> u64 {
+        self.get(key).and_then(|v| v.as_u64()).unwrap_or(default)
+    }
+}
+
+/// What the runner needs to know from a provider's config.
+pub trait ProviderConfig: Clone + Send + 'static {
+    /// Enabled, and set up enough to sync.
+    fn ready(&self) -> bool;
+    /// Quiet time after the last local change before syncing.
+    fn debounce_secs(&self) -> u64;
+    /// How often to sync for other devices' changes; `None` when there is
+    /// nowhere to pull them from.
+    fn interval_secs(&self) -> Option<u64>;
+}
+
+pub type SyncFuture = Pin<Box<dyn Future<Output = Result<Value, String>> + Send>>;
+/// One sync of the vault at the given root, returning the provider's report.
+pub type SyncFn<C> = Box<dyn Fn(PathBuf, C) -> SyncFuture + Send + Sync>;
+
+/// Outcome of the last sync, for `status`. Conflicts are kept by SyncCore.
+#[derive(Clone, Default)]
+pub struct LastSync {
+    pub last_sync_ms: Option<u64>,
+    pub last_error: Option<String>,
+}
+
+pub struct ProviderRunner<C> {
+    /// Name used for the provider's state file and in conflict records.
+    pub id: &'static str,
+    settings_path: PathBuf,
+    vault_config_path: PathBuf,
+    read_config: fn(&HashMap<String, Value>) -> C,
+    sync: SyncFn<C>,
+    last: Mutex<LastSync>,
+    /// Held while a sync runs, so the debouncer and manual syncs never
+    /// overlap.
+    busy: Mutex<()>,
+}
+
+impl<C: ProviderConfig> ProviderRunner<C> {
+    pub fn new(
+        id: &'static str,
+        settings_path: PathBuf,
+        vault_config_path: PathBuf,
+        read_config: fn(&HashMap<String, Value>) -> C,
+        sync: SyncFn<C>,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            id,
+            settings_path,
+            vault_config_path,
+            read_config,
+            sync,
+            last: Mutex::new(LastSync::default()),
+            busy: Mutex::new(()),
+        })
+    }
+
+    /// Settings are re-read every time, as the user may change them while
+    /// the provider is active.
+    pub fn config(&self) -> Result<C, String> {
+        let settings = crate::settings::read_settings_file(&self.settings_path)?;
+        Ok((self.read_config)(&settings))
+    }
+
+    /// The open vault, which can change while the app runs.
+    pub fn root(&self) -> Result<PathBuf, String> {
+        crate::vault::read_vault_root(&self.vault_config_path)
+    }
+
+    /// Waits for any running sync, for other work on the provider's remote.
+    pub async fn lock(&self) -> MutexGuard<'_, ()> {
+        self.busy.lock().await
+    }
+
+    pub async fn last_sync(&self) -> LastSync {
+        self.last.lock().await.clone()
+    }
+
+    /// Runs one sync and records its outcome.
+    pub async fn sync(&self, root: PathBuf, config: C) -> Result<Value, String> {
+        let _busy = self.busy.lock().await;
+        let result = (self.sync)(root, config).await;
+
+        let mut last = self.last.lock().await;
+        match &result {
+            Ok(_) => {
+                last.last_sync_ms = Some(super::now_ms());
+                last.last_error = None;
+            }
+            Err(e) => last.last_error = Some(e.clone()),
+        }
+        result
+    }
+
+    /// Syncs once the op log has been quiet for the debounce time, and every
+    /// interval to pick up other devices' changes. Settings and the vault are
+    /// re-read on every check. Ends with the runner.
+    pub fn spawn_debouncer(self: &Arc<Self>) {
+        // Registered right away, so a sync by another provider doesn't take
+        // this one for disabled and forget its state.
+        if let Ok(config) = self.config() {
+            super::set_provider_enabled(self.id, config.ready());
+        }
+        let runner: Weak<Self> = Arc::downgrade(self);
+        tauri::async_runtime::spawn(async move {
+            let mut seen_seq = None;
+            let mut changed_at = Instant::now();
+            let mut pending = true;
+            let mut last_sync = Instant::now();
+            loop {
+                tokio::time::sleep(CHECK_INTERVAL).await;
+                let Some(runner) = runner.upgrade() else {
+                    break;
+                };
+                let Ok(config) = runner.config() else {
+                    continue;
+                };
+                super::set_provider_enabled(runner.id, config.ready());
+                let Ok(root) = runner.root() else {
+                    continue;
+                };
+                if !config.ready() {
+                    continue;
+                }
+
+                let seq = SyncCore::open(&root).index().map(|index| index.last_seq).ok();
+                if seq != seen_seq {
+                    seen_seq = seq;
+                    changed_at = Instant::now();
+                    pending = true;
+                    continue;
+                }
+                let quiet = changed_at.elapsed() >= Duration::from_secs(config.debounce_secs());
+                let due = config
+                    .interval_secs()
+                    .is_some_and(|secs| last_sync.elapsed() >= Duration::from_secs(secs));
+                if !due && (!pending || !quiet) {
+                    continue;
+                }
+
+                pending = false;
+                last_sync = Instant::now();
+                if let Err(e) = runner.sync(root, config).await {
+                    eprintln!("Sync with {} failed: {}", runner.id, e);
+                }
+            }
+        });
+    }
+}
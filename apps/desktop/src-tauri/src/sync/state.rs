@@ -0,0 +1,74 @@
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use super::files::BaseRef;
+
+const PROVIDERS_DIR: &str = "providers";
+
+/// `.sync/providers/<id>.json`: how far a provider has synced. `cursor` is
+/// the provider's own position, `acked` the last local op it has taken, and
+/// `synced` what both sides agreed each path held after the last sync.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderState {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote_id: Option<String>,
+    pub cursor: u64,
+    pub acked: u64,
+    pub synced: BTreeMap<String, BaseRef>,
+    /// Provider revisions of folders, for providers that skip unchanged ones.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub folders: BTreeMap<String, String>,
+}
+
+impl ProviderState {
+    pub fn load(sync_dir: &Path, provider: &str) -> Result<Self, String> {
+        let path = sync_dir.join(PROVIDERS_DIR).join(format!("{}.json", provider));
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| format!("Invalid sync state for {}: {}", provider, e))
+    }
+
+    pub fn save(&self, sync_dir: &Path, provider: &str) -> Result<(), String> {
+        let dir = sync_dir.join(PROVIDERS_DIR);
+        fs::create_dir_all(&dir).map_err(|e| e.to_string())?;
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp = dir.join(format!("{}.json.tmp", provider));
+        fs::write(&tmp, content).map_err(|e| e.to_string())?;
+        fs::rename(&tmp, dir.join(format!("{}.json", provider))).map_err(|e| e.to_string())
+    }
+
+    /// `acked` of every provider with saved state.
+    pub fn acked_by_provider(sync_dir: &Path) -> Result<Vec<(String, u64)>, String> {
+        let dir = sync_dir.join(PROVIDERS_DIR);
+        if !dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut acked = Vec::new();
+        for entry in fs::read_dir(&dir).map_err(|e| e.to_string())? {
+            let path = entry.map_err(|e| e.to_string())?.path();
+            let Some(provider) = path.file_name().and_then(|n| n.to_str()).and_then(|n| n.strip_suffix(".json")) else {
+                continue;
+            };
+            acked.push((provider.to_string(), Self::load(sync_dir, provider)?.acked));
+        }
+        Ok(acked)
+    }
+
+    /// Forgets how far `provider` has synced; its next sync starts afresh.
+    pub fn remove(sync_dir: &Path, provider: &str) -> Result<(), String> {
+        match fs::remove_file(sync_dir.join(PROVIDERS_DIR).join(format!("{}.json", provider))) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    /// The content hash `path` had when last synced.
+    pub fn base_hash(&self, path: &str) -> Option<&str> {
+        self.synced.get(path).and_then(|base| base.content_hash.as_deref())
+    }
+}
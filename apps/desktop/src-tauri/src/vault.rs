@@ -26,17 +26,42 @@ fn is_hidden(entry: &DirEntry) -> bool {
         .unwrap_or(false)
 }
 
+pub fn vault_config_path_in(config_dir: &Path) -> PathBuf {
+    config_dir.join("vault.json")
+}
+
 // Helper to get path to vault.json
 fn get_config_path(app: &AppHandle) -> Result<PathBuf, String> {
     let config_dir = app.path().app_config_dir().map_err(|e| e.to_string())?;
     if !config_dir.exists() {
         fs::create_dir_all(&config_dir).map_err(|e| e.to_string())?;
     }
-    Ok(config_dir.join("vault.json"))
+    Ok(vault_config_path_in(&config_dir))
+}
+
+/// Reads the configured vault root for backend code running without an `AppHandle`.
+pub fn read_vault_root(config_path: &Path) -> Result<PathBuf, String> {
+    let content = fs::read_to_string(config_path).map_err(|_| "No vault configured".to_string())?;
+    let config: VaultConfig = serde_json::from_str(&content).map_err(|e| e.to_string())?;
+    let root = PathBuf::from(config.root_path);
+    if !root.exists() {
+        return Err("Vault root does not exist".to_string());
+    }
+    Ok(root)
+}
+
+/// The configured vault root, for commands.
+pub fn vault_root(app: AppHandle) -> Result<PathBuf, String> {
+    let config = get_vault_config(app).ok_or("No vault configured".to_string())?;
+    let root = PathBuf::from(config.root_path);
+    if !root.exists() {
+        return Err("Vault root does not exist".to_string());
+    }
+    Ok(root)
 }
 
 // Helper to safely resolve a relative path within the vault root
-fn resolve_safe_path(root: &Path, relative_path: &str) -> Result<PathBuf, String> {
+pub fn resolve_safe_path(root: &Path, relative_path: &str) -> Result<PathBuf, String> {
     let path = Path::new(relative_path);
     if path.is_absolute() {
         return Err("Path must be relative".to_string());
@@ -177,7 +202,11 @@ pub fn write_note_command(app: AppHandle, relative_path: String, contents: Strin
         }
     }
 
-    fs::write(full_path, contents).map_err(|e| e.to_string())
+    let previous = fs::read(&full_path).ok();
+    fs::write(full_path, &contents).map_err(|e| e.to_string())?;
+    crate::sync::observe(root, |core| core.record_write(&relative_path));
+    crate::history::record_write(root, &relative_path, previous.as_deref(), contents.as_bytes());
+    Ok(())
 }
 
 #[tauri::command]
@@ -207,7 +236,10 @@ pub fn rename_item(app: AppHandle, old_path: String, new_path: String) -> Result
         }
     }
 
-    fs::rename(full_old_path, full_new_path).map_err(|e| e.to_string())
+    fs::rename(full_old_path, full_new_path).map_err(|e| e.to_string())?;
+    crate::sync::observe(root, |core| core.record_move(&old_path, &new_path));
+    crate::history::record_move(root, &old_path, &new_path);
+    Ok(())
 }
 
 #[tauri::command]
@@ -226,10 +258,12 @@ pub fn delete_item(app: AppHandle, path: String) -> Result<(), String> {
     }
 
     if full_path.is_dir() {
-        fs::remove_dir_all(full_path).map_err(|e| e.to_string())
+        fs::remove_dir_all(full_path).map_err(|e| e.to_string())?;
     } else {
-        fs::remove_file(full_path).map_err(|e| e.to_string())
+        fs::remove_file(full_path).map_err(|e| e.to_string())?;
     }
+    crate::sync::observe(root, |core| core.record_delete(&path));
+    Ok(())
 }
 
 #[cfg(test)]
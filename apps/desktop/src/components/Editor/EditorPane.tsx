@@ -630,6 +630,7 @@ export function EditorPane({ onRefreshFiles }: EditorPaneProps) {
       }
   }, []);
 
+  const note = activeTab?.path || undefined;
   const getTtsData = useCallback(() => {
       // Return content or selection
       if (editorRef.current) {
@@ -642,19 +643,18 @@ export function EditorPane({ onRefreshFiles }: EditorPaneProps) {
               if (anchor !== head) {
                   const start = Math.min(anchor, head);
                   const end = Math.max(anchor, head);
-                  const selection = doc.slice(start, end);
-                  if (selection.trim().length > 0) {
-                      return { text: selection };
+                  if (doc.slice(start, end).trim().length > 0) {
+                      return { text: doc, range: { start, end }, note };
                   }
               }
-              return { text: doc };
+              return { text: doc, note };
           } catch (e) {
               console.error("Failed to parse editor state for TTS", e);
-              return { text: content };
+              return { text: content, note };
           }
       }
-      return { text: content };
-  }, [content]);
+      return { text: content, note };
+  }, [content, note]);
 
   return (
     <div className="editor-container">
@@ -1,10 +1,12 @@
 import React, { useEffect } from 'react';
 import { useTts } from './useTts';
+import { ChunkRange } from './ttsEngines';
 import { useSettings } from '../../contexts/SettingsContext';
 
 interface TtsPlayerProps {
     onHighlight?: (range: { from: number; to: number } | null) => void;
-    getData: () => { text: string };
+    /** The note's text, the range of it to read, and the note's path. */
+    getData: () => { text: string; range?: ChunkRange; note?: string };
 }
 
 export const TtsPlayer: React.FC<TtsPlayerProps> = ({ onHighlight, getData }) => {
@@ -14,26 +16,29 @@ export const TtsPlayer: React.FC<TtsPlayerProps> = ({ onHighlight, getData }) =>
   // Read defaults directly, no local state needed for controls as they are hidden
   const voice = (settings['tts.defaultVoice'] as string) || 'af_sky';
   const speed = (settings['tts.defaultSpeed'] as number) || 1.0;
+  const continueToEnd = Boolean(settings['tts.continueToEnd']);
 
   // Sync highlighting
   useEffect(() => {
       if (onHighlight) {
-          if (currentSegment) {
+          // Playback continues across notes; only highlight in the one being read.
+          const note = getData().note;
+          if (currentSegment && (!currentSegment.note || !note || currentSegment.note === note)) {
               onHighlight({ from: currentSegment.startChar, to: currentSegment.endChar });
           } else {
               onHighlight(null);
           }
       }
-  }, [currentSegment, onHighlight]);
+  }, [currentSegment, onHighlight, getData]);
 
   const handlePlay = () => {
-      const { text } = getData();
+      const { text, range, note } = getData();
       if (text) {
-          speak(text, voice, speed);
+          speak(text, voice, speed, { range, note, continueToEnd });
       }
   };
 
-  if (!status.installed) {
+  if (!(status.ready ?? status.installed)) {
     return (
       <div className="p-2 border-t flex items-center justify-between text-xs" style={{ borderColor: 'var(--ln-border)', background: 'var(--ln-bg)', color: 'var(--ln-fg)' }}>
           <span className="flex-1">TTS not installed. Install from Settings → Read Aloud.</span>
@@ -81,6 +81,12 @@ export const ttsPlugin: LiminalPlugin = {
                         step: 0.1
                     }]
                 },
+                {
+                    id: 'tts-continue',
+                    label: 'Continue Past Selection',
+                    description: 'After reading a selection, keep reading to the end of the note.',
+                    controls: [{ kind: 'boolean', key: 'tts.continueToEnd' }]
+                },
                 {
                     id: 'tts-preview',
                     label: 'Voice Preview',
@@ -0,0 +1,62 @@
+import { invoke } from '@tauri-apps/api/core';
+import { listen, UnlistenFn } from '@tauri-apps/api/event';
+import { TtsSegment } from './types';
+
+const PLUGIN_ID = 'core.tts';
+
+/** Cached audio to queue; `offset` is where its text starts in the note (UTF-16). */
+export interface PlaybackItem {
+  path: string;
+  offset?: number;
+  note?: string;
+}
+
+export interface PlaybackState {
+  playing: boolean;
+  /** Played past the last queued track; enqueueing more carries on. */
+  ended: boolean;
+  rate: number;
+  track: number;
+  ms: number;
+  queueMs: number;
+  durationMs: number;
+  segment: number | null;
+  tracks: Array<{ id: string; note: string | null; durationMs: number; segments: number }>;
+}
+
+export interface PlaybackSegmentEvent {
+  track: number;
+  index: number | null;
+  note: string | null;
+  segment: TtsSegment | null;
+}
+
+export interface PlaybackPositionEvent {
+  track: number;
+  note: string | null;
+  ms: number;
+  queueMs: number;
+}
+
+/** Calls a `playback_*` method of the backend player. */
+export async function playback(method: string, payload: Record<string, unknown> = {}): Promise<PlaybackState> {
+  const res = await invoke<any>('native_plugin_invoke', {
+    pluginId: PLUGIN_ID,
+    method: `playback_${method}`,
+    requestId: Math.random().toString(),
+    payload
+  });
+  if (!res.ok) {
+    throw new Error(res.error?.message || 'Playback failed');
+  }
+  return res.result as PlaybackState;
+}
+
+export const onPlaybackState = (handler: (state: PlaybackState) => void): Promise<UnlistenFn> =>
+  listen<PlaybackState>('tts://playback-state', event => handler(event.payload));
+
+export const onPlaybackSegment = (handler: (event: PlaybackSegmentEvent) => void): Promise<UnlistenFn> =>
+  listen<PlaybackSegmentEvent>('tts://playback-segment', event => handler(event.payload));
+
+export const onPlaybackPosition = (handler: (event: PlaybackPositionEvent) => void): Promise<UnlistenFn> =>
+  listen<PlaybackPositionEvent>('tts://playback-position', event => handler(event.payload));
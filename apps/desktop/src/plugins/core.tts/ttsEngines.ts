@@ -5,17 +5,34 @@ import { TtsResult, TtsSegment } from './types';
 const PLUGIN_ID = 'core.tts';
 
 export interface EngineChunkResult {
+  /** Cached audio file, for queueing on the backend player. */
+  path: string;
   url: string;
   revoke?: () => void;
   segments: TtsSegment[];
 }
 
-export async function synthesiseChunk(text: string, voice: string, speed: number): Promise<EngineChunkResult> {
+/** Part of the text to read (UTF-16); offsets in the result stay in the text's coordinates. */
+export interface ChunkRange {
+  start: number;
+  end?: number;
+}
+
+/**
+ * Synthesizes `text`, or only `range` of it. With `continueAfter`, the
+ * backend starts preparing the sentences that follow the range.
+ */
+export async function synthesiseChunk(
+  text: string,
+  voice: string,
+  speed: number,
+  { range, continueAfter = false }: { range?: ChunkRange; continueAfter?: boolean } = {}
+): Promise<EngineChunkResult> {
   const res = await invoke<any>('native_plugin_invoke', {
     pluginId: PLUGIN_ID,
     method: 'synthesize',
     requestId: Math.random().toString(),
-    payload: { text, voice, speed }
+    payload: { text, voice, speed, range, continue: continueAfter }
   });
 
   if (!res.ok) {
@@ -25,6 +42,7 @@ export async function synthesiseChunk(text: string, voice: string, speed: number
   const { path, segments } = res.result as TtsResult;
   const resolved = await resolveAudioSrc(path);
   return {
+    path,
     url: resolved.url,
     revoke: resolved.revoke,
     segments: segments || []
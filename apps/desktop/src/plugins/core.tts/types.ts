@@ -3,10 +3,25 @@ export interface TtsSegment {
   endChar: number;
   startMs: number;
   endMs: number;
+  /** Language the segment was read in, when known. */
+  lang?: string | null;
+  voice?: string;
+}
+
+/** Estimated timing of one word; offsets are UTF-16 like the segment offsets. */
+export interface TtsWord {
+  startChar: number;
+  endChar: number;
+  startMs: number;
+  endMs: number;
 }
 
 export interface TtsResult {
   path: string;
   duration_ms: number;
   segments: TtsSegment[];
+  /** Present when `word_timings` was requested. */
+  words?: TtsWord[];
+  /** Languages that had to be read with the requested voice instead. */
+  warnings?: string[];
 }
@@ -1,7 +1,8 @@
 import { useState, useEffect, useCallback, useRef } from 'react';
 import { invoke } from '@tauri-apps/api/core';
 import { useSettings } from '../../contexts/SettingsContext';
-import { synthesiseChunk } from './ttsEngines';
+import { ChunkRange, synthesiseChunk } from './ttsEngines';
+import { onPlaybackSegment, onPlaybackState, playback } from './playback';
 import { TtsSegment } from './types';
 
 const PLUGIN_ID = 'core.tts';
@@ -16,6 +17,9 @@ interface TtsChunk {
 export interface TtsStatus {
   installed: boolean;
   loaded: boolean;
+  /** Whether the selected engine can synthesize; the formant engine needs no model. */
+  ready?: boolean;
+  engine?: string;
 }
 
 const buildChunks = (text: string, maxChars: number = DEFAULT_CHUNK_SIZE): TtsChunk[] => {
@@ -71,17 +75,11 @@ export const useTts = () => {
   const [status, setStatus] = useState<TtsStatus>({ installed: false, loaded: false });
   const [isSynthesizing, setIsSynthesizing] = useState(false);
   const [isPlaying, setIsPlaying] = useState(false);
-  const [currentAudio, setCurrentAudio] = useState<HTMLAudioElement | null>(null);
-  const [revokeAudioUrl, setRevokeAudioUrl] = useState<(() => void) | null>(null);
   const [error, setError] = useState<string | null>(null);
   const [synthProgress, setSynthProgress] = useState<{ current: number; total: number } | null>(null);
-
-  const [segments, setSegments] = useState<TtsSegment[]>([]);
-  const [currentSegment, setCurrentSegment] = useState<TtsSegment | null>(null);
+  const [currentSegment, setCurrentSegment] = useState<(TtsSegment & { note?: string }) | null>(null);
   const runIdRef = useRef(0);
   const cancelRequestedRef = useRef(false);
-  const cancelResolversRef = useRef<Set<() => void>>(new Set());
-  const audioRef = useRef<HTMLAudioElement | null>(null);
 
   const checkStatus = useCallback(async () => {
     try {
@@ -136,33 +134,38 @@ export const useTts = () => {
     }
   };
 
-  const stopAudio = useCallback(() => {
-    const audio = audioRef.current;
-    if (audio) {
-      audio.pause();
-      audio.currentTime = 0;
-      audio.removeAttribute('src');
-      audio.load();
-    }
-    if (revokeAudioUrl) {
-      revokeAudioUrl();
-    }
-    setCurrentAudio(null);
-    setRevokeAudioUrl(null);
-    setIsPlaying(false);
-    setCurrentSegment(null);
-  }, [revokeAudioUrl]);
+  // Playback runs in the backend, so it survives reloads and navigation;
+  // resync on mount and follow its events.
+  useEffect(() => {
+    let disposed = false;
+    const unlisteners = [
+      onPlaybackState(state => setIsPlaying(state.playing && !state.ended)),
+      onPlaybackSegment(event => {
+        setCurrentSegment(event.segment ? { ...event.segment, note: event.note ?? undefined } : null);
+      })
+    ];
+    playback('status')
+      .then(state => {
+        if (!disposed) {
+          setIsPlaying(state.playing && !state.ended);
+        }
+      })
+      .catch(e => console.error('TTS playback status failed', e));
+    return () => {
+      disposed = true;
+      unlisteners.forEach(unlisten => unlisten.then(fn => fn()));
+    };
+  }, []);
 
   const cancelSynthesis = useCallback(async () => {
     cancelRequestedRef.current = true;
     runIdRef.current += 1;
-    cancelResolversRef.current.forEach(resolve => resolve());
-    cancelResolversRef.current.clear();
-    stopAudio();
     setIsSynthesizing(false);
     setSynthProgress(null);
-    setSegments([]);
+    setIsPlaying(false);
+    setCurrentSegment(null);
     try {
+      await playback('stop');
       await invoke<any>('native_plugin_invoke', {
         pluginId: PLUGIN_ID,
         method: 'cancel',
@@ -172,10 +175,22 @@ export const useTts = () => {
     } catch (e) {
       console.error('TTS cancel failed', e);
     }
-  }, [stopAudio]);
+  }, []);
 
-  const speak = async (text: string, voice: string = 'af_sky', speed: number = 1.0) => {
+  /**
+   * Reads `text` aloud, or only `range` of it; highlights stay in the
+   * coordinates of `text`, and `note` ties them to that note. With
+   * `continueToEnd`, reading carries on past the range to the end.
+   */
+  const speak = async (
+    text: string,
+    voice: string = 'af_sky',
+    speed: number = 1.0,
+    { range, note, continueToEnd = false }: { range?: ChunkRange; note?: string; continueToEnd?: boolean } = {}
+  ) => {
     if (!text) return;
+    const start = range?.start ?? 0;
+    const end = continueToEnd ? text.length : range?.end ?? text.length;
 
     // Stop previous
     await cancelSynthesis();
@@ -184,11 +199,10 @@ export const useTts = () => {
 
     setIsSynthesizing(true);
     setError(null);
-    setSegments([]);
     setCurrentSegment(null);
 
     try {
-      const chunks = buildChunks(text);
+      const chunks = buildChunks(text.slice(start, end));
       if (chunks.length === 0) {
         setIsSynthesizing(false);
         return;
@@ -196,14 +210,8 @@ export const useTts = () => {
 
       setSynthProgress({ current: 0, total: chunks.length });
 
-      if (!audioRef.current) {
-        const audio = document.createElement('audio');
-        audio.preload = 'auto';
-        audio.style.display = 'none';
-        document.body.appendChild(audio);
-        audioRef.current = audio;
-      }
-
+      // Each chunk joins the backend queue as soon as it is synthesized, so
+      // playback starts with the first one.
       for (let index = 0; index < chunks.length; index += 1) {
         if (cancelRequestedRef.current || runIdRef.current !== runId) {
           return;
@@ -211,91 +219,23 @@ export const useTts = () => {
 
         const chunk = chunks[index];
         setSynthProgress({ current: index + 1, total: chunks.length });
-        setIsSynthesizing(true);
-
-        const { url, revoke, segments: resSegments } = await synthesiseChunk(chunk.text, voice, speed);
 
+        // The backend prepares the following sentences while this chunk plays.
+        const { path } = await synthesiseChunk(text, voice, speed, {
+          range: { start: start + chunk.startChar, end: start + chunk.endChar },
+          continueAfter: index < chunks.length - 1
+        });
         if (runIdRef.current !== runId) {
-          if (revoke) {
-            revoke();
-          }
           return;
         }
 
-        const adjustedSegments = (resSegments || []).map(segment => ({
-          ...segment,
-          startChar: segment.startChar + chunk.startChar,
-          endChar: segment.endChar + chunk.startChar
-        }));
-        setSegments(adjustedSegments);
-
-        if (runIdRef.current !== runId) {
-          if (revoke) {
-            revoke();
-          }
-          return;
+        const item = { path, note };
+        if (index === 0) {
+          await playback('load', { items: [item], play: true });
+        } else {
+          await playback('enqueue', { items: [item] });
         }
-
-        await new Promise<void>((resolve, reject) => {
-          const audio = audioRef.current!;
-          audio.pause();
-          audio.currentTime = 0;
-          audio.playbackRate = 1.0;
-          audio.src = url;
-          audio.load();
-
-          const cancelResolver = () => {
-            cleanup();
-            resolve();
-          };
-
-          const cleanup = () => {
-            cancelResolversRef.current.delete(cancelResolver);
-            audio.onended = null;
-            audio.onerror = null;
-            audio.ontimeupdate = null;
-          };
-
-          cancelResolversRef.current.add(cancelResolver);
-
-          audio.onended = () => {
-            cleanup();
-            if (runIdRef.current === runId) {
-              setIsPlaying(false);
-              setCurrentAudio(null);
-              setCurrentSegment(null);
-              if (revoke) {
-                revoke();
-              }
-              setRevokeAudioUrl(null);
-            }
-            resolve();
-          };
-
-          audio.onerror = () => {
-            cleanup();
-            reject(new Error('Audio playback failed'));
-          };
-
-          audio.ontimeupdate = () => {
-            if (runIdRef.current !== runId) {
-              return;
-            }
-            const currentTimeMs = audio.currentTime * 1000;
-            const seg = adjustedSegments.find(s => currentTimeMs >= s.startMs && currentTimeMs < s.endMs);
-            setCurrentSegment(seg || null);
-          };
-
-          audio
-            .play()
-            .then(() => {
-              setCurrentAudio(audio);
-              setRevokeAudioUrl(revoke || null);
-              setIsPlaying(true);
-              setIsSynthesizing(false);
-            })
-            .catch(reject);
-        });
+        setIsPlaying(true);
       }
     } catch (e: any) {
       const message =
@@ -314,8 +254,10 @@ export const useTts = () => {
         setError('Something went wrong. Check the console for details.');
       }
     } finally {
-      setIsSynthesizing(false);
-      setSynthProgress(null);
+      if (runIdRef.current === runId) {
+        setIsSynthesizing(false);
+        setSynthProgress(null);
+      }
     }
   };
 
@@ -324,18 +266,23 @@ export const useTts = () => {
   }, [cancelSynthesis]);
 
   const pause = useCallback(() => {
-    if (currentAudio) {
-      currentAudio.pause();
-      setIsPlaying(false);
-    }
-  }, [currentAudio]);
+    setIsPlaying(false);
+    playback('pause').catch(e => console.error('TTS pause failed', e));
+  }, []);
 
   const resume = useCallback(() => {
-    if (currentAudio) {
-      currentAudio.play();
-      setIsPlaying(true);
-    }
-  }, [currentAudio]);
+    playback('play')
+      .then(() => setIsPlaying(true))
+      .catch(e => console.error('TTS resume failed', e));
+  }, []);
+
+  const seek = useCallback((position: { ms: number; track?: number } | { char: number }) => {
+    playback('seek', position).catch(e => console.error('TTS seek failed', e));
+  }, []);
+
+  const setRate = useCallback((rate: number) => {
+    playback('rate', { rate }).catch(e => console.error('TTS rate change failed', e));
+  }, []);
 
   return {
     status,
@@ -349,6 +296,8 @@ export const useTts = () => {
     speak,
     stop,
     pause,
-    resume
+    resume,
+    seek,
+    setRate
   };
 };
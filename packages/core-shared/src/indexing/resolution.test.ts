@@ -35,6 +35,13 @@ describe('resolveWikilinkTarget', () => {
     expect(resolveWikilinkTarget('duplicate', { existingNoteIds })).toBe('folder/duplicate.md');
   });
 
+  it('ignores Obsidian heading anchors and aliases', () => {
+    expect(resolveWikilinkTarget('nested#Section', { existingNoteIds })).toBe('folder/nested.md');
+    expect(resolveWikilinkTarget('nested|the nested note', { existingNoteIds })).toBe('folder/nested.md');
+    expect(resolveWikilinkTarget('folder/nested#^block1|see here', { existingNoteIds })).toBe('folder/nested.md');
+    expect(resolveWikilinkTarget('#Section', { existingNoteIds })).toBeNull();
+  });
+
   it('returns null for unresolved basename', () => {
     expect(resolveWikilinkTarget('missing', { existingNoteIds })).toBeNull();
   });
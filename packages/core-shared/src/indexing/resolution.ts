@@ -15,13 +15,19 @@ interface ResolveOptions {
  *    b. Search for any file ending with /{target}.md
  *    c. If multiple, sort paths and pick first (deterministic).
  *
- * @param targetRaw The raw text inside [[...]]
+ * Obsidian-style `[[note#heading|alias]]` resolves to the note itself.
+ *
+ * @param rawText The raw text inside [[...]]
  * @param opts Options including the list of existing notes
  * @returns The resolved NoteId or null if not found
  */
-export function resolveWikilinkTarget(targetRaw: string, opts: ResolveOptions): NoteId | null {
+export function resolveWikilinkTarget(rawText: string, opts: ResolveOptions): NoteId | null {
   const { existingNoteIds } = opts;
   const pathsSet = new Set(existingNoteIds);
+  const targetRaw = rawText.split('|')[0].split('#')[0].trim();
+  if (!targetRaw) {
+    return null;
+  }
 
   // 1. If path separators, treat as relative path
   if (targetRaw.includes('/') || targetRaw.includes('\\')) {